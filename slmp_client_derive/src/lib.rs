@@ -0,0 +1,320 @@
+//! slmp_clientの`SlmpStruct`を実装するderiveマクロ
+//!
+//! `slmp_client`の`derive`フィーチャーから利用する
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitInt, LitStr, Type};
+
+/// フィールドの属性
+struct FieldAttr {
+    /// 先頭デバイス名
+    device: LitStr,
+    /// デバイス上のデータ型
+    ty: Option<Type>,
+    /// 文字列の最大文字数
+    len: Option<usize>,
+    /// 複数ワードにまたがる値のワードの並びが上位ワードが先か
+    high_first: bool,
+}
+
+/// `ty`を指定したフィールドの変換方法
+enum Conversion {
+    /// 変換しない
+    None,
+    /// `Into`で変換する
+    Scalar,
+    /// 配列の要素ごとに`Into`で変換する
+    Array,
+}
+
+/// 同じ型か
+fn same_type(a: &Type, b: &Type) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// GX Worksでのデバイス名とその別名、デバイス番号が16進数か
+///
+/// slmp_clientの`SLMPDeviceCode`と合わせること
+const DEVICE_NAMES: &[(&str, bool)] = &[
+    ("SM", false),
+    ("SD", false),
+    ("X", true),
+    ("Y", true),
+    ("M", false),
+    ("L", false),
+    ("F", false),
+    ("V", false),
+    ("B", true),
+    ("D", false),
+    ("W", true),
+    ("TS", false),
+    ("TC", false),
+    ("TN", false),
+    ("LTS", false),
+    ("LTC", false),
+    ("LTN", false),
+    ("STS", false),
+    ("STC", false),
+    ("STN", false),
+    ("SS", false),
+    ("SC", false),
+    ("SN", false),
+    ("LSTS", false),
+    ("LSTC", false),
+    ("LSTN", false),
+    ("CS", false),
+    ("CC", false),
+    ("CN", false),
+    ("SB", true),
+    ("SW", true),
+    ("DX", true),
+    ("DY", true),
+    ("Z", false),
+    ("LZ", false),
+    ("R", false),
+    ("ZR", false),
+    ("RD", false),
+    ("LCS", false),
+    ("LCC", false),
+    ("LCN", false),
+];
+
+/// slmp_clientがデバイス名として解釈できるか
+fn is_device_name(name: &str) -> bool {
+    let upper = name.trim().to_ascii_uppercase();
+    DEVICE_NAMES.iter().any(|(prefix, hex)| {
+        let radix = if *hex { 16 } else { 10 };
+        match upper.strip_prefix(prefix) {
+            Some(number) => !number.starts_with('+') && u32::from_str_radix(number, radix).is_ok(),
+            None => false,
+        }
+    })
+}
+
+/// 文字列型か
+fn is_string(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => p.path.segments.last().is_some_and(|s| s.ident == "String"),
+        _ => false,
+    }
+}
+
+/// `ty`に指定した名前に対応する型
+fn codec_type(name: &LitStr) -> syn::Result<Type> {
+    let ty = match name.value().as_str() {
+        "u16" => quote!(u16),
+        "i16" => quote!(i16),
+        "u32" => quote!(u32),
+        "i32" => quote!(i32),
+        "u64" => quote!(u64),
+        "i64" => quote!(i64),
+        "f32" => quote!(f32),
+        "f64" => quote!(f64),
+        "bool" => quote!(bool),
+        "string" => quote!(::std::string::String),
+        "bcd16" => quote!(::slmp_client::SlmpBcd16),
+        "bcd32" => quote!(::slmp_client::SlmpBcd32),
+        _ => return Err(syn::Error::new(name.span(), "unknown ty")),
+    };
+    syn::parse2(ty)
+}
+
+/// `#[slmp(...)]`属性を解釈する
+fn parse_attr(field: &syn::Field) -> syn::Result<FieldAttr> {
+    let mut device = None;
+    let mut ty = None;
+    let mut len = None;
+    let mut high_first = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("slmp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("device") {
+                let name = meta.value()?.parse::<LitStr>()?;
+                if !is_device_name(&name.value()) {
+                    return Err(syn::Error::new(name.span(), "invalid device name"));
+                }
+                device = Some(name);
+            } else if meta.path.is_ident("ty") {
+                ty = Some(codec_type(&meta.value()?.parse::<LitStr>()?)?);
+            } else if meta.path.is_ident("len") {
+                let lit = meta.value()?.parse::<LitInt>()?;
+                let n: usize = lit.base10_parse()?;
+                if n == 0 {
+                    return Err(syn::Error::new(lit.span(), "len must be at least 1"));
+                }
+                len = Some(n);
+            } else if meta.path.is_ident("word_order") {
+                let order = meta.value()?.parse::<LitStr>()?;
+                high_first = match order.value().as_str() {
+                    "low_first" => false,
+                    "high_first" => true,
+                    _ => return Err(meta.error("word_order must be low_first or high_first")),
+                };
+            } else {
+                return Err(meta.error("unknown slmp attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    let device = device.ok_or_else(|| {
+        syn::Error::new_spanned(field, "missing #[slmp(device = \"...\")] attribute")
+    })?;
+    // 文字列は占有するデバイスの点数が決まらないため、最大文字数を必須とする
+    let string = is_string(ty.as_ref().unwrap_or(&field.ty));
+    if string && len.is_none() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "missing #[slmp(len = ...)] attribute for string field",
+        ));
+    }
+    if !string && len.is_some() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "len is only for string fields",
+        ));
+    }
+    Ok(FieldAttr {
+        device,
+        ty,
+        len,
+        high_first,
+    })
+}
+
+/// 構造体のフィールドをPLCのデバイスに対応付ける
+///
+/// 各フィールドに`#[slmp(device = "D100")]`で先頭デバイスを指定する。
+/// 解釈できないデバイス名はコンパイルエラーとなる。
+/// `ty`でデバイス上のデータ型(`u16`、`i16`、`u32`、`i32`、`u64`、`i64`、`f32`、`f64`、
+/// `bool`、`string`、`bcd16`、`bcd32`)、
+/// `word_order = "high_first"`で上位ワードが先の並びを指定できる。
+/// 文字列のフィールドには`len`で最大文字数の指定が必要
+#[proc_macro_derive(SlmpStruct, attributes(slmp))]
+pub fn derive_slmp_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "SlmpStruct requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SlmpStruct can only be derived for structs",
+            ))
+        }
+    };
+    let mut layout = Vec::new();
+    let mut decode = Vec::new();
+    let mut encode = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let attr = parse_attr(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        let name = ident.to_string();
+        let device = &attr.device;
+        let len = attr.len.unwrap_or(0);
+        let order = if attr.high_first {
+            quote!(::slmp_client::SlmpWordOrder::HighFirst)
+        } else {
+            quote!(::slmp_client::SlmpWordOrder::LowFirst)
+        };
+        let field_ty = &field.ty;
+        // `ty`を指定した場合はフィールドの型と相互に変換する、配列では要素ごとに変換する
+        let (codec, conversion) = match (&attr.ty, field_ty) {
+            (Some(ty), _) if same_type(ty, field_ty) => (field_ty.clone(), Conversion::None),
+            (Some(ty), Type::Array(a)) => {
+                let n = &a.len;
+                (syn::parse2(quote!([#ty; #n]))?, Conversion::Array)
+            }
+            (Some(ty), _) => (ty.clone(), Conversion::Scalar),
+            (None, _) => (field_ty.clone(), Conversion::None),
+        };
+        layout.push(quote! {
+            ::slmp_client::SlmpFieldLayout {
+                name: #name,
+                device: #device,
+                points: <#codec as ::slmp_client::SlmpFieldValue>::points(#len),
+                bit: <#codec as ::slmp_client::SlmpFieldValue>::BIT,
+            }
+        });
+        let decoded = quote! {
+            <#codec as ::slmp_client::SlmpFieldValue>::decode_field(&fields[#index], #order, #len)?
+        };
+        let (from, to) = match conversion {
+            Conversion::None => (decoded, quote!(&self.#ident)),
+            Conversion::Scalar => (
+                quote!(::core::convert::Into::into(#decoded)),
+                quote!(&::core::convert::Into::<#codec>::into(
+                    ::core::clone::Clone::clone(&self.#ident)
+                )),
+            ),
+            Conversion::Array => (
+                quote!(#decoded.map(::core::convert::Into::into)),
+                quote!(&::core::clone::Clone::clone(&self.#ident)
+                    .map(::core::convert::Into::into)),
+            ),
+        };
+        decode.push(quote!(#ident: #from));
+        encode.push(quote! {
+            <#codec as ::slmp_client::SlmpFieldValue>::encode_field(#to, #order, #len)?
+        });
+    }
+    let count = fields.len();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::slmp_client::SlmpStruct for #ident #ty_generics #where_clause {
+            fn layout() -> ::std::vec::Vec<::slmp_client::SlmpFieldLayout> {
+                ::std::vec![#(#layout),*]
+            }
+            fn decode_fields(
+                fields: &[::slmp_client::SlmpFieldData],
+            ) -> ::core::result::Result<Self, ::slmp_client::SlmpError> {
+                if fields.len() != #count {
+                    return ::core::result::Result::Err(
+                        ::slmp_client::SlmpError::MalformedFrame("wrong number of fields"),
+                    );
+                }
+                ::core::result::Result::Ok(#ident {
+                    #(#decode),*
+                })
+            }
+            fn encode_fields(
+                &self,
+            ) -> ::core::result::Result<
+                ::std::vec::Vec<::slmp_client::SlmpFieldData>,
+                ::slmp_client::SlmpError,
+            > {
+                ::core::result::Result::Ok(::std::vec![#(#encode),*])
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_device_name() {
+        for name in &["D100", "x1f", "ZR12000", "SB1F", "LSTN3", "SS5", " W0 "] {
+            assert!(is_device_name(name), "{}", name);
+        }
+        for name in &["", "D", "D1F", "Q10", "D+1", "DD100", "M-1"] {
+            assert!(!is_device_name(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,1591 @@
+use crate::operation::{self, Operation};
+use crate::split::max_batch_bits;
+use crate::structs::{FrameCodec, SLMPFrame, SlmpSubHeaderReq};
+use crate::{
+    make_remote_password_lock_cmd, make_remote_password_unlock_cmd, ReconnectPolicy, RetryPolicy,
+    SLMPClearMode, SLMPCommand, SLMPDataCode, SLMPDevice, SLMPDeviceBlock, SLMPDeviceBlockData,
+    SLMPDeviceData, SLMPFrameType, SLMPRequest, SlmpArrayLabel, SlmpArrayLabelData, SlmpCodec,
+    SlmpConnectionState, SlmpDiskState, SlmpError, SlmpEvent, SlmpFileEntry, SlmpLabelData,
+    SlmpPasswordFormat, SlmpStateCallback, SlmpStringTermination, SlmpStruct, SlmpTagValue,
+    SlmpWordOrder,
+};
+use rand::Rng;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::Instant;
+
+/// TCPとUDPの差異を吸収(tokio版)
+pub enum AsyncTCPorUDP {
+    UDP(UdpSocket),
+    TCP(TcpStream),
+}
+impl AsyncTCPorUDP {
+    /// 指定されたバイト列を全て送信する
+    /// # 引数
+    ///
+    /// * `buf` - 送信するバイト列
+    pub async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        use AsyncTCPorUDP::*;
+        match self {
+            TCP(stream) => stream.write_all(buf).await,
+            UDP(socket) => {
+                let send_size = socket.send(buf).await?;
+                if send_size != buf.len() {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "write size error"));
+                }
+                Ok(())
+            }
+        }
+    }
+    /// 受信する
+    /// # 引数
+    ///
+    /// * `buf` - 受信したデータを入れるバッファ、十分な量を確保しておくこと
+    ///
+    /// # 返値
+    ///
+    /// 受信したバイト数
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use AsyncTCPorUDP::*;
+        match self {
+            TCP(stream) => stream.read(buf).await,
+            UDP(socket) => socket.recv(buf).await,
+        }
+    }
+}
+
+/// SLMPの接続管理(tokio版)
+///
+/// 同期版の[SLMPConnectionInfo](crate::SLMPConnectionInfo)と異なり、
+/// 任意の通信路([SlmpTransport](crate::SlmpTransport))には対応しない
+pub struct AsyncSLMPConnectionInfo {
+    /// ネットワーク番号
+    network: u8,
+    /// ノード番号
+    node: u8,
+    /// プロセッサ番号
+    dst_proc: u16,
+    /// マルチドロップ番号
+    m_drop: u8,
+    /// 通信文の作成と解釈
+    codec: FrameCodec,
+    /// シリアル番号
+    seq_no: u16,
+    /// 通信ソケット、切断後は`None`
+    socket: Option<AsyncTCPorUDP>,
+    /// 受信時のタイムアウト
+    recv_timeout: Option<Duration>,
+    /// 接続自身が送信する要求のタイムアウト、単位は250ms
+    request_timeout: u16,
+    /// 受信バッファ
+    buf: Vec<u8>,
+    /// 再接続先、TCPの場合のみ
+    remote: Option<SocketAddr>,
+    /// 再接続方針
+    reconnect_policy: ReconnectPolicy,
+    /// 接続状態の変化の通知先
+    state_callback: Option<SlmpStateCallback>,
+    /// 再接続に成功した回数
+    reconnect_count: u64,
+    /// モニタ登録の世代、モニタ登録の要求の送信と再接続のたびに増える
+    monitor_generation: u64,
+    /// 再接続時にロックを解除するリモートパスワード
+    remote_password: Option<(String, SlmpPasswordFormat)>,
+    /// 3Eフレームで応答を受信する前にタイムアウトし、遅れた応答が届く可能性があるか
+    out_of_sync: bool,
+}
+
+impl AsyncSLMPConnectionInfo {
+    fn from_socket(socket: AsyncTCPorUDP) -> AsyncSLMPConnectionInfo {
+        let rnd: u8 = rand::thread_rng().gen();
+        AsyncSLMPConnectionInfo {
+            network: 1,
+            node: 1,
+            dst_proc: 0x3FF,
+            m_drop: 0,
+            codec: FrameCodec::new(),
+            seq_no: rnd as u16,
+            socket: Some(socket),
+            recv_timeout: None,
+            request_timeout: 40,
+            buf: Vec::new(),
+            remote: None,
+            reconnect_policy: ReconnectPolicy::default(),
+            state_callback: None,
+            reconnect_count: 0,
+            monitor_generation: 0,
+            remote_password: None,
+            out_of_sync: false,
+        }
+    }
+    /// TCPにてSLMP接続を作成
+    ///
+    /// 接続のタイムアウトはOSの既定値、指定するには[AsyncSLMPConnectionInfo::new_tcp_with_policy]を使う
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    pub async fn new_tcp(addr: SocketAddr) -> Result<AsyncSLMPConnectionInfo, SlmpError> {
+        let stream = TcpStream::connect(addr).await?;
+        let mut info = Self::from_socket(AsyncTCPorUDP::TCP(stream));
+        info.remote = Some(addr);
+        Ok(info)
+    }
+    /// 再接続方針を指定し、TCPにてSLMP接続を作成
+    ///
+    /// 最初の接続にも再接続方針の接続タイムアウトを使う
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    /// * `policy` - 再接続方針
+    pub async fn new_tcp_with_policy(
+        addr: SocketAddr,
+        policy: ReconnectPolicy,
+    ) -> Result<AsyncSLMPConnectionInfo, SlmpError> {
+        let stream = tokio::time::timeout(policy.connect_timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| SlmpError::Timeout)??;
+        let mut info = Self::from_socket(AsyncTCPorUDP::TCP(stream));
+        info.remote = Some(addr);
+        info.reconnect_policy = policy;
+        Ok(info)
+    }
+    /// UDPにてSLMP接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    pub async fn new_udp(addr: SocketAddr) -> Result<AsyncSLMPConnectionInfo, SlmpError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(addr).await?;
+        Ok(Self::from_socket(AsyncTCPorUDP::UDP(socket)))
+    }
+    pub fn get_network(&self) -> u8 {
+        self.network
+    }
+    pub fn set_network(&mut self, network: u8) {
+        self.network = network;
+    }
+    pub fn get_node(&self) -> u8 {
+        self.node
+    }
+    pub fn set_node(&mut self, node: u8) {
+        self.node = node
+    }
+    pub fn get_dst_proc(&self) -> u16 {
+        self.dst_proc
+    }
+    pub fn set_dst_proc(&mut self, dst_proc: u16) {
+        self.dst_proc = dst_proc
+    }
+    pub fn get_m_drop(&self) -> u8 {
+        self.m_drop
+    }
+    pub fn set_m_drop(&mut self, m_drop: u8) {
+        self.m_drop = m_drop
+    }
+    pub fn get_frame_type(&self) -> SLMPFrameType {
+        self.codec.frame_type
+    }
+    /// 通信フレームの種類を設定する
+    ///
+    /// 受信途中のデータは破棄される
+    ///
+    /// # 引数
+    ///
+    /// * `frame_type` - フレームの種類
+    pub fn set_frame_type(&mut self, frame_type: SLMPFrameType) {
+        self.codec.frame_type = frame_type;
+        self.buf.clear();
+    }
+    pub fn get_data_code(&self) -> SLMPDataCode {
+        self.codec.data_code
+    }
+    /// 通信データコードを設定する
+    ///
+    /// 受信途中のデータは破棄される
+    ///
+    /// # 引数
+    ///
+    /// * `data_code` - 通信データコード
+    pub fn set_data_code(&mut self, data_code: SLMPDataCode) {
+        self.codec.data_code = data_code;
+        self.buf.clear();
+    }
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.reconnect_policy
+    }
+    /// 再接続方針を設定する
+    ///
+    /// 再接続はTCPで接続した場合のみ行う
+    ///
+    /// # 引数
+    ///
+    /// * `policy` - 再接続方針
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy
+    }
+    /// 再接続に成功した回数
+    ///
+    /// 接続ごとに保持されるPLC側の状態(モニタ登録など)が失われたことの検出に使う
+    pub fn get_reconnect_count(&self) -> u64 {
+        self.reconnect_count
+    }
+    /// モニタ登録の世代
+    ///
+    /// モニタ登録の要求を送信するか再接続するたびに増える。
+    /// 登録時の値と異なれば、その登録は別のデバイスの登録で上書きされたか失われている
+    pub fn get_monitor_generation(&self) -> u64 {
+        self.monitor_generation
+    }
+    pub fn get_request_timeout(&self) -> u16 {
+        self.request_timeout
+    }
+    /// 接続自身が送信する要求(再接続時のロックの解除など)のタイムアウトを設定する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms、0でタイムアウトなし
+    pub fn set_request_timeout(&mut self, timeout: u16) {
+        self.request_timeout = timeout
+    }
+    /// リモートパスワードを設定し、ロックを解除する
+    ///
+    /// 以降は再接続するごとにロックを解除する。
+    /// 破棄する際はロックしないため、[AsyncSLMPConnectionInfo::lock_and_close]を使う
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub async fn set_remote_password(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.remote_password = Some((password.to_string(), format));
+        let ret = self.unlock_remote_password().await;
+        if ret.is_err() {
+            self.remote_password = None;
+        }
+        ret
+    }
+    /// リモートパスワードの設定を消去する、ロックはしない
+    pub fn clear_remote_password(&mut self) {
+        self.remote_password = None;
+    }
+    /// 設定したリモートパスワードでロックを解除する
+    async fn unlock_remote_password(&mut self) -> Result<(), SlmpError> {
+        let req = match &self.remote_password {
+            Some((password, format)) => make_remote_password_unlock_cmd(password, *format)?,
+            None => return Ok(()),
+        };
+        self.request_once(&req).await
+    }
+    /// リモートパスワードを設定していればロックし、接続を閉じる
+    pub async fn lock_and_close(mut self) -> Result<(), SlmpError> {
+        match self.remote_password.take() {
+            Some((password, format)) if self.socket.is_some() => {
+                let req = make_remote_password_lock_cmd(&password, format)?;
+                self.request_once(&req).await
+            }
+            _ => Ok(()),
+        }
+    }
+    /// 要求を送信し、応答を待つ
+    ///
+    /// 再接続の途中でも使うため、送受信に失敗しても再接続しない
+    async fn request_once(&mut self, req: &SLMPRequest) -> Result<(), SlmpError> {
+        let timeout = self.request_timeout;
+        let slmp_header =
+            req.make_sub_header(self.network, self.node, self.dst_proc, self.m_drop, timeout)?;
+        let serial = self.get_new_serial();
+        let recv_timeout = std::mem::replace(
+            &mut self.recv_timeout,
+            match timeout {
+                0 => None,
+                t => Some(Duration::from_millis(t as u64 * 250)),
+            },
+        );
+        let deadline = Instant::now() + Duration::from_millis(timeout as u64 * 250);
+        let ret = match self.write_frame(&slmp_header, serial, req).await {
+            Ok(()) => loop {
+                match self.recv_response(serial).await {
+                    Err(SlmpError::SerialMismatch { .. }) => {
+                        if timeout != 0 && Instant::now() > deadline {
+                            break Err(SlmpError::Timeout);
+                        }
+                    }
+                    r => break r.map(|_| ()),
+                }
+            },
+            Err(e) => Err(e),
+        };
+        self.recv_timeout = recv_timeout;
+        ret
+    }
+    /// 接続状態の変化の通知先を設定する
+    ///
+    /// # 引数
+    ///
+    /// * `callback` - 状態の変化ごとに呼ばれる関数
+    pub fn set_state_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(SlmpConnectionState) + Send + 'static,
+    {
+        self.state_callback = Some(Box::new(callback))
+    }
+    fn notify(&mut self, state: SlmpConnectionState) {
+        if let Some(callback) = self.state_callback.as_mut() {
+            callback(state)
+        }
+    }
+    /// 再接続方針に従いTCP接続をやり直す
+    ///
+    /// 受信途中のデータは破棄される
+    pub async fn reconnect(&mut self) -> Result<(), SlmpError> {
+        self.reconnect_with(self.reconnect_policy.max_attempts)
+            .await
+    }
+    /// 試行回数を指定してTCP接続をやり直す
+    ///
+    /// # 引数
+    ///
+    /// * `max_attempts` - 最大試行回数
+    async fn reconnect_with(&mut self, max_attempts: u32) -> Result<(), SlmpError> {
+        let addr = self.remote.ok_or(SlmpError::NotConnected)?;
+        self.socket = None;
+        self.buf.clear();
+        let policy = self.reconnect_policy;
+        let mut last_err = SlmpError::NotConnected;
+        for attempt in 1..=max_attempts {
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            self.notify(SlmpConnectionState::Reconnecting { attempt });
+            match tokio::time::timeout(policy.connect_timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    self.socket = Some(AsyncTCPorUDP::TCP(stream));
+                    self.out_of_sync = false;
+                    if let Err(e) = self.unlock_remote_password().await {
+                        self.socket = None;
+                        last_err = e;
+                        continue;
+                    }
+                    self.reconnect_count += 1;
+                    self.monitor_generation += 1;
+                    self.notify(SlmpConnectionState::Connected);
+                    return Ok(());
+                }
+                Ok(Err(e)) => last_err = e.into(),
+                Err(_) => last_err = SlmpError::Timeout,
+            }
+        }
+        self.notify(SlmpConnectionState::ReconnectFailed);
+        Err(last_err)
+    }
+    /// 遅れて届く応答を読み捨て、送受信を同期し直す
+    ///
+    /// TCPは接続し直し、UDPは受信タイムアウトまでに届いた応答を読み捨てる
+    async fn resync(&mut self) -> Result<(), SlmpError> {
+        self.buf.clear();
+        if self.remote.is_some() {
+            return self
+                .reconnect_with(self.reconnect_policy.max_attempts.max(1))
+                .await;
+        }
+        if let (Some(t), Some(socket)) = (self.recv_timeout, self.socket.as_mut()) {
+            let deadline = Instant::now() + t;
+            let mut buf = [0u8; 8194];
+            // 応答が途切れるまで読み捨てる、届き続ける場合は受信タイムアウトの時間で打ち切る
+            while let Ok(read) = tokio::time::timeout_at(deadline, socket.read(&mut buf)).await {
+                if let Err(e) = read {
+                    return Err(self.on_io_error(e));
+                }
+            }
+        }
+        self.out_of_sync = false;
+        Ok(())
+    }
+    /// 入出力エラーによる切断を記録する
+    ///
+    /// 再接続は次の送信の前に行う
+    ///
+    /// # 引数
+    ///
+    /// * `e` - 発生したエラー
+    ///
+    /// # 返値
+    ///
+    /// 呼び出し元へ返すエラー
+    fn on_io_error(&mut self, e: io::Error) -> SlmpError {
+        if self.remote.is_some() && self.socket.take().is_some() {
+            self.notify(SlmpConnectionState::Disconnected);
+        }
+        e.into()
+    }
+    /// 通信文を一つ送信する
+    ///
+    /// # 引数
+    ///
+    /// * `slmp_header` - サブヘッダ
+    /// * `seq_no` - シリアル番号
+    /// * `req` - 要求内容
+    async fn write_frame(
+        &mut self,
+        slmp_header: &SlmpSubHeaderReq,
+        seq_no: u16,
+        req: &SLMPRequest,
+    ) -> Result<(), SlmpError> {
+        let write_buf = self.codec.encode(slmp_header, seq_no, req)?;
+        let socket = self.socket.as_mut().ok_or(SlmpError::NotConnected)?;
+        match socket.write_all(&write_buf).await {
+            Ok(()) => {
+                self.codec.last_serial = seq_no;
+                Ok(())
+            }
+            Err(e) => Err(self.on_io_error(e)),
+        }
+    }
+    /// 新しい要求用のシリアル番号を取得する
+    /// # 返値
+    /// シリアル番号
+    fn get_new_serial(&mut self) -> u16 {
+        self.seq_no += 0x10;
+        if self.seq_no > 0xff00 {
+            // リトライ時に減算するため0x10以上から再開する
+            let rnd: u8 = rand::thread_rng().gen();
+            self.seq_no = 0x10 + rnd as u16;
+        }
+        self.seq_no
+    }
+    /// 通信文を一つ受信する
+    async fn recv_frame(&mut self) -> Result<SLMPFrame, SlmpError> {
+        let mut buf = [0u8; 8194];
+        loop {
+            match self.codec.decode(&self.buf) {
+                Ok(Some((size, frame))) => {
+                    self.buf.drain(..size);
+                    return Ok(frame);
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    self.buf.clear();
+                    return Err(e);
+                }
+            }
+            let socket = self.socket.as_mut().ok_or(SlmpError::NotConnected)?;
+            let read = match self.recv_timeout {
+                Some(t) => match tokio::time::timeout(t, socket.read(&mut buf)).await {
+                    Ok(r) => r,
+                    Err(_) => {
+                        // 3Eフレームは遅れた応答を次の要求の応答と区別できない
+                        if self.codec.frame_type == SLMPFrameType::Frame3E {
+                            self.out_of_sync = true;
+                        }
+                        return Err(SlmpError::Timeout);
+                    }
+                },
+                None => socket.read(&mut buf).await,
+            };
+            let recv_size = match read {
+                Ok(0) => {
+                    let e = io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed");
+                    return Err(self.on_io_error(e));
+                }
+                Ok(recv_size) => recv_size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.on_io_error(e)),
+            };
+            self.buf.extend_from_slice(&buf[..recv_size]);
+        }
+    }
+    /// SLMPの応答を受信
+    ///
+    /// 正常終了以外の終了コードを受信した場合は[SlmpError::EndCode]を返す
+    ///
+    /// # 返値
+    ///
+    /// シリアル番号と受信ペイロード
+    pub async fn recv_cmd(&mut self) -> Result<(u16, Vec<u8>), SlmpError> {
+        let frame = self.recv_frame().await?;
+        frame.into_result(self.network, self.node, self.dst_proc)
+    }
+    /// 指定したシリアル番号に対するSLMPの応答を受信
+    ///
+    /// # 引数
+    ///
+    /// * `serial` - 要求時のシリアル番号
+    ///
+    /// # 返値
+    ///
+    /// 受信ペイロード、異なるシリアル番号の応答を受信した場合は[SlmpError::SerialMismatch]
+    pub async fn recv_response(&mut self, serial: u16) -> Result<Vec<u8>, SlmpError> {
+        match self.recv_cmd().await {
+            Ok((ser_no, data)) => {
+                if ser_no != serial {
+                    return Err(SlmpError::SerialMismatch {
+                        expected: serial,
+                        actual: ser_no,
+                    });
+                }
+                Ok(data)
+            }
+            Err(SlmpError::EndCode { serial: ser_no, .. }) if ser_no != serial => {
+                Err(SlmpError::SerialMismatch {
+                    expected: serial,
+                    actual: ser_no,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// SLMPコマンドを送信する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms
+    /// * `cmd` - SLMPコマンド
+    /// * `sub_command` - サブコマンド
+    /// * `content_data` - コマンドごとの付属データ
+    ///
+    /// # 返値
+    ///
+    /// 送信したコマンドに対応するシリアル番号
+    pub async fn send_cmd(
+        &mut self,
+        timeout: u16,
+        cmd: SLMPCommand,
+        sub_command: u16,
+        content_data: &[u8],
+    ) -> Result<u16, SlmpError> {
+        self.send_request(timeout, &SLMPRequest::new(cmd, sub_command, content_data))
+            .await
+    }
+    /// SLMPの要求を送信する
+    ///
+    /// 3Eフレームで前の要求の応答を待つ間にタイムアウトしていた場合は、
+    /// 遅れて届く応答を取り違えないよう、先にTCPを接続し直すか届いた応答を読み捨てる。
+    /// 切断していれば再接続方針に従って再接続し、送信に失敗した場合は3回まで送信し直す
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信した要求に対応するシリアル番号
+    pub async fn send_request(
+        &mut self,
+        timeout: u16,
+        req: &SLMPRequest,
+    ) -> Result<u16, SlmpError> {
+        let slmp_header =
+            req.make_sub_header(self.network, self.node, self.dst_proc, self.m_drop, timeout)?;
+        if self.out_of_sync {
+            self.resync().await?;
+        }
+        if req.command == SLMPCommand::EntryMonitorDevice {
+            // 応答を受け取れなくても、前の登録は上書きされている可能性がある
+            self.monitor_generation += 1;
+        }
+        let mut seq_no = self.get_new_serial();
+        self.recv_timeout = match timeout {
+            0 => None,
+            t => Some(Duration::from_millis(t as u64 * 250)),
+        };
+        let mut last_err = SlmpError::NotConnected;
+        // 3回までリトライ
+        for _ in 0..3 {
+            if self.socket.is_none()
+                && self.remote.is_some()
+                && self.reconnect_policy.max_attempts > 0
+            {
+                self.reconnect().await?;
+            }
+            seq_no = seq_no.wrapping_sub(1);
+            match self.write_frame(&slmp_header, seq_no, req).await {
+                Ok(()) => return Ok(seq_no),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+/// 要求の送信から応答の受信、デコードまでを一度に行うSLMPクライアント(tokio版)
+///
+/// 要求の組み立てと応答のデコードは同期版の[SlmpClient](crate::SlmpClient)と共通
+pub struct AsyncSlmpClient {
+    /// SLMP接続情報
+    connection_info: AsyncSLMPConnectionInfo,
+    /// SLMPコマンドのタイムアウト、単位は250ms
+    timeout: u16,
+    /// 32bitアドレス版のコマンドを使用するか
+    addr_32bit: bool,
+    /// 冪等な要求の再送方針
+    retry_policy: RetryPolicy,
+}
+
+impl AsyncSlmpClient {
+    /// SLMP接続情報からクライアントを作成
+    ///
+    /// # 引数
+    ///
+    /// * `connection_info` - SLMP接続情報
+    pub fn new(connection_info: AsyncSLMPConnectionInfo) -> AsyncSlmpClient {
+        AsyncSlmpClient {
+            connection_info,
+            timeout: 40,
+            addr_32bit: false,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+    pub fn get_timeout(&self) -> u16 {
+        self.timeout
+    }
+    /// SLMPコマンドのタイムアウトを設定する
+    ///
+    /// 接続自身が送信する要求のタイムアウト([AsyncSLMPConnectionInfo::set_request_timeout])も合わせて設定する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - タイムアウト時間、単位は250ms、0でタイムアウトなし
+    pub fn set_timeout(&mut self, timeout: u16) {
+        self.timeout = timeout;
+        self.connection_info.set_request_timeout(timeout);
+    }
+    pub fn get_addr_32bit(&self) -> bool {
+        self.addr_32bit
+    }
+    pub fn set_addr_32bit(&mut self, addr_32bit: bool) {
+        self.addr_32bit = addr_32bit
+    }
+    pub fn get_retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy
+    }
+    pub fn connection_info(&self) -> &AsyncSLMPConnectionInfo {
+        &self.connection_info
+    }
+    pub fn connection_info_mut(&mut self) -> &mut AsyncSLMPConnectionInfo {
+        &mut self.connection_info
+    }
+    /// クライアントを破棄してSLMP接続情報を取り出す
+    pub fn into_inner(self) -> AsyncSLMPConnectionInfo {
+        self.connection_info
+    }
+    /// 要求を送信し、対応する応答を待つ
+    ///
+    /// 以前の要求に対する遅れた応答は読み捨てる。
+    /// 冪等な要求は通信エラーの際に再送方針に従って再送する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 応答のペイロード
+    pub async fn request(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let mut retries = 0;
+        loop {
+            match self.request_once(req).await {
+                Err(e) if self.retry_policy.should_retry(req, &e, retries) => retries += 1,
+                r => return r,
+            }
+        }
+    }
+    async fn request_once(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let serial = self.connection_info.send_request(self.timeout, req).await?;
+        let deadline = Instant::now() + Duration::from_millis(self.timeout as u64 * 250);
+        loop {
+            match self.connection_info.recv_response(serial).await {
+                Err(SlmpError::SerialMismatch { .. }) => {
+                    if self.timeout != 0 && Instant::now() > deadline {
+                        return Err(SlmpError::Timeout);
+                    }
+                }
+                r => return r,
+            }
+        }
+    }
+    /// 入出力を伴わない処理の要求を順に送信し、応答を渡して結果を得る
+    ///
+    /// # 引数
+    ///
+    /// * `op` - 処理
+    async fn run<O: Operation>(&mut self, mut op: O) -> Result<O::Output, SlmpError> {
+        while let Some(req) = op.next_request()? {
+            let res = self.request(&req).await;
+            op.on_response(res)?;
+        }
+        op.finish()
+    }
+    /// 単一の連続したワードデバイスを読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - デバイス個数
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub async fn read_words(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<u16>>, SlmpError> {
+        self.run(operation::read_words(target, count, self.addr_32bit))
+            .await
+    }
+    /// 単一の連続したビットデバイスを読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - デバイス個数
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub async fn read_bits(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<bool>>, SlmpError> {
+        let max_bits = max_batch_bits(self.connection_info.get_data_code());
+        self.run(operation::read_bits(
+            target,
+            count,
+            self.addr_32bit,
+            max_bits,
+        )?)
+        .await
+    }
+    /// 単一の連続したワードデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub async fn write_words(&mut self, targets: &[SLMPDeviceData<u16>]) -> Result<(), SlmpError> {
+        self.run(operation::write_words(targets, self.addr_32bit)?)
+            .await
+    }
+    /// 単一の連続したワードデバイスを型付きの値として読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - 値の個数
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    pub async fn read_values<T: SlmpCodec>(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+        order: SlmpWordOrder,
+    ) -> Result<Vec<T>, SlmpError> {
+        self.run(operation::read_values(
+            target,
+            count,
+            order,
+            self.addr_32bit,
+        )?)
+        .await
+    }
+    /// 単一の連続したワードデバイスへ型付きの値を書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `values` - 書き込む値
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    pub async fn write_values<T: SlmpCodec>(
+        &mut self,
+        target: SLMPDevice,
+        values: &[T],
+        order: SlmpWordOrder,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_values(
+            target,
+            values,
+            order,
+            self.addr_32bit,
+        )?)
+        .await
+    }
+    /// 1ワードに2文字ずつ格納した文字列を読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `words` - 文字列を格納した領域のワード数
+    /// * `termination` - 終端の扱い
+    pub async fn read_string(
+        &mut self,
+        target: SLMPDevice,
+        words: u16,
+        termination: SlmpStringTermination,
+    ) -> Result<String, SlmpError> {
+        self.run(operation::read_string(
+            target,
+            words,
+            termination,
+            self.addr_32bit,
+        ))
+        .await
+    }
+    /// 1ワードに2文字ずつ文字列を書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `s` - ASCII文字列
+    /// * `words` - 文字列を格納する領域のワード数、余りは終端の扱いに従って埋める
+    /// * `termination` - 終端の扱い
+    pub async fn write_string(
+        &mut self,
+        target: SLMPDevice,
+        s: &str,
+        words: u16,
+        termination: SlmpStringTermination,
+    ) -> Result<(), SlmpError> {
+        let plan = operation::write_string(target, s, words, termination, self.addr_32bit)?;
+        self.run(plan).await
+    }
+    /// フィールドをデバイスに対応付けた構造体を読み取る
+    ///
+    /// 複数ブロック一括読出しで全てのフィールドをまとめて読み取る
+    pub async fn read_struct<T: SlmpStruct>(&mut self) -> Result<T, SlmpError> {
+        self.run(operation::read_struct::<T>(self.addr_32bit)?)
+            .await
+    }
+    /// フィールドをデバイスに対応付けた構造体を書き込む
+    ///
+    /// ワードのフィールドは複数ブロック一括書込み、ビットのフィールドはランダム書込みで書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `value` - 書き込む構造体
+    pub async fn write_struct<T: SlmpStruct>(&mut self, value: &T) -> Result<(), SlmpError> {
+        self.run(operation::write_struct(value, self.addr_32bit)?)
+            .await
+    }
+    /// 単一の連続したビットデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub async fn write_bits(&mut self, targets: &[SLMPDeviceData<bool>]) -> Result<(), SlmpError> {
+        let max_bits = max_batch_bits(self.connection_info.get_data_code());
+        self.run(operation::write_bits(targets, self.addr_32bit, max_bits)?)
+            .await
+    }
+    /// 指定したデバイスをワード単位、ダブルワード単位で読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードアクセスするデバイスのリスト
+    /// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+    ///
+    /// # 返値
+    ///
+    /// ワードデータとダブルワードそれぞれのデバイスリスト
+    #[allow(clippy::type_complexity)]
+    pub async fn read_random(
+        &mut self,
+        target_word: &[SLMPDevice],
+        target_dword: &[SLMPDevice],
+    ) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+        self.run(operation::read_random(
+            target_word,
+            target_dword,
+            self.addr_32bit,
+        )?)
+        .await
+    }
+    /// 指定したワードデバイス、ダブルワードデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードデバイスデータのリスト
+    /// * `target_dword` - ダブルワードデバイスデータのリスト
+    pub async fn write_random_words(
+        &mut self,
+        target_word: &[SLMPDeviceData<u16>],
+        target_dword: &[SLMPDeviceData<u32>],
+    ) -> Result<(), SlmpError> {
+        let plan = operation::write_random_words(target_word, target_dword, self.addr_32bit)?;
+        self.run(plan).await
+    }
+    /// 指定したビットデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - ビットデバイスデータのリスト
+    pub async fn write_random_bits(
+        &mut self,
+        targets: &[SLMPDeviceData<bool>],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_random_bits(targets, self.addr_32bit)?)
+            .await
+    }
+    /// 複数の連続したデバイス(デバイスブロック)を読み取る
+    ///
+    /// 一度に読み出せるブロック数、点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードアクセスするデバイスブロックのリスト
+    /// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+    ///
+    /// # 返値
+    ///
+    /// データを含んだデバイスブロックのリスト
+    #[allow(clippy::type_complexity)]
+    pub async fn read_blocks(
+        &mut self,
+        target_word: &[SLMPDeviceBlock],
+        target_bit: &[SLMPDeviceBlock],
+    ) -> Result<
+        (
+            Vec<SLMPDeviceBlockData<u16>>,
+            Vec<SLMPDeviceBlockData<bool>>,
+        ),
+        SlmpError,
+    > {
+        self.run(operation::read_blocks(
+            target_word,
+            target_bit,
+            self.addr_32bit,
+        )?)
+        .await
+    }
+    /// 複数の連続したデバイス(デバイスブロック)へ書き込む
+    ///
+    /// 一度に書き込めるブロック数、点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
+    /// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
+    pub async fn write_blocks(
+        &mut self,
+        target_word: &[SLMPDeviceBlockData<u16>],
+        target_bit: &[SLMPDeviceBlockData<bool>],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_blocks(
+            target_word,
+            target_bit,
+            self.addr_32bit,
+        )?)
+        .await
+    }
+    /// ラベルをランダムに読み取る
+    ///
+    /// ラベル名には配列の要素、構造体のメンバ、`%1`のような省略ラベル名の参照を含めることができる
+    ///
+    /// # 引数
+    ///
+    /// * `names` - ラベル名のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルのデータ
+    pub async fn read_labels(
+        &mut self,
+        names: &[&str],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpLabelData>, SlmpError> {
+        self.run(operation::read_labels(names, abbreviations)?)
+            .await
+    }
+    /// ラベルをランダムに読み取り、データ型に従った値にする
+    ///
+    /// # 引数
+    ///
+    /// * `names` - ラベル名のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルの値
+    pub async fn read_label_values(
+        &mut self,
+        names: &[&str],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpTagValue>, SlmpError> {
+        self.run(operation::read_label_values(names, abbreviations)?)
+            .await
+    }
+    /// ラベルへランダムに書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - ラベル名と値のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    pub async fn write_labels(
+        &mut self,
+        labels: &[(&str, SlmpTagValue)],
+        abbreviations: &[&str],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_labels(labels, abbreviations)?)
+            .await
+    }
+    /// 配列ラベルを一括で読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - 配列ラベルのリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルのデータ
+    pub async fn read_array_labels(
+        &mut self,
+        labels: &[SlmpArrayLabel],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpLabelData>, SlmpError> {
+        self.run(operation::read_array_labels(labels, abbreviations)?)
+            .await
+    }
+    /// 配列ラベルへ一括で書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - 配列ラベルと書き込むデータのリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    pub async fn write_array_labels(
+        &mut self,
+        labels: &[SlmpArrayLabelData],
+        abbreviations: &[&str],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_array_labels(labels, abbreviations)?)
+            .await
+    }
+    /// ファイル全体を読み出す
+    ///
+    /// ファイルを開き、一度に扱えるバイト数ごとに読み出した後で閉じる
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、2はSDメモリカード、4はデータメモリ
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    ///
+    /// # 返値
+    ///
+    /// ファイルの内容
+    pub async fn download_file(
+        &mut self,
+        drive: u16,
+        path: &str,
+        password: &str,
+    ) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::FileDownload::new(drive, path, password)?)
+            .await
+    }
+    /// ファイル全体を書き込む
+    ///
+    /// 一時ファイルに一度に扱えるバイト数ごとに書き込んだ後、同名のファイルをバックアップに
+    /// コピーしてから置き換え、一時ファイルとバックアップを削除する。
+    /// 失敗した場合は元のファイルを残し、置換えの途中で失敗した場合はバックアップから書き戻す。
+    /// 書き戻せない場合は、パス名に`UPLOAD_TEMP_SUFFIX`と`UPLOAD_BACKUP_SUFFIX`を付けた
+    /// 一時ファイルとバックアップが残る。
+    /// 元のファイルがなくバックアップのみ残っている場合は`SlmpError::InvalidArgument`を返す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、2はSDメモリカード、4はデータメモリ
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    /// * `data` - ファイルの内容
+    pub async fn upload_file(
+        &mut self,
+        drive: u16,
+        path: &str,
+        password: &str,
+        data: &[u8],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::FileUpload::new(drive, path, password, data)?)
+            .await
+    }
+    /// ディレクトリ内の全てのファイルの情報を読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ディレクトリのパス名、空文字列はルートディレクトリ
+    ///
+    /// # 返値
+    ///
+    /// ファイルの情報のリスト
+    pub async fn read_dir(
+        &mut self,
+        drive: u16,
+        path: &str,
+    ) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        self.run(operation::ReadDir::new(drive, path)).await
+    }
+    /// ファイルを削除する
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    pub async fn delete_file(
+        &mut self,
+        drive: u16,
+        path: &str,
+        password: &str,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::delete_file(drive, path, password)?)
+            .await
+    }
+    /// ドライブ内の全てのファイルの情報をファイル番号の順に読み出す(Qシリーズ用)
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、0はプログラムメモリ、3は標準RAM、4は標準ROM
+    ///
+    /// # 返値
+    ///
+    /// ファイルの情報のリスト
+    pub async fn read_file_info(&mut self, drive: u16) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        self.run(operation::ReadFileInfo::new(drive)).await
+    }
+    /// ファイル名を指定してファイル全体を読み出す(Qシリーズ用)
+    ///
+    /// ファイル番号を検索し、一度に扱えるバイト数ごとに読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `name` - ファイル名、`MAIN.QPG`のような8.3形式
+    /// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+    ///
+    /// # 返値
+    ///
+    /// ファイルの内容
+    pub async fn download_legacy_file(
+        &mut self,
+        drive: u16,
+        name: &str,
+        keyword: &str,
+    ) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::LegacyFileDownload::new(drive, name, keyword))
+            .await
+    }
+    /// 自局のデュアルポートメモリを読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `start_addr` - 先頭アドレス
+    /// * `word_length` - ワード長
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったメモリの内容
+    pub async fn read_memory(
+        &mut self,
+        start_addr: u32,
+        word_length: u16,
+    ) -> Result<Vec<u16>, SlmpError> {
+        self.run(operation::read_memory(start_addr, word_length)?)
+            .await
+    }
+    /// 自局のデュアルポートメモリへ書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `start_addr` - 先頭アドレス
+    /// * `data` - 書き込みデータ
+    pub async fn write_memory(&mut self, start_addr: u32, data: &[u16]) -> Result<(), SlmpError> {
+        self.run(operation::write_memory(start_addr, data)?).await
+    }
+    /// リモートRUN
+    ///
+    /// # 引数
+    ///
+    /// * `force` - 強制実行するかどうか
+    /// * `clear_mode` - クリアモード
+    pub async fn remote_run(
+        &mut self,
+        force: bool,
+        clear_mode: SLMPClearMode,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::remote_run(force, clear_mode)).await
+    }
+    /// リモートSTOP
+    pub async fn remote_stop(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_stop()).await
+    }
+    /// リモートPAUSE
+    ///
+    /// # 引数
+    ///
+    /// * `force` - 強制実行するかどうか
+    pub async fn remote_pause(&mut self, force: bool) -> Result<(), SlmpError> {
+        self.run(operation::remote_pause(force)).await
+    }
+    /// リモートラッチクリア
+    pub async fn remote_latch_clear(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_latch_clear()).await
+    }
+    /// リモートRESET
+    pub async fn remote_reset(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_reset()).await
+    }
+    /// リモートパスワードによるロックを解除する
+    ///
+    /// 再接続後も解除を続けるには[AsyncSLMPConnectionInfo::set_remote_password]を使う
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub async fn remote_password_unlock(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::remote_password_unlock(password, format)?)
+            .await
+    }
+    /// リモートパスワードでロックする
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub async fn remote_password_lock(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::remote_password_lock(password, format)?)
+            .await
+    }
+    /// ドライブのメモリ使用状況を読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `head` - 先頭クラスタ番号
+    /// * `count` - クラスタ数、16の倍数
+    ///
+    /// # 返値
+    ///
+    /// ドライブ全体の空きクラスタ数と読み出した範囲のクラスタごとの使用状況、
+    /// ドライブの容量やファイル数は含まない
+    pub async fn read_disk_state(
+        &mut self,
+        drive: u16,
+        head: u16,
+        count: u16,
+    ) -> Result<SlmpDiskState, SlmpError> {
+        self.run(operation::read_disk_state(drive, head, count)?)
+            .await
+    }
+    /// ドライブをデフラグし、連続した空き領域を増やす
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    pub async fn defrag(&mut self, drive: u16) -> Result<(), SlmpError> {
+        self.run(operation::defrag(drive)).await
+    }
+    /// 形名読み出し
+    ///
+    /// # 返値
+    ///
+    /// 形名と形名コード
+    pub async fn read_type_name(&mut self) -> Result<(String, u16), SlmpError> {
+        self.run(operation::read_type_name()).await
+    }
+    /// セルフテスト
+    ///
+    /// # 引数
+    ///
+    /// * `data` - 折り返しチェックのための文字列データ、0-9もしくはA-Zのみが使用可能
+    ///
+    /// # 返値
+    ///
+    /// 折り返されたデータ
+    pub async fn self_test(&mut self, data: &[u8]) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::self_test(data)?).await
+    }
+    /// エラークリア
+    pub async fn clear_error(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_error()).await
+    }
+    /// エラー履歴をクリアする
+    pub async fn clear_error_history(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_error_history()).await
+    }
+    /// イベント履歴の件数を読み出す
+    pub async fn read_event_count(&mut self) -> Result<u32, SlmpError> {
+        self.run(operation::read_event_count()).await
+    }
+    /// イベント履歴を全て読み出す
+    ///
+    /// 件数を読み出した後、一度に扱える件数ごとに読み出す
+    ///
+    /// # 返値
+    ///
+    /// 新しい順のイベント履歴
+    pub async fn read_event_history(&mut self) -> Result<Vec<SlmpEvent>, SlmpError> {
+        self.run(operation::ReadEventHistory::new()).await
+    }
+    /// イベント履歴をクリアする
+    pub async fn clear_event_history(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_event_history()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SLMPDeviceCode;
+
+    #[tokio::test]
+    async fn test_async_read_words() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            let (size, from) = socket.recv_from(&mut buf).await.unwrap();
+            // D100から2点のワード読み出し要求
+            assert_eq!(
+                &buf[15..size],
+                &[0x01, 0x04, 0x00, 0x00, 0x64, 0x00, 0x00, 0xa8, 0x02, 0x00]
+            );
+            let res = [
+                0xd4, 0x00, buf[2], buf[3], 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x06, 0x00,
+                0x00, 0x00, 0x34, 0x12, 0x02, 0x00,
+            ];
+            socket.send_to(&res, from).await.unwrap();
+        });
+        let mut client =
+            AsyncSlmpClient::new(AsyncSLMPConnectionInfo::new_udp(addr).await.unwrap());
+        client.set_timeout(4);
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let ret = client.read_words(dev, 2).await.unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+    }
+
+    #[tokio::test]
+    async fn test_async_timeout() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let mut client =
+            AsyncSlmpClient::new(AsyncSLMPConnectionInfo::new_udp(addr).await.unwrap());
+        client.set_timeout(1);
+        assert!(matches!(
+            client.clear_error().await,
+            Err(SlmpError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_async_split_and_file_transfer() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = AsyncSLMPConnectionInfo::new_tcp(server.local_addr())
+            .await
+            .unwrap();
+        let mut client = AsyncSlmpClient::new(connection_info);
+        let d = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr,
+        };
+        let m = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::M,
+            addr,
+        };
+
+        let words: Vec<SLMPDeviceData<u16>> = (0..2000)
+            .map(|i| SLMPDeviceData {
+                dev: d(i),
+                value: i as u16,
+            })
+            .collect();
+        client.write_words(&words).await.unwrap();
+        assert_eq!(client.read_words(d(0), 2000).await.unwrap(), words);
+        let bits: Vec<SLMPDeviceData<bool>> = (0..7201)
+            .map(|i| SLMPDeviceData {
+                dev: m(i),
+                value: i % 3 == 0,
+            })
+            .collect();
+        client.write_bits(&bits).await.unwrap();
+        assert_eq!(client.read_bits(m(0), 7201).await.unwrap(), bits);
+
+        let data: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        client.upload_file(2, "LOG/A.CSV", "", &data).await.unwrap();
+        assert_eq!(
+            client.download_file(2, "LOG/A.CSV", "").await.unwrap(),
+            data
+        );
+        client.delete_file(2, "LOG/A.CSV", "").await.unwrap();
+        assert!(server.state().files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_reconnect_and_unlock() {
+        use crate::{SlmpConnectionState, SlmpServer};
+        use std::net::{IpAddr, Ipv4Addr};
+        use std::sync::{Arc, Mutex};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().remote_password = Some("secret123".to_string());
+        let d = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 0,
+        };
+        let mut connection_info = AsyncSLMPConnectionInfo::new_tcp_with_policy(
+            server.local_addr(),
+            ReconnectPolicy::new(1),
+        )
+        .await
+        .unwrap();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let log = states.clone();
+        connection_info.set_state_callback(move |state| log.lock().unwrap().push(state));
+        connection_info
+            .set_remote_password("secret123", SlmpPasswordFormat::IqR)
+            .await
+            .unwrap();
+        let mut client = AsyncSlmpClient::new(connection_info);
+        client.set_timeout(4);
+        client.read_words(d, 1).await.unwrap();
+
+        // 再接続しても自動で解除する
+        client.connection_info_mut().reconnect().await.unwrap();
+        assert_eq!(client.connection_info().get_reconnect_count(), 1);
+        client.read_words(d, 1).await.unwrap();
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                SlmpConnectionState::Reconnecting { attempt: 1 },
+                SlmpConnectionState::Connected,
+            ]
+        );
+
+        // 接続自身が送信する要求も設定したタイムアウトで打ち切る
+        server.state().drop_requests = 1;
+        client.set_timeout(1);
+        let start = Instant::now();
+        assert!(matches!(
+            client
+                .connection_info_mut()
+                .set_remote_password("secret123", SlmpPasswordFormat::IqR)
+                .await,
+            Err(SlmpError::Timeout)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        client
+            .connection_info_mut()
+            .set_remote_password("secret123", SlmpPasswordFormat::IqR)
+            .await
+            .unwrap();
+        client.into_inner().lock_and_close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_async_retry_on_timeout() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let server =
+            SlmpServer::start_udp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 0,
+        };
+        server.state().devices.set_word(dev, 0x1234);
+        server.state().drop_requests = 1;
+        let connection_info = AsyncSLMPConnectionInfo::new_udp(server.local_addr())
+            .await
+            .unwrap();
+        let mut client = AsyncSlmpClient::new(connection_info);
+        client.set_timeout(1);
+        client.set_retry_policy(RetryPolicy {
+            max_retries: 1,
+            retry_on_timeout: true,
+        });
+        assert_eq!(client.read_words(dev, 1).await.unwrap()[0].value, 0x1234);
+    }
+
+    #[tokio::test]
+    async fn test_async_3e_late_response_after_timeout() {
+        use crate::{SLMPFrameType, SlmpServer};
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let any = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        for &tcp in &[false, true] {
+            let server = if tcp {
+                SlmpServer::start_tcp(any).unwrap()
+            } else {
+                SlmpServer::start_udp(any).unwrap()
+            };
+            let d = |addr| SLMPDevice {
+                d_code: SLMPDeviceCode::D,
+                addr,
+            };
+            server.state().devices.set_word(d(0), 1);
+            server.state().devices.set_word(d(1), 2);
+            // 最初の要求の応答がタイムアウトの後に届く
+            server.state().delay_requests = 1;
+            server.state().response_delay = Duration::from_millis(400);
+            let mut connection_info = if tcp {
+                AsyncSLMPConnectionInfo::new_tcp(server.local_addr()).await
+            } else {
+                AsyncSLMPConnectionInfo::new_udp(server.local_addr()).await
+            }
+            .unwrap();
+            connection_info.set_frame_type(SLMPFrameType::Frame3E);
+            let mut client = AsyncSlmpClient::new(connection_info);
+            client.set_timeout(1);
+            assert!(matches!(
+                client.read_words(d(0), 1).await,
+                Err(SlmpError::Timeout)
+            ));
+            // 遅れた応答を次の要求の応答と取り違えない
+            assert_eq!(client.read_words(d(1), 1).await.unwrap()[0].value, 2);
+            assert_eq!(client.read_words(d(0), 1).await.unwrap()[0].value, 1);
+        }
+    }
+}
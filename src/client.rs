@@ -0,0 +1,1181 @@
+use crate::operation::{self, Operation};
+use crate::split::max_batch_bits;
+use crate::{
+    RetryPolicy, SLMPClearMode, SLMPConnectionInfo, SLMPDevice, SLMPDeviceBlock,
+    SLMPDeviceBlockData, SLMPDeviceData, SLMPRequest, SlmpArrayLabel, SlmpArrayLabelData,
+    SlmpCodec, SlmpDiskState, SlmpError, SlmpEvent, SlmpFileEntry, SlmpLabelData,
+    SlmpPasswordFormat, SlmpStringTermination, SlmpStruct, SlmpTagValue, SlmpWordOrder,
+};
+use std::time::{Duration, Instant};
+
+/// 要求の送信から応答の受信、デコードまでを一度に行うSLMPクライアント
+pub struct SlmpClient {
+    /// SLMP接続情報
+    connection_info: SLMPConnectionInfo,
+    /// SLMPコマンドのタイムアウト、単位は250ms
+    timeout: u16,
+    /// 32bitアドレス版のコマンドを使用するか
+    addr_32bit: bool,
+    /// 冪等な要求の再送方針
+    retry_policy: RetryPolicy,
+}
+
+impl SlmpClient {
+    /// SLMP接続情報からクライアントを作成
+    ///
+    /// # 引数
+    ///
+    /// * `connection_info` - SLMP接続情報
+    pub fn new(connection_info: SLMPConnectionInfo) -> SlmpClient {
+        SlmpClient {
+            connection_info,
+            timeout: 40,
+            addr_32bit: false,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+    pub fn get_timeout(&self) -> u16 {
+        self.timeout
+    }
+    /// SLMPコマンドのタイムアウトを設定する
+    ///
+    /// 接続自身が送信する要求のタイムアウト([SLMPConnectionInfo::set_request_timeout])も合わせて設定する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - タイムアウト時間、単位は250ms、0でタイムアウトなし
+    pub fn set_timeout(&mut self, timeout: u16) {
+        self.timeout = timeout;
+        self.connection_info.set_request_timeout(timeout);
+    }
+    pub fn get_addr_32bit(&self) -> bool {
+        self.addr_32bit
+    }
+    pub fn set_addr_32bit(&mut self, addr_32bit: bool) {
+        self.addr_32bit = addr_32bit
+    }
+    pub fn get_retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy
+    }
+    pub fn connection_info(&self) -> &SLMPConnectionInfo {
+        &self.connection_info
+    }
+    pub fn connection_info_mut(&mut self) -> &mut SLMPConnectionInfo {
+        &mut self.connection_info
+    }
+    /// クライアントを破棄してSLMP接続情報を取り出す
+    pub fn into_inner(self) -> SLMPConnectionInfo {
+        self.connection_info
+    }
+    /// 要求を送信し、対応する応答を待つ
+    ///
+    /// 以前の要求に対する遅れた応答は読み捨てる。
+    /// 冪等な要求は通信エラーの際に再送方針に従って再送する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 応答のペイロード
+    pub fn request(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let mut retries = 0;
+        loop {
+            match self.request_once(req) {
+                Err(e) if self.retry_policy.should_retry(req, &e, retries) => retries += 1,
+                r => return r,
+            }
+        }
+    }
+    fn request_once(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let serial = self.connection_info.send_request(self.timeout, req)?;
+        let deadline = Instant::now() + Duration::from_millis(self.timeout as u64 * 250);
+        loop {
+            match self.connection_info.recv_response(serial) {
+                Err(SlmpError::SerialMismatch { .. }) => {
+                    if self.timeout != 0 && Instant::now() > deadline {
+                        return Err(SlmpError::Timeout);
+                    }
+                }
+                r => return r,
+            }
+        }
+    }
+    /// 入出力を伴わない処理の要求を順に送信し、応答を渡して結果を得る
+    ///
+    /// # 引数
+    ///
+    /// * `op` - 処理
+    fn run<O: Operation>(&mut self, mut op: O) -> Result<O::Output, SlmpError> {
+        while let Some(req) = op.next_request()? {
+            let res = self.request(&req);
+            op.on_response(res)?;
+        }
+        op.finish()
+    }
+    /// 単一の連続したワードデバイスを読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - デバイス個数
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub fn read_words(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<u16>>, SlmpError> {
+        self.run(operation::read_words(target, count, self.addr_32bit))
+    }
+    /// 単一の連続したビットデバイスを読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - デバイス個数
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub fn read_bits(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<bool>>, SlmpError> {
+        let max_bits = max_batch_bits(self.connection_info.get_data_code());
+        self.run(operation::read_bits(
+            target,
+            count,
+            self.addr_32bit,
+            max_bits,
+        )?)
+    }
+    /// 単一の連続したワードデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub fn write_words(&mut self, targets: &[SLMPDeviceData<u16>]) -> Result<(), SlmpError> {
+        self.run(operation::write_words(targets, self.addr_32bit)?)
+    }
+    /// 単一の連続したワードデバイスを型付きの値として読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - 値の個数
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    pub fn read_values<T: SlmpCodec>(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+        order: SlmpWordOrder,
+    ) -> Result<Vec<T>, SlmpError> {
+        self.run(operation::read_values(
+            target,
+            count,
+            order,
+            self.addr_32bit,
+        )?)
+    }
+    /// 単一の連続したワードデバイスへ型付きの値を書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `values` - 書き込む値
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    pub fn write_values<T: SlmpCodec>(
+        &mut self,
+        target: SLMPDevice,
+        values: &[T],
+        order: SlmpWordOrder,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_values(
+            target,
+            values,
+            order,
+            self.addr_32bit,
+        )?)
+    }
+    /// 1ワードに2文字ずつ格納した文字列を読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `words` - 文字列を格納した領域のワード数
+    /// * `termination` - 終端の扱い
+    pub fn read_string(
+        &mut self,
+        target: SLMPDevice,
+        words: u16,
+        termination: SlmpStringTermination,
+    ) -> Result<String, SlmpError> {
+        self.run(operation::read_string(
+            target,
+            words,
+            termination,
+            self.addr_32bit,
+        ))
+    }
+    /// 1ワードに2文字ずつ文字列を書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `s` - ASCII文字列
+    /// * `words` - 文字列を格納する領域のワード数、余りは終端の扱いに従って埋める
+    /// * `termination` - 終端の扱い
+    pub fn write_string(
+        &mut self,
+        target: SLMPDevice,
+        s: &str,
+        words: u16,
+        termination: SlmpStringTermination,
+    ) -> Result<(), SlmpError> {
+        let plan = operation::write_string(target, s, words, termination, self.addr_32bit)?;
+        self.run(plan)
+    }
+    /// フィールドをデバイスに対応付けた構造体を読み取る
+    ///
+    /// 複数ブロック一括読出しで全てのフィールドをまとめて読み取る
+    pub fn read_struct<T: SlmpStruct>(&mut self) -> Result<T, SlmpError> {
+        self.run(operation::read_struct::<T>(self.addr_32bit)?)
+    }
+    /// フィールドをデバイスに対応付けた構造体を書き込む
+    ///
+    /// ワードのフィールドは複数ブロック一括書込み、ビットのフィールドはランダム書込みで書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `value` - 書き込む構造体
+    pub fn write_struct<T: SlmpStruct>(&mut self, value: &T) -> Result<(), SlmpError> {
+        self.run(operation::write_struct(value, self.addr_32bit)?)
+    }
+    /// 単一の連続したビットデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub fn write_bits(&mut self, targets: &[SLMPDeviceData<bool>]) -> Result<(), SlmpError> {
+        let max_bits = max_batch_bits(self.connection_info.get_data_code());
+        self.run(operation::write_bits(targets, self.addr_32bit, max_bits)?)
+    }
+    /// 指定したデバイスをワード単位、ダブルワード単位で読み取る
+    ///
+    /// 一度に読み出せる点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードアクセスするデバイスのリスト
+    /// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+    ///
+    /// # 返値
+    ///
+    /// ワードデータとダブルワードそれぞれのデバイスリスト
+    #[allow(clippy::type_complexity)]
+    pub fn read_random(
+        &mut self,
+        target_word: &[SLMPDevice],
+        target_dword: &[SLMPDevice],
+    ) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+        self.run(operation::read_random(
+            target_word,
+            target_dword,
+            self.addr_32bit,
+        )?)
+    }
+    /// 指定したワードデバイス、ダブルワードデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードデバイスデータのリスト
+    /// * `target_dword` - ダブルワードデバイスデータのリスト
+    pub fn write_random_words(
+        &mut self,
+        target_word: &[SLMPDeviceData<u16>],
+        target_dword: &[SLMPDeviceData<u32>],
+    ) -> Result<(), SlmpError> {
+        let plan = operation::write_random_words(target_word, target_dword, self.addr_32bit)?;
+        self.run(plan)
+    }
+    /// 指定したビットデバイスへ書き込む
+    ///
+    /// 一度に書き込める点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - ビットデバイスデータのリスト
+    pub fn write_random_bits(&mut self, targets: &[SLMPDeviceData<bool>]) -> Result<(), SlmpError> {
+        self.run(operation::write_random_bits(targets, self.addr_32bit)?)
+    }
+    /// 複数の連続したデバイス(デバイスブロック)を読み取る
+    ///
+    /// 一度に読み出せるブロック数、点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードアクセスするデバイスブロックのリスト
+    /// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+    ///
+    /// # 返値
+    ///
+    /// データを含んだデバイスブロックのリスト
+    #[allow(clippy::type_complexity)]
+    pub fn read_blocks(
+        &mut self,
+        target_word: &[SLMPDeviceBlock],
+        target_bit: &[SLMPDeviceBlock],
+    ) -> Result<
+        (
+            Vec<SLMPDeviceBlockData<u16>>,
+            Vec<SLMPDeviceBlockData<bool>>,
+        ),
+        SlmpError,
+    > {
+        self.run(operation::read_blocks(
+            target_word,
+            target_bit,
+            self.addr_32bit,
+        )?)
+    }
+    /// 複数の連続したデバイス(デバイスブロック)へ書き込む
+    ///
+    /// 一度に書き込めるブロック数、点数を超える場合は複数の要求に分割する
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
+    /// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
+    pub fn write_blocks(
+        &mut self,
+        target_word: &[SLMPDeviceBlockData<u16>],
+        target_bit: &[SLMPDeviceBlockData<bool>],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_blocks(
+            target_word,
+            target_bit,
+            self.addr_32bit,
+        )?)
+    }
+    /// ラベルをランダムに読み取る
+    ///
+    /// ラベル名には配列の要素、構造体のメンバ、`%1`のような省略ラベル名の参照を含めることができる
+    ///
+    /// # 引数
+    ///
+    /// * `names` - ラベル名のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルのデータ
+    pub fn read_labels(
+        &mut self,
+        names: &[&str],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpLabelData>, SlmpError> {
+        self.run(operation::read_labels(names, abbreviations)?)
+    }
+    /// ラベルをランダムに読み取り、データ型に従った値にする
+    ///
+    /// # 引数
+    ///
+    /// * `names` - ラベル名のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルの値
+    pub fn read_label_values(
+        &mut self,
+        names: &[&str],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpTagValue>, SlmpError> {
+        self.run(operation::read_label_values(names, abbreviations)?)
+    }
+    /// ラベルへランダムに書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - ラベル名と値のリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    pub fn write_labels(
+        &mut self,
+        labels: &[(&str, SlmpTagValue)],
+        abbreviations: &[&str],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_labels(labels, abbreviations)?)
+    }
+    /// 配列ラベルを一括で読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - 配列ラベルのリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    ///
+    /// # 返値
+    ///
+    /// 指定した順のラベルのデータ
+    pub fn read_array_labels(
+        &mut self,
+        labels: &[SlmpArrayLabel],
+        abbreviations: &[&str],
+    ) -> Result<Vec<SlmpLabelData>, SlmpError> {
+        self.run(operation::read_array_labels(labels, abbreviations)?)
+    }
+    /// 配列ラベルへ一括で書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `labels` - 配列ラベルと書き込むデータのリスト
+    /// * `abbreviations` - 省略ラベル名のリスト
+    pub fn write_array_labels(
+        &mut self,
+        labels: &[SlmpArrayLabelData],
+        abbreviations: &[&str],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::write_array_labels(labels, abbreviations)?)
+    }
+    /// ファイル全体を読み出す
+    ///
+    /// ファイルを開き、一度に扱えるバイト数ごとに読み出した後で閉じる
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、2はSDメモリカード、4はデータメモリ
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    ///
+    /// # 返値
+    ///
+    /// ファイルの内容
+    pub fn download_file(
+        &mut self,
+        drive: u16,
+        path: &str,
+        password: &str,
+    ) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::FileDownload::new(drive, path, password)?)
+    }
+    /// ファイル全体を書き込む
+    ///
+    /// 一時ファイルに一度に扱えるバイト数ごとに書き込んだ後、同名のファイルをバックアップに
+    /// コピーしてから置き換え、一時ファイルとバックアップを削除する。
+    /// 失敗した場合は元のファイルを残し、置換えの途中で失敗した場合はバックアップから書き戻す。
+    /// 書き戻せない場合は、パス名に`UPLOAD_TEMP_SUFFIX`と`UPLOAD_BACKUP_SUFFIX`を付けた
+    /// 一時ファイルとバックアップが残る。
+    /// 元のファイルがなくバックアップのみ残っている場合は`SlmpError::InvalidArgument`を返す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、2はSDメモリカード、4はデータメモリ
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    /// * `data` - ファイルの内容
+    pub fn upload_file(
+        &mut self,
+        drive: u16,
+        path: &str,
+        password: &str,
+        data: &[u8],
+    ) -> Result<(), SlmpError> {
+        self.run(operation::FileUpload::new(drive, path, password, data)?)
+    }
+    /// ディレクトリ内の全てのファイルの情報を読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ディレクトリのパス名、空文字列はルートディレクトリ
+    ///
+    /// # 返値
+    ///
+    /// ファイルの情報のリスト
+    pub fn read_dir(&mut self, drive: u16, path: &str) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        self.run(operation::ReadDir::new(drive, path))
+    }
+    /// ファイルを削除する
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    pub fn delete_file(&mut self, drive: u16, path: &str, password: &str) -> Result<(), SlmpError> {
+        self.run(operation::delete_file(drive, path, password)?)
+    }
+    /// ドライブ内の全てのファイルの情報をファイル番号の順に読み出す(Qシリーズ用)
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号、0はプログラムメモリ、3は標準RAM、4は標準ROM
+    ///
+    /// # 返値
+    ///
+    /// ファイルの情報のリスト
+    pub fn read_file_info(&mut self, drive: u16) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        self.run(operation::ReadFileInfo::new(drive))
+    }
+    /// ファイル名を指定してファイル全体を読み出す(Qシリーズ用)
+    ///
+    /// ファイル番号を検索し、一度に扱えるバイト数ごとに読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `name` - ファイル名、`MAIN.QPG`のような8.3形式
+    /// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+    ///
+    /// # 返値
+    ///
+    /// ファイルの内容
+    pub fn download_legacy_file(
+        &mut self,
+        drive: u16,
+        name: &str,
+        keyword: &str,
+    ) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::LegacyFileDownload::new(drive, name, keyword))
+    }
+    /// 自局のデュアルポートメモリを読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `start_addr` - 先頭アドレス
+    /// * `word_length` - ワード長
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったメモリの内容
+    pub fn read_memory(
+        &mut self,
+        start_addr: u32,
+        word_length: u16,
+    ) -> Result<Vec<u16>, SlmpError> {
+        self.run(operation::read_memory(start_addr, word_length)?)
+    }
+    /// 自局のデュアルポートメモリへ書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `start_addr` - 先頭アドレス
+    /// * `data` - 書き込みデータ
+    pub fn write_memory(&mut self, start_addr: u32, data: &[u16]) -> Result<(), SlmpError> {
+        self.run(operation::write_memory(start_addr, data)?)
+    }
+    /// リモートRUN
+    ///
+    /// # 引数
+    ///
+    /// * `force` - 強制実行するかどうか
+    /// * `clear_mode` - クリアモード
+    pub fn remote_run(&mut self, force: bool, clear_mode: SLMPClearMode) -> Result<(), SlmpError> {
+        self.run(operation::remote_run(force, clear_mode))
+    }
+    /// リモートSTOP
+    pub fn remote_stop(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_stop())
+    }
+    /// リモートPAUSE
+    ///
+    /// # 引数
+    ///
+    /// * `force` - 強制実行するかどうか
+    pub fn remote_pause(&mut self, force: bool) -> Result<(), SlmpError> {
+        self.run(operation::remote_pause(force))
+    }
+    /// リモートラッチクリア
+    pub fn remote_latch_clear(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_latch_clear())
+    }
+    /// リモートRESET
+    pub fn remote_reset(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::remote_reset())
+    }
+    /// リモートパスワードによるロックを解除する
+    ///
+    /// 再接続後も解除を続けるには`SLMPConnectionInfo::set_remote_password`を使う
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub fn remote_password_unlock(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::remote_password_unlock(password, format)?)
+    }
+    /// リモートパスワードでロックする
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub fn remote_password_lock(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.run(operation::remote_password_lock(password, format)?)
+    }
+    /// ドライブのメモリ使用状況を読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `head` - 先頭クラスタ番号
+    /// * `count` - クラスタ数、16の倍数
+    ///
+    /// # 返値
+    ///
+    /// ドライブ全体の空きクラスタ数と読み出した範囲のクラスタごとの使用状況、
+    /// ドライブの容量やファイル数は含まない
+    pub fn read_disk_state(
+        &mut self,
+        drive: u16,
+        head: u16,
+        count: u16,
+    ) -> Result<SlmpDiskState, SlmpError> {
+        self.run(operation::read_disk_state(drive, head, count)?)
+    }
+    /// ドライブをデフラグし、連続した空き領域を増やす
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    pub fn defrag(&mut self, drive: u16) -> Result<(), SlmpError> {
+        self.run(operation::defrag(drive))
+    }
+    /// 形名読み出し
+    ///
+    /// # 返値
+    ///
+    /// 形名と形名コード
+    pub fn read_type_name(&mut self) -> Result<(String, u16), SlmpError> {
+        self.run(operation::read_type_name())
+    }
+    /// セルフテスト
+    ///
+    /// # 引数
+    ///
+    /// * `data` - 折り返しチェックのための文字列データ、0-9もしくはA-Zのみが使用可能
+    ///
+    /// # 返値
+    ///
+    /// 折り返されたデータ
+    pub fn self_test(&mut self, data: &[u8]) -> Result<Vec<u8>, SlmpError> {
+        self.run(operation::self_test(data)?)
+    }
+    /// エラークリア
+    pub fn clear_error(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_error())
+    }
+    /// エラー履歴をクリアする
+    pub fn clear_error_history(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_error_history())
+    }
+    /// イベント履歴の件数を読み出す
+    pub fn read_event_count(&mut self) -> Result<u32, SlmpError> {
+        self.run(operation::read_event_count())
+    }
+    /// イベント履歴を全て読み出す
+    ///
+    /// 件数を読み出した後、一度に扱える件数ごとに読み出す
+    ///
+    /// # 返値
+    ///
+    /// 新しい順のイベント履歴
+    pub fn read_event_history(&mut self) -> Result<Vec<SlmpEvent>, SlmpError> {
+        self.run(operation::ReadEventHistory::new())
+    }
+    /// イベント履歴をクリアする
+    pub fn clear_event_history(&mut self) -> Result<(), SlmpError> {
+        self.run(operation::clear_event_history())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SLMPDataCode, SLMPDeviceCode, MAX_FILE_CHUNK};
+    use std::net::UdpSocket;
+
+    #[test]
+    fn test_read_words_skips_stale_response() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            let (_, from) = socket.recv_from(&mut buf).unwrap();
+            let mut stale = vec![
+                0xd4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x04, 0x00, 0x00,
+                0x00, 0xff, 0xff,
+            ];
+            stale[2] = buf[2].wrapping_add(0x80);
+            stale[3] = buf[3];
+            socket.send_to(&stale, from).unwrap();
+            let res = [
+                0xd4, 0x00, buf[2], buf[3], 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x06, 0x00,
+                0x00, 0x00, 0x34, 0x12, 0x02, 0x00,
+            ];
+            socket.send_to(&res, from).unwrap();
+        });
+        let mut client = SlmpClient::new(SLMPConnectionInfo::new_udp(addr).unwrap());
+        client.set_timeout(4);
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let ret = client.read_words(dev, 2).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+        assert_eq!(ret[1].dev.addr, 101);
+    }
+
+    #[test]
+    fn test_read_words_ascii() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            let (size, from) = socket.recv_from(&mut buf).unwrap();
+            assert!(buf[..size].ends_with(b"04010000D*0001000002"));
+            let mut res = b"D40000000000010103FF00000C000012340002".to_vec();
+            // 要求のシリアル番号を応答へ複写
+            res[4..8].copy_from_slice(&buf[4..8]);
+            socket.send_to(&res, from).unwrap();
+        });
+        let mut connection_info = SLMPConnectionInfo::new_udp(addr).unwrap();
+        connection_info.set_data_code(SLMPDataCode::Ascii);
+        let mut client = SlmpClient::new(connection_info);
+        client.set_timeout(4);
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let ret = client.read_words(dev, 2).unwrap();
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+    }
+
+    #[test]
+    fn test_reconnect_and_retry() {
+        use crate::{ReconnectPolicy, SlmpConnectionState};
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::sync::{Arc, Mutex};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            // 16bitアドレス版のワード一括読出しの要求長
+            let mut buf = [0u8; 25];
+            // 最初の接続は要求を受け取った後に切断する
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.read_exact(&mut buf).unwrap();
+            drop(stream);
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.read_exact(&mut buf).unwrap();
+            let res = [
+                0xd4, 0x00, buf[2], buf[3], 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x04, 0x00,
+                0x00, 0x00, 0x34, 0x12,
+            ];
+            stream.write_all(&res).unwrap();
+        });
+        let mut connection_info = SLMPConnectionInfo::new_tcp(addr).unwrap();
+        connection_info.set_reconnect_policy(ReconnectPolicy::new(3));
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let log = states.clone();
+        connection_info.set_state_callback(move |state| log.lock().unwrap().push(state));
+        let mut client = SlmpClient::new(connection_info);
+        client.set_timeout(4);
+        client.set_retry_policy(RetryPolicy {
+            max_retries: 1,
+            retry_on_timeout: false,
+        });
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let ret = client.read_words(dev, 1).unwrap();
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                SlmpConnectionState::Disconnected,
+                SlmpConnectionState::Reconnecting { attempt: 1 },
+                SlmpConnectionState::Connected,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_3e_late_response_after_timeout() {
+        use crate::{SLMPFrameType, SlmpServer};
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let any = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        for &tcp in &[false, true] {
+            let server = if tcp {
+                SlmpServer::start_tcp(any).unwrap()
+            } else {
+                SlmpServer::start_udp(any).unwrap()
+            };
+            let d = |addr| SLMPDevice {
+                d_code: SLMPDeviceCode::D,
+                addr,
+            };
+            server.state().devices.set_word(d(0), 1);
+            server.state().devices.set_word(d(1), 2);
+            // 最初の要求の応答がタイムアウトの後に届く
+            server.state().delay_requests = 1;
+            server.state().response_delay = Duration::from_millis(400);
+            let mut connection_info = if tcp {
+                SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap()
+            } else {
+                SLMPConnectionInfo::new_udp(server.local_addr()).unwrap()
+            };
+            connection_info.set_frame_type(SLMPFrameType::Frame3E);
+            let mut client = SlmpClient::new(connection_info);
+            client.set_timeout(1);
+            assert!(matches!(
+                client.read_words(d(0), 1),
+                Err(SlmpError::Timeout)
+            ));
+            // 遅れた応答を次の要求の応答と取り違えない
+            assert_eq!(client.read_words(d(1), 1).unwrap()[0].value, 2);
+            assert_eq!(client.read_words(d(0), 1).unwrap()[0].value, 1);
+            let reconnect_count = client.connection_info().get_reconnect_count();
+            assert_eq!(reconnect_count, if tcp { 1 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_split_large_requests() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let d = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr,
+        };
+        let m = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::M,
+            addr,
+        };
+
+        let words: Vec<SLMPDeviceData<u16>> = (0..2000)
+            .map(|i| SLMPDeviceData {
+                dev: d(i),
+                value: i as u16,
+            })
+            .collect();
+        client.write_words(&words).unwrap();
+        let ret = client.read_words(d(0), 2000).unwrap();
+        assert_eq!(ret, words);
+
+        let bits: Vec<SLMPDeviceData<bool>> = (0..7201)
+            .map(|i| SLMPDeviceData {
+                dev: m(i),
+                value: i % 3 == 0,
+            })
+            .collect();
+        client.write_bits(&bits).unwrap();
+        let ret = client.read_bits(m(0), 7201).unwrap();
+        assert_eq!(ret, bits);
+
+        let target: Vec<SLMPDevice> = (0..300).map(d).collect();
+        let (ret, _) = client.read_random(&target, &[]).unwrap();
+        assert_eq!(ret, words[..300].to_vec());
+
+        let mut block = SLMPDeviceBlockData::<u16>::new(d(5000), 1000);
+        let values: Vec<u16> = (0..1000).map(|i| i * 2).collect();
+        block.sets(&values);
+        client.write_blocks(&[block], &[]).unwrap();
+        let block = SLMPDeviceBlock {
+            top_device: d(5000),
+            count: 1000,
+        };
+        let bit_block = SLMPDeviceBlock {
+            top_device: m(0),
+            count: 2,
+        };
+        let (ret_word, ret_bit) = client.read_blocks(&[block], &[bit_block]).unwrap();
+        assert_eq!(ret_word[0].values(), &values[..]);
+        assert_eq!(ret_bit[0].values().len(), 32);
+        assert!(ret_bit[0].values()[0]);
+        assert!(!ret_bit[0].values()[1]);
+    }
+
+    #[test]
+    fn test_typed_values() {
+        use crate::{SlmpBcd16, SlmpServer};
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let d = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr,
+        };
+        let order = SlmpWordOrder::LowFirst;
+        client.write_values(d(0), &[1.5f32, -2.0], order).unwrap();
+        assert_eq!(server.state().devices.get_word(d(1)), 0x3fc0);
+        let ret: Vec<f32> = client.read_values(d(0), 2, order).unwrap();
+        assert_eq!(ret, vec![1.5, -2.0]);
+        client
+            .write_values(d(10), &[-70000i32], SlmpWordOrder::HighFirst)
+            .unwrap();
+        let ret: Vec<i32> = client
+            .read_values(d(10), 1, SlmpWordOrder::HighFirst)
+            .unwrap();
+        assert_eq!(ret, vec![-70000]);
+        client
+            .write_values(d(20), &[SlmpBcd16(1999)], order)
+            .unwrap();
+        assert_eq!(server.state().devices.get_word(d(20)), 0x1999);
+        client
+            .write_string(d(30), "HELLO", 4, SlmpStringTermination::Nul)
+            .unwrap();
+        let ret = client
+            .read_string(d(30), 4, SlmpStringTermination::Nul)
+            .unwrap();
+        assert_eq!(ret, "HELLO");
+    }
+
+    #[test]
+    fn test_file_transfer() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let data: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        client.upload_file(2, "LOG/A.CSV", "", &data).unwrap();
+        assert_eq!(
+            server.state().files.get(&(2, "LOG/A.CSV".to_string())),
+            Some(&data)
+        );
+        assert_eq!(client.download_file(2, "LOG/A.CSV", "").unwrap(), data);
+
+        // 既存のファイルは置き換える
+        client.upload_file(2, "LOG/A.CSV", "", b"a,b\n").unwrap();
+        assert_eq!(client.download_file(2, "LOG/A.CSV", "").unwrap(), b"a,b\n");
+
+        for i in 0..4 {
+            client
+                .upload_file(2, &format!("LOG/{}.CSV", i), "", &[])
+                .unwrap();
+        }
+        let names: Vec<String> = client
+            .read_dir(2, "LOG")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["0.CSV", "1.CSV", "2.CSV", "3.CSV", "A.CSV"]);
+        client.delete_file(2, "LOG/A.CSV", "").unwrap();
+        assert!(client.download_file(2, "LOG/A.CSV", "").is_err());
+    }
+
+    #[test]
+    fn test_upload_failure_keeps_file() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        client.upload_file(2, "RECIPE.CSV", "", b"old").unwrap();
+
+        // 2つ目の書込みで失敗する
+        server.state().file_size_limit = Some(MAX_FILE_CHUNK + 100);
+        let data = vec![0x55; MAX_FILE_CHUNK * 2];
+        assert!(matches!(
+            client.upload_file(2, "RECIPE.CSV", "", &data),
+            Err(SlmpError::EndCode { .. })
+        ));
+        assert_eq!(client.download_file(2, "RECIPE.CSV", "").unwrap(), b"old");
+        assert_eq!(server.state().files.len(), 1);
+
+        server.state().file_size_limit = None;
+        client.upload_file(2, "RECIPE.CSV", "", &data).unwrap();
+        assert_eq!(client.download_file(2, "RECIPE.CSV", "").unwrap(), data);
+        assert_eq!(server.state().files.len(), 1);
+    }
+
+    #[test]
+    fn test_upload_copy_failure_restores_file() {
+        use crate::SlmpServer;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        client.upload_file(2, "LOG/RECIPE.CSV", "", b"old").unwrap();
+
+        // 一時ファイルとバックアップは作成できるが、一時ファイルのコピーで容量が不足する
+        let data = vec![0x55; 100];
+        server.state().drive_capacity = Some(data.len() + 6);
+        assert!(matches!(
+            client.upload_file(2, "LOG/RECIPE.CSV", "", &data),
+            Err(SlmpError::EndCode { .. })
+        ));
+        assert_eq!(
+            client.download_file(2, "LOG/RECIPE.CSV", "").unwrap(),
+            b"old"
+        );
+        assert_eq!(server.state().files.len(), 1);
+
+        // 前回の失敗で残った一時ファイルとバックアップは削除して置き換える
+        server.state().drive_capacity = None;
+        for name in &["LOG/RECIPE.CSV.TMP", "LOG/RECIPE.CSV.BAK"] {
+            server
+                .state()
+                .files
+                .insert((2, name.to_string()), vec![0; 10]);
+        }
+        client.upload_file(2, "LOG/RECIPE.CSV", "", &data).unwrap();
+        assert_eq!(client.download_file(2, "LOG/RECIPE.CSV", "").unwrap(), data);
+        assert_eq!(server.state().files.len(), 1);
+
+        // 元のファイルがなくバックアップのみ残る場合は書き込まない
+        server.state().files.clear();
+        server
+            .state()
+            .files
+            .insert((2, "LOG/RECIPE.CSV.BAK".to_string()), b"old".to_vec());
+        assert!(matches!(
+            client.upload_file(2, "LOG/RECIPE.CSV", "", &data),
+            Err(SlmpError::InvalidArgument(_))
+        ));
+        assert_eq!(server.state().files.len(), 1);
+    }
+
+    #[test]
+    fn test_remote_password() {
+        use crate::{ReconnectPolicy, SlmpServer};
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().remote_password = Some("secret123".to_string());
+        let d = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 0,
+        };
+        let mut connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        connection_info.set_reconnect_policy(ReconnectPolicy::new(1));
+        let mut client = SlmpClient::new(connection_info);
+        assert!(matches!(
+            client.read_words(d, 1),
+            Err(SlmpError::EndCode { code: 0xC201, .. })
+        ));
+        assert!(matches!(
+            client
+                .connection_info_mut()
+                .set_remote_password("wrong-pass", SlmpPasswordFormat::IqR),
+            Err(SlmpError::EndCode { code: 0xC810, .. })
+        ));
+        client
+            .connection_info_mut()
+            .set_remote_password("secret123", SlmpPasswordFormat::IqR)
+            .unwrap();
+        client.read_words(d, 1).unwrap();
+
+        // 再接続しても自動で解除する
+        client.connection_info_mut().reconnect().unwrap();
+        client.read_words(d, 1).unwrap();
+
+        client
+            .remote_password_lock("secret123", SlmpPasswordFormat::IqR)
+            .unwrap();
+        assert!(client.read_words(d, 1).is_err());
+        client
+            .remote_password_unlock("secret123", SlmpPasswordFormat::IqR)
+            .unwrap();
+        client.read_words(d, 1).unwrap();
+
+        // 接続自身が送信する要求も設定したタイムアウトで打ち切る
+        server.state().drop_requests = 1;
+        client.set_timeout(1);
+        let start = Instant::now();
+        assert!(matches!(
+            client
+                .connection_info_mut()
+                .set_remote_password("secret123", SlmpPasswordFormat::IqR),
+            Err(SlmpError::Timeout)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        client.into_inner().lock_and_close().unwrap();
+    }
+    #[test]
+    fn test_event_history() {
+        use crate::{SlmpEventType, SlmpServer, SlmpTimestamp};
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let events: Vec<SlmpEvent> = (0..70)
+            .map(|i| SlmpEvent {
+                code: 0x1000 + i,
+                event_type: if i < 3 {
+                    SlmpEventType::Error
+                } else {
+                    SlmpEventType::Info
+                },
+                io_number: 0x3e00,
+                time: SlmpTimestamp {
+                    year: 2024,
+                    month: 1,
+                    day: 2,
+                    hour: 3,
+                    minute: 4,
+                    second: (i / 2) as u8,
+                },
+                detail: vec![i as u8; i as usize % 5],
+            })
+            .collect();
+        server.state().events = events.clone();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        assert_eq!(client.read_event_count().unwrap(), 70);
+        assert_eq!(client.read_event_history().unwrap(), events);
+
+        client.clear_error_history().unwrap();
+        assert_eq!(client.read_event_count().unwrap(), 67);
+        client.clear_event_history().unwrap();
+        assert!(client.read_event_history().unwrap().is_empty());
+    }
+}
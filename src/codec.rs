@@ -0,0 +1,373 @@
+use crate::SlmpError;
+
+/// 複数ワードにまたがる値のワードの並び
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum SlmpWordOrder {
+    /// 下位ワードを小さいアドレスに置く、MELSECの標準
+    #[default]
+    LowFirst,
+    /// 上位ワードを小さいアドレスに置く
+    HighFirst,
+}
+
+/// ワードデバイスに格納した文字列の終端の扱い
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum SlmpStringTermination {
+    /// NUL文字で終端する、書き込みでは終端の分の領域が必要
+    #[default]
+    Nul,
+    /// 終端しない、余りはNUL文字で埋めて読み取りでは末尾のNUL文字を除く
+    Padded,
+    /// 終端しない、余りは空白で埋めて読み取りでは末尾の空白を除く
+    Space,
+}
+
+/// ワードデバイスの値との相互変換
+pub trait SlmpCodec: Sized {
+    /// 一つの値が占めるワード数
+    const WORDS: usize;
+    /// ワードの並びから値を作成
+    ///
+    /// # 引数
+    ///
+    /// * `words` - `WORDS`個のワード
+    /// * `order` - ワードの並び
+    fn decode_words(words: &[u16], order: SlmpWordOrder) -> Result<Self, SlmpError>;
+    /// 値をワードの並びへ変換
+    ///
+    /// # 引数
+    ///
+    /// * `order` - ワードの並び
+    fn encode_words(&self, order: SlmpWordOrder) -> Result<Vec<u16>, SlmpError>;
+}
+
+/// BCDで格納した4桁の値
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct SlmpBcd16(pub u16);
+
+/// BCDで格納した8桁の値
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct SlmpBcd32(pub u32);
+
+impl From<u16> for SlmpBcd16 {
+    fn from(v: u16) -> Self {
+        SlmpBcd16(v)
+    }
+}
+
+impl From<SlmpBcd16> for u16 {
+    fn from(v: SlmpBcd16) -> Self {
+        v.0
+    }
+}
+
+impl From<u32> for SlmpBcd32 {
+    fn from(v: u32) -> Self {
+        SlmpBcd32(v)
+    }
+}
+
+impl From<SlmpBcd32> for u32 {
+    fn from(v: SlmpBcd32) -> Self {
+        v.0
+    }
+}
+
+/// ワードの並びを下位ワードが先の並びとして取り出す
+fn low_first(words: &[u16], n: usize, order: SlmpWordOrder) -> Result<Vec<u16>, SlmpError> {
+    if words.len() != n {
+        return Err(SlmpError::InvalidArgument("wrong number of words"));
+    }
+    let mut ret = words.to_vec();
+    if order == SlmpWordOrder::HighFirst {
+        ret.reverse();
+    }
+    Ok(ret)
+}
+
+/// 下位ワードが先の並びを指定した並びにする
+fn ordered(mut words: Vec<u16>, order: SlmpWordOrder) -> Vec<u16> {
+    if order == SlmpWordOrder::HighFirst {
+        words.reverse();
+    }
+    words
+}
+
+fn to_u64(words: &[u16], n: usize, order: SlmpWordOrder) -> Result<u64, SlmpError> {
+    Ok(low_first(words, n, order)?
+        .iter()
+        .rev()
+        .fold(0u64, |v, w| (v << 16) | *w as u64))
+}
+
+fn from_u64(value: u64, n: usize, order: SlmpWordOrder) -> Vec<u16> {
+    ordered((0..n).map(|i| (value >> (16 * i)) as u16).collect(), order)
+}
+
+fn f32_from_u64(v: u64) -> f32 {
+    f32::from_bits(v as u32)
+}
+
+fn f32_to_u64(v: f32) -> u64 {
+    v.to_bits() as u64
+}
+
+macro_rules! impl_codec {
+    ($t:ty, $n:expr, $from:expr, $to:expr) => {
+        impl SlmpCodec for $t {
+            const WORDS: usize = $n;
+            fn decode_words(words: &[u16], order: SlmpWordOrder) -> Result<Self, SlmpError> {
+                Ok($from(to_u64(words, $n, order)?))
+            }
+            fn encode_words(&self, order: SlmpWordOrder) -> Result<Vec<u16>, SlmpError> {
+                Ok(from_u64($to(*self), $n, order))
+            }
+        }
+    };
+}
+
+impl_codec!(u16, 1, |v| v as u16, |v: u16| v as u64);
+impl_codec!(i16, 1, |v| v as u16 as i16, |v: i16| v as u16 as u64);
+impl_codec!(u32, 2, |v| v as u32, |v: u32| v as u64);
+impl_codec!(i32, 2, |v| v as u32 as i32, |v: i32| v as u32 as u64);
+impl_codec!(u64, 4, |v| v, |v: u64| v);
+impl_codec!(i64, 4, |v| v as i64, |v: i64| v as u64);
+impl_codec!(f32, 2, f32_from_u64, f32_to_u64);
+impl_codec!(f64, 4, f64::from_bits, f64::to_bits);
+
+/// BCDの値を数値へ変換
+///
+/// # 引数
+///
+/// * `bcd` - BCDの値
+/// * `digits` - 桁数
+fn decode_bcd(bcd: u64, digits: usize) -> Result<u64, SlmpError> {
+    (0..digits).rev().try_fold(0u64, |v, i| {
+        let d = (bcd >> (4 * i)) & 0xf;
+        if d > 9 {
+            return Err(SlmpError::MalformedFrame("invalid BCD digit"));
+        }
+        Ok(v * 10 + d)
+    })
+}
+
+/// 数値をBCDの値へ変換
+///
+/// # 引数
+///
+/// * `value` - 数値
+/// * `digits` - 桁数
+fn encode_bcd(mut value: u64, digits: usize) -> Result<u64, SlmpError> {
+    let mut ret = 0;
+    for i in 0..digits {
+        ret |= (value % 10) << (4 * i);
+        value /= 10;
+    }
+    if value != 0 {
+        return Err(SlmpError::InvalidArgument("too large value for BCD"));
+    }
+    Ok(ret)
+}
+
+impl SlmpCodec for SlmpBcd16 {
+    const WORDS: usize = 1;
+    fn decode_words(words: &[u16], order: SlmpWordOrder) -> Result<Self, SlmpError> {
+        Ok(SlmpBcd16(decode_bcd(to_u64(words, 1, order)?, 4)? as u16))
+    }
+    fn encode_words(&self, order: SlmpWordOrder) -> Result<Vec<u16>, SlmpError> {
+        Ok(from_u64(encode_bcd(self.0 as u64, 4)?, 1, order))
+    }
+}
+
+impl SlmpCodec for SlmpBcd32 {
+    const WORDS: usize = 2;
+    fn decode_words(words: &[u16], order: SlmpWordOrder) -> Result<Self, SlmpError> {
+        Ok(SlmpBcd32(decode_bcd(to_u64(words, 2, order)?, 8)? as u32))
+    }
+    fn encode_words(&self, order: SlmpWordOrder) -> Result<Vec<u16>, SlmpError> {
+        Ok(from_u64(encode_bcd(self.0 as u64, 8)?, 2, order))
+    }
+}
+
+/// ワードの並びを値のリストへ変換
+///
+/// # 引数
+///
+/// * `words` - ワードの並び、値のワード数の倍数であること
+/// * `order` - 複数ワードにまたがる値のワードの並び
+///
+/// # 例
+///
+/// ```
+/// use slmp_client::*;
+/// let values: Vec<f32> = decode_values(&[0x0000, 0x3fc0], SlmpWordOrder::LowFirst).unwrap();
+/// assert_eq!(values, vec![1.5]);
+/// ```
+pub fn decode_values<T: SlmpCodec>(
+    words: &[u16],
+    order: SlmpWordOrder,
+) -> Result<Vec<T>, SlmpError> {
+    if words.len() / T::WORDS * T::WORDS != words.len() {
+        return Err(SlmpError::MalformedFrame("wrong number of words"));
+    }
+    words
+        .chunks(T::WORDS)
+        .map(|w| T::decode_words(w, order))
+        .collect()
+}
+
+/// 値のリストをワードの並びへ変換
+///
+/// # 引数
+///
+/// * `values` - 値のリスト
+/// * `order` - 複数ワードにまたがる値のワードの並び
+pub fn encode_values<T: SlmpCodec>(
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<Vec<u16>, SlmpError> {
+    let mut ret = Vec::with_capacity(values.len() * T::WORDS);
+    for v in values.iter() {
+        ret.extend(v.encode_words(order)?);
+    }
+    Ok(ret)
+}
+
+/// 1ワードに2文字ずつ格納した文字列を取り出す
+///
+/// 各ワードの下位バイトが先の文字となる
+///
+/// # 引数
+///
+/// * `words` - 文字列を格納したワードの並び
+/// * `termination` - 終端の扱い
+///
+/// # 例
+///
+/// ```
+/// use slmp_client::*;
+/// let s = decode_string(&[0x4241, 0x0043, 0x4545], SlmpStringTermination::Nul).unwrap();
+/// assert_eq!(s, "ABC");
+/// ```
+pub fn decode_string(
+    words: &[u16],
+    termination: SlmpStringTermination,
+) -> Result<String, SlmpError> {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let bytes = match termination {
+        SlmpStringTermination::Nul => match bytes.iter().position(|b| *b == 0) {
+            Some(pos) => &bytes[..pos],
+            None => return Err(SlmpError::MalformedFrame("no NUL terminator")),
+        },
+        SlmpStringTermination::Padded => {
+            let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |p| p + 1);
+            &bytes[..len]
+        }
+        SlmpStringTermination::Space => {
+            let len = bytes.iter().rposition(|b| *b != b' ').map_or(0, |p| p + 1);
+            &bytes[..len]
+        }
+    };
+    if !bytes.is_ascii() {
+        return Err(SlmpError::MalformedFrame("non-ASCII string"));
+    }
+    Ok(bytes.iter().map(|b| *b as char).collect())
+}
+
+/// 文字列を1ワードに2文字ずつ格納する
+///
+/// # 引数
+///
+/// * `s` - ASCII文字列
+/// * `words` - 格納する領域のワード数
+/// * `termination` - 終端の扱い
+///
+/// # 返値
+///
+/// 余りを埋めた`words`個のワード
+pub fn encode_string(
+    s: &str,
+    words: usize,
+    termination: SlmpStringTermination,
+) -> Result<Vec<u16>, SlmpError> {
+    if !s.is_ascii() {
+        return Err(SlmpError::InvalidArgument("non-ASCII string"));
+    }
+    let (pad, need) = match termination {
+        SlmpStringTermination::Nul => (0, s.len() + 1),
+        SlmpStringTermination::Padded => (0, s.len()),
+        SlmpStringTermination::Space => (b' ', s.len()),
+    };
+    if need > words * 2 {
+        return Err(SlmpError::InvalidArgument("too long string"));
+    }
+    let mut bytes = s.as_bytes().to_vec();
+    if termination == SlmpStringTermination::Nul {
+        bytes.push(0);
+    }
+    bytes.resize(words * 2, pad);
+    Ok(bytes
+        .chunks(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_codec() {
+        let order = SlmpWordOrder::LowFirst;
+        assert_eq!(decode_values::<i16>(&[0xfffe], order).unwrap(), vec![-2]);
+        assert_eq!(
+            decode_values::<i32>(&[0x5678, 0x1234, 0xffff, 0xffff], order).unwrap(),
+            vec![0x12345678, -1]
+        );
+        assert_eq!(
+            encode_values(&[-100000i32], SlmpWordOrder::HighFirst).unwrap(),
+            vec![0xfffe, 0x7960]
+        );
+        let words = encode_values(&[3.25f64], order).unwrap();
+        assert_eq!(words, vec![0, 0, 0, 0x400a]);
+        assert_eq!(decode_values::<f64>(&words, order).unwrap(), vec![3.25]);
+        let words = encode_values(&[-0.5f32], SlmpWordOrder::HighFirst).unwrap();
+        assert_eq!(words, vec![0xbf00, 0x0000]);
+        assert!(decode_values::<u32>(&[1, 2, 3], order).is_err());
+    }
+
+    #[test]
+    fn test_bcd_codec() {
+        let order = SlmpWordOrder::LowFirst;
+        assert_eq!(SlmpBcd16(1234).encode_words(order).unwrap(), vec![0x1234]);
+        assert_eq!(
+            SlmpBcd32(12345678).encode_words(order).unwrap(),
+            vec![0x5678, 0x1234]
+        );
+        assert_eq!(
+            decode_values::<SlmpBcd32>(&[0x5678, 0x1234], order).unwrap(),
+            vec![SlmpBcd32(12345678)]
+        );
+        assert!(SlmpBcd16(10000).encode_words(order).is_err());
+        assert!(SlmpBcd16::decode_words(&[0x12a4], order).is_err());
+    }
+
+    #[test]
+    fn test_string_codec() {
+        let words = encode_string("ABC", 3, SlmpStringTermination::Nul).unwrap();
+        assert_eq!(words, vec![0x4241, 0x0043, 0x0000]);
+        assert!(encode_string("ABCDEF", 3, SlmpStringTermination::Nul).is_err());
+        let words = encode_string("ABCDEF", 3, SlmpStringTermination::Padded).unwrap();
+        assert_eq!(
+            decode_string(&words, SlmpStringTermination::Padded).unwrap(),
+            "ABCDEF"
+        );
+        assert!(decode_string(&words, SlmpStringTermination::Nul).is_err());
+        let words = encode_string("AB", 2, SlmpStringTermination::Space).unwrap();
+        assert_eq!(words, vec![0x4241, 0x2020]);
+        assert_eq!(
+            decode_string(&words, SlmpStringTermination::Space).unwrap(),
+            "AB"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{SLMPDevice, SLMPDeviceCode};
+use std::fmt;
+use std::str::FromStr;
+
+/// デバイス名の解釈に失敗した
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlmpDeviceParseError {
+    /// 空の文字列
+    Empty,
+    /// 該当するデバイスがない
+    UnknownDevice(String),
+    /// デバイス番号が不正
+    InvalidNumber {
+        /// デバイス
+        d_code: SLMPDeviceCode,
+        /// デバイス番号の文字列
+        number: String,
+    },
+}
+
+impl fmt::Display for SlmpDeviceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlmpDeviceParseError::Empty => write!(f, "empty device name"),
+            SlmpDeviceParseError::UnknownDevice(s) => write!(f, "unknown device: {}", s),
+            SlmpDeviceParseError::InvalidNumber { d_code, number } => write!(
+                f,
+                "invalid device number for {}: {:?}",
+                d_code.name(),
+                number
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SlmpDeviceParseError {}
+
+/// GX Worksでのデバイス名と、別名
+///
+/// 長い名前から順に照合する
+fn device_names() -> Vec<(&'static str, SLMPDeviceCode)> {
+    let mut names: Vec<(&'static str, SLMPDeviceCode)> =
+        SLMPDeviceCode::ALL.iter().map(|d| (d.name(), *d)).collect();
+    // Qシリーズでの積算タイマの表記
+    names.extend_from_slice(&[
+        ("SS", SLMPDeviceCode::STS),
+        ("SC", SLMPDeviceCode::STC),
+        ("SN", SLMPDeviceCode::STN),
+    ]);
+    names.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    names
+}
+
+/// デバイス名を解釈する
+///
+/// # 引数
+///
+/// * `s` - デバイス名
+/// * `octal_xy` - X、Yのデバイス番号を8進数とするか
+fn parse_device(s: &str, octal_xy: bool) -> Result<SLMPDevice, SlmpDeviceParseError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(SlmpDeviceParseError::Empty);
+    }
+    let upper = s.to_ascii_uppercase();
+    let mut error = SlmpDeviceParseError::UnknownDevice(s.to_string());
+    for (name, d_code) in device_names() {
+        let number = match upper.strip_prefix(name) {
+            Some(number) => number,
+            None => continue,
+        };
+        let radix = match d_code {
+            SLMPDeviceCode::X | SLMPDeviceCode::Y if octal_xy => 8,
+            d if d.is_hex_addr() => 16,
+            _ => 10,
+        };
+        // 解釈できなければ、より短い名前のデバイスとして試す
+        match u32::from_str_radix(number, radix) {
+            Ok(addr) if !number.starts_with('+') => return Ok(SLMPDevice { d_code, addr }),
+            _ => {
+                if matches!(error, SlmpDeviceParseError::UnknownDevice(_)) {
+                    error = SlmpDeviceParseError::InvalidNumber {
+                        d_code,
+                        number: s[name.len()..].to_string(),
+                    };
+                }
+            }
+        }
+    }
+    Err(error)
+}
+
+impl FromStr for SLMPDevice {
+    type Err = SlmpDeviceParseError;
+
+    /// GX Worksでのデバイス名から作成
+    ///
+    /// X、Y、B、W、SB、SW、DX、DYのデバイス番号は16進数、それ以外は10進数とする
+    ///
+    /// # 例
+    ///
+    /// ```
+    /// use slmp_client::*;
+    /// let dev: SLMPDevice = "X1F".parse().unwrap();
+    /// assert_eq!(dev.d_code, SLMPDeviceCode::X);
+    /// assert_eq!(dev.addr, 0x1f);
+    /// assert_eq!(dev.to_string(), "X1F");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_device(s, false)
+    }
+}
+
+impl SLMPDevice {
+    /// X、Yのデバイス番号を8進数とするFXシリーズのデバイス名から作成
+    ///
+    /// # 引数
+    ///
+    /// * `s` - デバイス名
+    pub fn parse_octal_xy(s: &str) -> Result<SLMPDevice, SlmpDeviceParseError> {
+        parse_device(s, true)
+    }
+    /// X、Yのデバイス番号を8進数としたデバイス名
+    pub fn to_string_octal_xy(&self) -> String {
+        match self.d_code {
+            SLMPDeviceCode::X | SLMPDeviceCode::Y => {
+                format!("{}{:o}", self.d_code.name(), self.addr)
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SLMPDevice {
+    /// GX Worksでのデバイス名
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.d_code.is_hex_addr() {
+            write!(f, "{}{:X}", self.d_code.name(), self.addr)
+        } else {
+            write!(f, "{}{}", self.d_code.name(), self.addr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> (SLMPDeviceCode, u32) {
+        let dev: SLMPDevice = s.parse().unwrap();
+        (dev.d_code, dev.addr)
+    }
+
+    #[test]
+    fn test_parse_device() {
+        assert_eq!(parse("D100"), (SLMPDeviceCode::D, 100));
+        assert_eq!(parse("X1F"), (SLMPDeviceCode::X, 0x1f));
+        assert_eq!(parse("ZR12000"), (SLMPDeviceCode::ZR, 12000));
+        assert_eq!(parse("dx1a"), (SLMPDeviceCode::DX, 0x1a));
+        assert_eq!(parse("LSTN3"), (SLMPDeviceCode::LSTN, 3));
+        assert_eq!(parse("SS5"), (SLMPDeviceCode::STS, 5));
+        assert_eq!(parse("RD10"), (SLMPDeviceCode::RD, 10));
+        assert_eq!(parse("BA0"), (SLMPDeviceCode::B, 0xa0));
+        assert_eq!(parse("SB1F"), (SLMPDeviceCode::SB, 0x1f));
+        let dev = SLMPDevice::parse_octal_xy("X17").unwrap();
+        assert_eq!(dev.addr, 0o17);
+        assert_eq!(dev.to_string_octal_xy(), "X17");
+        assert!(SLMPDevice::parse_octal_xy("X18").is_err());
+    }
+
+    #[test]
+    fn test_parse_device_error() {
+        assert_eq!("".parse::<SLMPDevice>(), Err(SlmpDeviceParseError::Empty));
+        assert!(matches!(
+            "Q10".parse::<SLMPDevice>(),
+            Err(SlmpDeviceParseError::UnknownDevice(_))
+        ));
+        assert_eq!(
+            "D1F".parse::<SLMPDevice>(),
+            Err(SlmpDeviceParseError::InvalidNumber {
+                d_code: SLMPDeviceCode::D,
+                number: String::from("1F"),
+            })
+        );
+        assert!("D".parse::<SLMPDevice>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for d_code in SLMPDeviceCode::ALL.iter() {
+            let dev = SLMPDevice {
+                d_code: *d_code,
+                addr: 0x1234,
+            };
+            assert_eq!(dev.to_string().parse::<SLMPDevice>().unwrap(), dev);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use crate::{
+    decode_read_block_response, decode_string, decode_values, encode_string, encode_values,
+    make_read_block_cmd_16, make_read_block_cmd_32, make_write_block_cmd_16,
+    make_write_block_cmd_32, make_write_random_bits_cmd_16, make_write_random_bits_cmd_32,
+    SLMPConnectionInfo, SLMPDevice, SLMPDeviceBlock, SLMPDeviceBlockData, SLMPDeviceData,
+    SLMPRequest, SlmpCodec, SlmpError, SlmpWordOrder,
+};
+use std::convert::TryInto;
+
+/// 構造体の一つのフィールドが占めるデバイス
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SlmpFieldLayout {
+    /// フィールド名
+    pub name: &'static str,
+    /// 先頭デバイス名
+    pub device: &'static str,
+    /// ワード単位の点数、ビットのフィールドではビット単位の点数
+    pub points: usize,
+    /// ビットデバイスとして読み書きするか
+    pub bit: bool,
+}
+
+/// 一つのフィールドの読み書きする値
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlmpFieldData {
+    /// ワードのフィールドの値
+    Words(Vec<u16>),
+    /// ビットのフィールドの値
+    Bits(Vec<bool>),
+}
+
+/// 構造体のフィールドにできる型
+pub trait SlmpFieldValue: Sized {
+    /// ビットデバイスとして読み書きするか
+    const BIT: bool = false;
+    /// 占める点数
+    ///
+    /// # 引数
+    ///
+    /// * `len` - 文字列の最大文字数
+    fn points(len: usize) -> usize;
+    /// 読み取った値から作成
+    ///
+    /// # 引数
+    ///
+    /// * `data` - 読み取った値
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    /// * `len` - 文字列の最大文字数
+    fn decode_field(
+        data: &SlmpFieldData,
+        order: SlmpWordOrder,
+        len: usize,
+    ) -> Result<Self, SlmpError>;
+    /// 書き込む値を作成
+    ///
+    /// # 引数
+    ///
+    /// * `order` - 複数ワードにまたがる値のワードの並び
+    /// * `len` - 文字列の最大文字数
+    fn encode_field(&self, order: SlmpWordOrder, len: usize) -> Result<SlmpFieldData, SlmpError>;
+}
+
+fn words(data: &SlmpFieldData) -> Result<&[u16], SlmpError> {
+    match data {
+        SlmpFieldData::Words(w) => Ok(w),
+        SlmpFieldData::Bits(_) => Err(SlmpError::MalformedFrame("word field expected")),
+    }
+}
+
+fn bits(data: &SlmpFieldData) -> Result<&[bool], SlmpError> {
+    match data {
+        SlmpFieldData::Bits(b) => Ok(b),
+        SlmpFieldData::Words(_) => Err(SlmpError::MalformedFrame("bit field expected")),
+    }
+}
+
+impl<T: SlmpCodec> SlmpFieldValue for T {
+    fn points(_len: usize) -> usize {
+        T::WORDS
+    }
+    fn decode_field(
+        data: &SlmpFieldData,
+        order: SlmpWordOrder,
+        _len: usize,
+    ) -> Result<Self, SlmpError> {
+        T::decode_words(words(data)?, order)
+    }
+    fn encode_field(&self, order: SlmpWordOrder, _len: usize) -> Result<SlmpFieldData, SlmpError> {
+        Ok(SlmpFieldData::Words(self.encode_words(order)?))
+    }
+}
+
+impl<T: SlmpCodec, const N: usize> SlmpFieldValue for [T; N] {
+    fn points(_len: usize) -> usize {
+        T::WORDS * N
+    }
+    fn decode_field(
+        data: &SlmpFieldData,
+        order: SlmpWordOrder,
+        _len: usize,
+    ) -> Result<Self, SlmpError> {
+        decode_values::<T>(words(data)?, order)?
+            .try_into()
+            .map_err(|_| SlmpError::MalformedFrame("wrong number of words"))
+    }
+    fn encode_field(&self, order: SlmpWordOrder, _len: usize) -> Result<SlmpFieldData, SlmpError> {
+        Ok(SlmpFieldData::Words(encode_values(self, order)?))
+    }
+}
+
+impl SlmpFieldValue for bool {
+    const BIT: bool = true;
+    fn points(_len: usize) -> usize {
+        1
+    }
+    fn decode_field(
+        data: &SlmpFieldData,
+        _order: SlmpWordOrder,
+        _len: usize,
+    ) -> Result<Self, SlmpError> {
+        bits(data)?
+            .first()
+            .copied()
+            .ok_or(SlmpError::MalformedFrame("wrong number of bits"))
+    }
+    fn encode_field(&self, _order: SlmpWordOrder, _len: usize) -> Result<SlmpFieldData, SlmpError> {
+        Ok(SlmpFieldData::Bits(vec![*self]))
+    }
+}
+
+impl<const N: usize> SlmpFieldValue for [bool; N] {
+    const BIT: bool = true;
+    fn points(_len: usize) -> usize {
+        N
+    }
+    fn decode_field(
+        data: &SlmpFieldData,
+        _order: SlmpWordOrder,
+        _len: usize,
+    ) -> Result<Self, SlmpError> {
+        bits(data)?
+            .get(..N)
+            .and_then(|b| b.try_into().ok())
+            .ok_or(SlmpError::MalformedFrame("wrong number of bits"))
+    }
+    fn encode_field(&self, _order: SlmpWordOrder, _len: usize) -> Result<SlmpFieldData, SlmpError> {
+        Ok(SlmpFieldData::Bits(self.to_vec()))
+    }
+}
+
+/// NUL終端の文字列、`len`で最大文字数を指定する
+impl SlmpFieldValue for String {
+    fn points(len: usize) -> usize {
+        len / 2 + 1
+    }
+    fn decode_field(
+        data: &SlmpFieldData,
+        _order: SlmpWordOrder,
+        _len: usize,
+    ) -> Result<Self, SlmpError> {
+        decode_string(words(data)?, crate::SlmpStringTermination::Nul)
+    }
+    fn encode_field(&self, _order: SlmpWordOrder, len: usize) -> Result<SlmpFieldData, SlmpError> {
+        Ok(SlmpFieldData::Words(encode_string(
+            self,
+            Self::points(len),
+            crate::SlmpStringTermination::Nul,
+        )?))
+    }
+}
+
+/// フィールドをPLCのデバイスに対応付けた構造体
+///
+/// `derive`フィーチャーを有効にすると`#[derive(SlmpStruct)]`で実装できる
+///
+/// # 例
+///
+/// ```ignore
+/// use slmp_client::*;
+///
+/// #[derive(SlmpStruct)]
+/// struct Recipe {
+///     #[slmp(device = "D100", ty = "f32")]
+///     speed: f32,
+///     #[slmp(device = "D102", ty = "bcd16")]
+///     count: u16,
+///     #[slmp(device = "D110", len = 16)]
+///     name: String,
+///     #[slmp(device = "M0")]
+///     enabled: bool,
+/// }
+/// ```
+pub trait SlmpStruct: Sized {
+    /// フィールドの並び順のデバイスの配置
+    fn layout() -> Vec<SlmpFieldLayout>;
+    /// フィールドの並び順の値から作成
+    fn decode_fields(fields: &[SlmpFieldData]) -> Result<Self, SlmpError>;
+    /// フィールドの並び順の書き込む値を作成
+    fn encode_fields(&self) -> Result<Vec<SlmpFieldData>, SlmpError>;
+}
+
+/// 構造体を読み書きするデバイスブロック
+///
+/// # 返値
+///
+/// ワードデバイスのブロックとビットデバイスのブロック
+pub(crate) fn struct_blocks<T: SlmpStruct>(
+) -> Result<(Vec<SLMPDeviceBlock>, Vec<SLMPDeviceBlock>), SlmpError> {
+    let mut word = Vec::new();
+    let mut bit = Vec::new();
+    for field in T::layout() {
+        let top_device: SLMPDevice = field
+            .device
+            .parse()
+            .map_err(|_| SlmpError::InvalidArgument("invalid device name in struct layout"))?;
+        if field.bit {
+            bit.push(SLMPDeviceBlock {
+                top_device,
+                count: field.points.div_ceil(16) as u16,
+            });
+        } else {
+            word.push(SLMPDeviceBlock {
+                top_device,
+                count: field.points as u16,
+            });
+        }
+    }
+    Ok((word, bit))
+}
+
+/// 読み取ったデバイスブロックから構造体を作成
+pub(crate) fn struct_from_blocks<T: SlmpStruct>(
+    ret_word: &[SLMPDeviceBlockData<u16>],
+    ret_bit: &[SLMPDeviceBlockData<bool>],
+) -> Result<T, SlmpError> {
+    let mut ret_word = ret_word.iter();
+    let mut ret_bit = ret_bit.iter();
+    let mut fields = Vec::new();
+    for field in T::layout() {
+        let data = if field.bit {
+            ret_bit.next().map(|b| {
+                SlmpFieldData::Bits(b.values().iter().take(field.points).copied().collect())
+            })
+        } else {
+            ret_word
+                .next()
+                .map(|b| SlmpFieldData::Words(b.values().to_vec()))
+        };
+        fields.push(data.ok_or(SlmpError::MalformedFrame("missing block"))?);
+    }
+    T::decode_fields(&fields)
+}
+
+/// ワードのフィールドのデバイスブロックと、ビットのフィールドのデバイス
+type StructWriteTargets = (Vec<SLMPDeviceBlockData<u16>>, Vec<SLMPDeviceData<bool>>);
+
+/// 構造体を書き込むデバイスのリスト
+///
+/// # 返値
+///
+/// ワードのフィールドのデバイスブロックと、ビットのフィールドのデバイス
+pub(crate) fn struct_to_blocks<T: SlmpStruct>(value: &T) -> Result<StructWriteTargets, SlmpError> {
+    let (word, bit) = struct_blocks::<T>()?;
+    let mut word = word.into_iter();
+    let mut bit = bit.into_iter();
+    let mut ret_word = Vec::new();
+    let mut ret_bit = Vec::new();
+    for data in value.encode_fields()? {
+        match data {
+            SlmpFieldData::Words(w) => {
+                let block = word
+                    .next()
+                    .ok_or(SlmpError::InvalidArgument("too many fields"))?;
+                if w.len() != block.count as usize {
+                    return Err(SlmpError::InvalidArgument("wrong number of words"));
+                }
+                let mut dbd = SLMPDeviceBlockData::<u16>::new(block.top_device, block.count);
+                dbd.sets(&w);
+                ret_word.push(dbd);
+            }
+            SlmpFieldData::Bits(b) => {
+                let block = bit
+                    .next()
+                    .ok_or(SlmpError::InvalidArgument("too many fields"))?;
+                for (i, value) in b.into_iter().enumerate() {
+                    let dev = SLMPDevice {
+                        d_code: block.top_device.d_code,
+                        addr: block.top_device.addr.wrapping_add(i as u32),
+                    };
+                    ret_bit.push(SLMPDeviceData { dev, value });
+                }
+            }
+        }
+    }
+    Ok((ret_word, ret_bit))
+}
+
+/// 構造体の読み取り要求作成(16bitアドレス版)
+/// # 返値
+/// 要求内容
+pub fn make_read_struct_cmd_16<T: SlmpStruct>() -> Result<SLMPRequest, SlmpError> {
+    let (word, bit) = struct_blocks::<T>()?;
+    make_read_block_cmd_16(&word, &bit)
+}
+/// 構造体の読み取り要求送信(16bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_struct_cmd_16<T: SlmpStruct>(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_struct_cmd_16::<T>()?)
+}
+
+/// 構造体の読み取り要求作成(32bitアドレス版)
+/// # 返値
+/// 要求内容
+pub fn make_read_struct_cmd_32<T: SlmpStruct>() -> Result<SLMPRequest, SlmpError> {
+    let (word, bit) = struct_blocks::<T>()?;
+    make_read_block_cmd_32(&word, &bit)
+}
+/// 構造体の読み取り要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_struct_cmd_32<T: SlmpStruct>(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_struct_cmd_32::<T>()?)
+}
+
+/// 構造体の読み取り応答を解釈
+/// # 引数
+/// * `buf` - 応答データ
+/// # 返値
+/// 読み取った構造体
+pub fn decode_read_struct_response<T: SlmpStruct>(buf: &[u8]) -> Result<T, SlmpError> {
+    let (word, bit) = struct_blocks::<T>()?;
+    let (ret_word, ret_bit) = decode_read_block_response(buf, &word, &bit)?;
+    struct_from_blocks(&ret_word, &ret_bit)
+}
+
+/// 構造体の書き込み要求作成(16bitアドレス版)
+///
+/// ビットのフィールドは周囲のビットを書き換えないよう、ビット単位のランダム書込みの要求とする
+/// # 引数
+/// * `value` - 書き込む構造体
+/// # 返値
+/// ワードのフィールドの複数ブロック一括書込み、ビットのフィールドのランダム書込みの要求
+pub fn make_write_struct_cmd_16<T: SlmpStruct>(value: &T) -> Result<Vec<SLMPRequest>, SlmpError> {
+    let (word, bit) = struct_to_blocks(value)?;
+    let mut ret = Vec::new();
+    if !word.is_empty() {
+        ret.push(make_write_block_cmd_16(&word, &[])?);
+    }
+    if !bit.is_empty() {
+        ret.push(make_write_random_bits_cmd_16(&bit)?);
+    }
+    Ok(ret)
+}
+
+/// 構造体の書き込み要求作成(32bitアドレス版)
+///
+/// ビットのフィールドは周囲のビットを書き換えないよう、ビット単位のランダム書込みの要求とする
+/// # 引数
+/// * `value` - 書き込む構造体
+/// # 返値
+/// ワードのフィールドの複数ブロック一括書込み、ビットのフィールドのランダム書込みの要求
+pub fn make_write_struct_cmd_32<T: SlmpStruct>(value: &T) -> Result<Vec<SLMPRequest>, SlmpError> {
+    let (word, bit) = struct_to_blocks(value)?;
+    let mut ret = Vec::new();
+    if !word.is_empty() {
+        ret.push(make_write_block_cmd_32(&word, &[])?);
+    }
+    if !bit.is_empty() {
+        ret.push(make_write_random_bits_cmd_32(&bit)?);
+    }
+    Ok(ret)
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::*;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    #[derive(SlmpStruct, Clone, Debug, PartialEq)]
+    struct Recipe {
+        #[slmp(device = "D100")]
+        speed: f32,
+        #[slmp(device = "D102", ty = "bcd16")]
+        count: u16,
+        #[slmp(device = "D103", ty = "i32", word_order = "high_first")]
+        position: i32,
+        #[slmp(device = "D110", len = 7)]
+        name: String,
+        #[slmp(device = "D120")]
+        limits: [i16; 3],
+        #[slmp(device = "M5")]
+        enabled: bool,
+        #[slmp(device = "M20")]
+        flags: [bool; 3],
+    }
+
+    #[test]
+    fn test_struct_layout() {
+        let layout = Recipe::layout();
+        assert_eq!(layout.len(), 7);
+        assert_eq!(layout[3].points, 4);
+        assert_eq!(layout[4].points, 3);
+        assert!(layout[6].bit);
+        assert_eq!(layout[6].points, 3);
+    }
+
+    #[test]
+    fn test_read_write_struct() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().devices.set_bit("M6".parse().unwrap(), true);
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let recipe = Recipe {
+            speed: 2.5,
+            count: 1234,
+            position: -2,
+            name: String::from("MIXER"),
+            limits: [-1, 0, 1],
+            enabled: true,
+            flags: [true, false, true],
+        };
+        client.write_struct(&recipe).unwrap();
+        {
+            let state = server.state();
+            assert_eq!(state.devices.get_word("D102".parse().unwrap()), 0x1234);
+            assert_eq!(state.devices.get_word("D103".parse().unwrap()), 0xffff);
+            assert_eq!(state.devices.get_word("D104".parse().unwrap()), 0xfffe);
+            // ビットのフィールドの周囲は書き換えない
+            assert!(state.devices.get_bit("M6".parse().unwrap()));
+        }
+        let ret: Recipe = client.read_struct().unwrap();
+        assert_eq!(ret, recipe);
+    }
+}
@@ -1,45 +1,54 @@
 use crate::enums::SLMPCommand;
-use crate::SLMPConnectionInfo;
+use crate::{SLMPConnectionInfo, SLMPRequest, SlmpError};
 
-/// 自局のデュアルポートメモリのデータに対する読み取り要求の送信
+/// 自局のデュアルポートメモリのデータに対する読み取り要求の作成
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `start_addr` - 先頭アドレス
 /// * `word_length` - ワード長
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_read_memory(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
-    start_addr: u32,
-    word_length: u16,
-) -> Option<u16> {
+/// 要求内容
+pub fn make_read_memory(start_addr: u32, word_length: u16) -> Result<SLMPRequest, SlmpError> {
     let mut buf = [0u8; 6];
     buf[0] = start_addr as u8;
     buf[1] = (start_addr >> 8) as u8;
     buf[2] = (start_addr >> 16) as u8;
     buf[3] = (start_addr >> 24) as u8;
     if word_length == 0 || word_length > 480 {
-        return None;
+        return Err(SlmpError::InvalidArgument("word length out of range"));
     }
     buf[4] = word_length as u8;
     buf[5] = (word_length >> 8) as u8;
 
-    connection_info.send_cmd(timeout, SLMPCommand::MemoryRead, 0x00, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::MemoryRead, 0x00, &buf))
+}
+/// 自局のデュアルポートメモリのデータに対する読み取り要求の送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `start_addr` - 先頭アドレス
+/// * `word_length` - ワード長
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_memory(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    start_addr: u32,
+    word_length: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_memory(start_addr, word_length)?)
 }
 
 /// 自局のデュアルポートメモリないし拡張ユニットのメモリのデータに対する読み取り要求に対する応答の処理
 /// # 引数
 /// * `buf` - 応答内容の入ったバッファ
 /// # 返り値
-/// 読み取ったメモリの内容、もしくはエラー内容を含んだ文字列
-pub fn decode_read_memory(buf: &[u8]) -> Result<Vec<u16>, &'static str> {
+/// 読み取ったメモリの内容
+pub fn decode_read_memory(buf: &[u8]) -> Result<Vec<u16>, SlmpError> {
     let mut ret = Vec::new();
     let buf_length = buf.len();
     let mut i = 0;
-    if buf_length < 2 && (buf_length % 2 != 0) {
-        return Err("Few length buf");
+    if buf_length < 2 || buf_length % 2 == 1 {
+        return Err(SlmpError::MalformedFrame("Few length buf"));
     }
     while i < buf_length {
         ret.push((buf[i] as u16) + ((buf[i + 1] as u16) << 8));
@@ -48,118 +57,174 @@ pub fn decode_read_memory(buf: &[u8]) -> Result<Vec<u16>, &'static str> {
     Ok(ret)
 }
 
-/// 自局のデュアルポートメモリのデータに対する書き込み要求の送信
+/// 自局のデュアルポートメモリのデータに対する書き込み要求の作成
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `start_addr` - 先頭アドレス
 /// * `word_length` - ワード長
 /// * `data` - 書き込みデータ
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_write_memory(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
+/// 要求内容
+pub fn make_write_memory(
     start_addr: u32,
     word_length: u16,
     data: &[u16],
-) -> Option<u16> {
-    let mut buf = Vec::new();
-    buf.push(start_addr as u8);
-    buf.push((start_addr >> 8) as u8);
-    buf.push((start_addr >> 16) as u8);
-    buf.push((start_addr >> 24) as u8);
+) -> Result<SLMPRequest, SlmpError> {
     if word_length == 0 || word_length > 480 {
-        return None;
+        return Err(SlmpError::InvalidArgument("word length out of range"));
     }
-    buf.push(word_length as u8);
-    buf.push((word_length >> 8) as u8);
     if (word_length as usize) != data.len() {
-        return None;
+        return Err(SlmpError::InvalidArgument("word length differs from data"));
     }
+    let mut buf = vec![
+        start_addr as u8,
+        (start_addr >> 8) as u8,
+        (start_addr >> 16) as u8,
+        (start_addr >> 24) as u8,
+    ];
+    buf.push(word_length as u8);
+    buf.push((word_length >> 8) as u8);
     for datum in data {
         buf.push(*datum as u8);
         buf.push((*datum >> 8) as u8);
     }
 
-    connection_info.send_cmd(timeout, SLMPCommand::MemoryWrite, 0x00, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::MemoryWrite, 0x00, &buf))
+}
+/// 自局のデュアルポートメモリのデータに対する書き込み要求の送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `start_addr` - 先頭アドレス
+/// * `word_length` - ワード長
+/// * `data` - 書き込みデータ
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_memory(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    start_addr: u32,
+    word_length: u16,
+    data: &[u16],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_memory(start_addr, word_length, data)?)
 }
 
-/// 拡張ユニットのメモリに対してデータの読み取り要求の送信
+/// 拡張ユニットのメモリに対してデータの読み取り要求の作成
 ///
 /// 応答は[decode_read_memory]で処理する
 ///
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `start_addr` - 先頭アドレス
 /// * `byte_length` - バイト数、アドレス単位がワード(2バイト)であることに注意
 /// * `unit_no` - ユニット番号
 /// # 返値
-/// 発行したコマンドのシリアル
+/// 要求内容
 ///
-pub fn send_read_extend_unit_memory(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
+pub fn make_read_extend_unit_memory(
     start_addr: u32,
     byte_length: u16,
     unit_no: u16,
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let mut buf = [0; 8];
     buf[0] = start_addr as u8;
     buf[1] = (start_addr >> 8) as u8;
     buf[2] = (start_addr >> 16) as u8;
     buf[3] = (start_addr >> 24) as u8;
-    if byte_length < 2 || byte_length > 1920 {
-        return None;
+    if !(2..=1920).contains(&byte_length) {
+        return Err(SlmpError::InvalidArgument("byte length out of range"));
     }
     buf[4] = byte_length as u8;
     buf[5] = (byte_length >> 8) as u8;
     buf[6] = unit_no as u8;
     buf[7] = (unit_no >> 8) as u8;
 
-    connection_info.send_cmd(timeout, SLMPCommand::ExtendUnitRead, 0x00, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::ExtendUnitRead, 0x00, &buf))
 }
-
-/// 拡張ユニットのメモリに対してデータの書き込み要求の送信
+/// 拡張ユニットのメモリに対してデータの読み取り要求の送信
+///
+/// 応答は[decode_read_memory]で処理する
+///
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
 /// * `start_addr` - 先頭アドレス
 /// * `byte_length` - バイト数、アドレス単位がワード(2バイト)であることに注意
 /// * `unit_no` - ユニット番号
-/// * `data` - 書き込みデータ
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_write_extend_unit_memory(
+///
+pub fn send_read_extend_unit_memory(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     start_addr: u32,
     byte_length: u16,
     unit_no: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_read_extend_unit_memory(start_addr, byte_length, unit_no)?,
+    )
+}
+
+/// 拡張ユニットのメモリに対してデータの書き込み要求の作成
+/// # 引数
+/// * `start_addr` - 先頭アドレス
+/// * `byte_length` - バイト数、アドレス単位がワード(2バイト)であることに注意
+/// * `unit_no` - ユニット番号
+/// * `data` - 書き込みデータ
+/// # 返値
+/// 要求内容
+pub fn make_write_extend_unit_memory(
+    start_addr: u32,
+    byte_length: u16,
+    unit_no: u16,
     data: &[u16],
-) -> Option<u16> {
-    let mut buf = Vec::new();
-    buf.push(start_addr as u8);
-    buf.push((start_addr >> 8) as u8);
-    buf.push((start_addr >> 16) as u8);
-    buf.push((start_addr >> 24) as u8);
+) -> Result<SLMPRequest, SlmpError> {
     if byte_length == 0 || byte_length > 1920 {
-        return None;
+        return Err(SlmpError::InvalidArgument("byte length out of range"));
     }
+    if (byte_length as usize) != data.len() * 2 {
+        return Err(SlmpError::InvalidArgument("byte length differs from data"));
+    }
+    let mut buf = vec![
+        start_addr as u8,
+        (start_addr >> 8) as u8,
+        (start_addr >> 16) as u8,
+        (start_addr >> 24) as u8,
+    ];
     buf.push(byte_length as u8);
     buf.push((byte_length >> 8) as u8);
     buf.push(unit_no as u8);
     buf.push((unit_no >> 8) as u8);
-    if (byte_length as usize) != data.len() * 2 {
-        return None;
-    }
     for datum in data {
         buf.push(*datum as u8);
         buf.push((*datum >> 8) as u8);
     }
 
-    connection_info.send_cmd(timeout, SLMPCommand::ExtendUnitWrite, 0x00, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::ExtendUnitWrite, 0x00, &buf))
+}
+/// 拡張ユニットのメモリに対してデータの書き込み要求の送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `start_addr` - 先頭アドレス
+/// * `byte_length` - バイト数、アドレス単位がワード(2バイト)であることに注意
+/// * `unit_no` - ユニット番号
+/// * `data` - 書き込みデータ
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_extend_unit_memory(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    start_addr: u32,
+    byte_length: u16,
+    unit_no: u16,
+    data: &[u16],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_write_extend_unit_memory(start_addr, byte_length, unit_no, data)?,
+    )
 }
 
 #[cfg(test)]
@@ -1,5 +1,5 @@
 /// SLMPで定義されているコマンド
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum SLMPCommand {
     // Device
     DeviceRead = 0x0401,
@@ -135,8 +135,36 @@ pub enum SLMPCommand {
     WatchdogCounterOffsetConfig = 0x3211,
 }
 
+impl SLMPCommand {
+    /// 何度実行しても結果の変わらない、再送してよいコマンドか
+    pub fn is_idempotent(&self) -> bool {
+        use SLMPCommand::*;
+        matches!(
+            self,
+            DeviceRead
+                | DeviceReadRandom
+                | ReadBlock
+                | ArrayLabelRead
+                | LabelReadRandom
+                | MemoryRead
+                | ExtendUnitRead
+                | ReadTypeName
+                | ReadDiskState
+                | ReadFileInfo
+                | ReadFileInfoWithTitle
+                | ReadFileNoInfo
+                | FileSearch
+                | ReadDir
+                | SearchDir
+                | SelfTest
+                | GetEventNum
+                | GetEventHistory
+        )
+    }
+}
+
 /// SLMPで定義されているデバイス
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum SLMPDeviceCode {
     SM = 0x91,
     SD = 0xA9,
@@ -178,8 +206,144 @@ pub enum SLMPDeviceCode {
     LCN = 0x56,
 }
 
+impl SLMPDeviceCode {
+    /// 全てのデバイス
+    pub const ALL: [SLMPDeviceCode; 38] = {
+        use SLMPDeviceCode::*;
+        [
+            SM, SD, X, Y, M, L, F, V, B, D, W, TS, TC, TN, LTS, LTC, LTN, STS, STC, STN, LSTS,
+            LSTC, LSTN, CS, CC, CN, SB, SW, DX, DY, Z, LZ, R, ZR, RD, LCS, LCC, LCN,
+        ]
+    };
+    /// デバイスコード番号から対応するデバイスを得る
+    pub fn get(code: u16) -> Option<SLMPDeviceCode> {
+        Self::ALL.iter().find(|d| **d as u16 == code).copied()
+    }
+    /// GX Worksでのデバイス名
+    pub fn name(&self) -> &'static str {
+        self.ascii_name_long().trim_end_matches('*')
+    }
+    /// ASCIIコードでの2文字のデバイスコード
+    ///
+    /// # 返値
+    ///
+    /// デバイスコード、2文字で表せないデバイスは`None`
+    pub fn ascii_name(&self) -> Option<&'static str> {
+        use SLMPDeviceCode::*;
+        let name = match self {
+            SM => "SM",
+            SD => "SD",
+            X => "X*",
+            Y => "Y*",
+            M => "M*",
+            L => "L*",
+            F => "F*",
+            V => "V*",
+            B => "B*",
+            D => "D*",
+            W => "W*",
+            TS => "TS",
+            TC => "TC",
+            TN => "TN",
+            STS => "SS",
+            STC => "SC",
+            STN => "SN",
+            CS => "CS",
+            CC => "CC",
+            CN => "CN",
+            SB => "SB",
+            SW => "SW",
+            DX => "DX",
+            DY => "DY",
+            Z => "Z*",
+            R => "R*",
+            ZR => "ZR",
+            _ => return None,
+        };
+        Some(name)
+    }
+    /// ASCIIコードでの4文字のデバイスコード
+    pub fn ascii_name_long(&self) -> &'static str {
+        use SLMPDeviceCode::*;
+        match self {
+            SM => "SM**",
+            SD => "SD**",
+            X => "X***",
+            Y => "Y***",
+            M => "M***",
+            L => "L***",
+            F => "F***",
+            V => "V***",
+            B => "B***",
+            D => "D***",
+            W => "W***",
+            TS => "TS**",
+            TC => "TC**",
+            TN => "TN**",
+            LTS => "LTS*",
+            LTC => "LTC*",
+            LTN => "LTN*",
+            STS => "STS*",
+            STC => "STC*",
+            STN => "STN*",
+            LSTS => "LSTS",
+            LSTC => "LSTC",
+            LSTN => "LSTN",
+            CS => "CS**",
+            CC => "CC**",
+            CN => "CN**",
+            SB => "SB**",
+            SW => "SW**",
+            DX => "DX**",
+            DY => "DY**",
+            Z => "Z***",
+            LZ => "LZ**",
+            R => "R***",
+            ZR => "ZR**",
+            RD => "RD**",
+            LCS => "LCS*",
+            LCC => "LCC*",
+            LCN => "LCN*",
+        }
+    }
+    /// デバイス番号が16進数で表されるかどうか
+    pub fn is_hex_addr(&self) -> bool {
+        use SLMPDeviceCode::*;
+        matches!(self, X | Y | B | W | SB | SW | DX | DY)
+    }
+    /// ビットデバイスかどうか
+    pub fn is_bit(&self) -> bool {
+        use SLMPDeviceCode::*;
+        matches!(
+            self,
+            SM | X
+                | Y
+                | M
+                | L
+                | F
+                | V
+                | B
+                | TS
+                | TC
+                | LTS
+                | LTC
+                | STS
+                | STC
+                | LSTS
+                | LSTC
+                | CS
+                | CC
+                | SB
+                | DX
+                | DY
+                | LCS
+                | LCC
+        )
+    }
+}
+
 /// SLMPで定義されている終了コード
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum SLMPEndCode {
     Success = 0x00,
     WrongCommand = 0xC059,
@@ -271,24 +435,8 @@ impl FromPrimitive for SLMPEndCode {
     }
 }
 
-impl PartialEq for SLMPCommand {
-    fn eq(&self, other: &Self) -> bool {
-        (*self as u32) == (*other as u32)
-    }
-}
-impl PartialEq for SLMPDeviceCode {
-    fn eq(&self, other: &Self) -> bool {
-        (*self as u32) == (*other as u32)
-    }
-}
-impl PartialEq for SLMPEndCode {
-    fn eq(&self, other: &Self) -> bool {
-        (*self as u32) == (*other as u32)
-    }
-}
-
 /// クリアモード
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum SLMPClearMode {
     /// デバイスをクリアしない
     Not = 0x00,
@@ -298,6 +446,24 @@ pub enum SLMPClearMode {
     AllClear = 0x02,
 }
 
+/// 通信データコード
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SLMPDataCode {
+    /// バイナリコード
+    Binary,
+    /// ASCIIコード
+    Ascii,
+}
+
+/// 通信フレームの種類
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SLMPFrameType {
+    /// 3Eフレーム、シリアル番号を持たない
+    Frame3E,
+    /// 4Eフレーム
+    Frame4E,
+}
+
 use std::io;
 use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpStream, UdpSocket};
@@ -343,17 +509,17 @@ impl TCPorUDP {
     /// 読み取りタイムアウトの設定
     /// # 引数
     ///
-    /// * `timeout` - ミリ秒単位での指定
+    /// * `timeout` - ミリ秒単位での指定、0の場合は無期限に待つ
     ///
-    pub fn set_timeout(&mut self, timeout: u64) {
+    pub fn set_timeout(&mut self, timeout: u64) -> io::Result<()> {
         use TCPorUDP::*;
-        let result = match self {
-            TCP(stream) => stream.set_read_timeout(Some(Duration::from_millis(timeout))),
-            UDP(socket, _) => socket.set_read_timeout(Some(Duration::from_millis(timeout))),
+        let timeout = match timeout {
+            0 => None,
+            t => Some(Duration::from_millis(t)),
         };
-        match result {
-            Ok(_) => (),
-            Err(e) => eprintln!("{}", e),
+        match self {
+            TCP(stream) => stream.set_read_timeout(timeout),
+            UDP(socket, _) => socket.set_read_timeout(timeout),
         }
     }
 }
@@ -0,0 +1,118 @@
+use crate::structs::ErrInfo;
+use crate::SLMPEndCode;
+use std::fmt;
+use std::io;
+
+/// SLMPクライアントで発生するエラー
+#[derive(Debug)]
+pub enum SlmpError {
+    /// ソケット等の入出力エラー
+    Io(io::Error),
+    /// 応答待ちのタイムアウト
+    Timeout,
+    /// 受信したフレームの形式が不正
+    MalformedFrame(&'static str),
+    /// 応答のシリアル番号が要求と一致しない
+    SerialMismatch {
+        /// 要求時のシリアル番号
+        expected: u16,
+        /// 受信したシリアル番号
+        actual: u16,
+    },
+    /// 応答のネットワーク番号が要求と一致しない
+    UnexpectedNetwork { expected: u8, actual: u8 },
+    /// 応答の局番が要求と一致しない
+    UnexpectedNode { expected: u8, actual: u8 },
+    /// 応答のプロセッサ番号が要求と一致しない
+    UnexpectedProcessor { expected: u16, actual: u16 },
+    /// 正常終了以外の終了コードを受信
+    EndCode {
+        /// 応答のシリアル番号
+        serial: u16,
+        /// 終了コード
+        code: u16,
+        /// エラー情報
+        info: Option<ErrInfo>,
+    },
+    /// 引数が不正
+    InvalidArgument(&'static str),
+    /// 接続されていない
+    NotConnected,
+}
+
+impl SlmpError {
+    /// 終了コードのエラーであれば、対応する終了コードを得る
+    pub fn end_code(&self) -> Option<SLMPEndCode> {
+        match self {
+            SlmpError::EndCode { code, .. } => SLMPEndCode::get(*code),
+            _ => None,
+        }
+    }
+    /// エラーが発生した応答のシリアル番号を得る
+    pub fn serial(&self) -> Option<u16> {
+        match self {
+            SlmpError::EndCode { serial, .. } => Some(*serial),
+            SlmpError::SerialMismatch { actual, .. } => Some(*actual),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SlmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlmpError::Io(e) => write!(f, "I/O error: {}", e),
+            SlmpError::Timeout => write!(f, "response timed out"),
+            SlmpError::MalformedFrame(s) => write!(f, "malformed frame: {}", s),
+            SlmpError::SerialMismatch { expected, actual } => write!(
+                f,
+                "serial mismatch: expected {:#06x}, received {:#06x}",
+                expected, actual
+            ),
+            SlmpError::UnexpectedNetwork { expected, actual } => write!(
+                f,
+                "unexpected network number: expected {}, received {}",
+                expected, actual
+            ),
+            SlmpError::UnexpectedNode { expected, actual } => write!(
+                f,
+                "unexpected node number: expected {}, received {}",
+                expected, actual
+            ),
+            SlmpError::UnexpectedProcessor { expected, actual } => write!(
+                f,
+                "unexpected processor number: expected {:#06x}, received {:#06x}",
+                expected, actual
+            ),
+            SlmpError::EndCode { serial, code, .. } => match SLMPEndCode::get(*code) {
+                Some(c) => write!(
+                    f,
+                    "end code {:#06x} ({:?}) for serial {:#06x}",
+                    code, c, serial
+                ),
+                None => write!(f, "end code {:#06x} for serial {:#06x}", code, serial),
+            },
+            SlmpError::InvalidArgument(s) => write!(f, "invalid argument: {}", s),
+            SlmpError::NotConnected => write!(f, "not connected"),
+        }
+    }
+}
+
+impl std::error::Error for SlmpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SlmpError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SlmpError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => SlmpError::Timeout,
+            io::ErrorKind::NotConnected => SlmpError::NotConnected,
+            _ => SlmpError::Io(e),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{SLMPCommand, SLMPConnectionInfo, SLMPRequest, SlmpError, SlmpTimestamp};
+
+/// イベント履歴の読出しで一度に扱える件数
+pub const MAX_EVENT_HISTORY: usize = 32;
+/// イベント履歴の1件あたりの固定部分のバイト数
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// イベントの種別
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpEventType {
+    /// エラー
+    Error,
+    /// 警告
+    Warning,
+    /// 情報
+    Info,
+    /// その他の種別
+    Other(u8),
+}
+
+impl SlmpEventType {
+    /// 種別番号から作成
+    pub fn from_id(id: u8) -> SlmpEventType {
+        use SlmpEventType::*;
+        match id {
+            0 => Error,
+            1 => Warning,
+            2 => Info,
+            id => Other(id),
+        }
+    }
+    /// 種別番号
+    pub fn id(&self) -> u8 {
+        use SlmpEventType::*;
+        match self {
+            Error => 0,
+            Warning => 1,
+            Info => 2,
+            Other(id) => *id,
+        }
+    }
+}
+
+/// イベント履歴の1件
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SlmpEvent {
+    /// イベントコード、エラーであればエラーコード
+    pub code: u16,
+    /// 種別
+    pub event_type: SlmpEventType,
+    /// 発生元ユニットの先頭入出力番号、CPUユニットは0x3E00
+    pub io_number: u16,
+    /// 発生日時
+    pub time: SlmpTimestamp,
+    /// 詳細情報
+    pub detail: Vec<u8>,
+}
+
+/// イベント履歴の1件を追加する
+///
+/// # 引数
+///
+/// * `buf` - 追加先
+/// * `event` - イベント
+#[cfg(any(test, feature = "simulator"))]
+pub(crate) fn push_event(buf: &mut Vec<u8>, event: &SlmpEvent) {
+    buf.extend_from_slice(&event.code.to_le_bytes());
+    buf.push(event.event_type.id());
+    buf.push(0);
+    buf.extend_from_slice(&event.io_number.to_le_bytes());
+    buf.extend_from_slice(&event.time.year.to_le_bytes());
+    buf.push(event.time.month);
+    buf.push(event.time.day);
+    buf.push(event.time.hour);
+    buf.push(event.time.minute);
+    buf.push(event.time.second);
+    buf.push(0);
+    buf.extend_from_slice(&(event.detail.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&event.detail);
+}
+
+/// イベント履歴の件数の読出し要求を作成
+/// # 返値
+/// 要求内容
+pub fn make_get_event_num_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::GetEventNum, 0, &[])
+}
+/// イベント履歴の件数の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_get_event_num_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_get_event_num_cmd())
+}
+/// イベント履歴の件数の読出し応答の処理
+/// # 引数
+/// * `buf` - 応答内容の入ったバッファ
+/// # 返値
+/// イベント履歴の件数
+pub fn decode_get_event_num_response(buf: &[u8]) -> Result<u32, SlmpError> {
+    if buf.len() != 4 {
+        return Err(SlmpError::MalformedFrame(
+            "wrong event number response length",
+        ));
+    }
+    Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+}
+/// イベント履歴の読出し要求を作成
+/// # 引数
+/// * `head` - 読み出す先頭の履歴番号、0が最新
+/// * `count` - 読み出す件数、最大`MAX_EVENT_HISTORY`件
+/// # 返値
+/// 要求内容
+pub fn make_get_event_history_cmd(head: u32, count: u16) -> Result<SLMPRequest, SlmpError> {
+    if count == 0 || count as usize > MAX_EVENT_HISTORY {
+        return Err(SlmpError::InvalidArgument("wrong event history count"));
+    }
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&head.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    Ok(SLMPRequest::new(SLMPCommand::GetEventHistory, 0, &buf))
+}
+/// イベント履歴の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `head` - 読み出す先頭の履歴番号、0が最新
+/// * `count` - 読み出す件数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_get_event_history_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    head: u32,
+    count: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_get_event_history_cmd(head, count)?)
+}
+/// イベント履歴の読出し応答の処理
+///
+/// 応答は件数に続き、1件ごとにイベントコード、種別、発生元、発生日時、詳細情報が並ぶ
+/// # 引数
+/// * `buf` - 応答内容の入ったバッファ
+/// # 返値
+/// 新しい順のイベント履歴
+pub fn decode_get_event_history_response(buf: &[u8]) -> Result<Vec<SlmpEvent>, SlmpError> {
+    if buf.len() < 2 {
+        return Err(SlmpError::MalformedFrame(
+            "too short event history response",
+        ));
+    }
+    let count = buf[0] as usize + ((buf[1] as usize) << 8);
+    let mut pos = 2;
+    let mut ret = Vec::with_capacity(count);
+    for _ in 0..count {
+        if buf.len() < pos + EVENT_HEADER_SIZE {
+            return Err(SlmpError::MalformedFrame(
+                "too short event history response",
+            ));
+        }
+        let b = &buf[pos..pos + EVENT_HEADER_SIZE];
+        let length = b[14] as usize + ((b[15] as usize) << 8);
+        pos += EVENT_HEADER_SIZE;
+        if buf.len() < pos + length {
+            return Err(SlmpError::MalformedFrame(
+                "too short event history response",
+            ));
+        }
+        ret.push(SlmpEvent {
+            code: b[0] as u16 + ((b[1] as u16) << 8),
+            event_type: SlmpEventType::from_id(b[2]),
+            io_number: b[4] as u16 + ((b[5] as u16) << 8),
+            time: SlmpTimestamp {
+                year: b[6] as u16 + ((b[7] as u16) << 8),
+                month: b[8],
+                day: b[9],
+                hour: b[10],
+                minute: b[11],
+                second: b[12],
+            },
+            detail: buf[pos..pos + length].to_vec(),
+        });
+        pos += length;
+    }
+    if pos != buf.len() {
+        return Err(SlmpError::MalformedFrame(
+            "wrong event history response length",
+        ));
+    }
+    Ok(ret)
+}
+/// イベント履歴のクリア要求を作成
+/// # 返値
+/// 要求内容
+pub fn make_clear_event_history_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::ClearEventHistory, 0, &[])
+}
+/// イベント履歴のクリア要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_clear_event_history_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_clear_event_history_cmd())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_history() {
+        let event = SlmpEvent {
+            code: 0x1900,
+            event_type: SlmpEventType::Error,
+            io_number: 0x3e00,
+            time: SlmpTimestamp {
+                year: 2024,
+                month: 5,
+                day: 17,
+                hour: 9,
+                minute: 30,
+                second: 15,
+            },
+            detail: vec![1, 2, 3],
+        };
+        let mut buf = vec![2, 0];
+        push_event(&mut buf, &event);
+        push_event(
+            &mut buf,
+            &SlmpEvent {
+                event_type: SlmpEventType::Other(7),
+                detail: Vec::new(),
+                ..event.clone()
+            },
+        );
+        assert_eq!(&buf[2..8], &[0x00, 0x19, 0, 0, 0x00, 0x3e]);
+        let ret = decode_get_event_history_response(&buf).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0], event);
+        assert_eq!(ret[1].event_type, SlmpEventType::Other(7));
+        assert!(ret[1].detail.is_empty());
+        assert!(decode_get_event_history_response(&buf[..buf.len() - 1]).is_err());
+        assert!(make_get_event_history_cmd(0, MAX_EVENT_HISTORY as u16 + 1).is_err());
+        assert_eq!(decode_get_event_num_response(&[3, 0, 0, 0]).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_event_history_layout() {
+        #[rustfmt::skip]
+        let fixture = [
+            0x01, 0x00,             // 件数
+            0x00, 0x19,             // イベントコード
+            0x00,                   // 種別、エラー
+            0x00,                   // 予約
+            0x00, 0x3e,             // 発生元の先頭入出力番号
+            0xe8, 0x07,             // 年
+            0x05, 0x11,             // 月、日
+            0x09, 0x1e, 0x0f,       // 時、分、秒
+            0x00,                   // 予約
+            0x03, 0x00,             // 詳細情報のバイト数
+            0x01, 0x02, 0x03,       // 詳細情報
+        ];
+        let event = SlmpEvent {
+            code: 0x1900,
+            event_type: SlmpEventType::Error,
+            io_number: 0x3e00,
+            time: SlmpTimestamp {
+                year: 2024,
+                month: 5,
+                day: 17,
+                hour: 9,
+                minute: 30,
+                second: 15,
+            },
+            detail: vec![1, 2, 3],
+        };
+        assert_eq!(
+            decode_get_event_history_response(&fixture).unwrap(),
+            vec![event.clone()]
+        );
+        let mut buf = vec![1, 0];
+        push_event(&mut buf, &event);
+        assert_eq!(buf, fixture);
+    }
+}
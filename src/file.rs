@@ -0,0 +1,708 @@
+use crate::{SLMPCommand, SLMPConnectionInfo, SLMPRequest, SlmpError};
+
+/// ファイル名をUTF-16で指定するサブコマンド
+const SUB_UNICODE: u16 = 0x0040;
+/// ファイルの読出し、書込みで一度に扱えるバイト数
+pub const MAX_FILE_CHUNK: usize = 1920;
+/// ファイル全体の書込み中に使う一時ファイルのパス名の接尾辞
+pub const UPLOAD_TEMP_SUFFIX: &str = ".TMP";
+/// ファイル全体の書込み中に使う元のファイルのバックアップのパス名の接尾辞
+pub const UPLOAD_BACKUP_SUFFIX: &str = ".BAK";
+
+/// ファイルを開く際のモード
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpFileOpenMode {
+    /// 読出し
+    Read = 0x0000,
+    /// 書込み
+    Write = 0x0100,
+}
+
+/// ファイルを閉じる範囲
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpFileCloseType {
+    /// 指定したファイルポインタのファイル
+    Pointer = 0,
+    /// この接続で開いた全てのファイル
+    Connection = 1,
+    /// 全ての接続で開いた全てのファイル
+    All = 2,
+}
+
+/// ファイルの更新日時やイベントの発生日時
+///
+/// ファイルの更新日時はFATのタイムスタンプと同じく、秒は2秒単位、年は1980年から2107年まで
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SlmpTimestamp {
+    /// 西暦年
+    pub year: u16,
+    /// 月、1から12
+    pub month: u8,
+    /// 日、1から31
+    pub day: u8,
+    /// 時、0から23
+    pub hour: u8,
+    /// 分、0から59
+    pub minute: u8,
+    /// 秒、0から59
+    pub second: u8,
+}
+
+impl SlmpTimestamp {
+    /// FAT形式の日付と時刻から作成
+    ///
+    /// # 引数
+    ///
+    /// * `date` - 日付、上位7bitから年(1980年からの経過年)、月、日
+    /// * `time` - 時刻、上位5bitから時、分、秒/2
+    pub fn from_fat(date: u16, time: u16) -> SlmpTimestamp {
+        SlmpTimestamp {
+            year: 1980 + (date >> 9),
+            month: ((date >> 5) & 0x0f) as u8,
+            day: (date & 0x1f) as u8,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3f) as u8,
+            second: ((time & 0x1f) * 2) as u8,
+        }
+    }
+    /// FAT形式の日付と時刻
+    ///
+    /// # 返値
+    ///
+    /// 日付と時刻
+    pub fn to_fat(&self) -> Result<(u16, u16), SlmpError> {
+        if !(1980..=2107).contains(&self.year)
+            || !(1..=12).contains(&self.month)
+            || !(1..=31).contains(&self.day)
+            || self.hour > 23
+            || self.minute > 59
+            || self.second > 59
+        {
+            return Err(SlmpError::InvalidArgument("invalid file timestamp"));
+        }
+        let date = ((self.year - 1980) << 9) | ((self.month as u16) << 5) | self.day as u16;
+        let time =
+            ((self.hour as u16) << 11) | ((self.minute as u16) << 5) | (self.second as u16 / 2);
+        Ok((date, time))
+    }
+}
+
+/// ディレクトリ内のファイルの情報
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SlmpFileEntry {
+    /// ファイル名
+    pub name: String,
+    /// 属性
+    pub attribute: u16,
+    /// 更新日時
+    pub modified: SlmpTimestamp,
+    /// ファイルサイズ、単位はバイト
+    pub size: u32,
+}
+
+impl SlmpFileEntry {
+    /// 読出し専用の属性
+    pub const READ_ONLY: u16 = 0x0001;
+    /// ディレクトリの属性
+    pub const DIRECTORY: u16 = 0x0010;
+
+    /// 読出し専用か
+    pub fn is_read_only(&self) -> bool {
+        self.attribute & Self::READ_ONLY != 0
+    }
+    /// ディレクトリか
+    pub fn is_dir(&self) -> bool {
+        self.attribute & Self::DIRECTORY != 0
+    }
+}
+
+/// パスワードを文字数とASCIIで追加する、空文字列はパスワードなし
+fn push_password(buf: &mut Vec<u8>, password: &str) -> Result<(), SlmpError> {
+    if !password.is_ascii() {
+        return Err(SlmpError::InvalidArgument("non-ASCII file password"));
+    }
+    if password.len() > 32 {
+        return Err(SlmpError::InvalidArgument("too long file password"));
+    }
+    buf.extend_from_slice(&(password.len() as u16).to_le_bytes());
+    buf.extend_from_slice(password.as_bytes());
+    Ok(())
+}
+
+/// パス名を文字数とUTF-16で追加する
+fn push_path(buf: &mut Vec<u8>, path: &str) -> Result<(), SlmpError> {
+    let chars: Vec<u16> = path.encode_utf16().collect();
+    if chars.len() > 252 {
+        return Err(SlmpError::InvalidArgument("too long path name"));
+    }
+    buf.extend_from_slice(&(chars.len() as u16).to_le_bytes());
+    for c in chars {
+        buf.extend_from_slice(&c.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// 応答を読み進める
+pub(crate) struct Reader<'a> {
+    pub(crate) buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], SlmpError> {
+        if self.buf.len() < n {
+            return Err(SlmpError::MalformedFrame("file response too short"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+    pub(crate) fn u16(&mut self) -> Result<u16, SlmpError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+    pub(crate) fn u32(&mut self) -> Result<u32, SlmpError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    pub(crate) fn finish(&self) -> Result<(), SlmpError> {
+        if !self.buf.is_empty() {
+            return Err(SlmpError::MalformedFrame("unexpected file response data"));
+        }
+        Ok(())
+    }
+}
+
+/// ファイルを開く要求を作成
+/// # 引数
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `mode` - 開く際のモード
+/// * `drive` - ドライブ番号、2はSDメモリカード、4はデータメモリ
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_open_file_cmd(
+    password: &str,
+    mode: SlmpFileOpenMode,
+    drive: u16,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_password(&mut buf, password)?;
+    buf.extend_from_slice(&(mode as u16).to_le_bytes());
+    buf.extend_from_slice(&drive.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(SLMPCommand::OpenFile, SUB_UNICODE, &buf))
+}
+/// ファイルを開く要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `mode` - 開く際のモード
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_open_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    mode: SlmpFileOpenMode,
+    drive: u16,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_open_file_cmd(password, mode, drive, path)?)
+}
+/// ファイルを開く応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ファイルポインタ
+pub fn decode_open_file_response(buf: &[u8]) -> Result<u16, SlmpError> {
+    let mut r = Reader { buf };
+    let pointer = r.u16()?;
+    r.finish()?;
+    Ok(pointer)
+}
+/// ファイルの読出し要求を作成
+/// # 引数
+/// * `pointer` - ファイルポインタ
+/// * `offset` - 読み出す位置、単位はバイト
+/// * `size` - 読み出すバイト数
+/// # 返値
+/// 要求内容
+pub fn make_read_file_cmd(pointer: u16, offset: u32, size: u16) -> Result<SLMPRequest, SlmpError> {
+    if size as usize > MAX_FILE_CHUNK {
+        return Err(SlmpError::InvalidArgument("too large file read"));
+    }
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&pointer.to_le_bytes());
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    Ok(SLMPRequest::new(SLMPCommand::ReadFile, 0, &buf))
+}
+/// ファイルの読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `pointer` - ファイルポインタ
+/// * `offset` - 読み出す位置、単位はバイト
+/// * `size` - 読み出すバイト数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    pointer: u16,
+    offset: u32,
+    size: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_file_cmd(pointer, offset, size)?)
+}
+/// ファイルの読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// 読み出したデータ、ファイルの終端ではバイト数が要求より少なくなる
+pub fn decode_read_file_response(buf: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    let mut r = Reader { buf };
+    let size = r.u16()? as usize;
+    let data = r.take(size)?.to_vec();
+    r.finish()?;
+    Ok(data)
+}
+/// ファイルの書込み要求を作成
+/// # 引数
+/// * `pointer` - ファイルポインタ
+/// * `offset` - 書き込む位置、単位はバイト
+/// * `data` - 書き込むデータ
+/// # 返値
+/// 要求内容
+pub fn make_write_file_cmd(
+    pointer: u16,
+    offset: u32,
+    data: &[u8],
+) -> Result<SLMPRequest, SlmpError> {
+    if data.len() > MAX_FILE_CHUNK {
+        return Err(SlmpError::InvalidArgument("too large file write"));
+    }
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&pointer.to_le_bytes());
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    buf.extend_from_slice(data);
+    Ok(SLMPRequest::new(SLMPCommand::WriteFile, 0, &buf))
+}
+/// ファイルの書込み要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `pointer` - ファイルポインタ
+/// * `offset` - 書き込む位置、単位はバイト
+/// * `data` - 書き込むデータ
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    pointer: u16,
+    offset: u32,
+    data: &[u8],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_file_cmd(pointer, offset, data)?)
+}
+/// ファイルの書込み応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// 書き込んだバイト数
+pub fn decode_write_file_response(buf: &[u8]) -> Result<u16, SlmpError> {
+    let mut r = Reader { buf };
+    let size = r.u16()?;
+    r.finish()?;
+    Ok(size)
+}
+/// ファイルを閉じる要求を作成
+/// # 引数
+/// * `pointer` - ファイルポインタ
+/// * `close_type` - 閉じる範囲
+/// # 返値
+/// 要求内容
+pub fn make_close_file_cmd(pointer: u16, close_type: SlmpFileCloseType) -> SLMPRequest {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&pointer.to_le_bytes());
+    buf.extend_from_slice(&(close_type as u16).to_le_bytes());
+    SLMPRequest::new(SLMPCommand::CloseFile, 0, &buf)
+}
+/// ファイルを閉じる要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `pointer` - ファイルポインタ
+/// * `close_type` - 閉じる範囲
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_close_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    pointer: u16,
+    close_type: SlmpFileCloseType,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_close_file_cmd(pointer, close_type))
+}
+/// ファイルの新規作成要求を作成
+/// # 引数
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `size` - ファイルサイズ、単位はバイト
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_new_file_cmd(
+    password: &str,
+    drive: u16,
+    size: u32,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_password(&mut buf, password)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(SLMPCommand::NewFileB, SUB_UNICODE, &buf))
+}
+/// ファイルの新規作成要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `size` - ファイルサイズ、単位はバイト
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_new_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    drive: u16,
+    size: u32,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_new_file_cmd(password, drive, size, path)?)
+}
+/// ファイルの削除要求を作成
+/// # 引数
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_delete_file_cmd(
+    password: &str,
+    drive: u16,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_password(&mut buf, password)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(SLMPCommand::DeleteFile, SUB_UNICODE, &buf))
+}
+/// ファイルの削除要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_delete_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    drive: u16,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_delete_file_cmd(password, drive, path)?)
+}
+/// ファイルのコピー要求を作成
+/// # 引数
+/// * `dst` - コピー先のファイルパスワード、ドライブ番号、パス名
+/// * `src` - コピー元のファイルパスワード、ドライブ番号、パス名
+/// # 返値
+/// 要求内容
+pub fn make_copy_file_cmd(
+    dst: (&str, u16, &str),
+    src: (&str, u16, &str),
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    for (password, drive, path) in [dst, src].iter() {
+        push_password(&mut buf, password)?;
+        buf.extend_from_slice(&drive.to_le_bytes());
+        push_path(&mut buf, path)?;
+    }
+    Ok(SLMPRequest::new(SLMPCommand::CopyFile, SUB_UNICODE, &buf))
+}
+/// ファイルのコピー要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `dst` - コピー先のファイルパスワード、ドライブ番号、パス名
+/// * `src` - コピー元のファイルパスワード、ドライブ番号、パス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_copy_file_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    dst: (&str, u16, &str),
+    src: (&str, u16, &str),
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_copy_file_cmd(dst, src)?)
+}
+/// ファイルの属性変更要求を作成
+/// # 引数
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `attribute` - 属性、[SlmpFileEntry::READ_ONLY]など
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_change_file_state_cmd(
+    password: &str,
+    drive: u16,
+    attribute: u16,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_password(&mut buf, password)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&attribute.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(
+        SLMPCommand::ChangeFileState,
+        SUB_UNICODE,
+        &buf,
+    ))
+}
+/// ファイルの属性変更要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+/// * `drive` - ドライブ番号
+/// * `attribute` - 属性
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_change_file_state_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    drive: u16,
+    attribute: u16,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_change_file_state_cmd(password, drive, attribute, path)?,
+    )
+}
+/// ファイルの更新日時変更要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// * `modified` - 更新日時
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_change_file_date_cmd(
+    drive: u16,
+    modified: &SlmpTimestamp,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let (date, time) = modified.to_fat()?;
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&time.to_le_bytes());
+    buf.extend_from_slice(&date.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(
+        SLMPCommand::ChangeFileDate,
+        SUB_UNICODE,
+        &buf,
+    ))
+}
+/// ファイルの更新日時変更要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `modified` - 更新日時
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_change_file_date_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    modified: &SlmpTimestamp,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_change_file_date_cmd(drive, modified, path)?)
+}
+/// ディレクトリ内のファイル一覧の読出し要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// * `head` - 読み出す先頭のファイル番号、0始まり
+/// * `count` - 読み出すファイル数
+/// * `path` - ディレクトリのパス名、空文字列はルートディレクトリ
+/// # 返値
+/// 要求内容
+pub fn make_read_dir_cmd(
+    drive: u16,
+    head: u32,
+    count: u16,
+    path: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&head.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(SLMPCommand::ReadDir, SUB_UNICODE, &buf))
+}
+/// ディレクトリ内のファイル一覧の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `head` - 読み出す先頭のファイル番号、0始まり
+/// * `count` - 読み出すファイル数
+/// * `path` - ディレクトリのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_dir_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    head: u32,
+    count: u16,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_dir_cmd(drive, head, count, path)?)
+}
+/// ディレクトリ内のファイル一覧の読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ファイルの情報のリスト、ディレクトリの終端では要求より少なくなる
+pub fn decode_read_dir_response(buf: &[u8]) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+    let mut r = Reader { buf };
+    let mut ret = Vec::new();
+    while !r.buf.is_empty() {
+        let length = r.u16()? as usize;
+        let chars: Vec<u16> = r
+            .take(length * 2)?
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let attribute = r.u16()?;
+        let time = r.u16()?;
+        let date = r.u16()?;
+        let size = r.u32()?;
+        ret.push(SlmpFileEntry {
+            name: String::from_utf16_lossy(&chars),
+            attribute,
+            modified: SlmpTimestamp::from_fat(date, time),
+            size,
+        });
+    }
+    Ok(ret)
+}
+/// ディレクトリ内のファイルの検索要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 要求内容
+pub fn make_search_dir_cmd(drive: u16, path: &str) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&drive.to_le_bytes());
+    push_path(&mut buf, path)?;
+    Ok(SLMPRequest::new(SLMPCommand::SearchDir, SUB_UNICODE, &buf))
+}
+/// ディレクトリ内のファイルの検索要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_search_dir_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    path: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_search_dir_cmd(drive, path)?)
+}
+/// ディレクトリ内のファイルの検索応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ディレクトリ内のファイル番号
+pub fn decode_search_dir_response(buf: &[u8]) -> Result<u32, SlmpError> {
+    let mut r = Reader { buf };
+    let number = r.u32()?;
+    r.finish()?;
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_file() {
+        let req = make_open_file_cmd("", SlmpFileOpenMode::Write, 2, "LOG/A.CSV").unwrap();
+        assert_eq!(req.command, SLMPCommand::OpenFile);
+        assert_eq!(req.sub_command, 0x0040);
+        assert_eq!(
+            &req.data[..8],
+            &[0x00, 0x00, 0x00, 0x01, 0x02, 0x00, 0x09, 0x00]
+        );
+        assert_eq!(&req.data[8..12], &[b'L', 0, b'O', 0]);
+        assert_eq!(decode_open_file_response(&[0x05, 0x00]).unwrap(), 5);
+        assert!(make_open_file_cmd("\u{3042}", SlmpFileOpenMode::Read, 2, "A").is_err());
+    }
+
+    #[test]
+    fn test_read_dir() {
+        let res = [
+            0x01, 0x00, b'A', 0x00, // ファイル名
+            0x10, 0x00, // 属性
+            0x8c, 0x58, // 11:04:24
+            0x52, 0x5b, // 2025-10-18
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let ret = decode_read_dir_response(&res).unwrap();
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].name, "A");
+        assert!(ret[0].is_dir());
+        assert_eq!(
+            ret[0].modified,
+            SlmpTimestamp {
+                year: 2025,
+                month: 10,
+                day: 18,
+                hour: 11,
+                minute: 4,
+                second: 24,
+            }
+        );
+        assert_eq!(ret[0].modified.to_fat().unwrap(), (0x5b52, 0x588c));
+        assert!(decode_read_dir_response(&res[..12]).is_err());
+    }
+}
@@ -1,31 +1,24 @@
 use crate::enums::SLMPCommand;
 use crate::packing::{
-    pack_bits_by_bit, pack_words_by_word, unpack_bits_by_bit, unpack_words_by_word, SLMPDevice,
-    SLMPDeviceBlock, SLMPDeviceBlockData, SLMPDeviceData,
+    pack_bits_by_bit, pack_words_by_word, unpack_bits_by_bit, unpack_dwords_by_dword,
+    unpack_words_by_word, SLMPDevice, SLMPDeviceBlock, SLMPDeviceBlockData, SLMPDeviceData,
+};
+use crate::split::offset_device;
+use crate::{
+    decode_values, encode_values, SLMPConnectionInfo, SLMPRequest, SlmpCodec, SlmpError,
+    SlmpWordOrder,
 };
-use crate::SLMPConnectionInfo;
 
-/// 単一の連続したデバイスの読み取り要求送信(16bitアドレス版)
+/// 単一の連続したデバイスの読み取り要求作成(16bitアドレス版)
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `target` - 読み取り対象デバイス
 /// * `count` - デバイス個数
 /// * `is_bit` - ビットデバイスとして取り扱う?
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_read_cmd_16(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
-    target: SLMPDevice,
-    count: u16,
-    is_bit: bool,
-) -> Option<u16> {
-    let buf1 = target.pack16();
+/// 要求内容
+pub fn make_read_cmd_16(target: SLMPDevice, count: u16, is_bit: bool) -> SLMPRequest {
     let mut buf = [0u8; 6];
-    for i in 0..4 {
-        buf[i] = buf1[i];
-    }
+    buf[..4].copy_from_slice(&target.pack16());
     buf[4] = count as u8;
     buf[5] = (count >> 8) as u8;
     let s_cmd;
@@ -43,10 +36,9 @@ pub fn send_read_cmd_16(
     } else {
         s_cmd = 0;
     }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceRead, s_cmd, &buf)
+    SLMPRequest::new(SLMPCommand::DeviceRead, s_cmd, &buf)
 }
-
-/// 単一の連続したデバイスの読み取り要求送信(32bitアドレス版)
+/// 単一の連続したデバイスの読み取り要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -55,18 +47,26 @@ pub fn send_read_cmd_16(
 /// * `is_bit` - ビットデバイスとして取り扱う?
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_read_cmd_32(
+pub fn send_read_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target: SLMPDevice,
     count: u16,
     is_bit: bool,
-) -> Option<u16> {
-    let buf1 = target.pack32();
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_cmd_16(target, count, is_bit))
+}
+
+/// 単一の連続したデバイスの読み取り要求作成(32bitアドレス版)
+/// # 引数
+/// * `target` - 読み取り対象デバイス
+/// * `count` - デバイス個数
+/// * `is_bit` - ビットデバイスとして取り扱う?
+/// # 返値
+/// 要求内容
+pub fn make_read_cmd_32(target: SLMPDevice, count: u16, is_bit: bool) -> SLMPRequest {
     let mut buf = [0u8; 8];
-    for i in 0..6 {
-        buf[i] = buf1[i];
-    }
+    buf[..6].copy_from_slice(&target.pack32());
     buf[6] = count as u8;
     buf[7] = (count >> 8) as u8;
     let s_cmd;
@@ -84,7 +84,25 @@ pub fn send_read_cmd_32(
     } else {
         s_cmd = 2;
     }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceRead, s_cmd, &buf)
+    SLMPRequest::new(SLMPCommand::DeviceRead, s_cmd, &buf)
+}
+/// 単一の連続したデバイスの読み取り要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target` - 読み取り対象デバイス
+/// * `count` - デバイス個数
+/// * `is_bit` - ビットデバイスとして取り扱う?
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_cmd_32(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    target: SLMPDevice,
+    count: u16,
+    is_bit: bool,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_cmd_32(target, count, is_bit))
 }
 
 /// 単一の連続したビットデバイス読み取り要求に対する応答の処理
@@ -92,16 +110,15 @@ pub fn send_read_cmd_32(
 /// * `buf` - 応答内容の入ったバッファ
 /// * `target` - 要求時に指定したデバイス
 /// # 返値
-/// 読み取りに成功した場合、読み取ったデバイスのリスト。
-/// 失敗した場合、エラー内容を含んだ文字列。
+/// 読み取ったデバイスのリスト
 pub fn decode_read_bit_response(
     buf: &[u8],
     target: SLMPDevice,
-) -> Result<Vec<SLMPDeviceData<bool>>, &'static str> {
+) -> Result<Vec<SLMPDeviceData<bool>>, SlmpError> {
     let mut ret = Vec::new();
-    let mut idx = target.addr;
-    let bs = unpack_bits_by_bit(buf)?;
-    for b in bs {
+    let bs = unpack_bits_by_bit(buf).map_err(SlmpError::MalformedFrame)?;
+    for (i, b) in bs.into_iter().enumerate() {
+        let idx = target.addr.wrapping_add(i as u32);
         ret.push(SLMPDeviceData::<bool> {
             dev: SLMPDevice {
                 d_code: target.d_code,
@@ -109,7 +126,6 @@ pub fn decode_read_bit_response(
             },
             value: b,
         });
-        idx += 1;
     }
     Ok(ret)
 }
@@ -119,13 +135,18 @@ pub fn decode_read_bit_response(
 /// * `buf` - 応答内容の入ったバッファ
 /// * `target` - 要求時に指定したデバイス
 /// # 返値
-/// 読み取りに成功した場合、読み取ったデバイスのリスト。
-/// 失敗した場合、エラー内容を含んだ文字列。
-pub fn decode_read_word_response(buf: &[u8], target: SLMPDevice) -> Vec<SLMPDeviceData<u16>> {
+/// 読み取ったデバイスのリスト
+pub fn decode_read_word_response(
+    buf: &[u8],
+    target: SLMPDevice,
+) -> Result<Vec<SLMPDeviceData<u16>>, SlmpError> {
+    if buf.len() % 2 == 1 {
+        return Err(SlmpError::MalformedFrame("odd length word data"));
+    }
     let mut ret = Vec::new();
-    let mut idx = target.addr;
     let ws = unpack_words_by_word(buf);
-    for w in ws {
+    for (i, w) in ws.into_iter().enumerate() {
+        let idx = target.addr.wrapping_add(i as u32);
         ret.push(SLMPDeviceData::<u16> {
             dev: SLMPDevice {
                 d_code: target.d_code,
@@ -133,11 +154,34 @@ pub fn decode_read_word_response(buf: &[u8], target: SLMPDevice) -> Vec<SLMPDevi
             },
             value: w,
         });
-        idx += 1;
     }
-    ret
+    Ok(ret)
 }
 
+/// 単一の連続したビットデバイスの書き込み要求作成(16bitアドレス版)
+/// # 引数
+/// * `target` - 書き込むデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_bit_cmd_16(targets: &[SLMPDeviceData<bool>]) -> Result<SLMPRequest, SlmpError> {
+    let s_cmd = 1;
+    if targets.is_empty() {
+        return Err(SlmpError::InvalidArgument("no target"));
+    }
+    if targets.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many targets"));
+    }
+    let mut buf = Vec::from(targets[0].dev.pack16());
+    buf.push(targets.len() as u8);
+    buf.push((targets.len() >> 8) as u8);
+    let d_buf: Vec<bool> = targets.iter().map(|d| d.value).collect();
+    buf.extend_from_slice(&pack_bits_by_bit(&d_buf));
+    Ok(SLMPRequest::new(
+        SLMPCommand::DeviceWrite,
+        s_cmd,
+        buf.as_slice(),
+    ))
+}
 /// 単一の連続したビットデバイスの書き込み要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
@@ -149,25 +193,34 @@ pub fn send_write_bit_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     targets: &[SLMPDeviceData<bool>],
-) -> Option<u16> {
-    let s_cmd = 1;
-    let mut buf = Vec::new();
-    for d in targets[0].dev.pack16().iter() {
-        buf.push(*d);
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_bit_cmd_16(targets)?)
+}
+
+/// 単一の連続したビットデバイスの書き込み要求作成(32bitアドレス版)
+/// # 引数
+/// * `target` - 書き込むデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_bit_cmd_32(targets: &[SLMPDeviceData<bool>]) -> Result<SLMPRequest, SlmpError> {
+    let s_cmd = 3;
+    if targets.is_empty() {
+        return Err(SlmpError::InvalidArgument("no target"));
+    }
+    if targets.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many targets"));
     }
+    let mut buf = Vec::from(targets[0].dev.pack32());
     buf.push(targets.len() as u8);
     buf.push((targets.len() >> 8) as u8);
-    let mut d_buf = Vec::new();
-    for d in targets.iter() {
-        d_buf.push(d.value);
-    }
-    let d_buf = pack_bits_by_bit(d_buf.as_slice());
-    for d in d_buf.iter() {
-        buf.push(*d);
-    }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceWrite, s_cmd, buf.as_slice())
+    let d_buf: Vec<bool> = targets.iter().map(|d| d.value).collect();
+    buf.extend_from_slice(&pack_bits_by_bit(&d_buf));
+    Ok(SLMPRequest::new(
+        SLMPCommand::DeviceWrite,
+        s_cmd,
+        buf.as_slice(),
+    ))
 }
-
 /// 単一の連続したビットデバイスの書き込み要求送信(32bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
@@ -179,25 +232,34 @@ pub fn send_write_bit_cmd_32(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     targets: &[SLMPDeviceData<bool>],
-) -> Option<u16> {
-    let s_cmd = 3;
-    let mut buf = Vec::new();
-    for d in targets[0].dev.pack32().iter() {
-        buf.push(*d);
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_bit_cmd_32(targets)?)
+}
+
+/// 単一の連続したワードデバイスの書き込み要求作成(16bitアドレス版)
+/// # 引数
+/// * `target` - 書き込むデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_word_cmd_16(targets: &[SLMPDeviceData<u16>]) -> Result<SLMPRequest, SlmpError> {
+    let s_cmd = 0;
+    if targets.is_empty() {
+        return Err(SlmpError::InvalidArgument("no target"));
     }
+    if targets.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many targets"));
+    }
+    let mut buf = Vec::from(targets[0].dev.pack16());
     buf.push(targets.len() as u8);
     buf.push((targets.len() >> 8) as u8);
-    let mut d_buf = Vec::new();
-    for d in targets.iter() {
-        d_buf.push(d.value);
-    }
-    let d_buf = pack_bits_by_bit(d_buf.as_slice());
-    for d in d_buf.iter() {
-        buf.push(*d);
-    }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceWrite, s_cmd, buf.as_slice())
+    let d_buf: Vec<u16> = targets.iter().map(|d| d.value).collect();
+    buf.extend_from_slice(&pack_words_by_word(&d_buf));
+    Ok(SLMPRequest::new(
+        SLMPCommand::DeviceWrite,
+        s_cmd,
+        buf.as_slice(),
+    ))
 }
-
 /// 単一の連続したワードデバイスの書き込み要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
@@ -209,25 +271,34 @@ pub fn send_write_word_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     targets: &[SLMPDeviceData<u16>],
-) -> Option<u16> {
-    let s_cmd = 0;
-    let mut buf = Vec::new();
-    for d in targets[0].dev.pack16().iter() {
-        buf.push(*d);
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_word_cmd_16(targets)?)
+}
+
+/// 単一の連続したワードデバイスの書き込み要求作成(32bitアドレス版)
+/// # 引数
+/// * `target` - 書き込むデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_word_cmd_32(targets: &[SLMPDeviceData<u16>]) -> Result<SLMPRequest, SlmpError> {
+    let s_cmd = 2;
+    if targets.is_empty() {
+        return Err(SlmpError::InvalidArgument("no target"));
     }
+    if targets.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many targets"));
+    }
+    let mut buf = Vec::from(targets[0].dev.pack32());
     buf.push(targets.len() as u8);
     buf.push((targets.len() >> 8) as u8);
-    let mut d_buf = Vec::new();
-    for d in targets.iter() {
-        d_buf.push(d.value);
-    }
-    let d_buf = pack_words_by_word(d_buf.as_slice());
-    for d in d_buf.iter() {
-        buf.push(*d);
-    }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceWrite, s_cmd, buf.as_slice())
+    let d_buf: Vec<u16> = targets.iter().map(|d| d.value).collect();
+    buf.extend_from_slice(&pack_words_by_word(&d_buf));
+    Ok(SLMPRequest::new(
+        SLMPCommand::DeviceWrite,
+        s_cmd,
+        buf.as_slice(),
+    ))
 }
-
 /// 単一の連続したワードデバイスの書き込み要求送信(32bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
@@ -239,48 +310,131 @@ pub fn send_write_word_cmd_32(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     targets: &[SLMPDeviceData<u16>],
-) -> Option<u16> {
-    let s_cmd = 2;
-    let mut buf = Vec::new();
-    for d in targets[0].dev.pack32().iter() {
-        buf.push(*d);
-    }
-    buf.push(targets.len() as u8);
-    buf.push((targets.len() >> 8) as u8);
-    let mut d_buf = Vec::new();
-    for d in targets.iter() {
-        d_buf.push(d.value);
-    }
-    let d_buf = pack_words_by_word(d_buf.as_slice());
-    for d in d_buf.iter() {
-        buf.push(*d);
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_word_cmd_32(targets)?)
+}
+
+/// 単一の連続したワードデバイスの読み取り応答を型付きの値として解釈
+/// # 引数
+/// * `buf` - 応答データ
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// # 返値
+/// 値のリスト
+pub fn decode_read_value_response<T: SlmpCodec>(
+    buf: &[u8],
+    order: SlmpWordOrder,
+) -> Result<Vec<T>, SlmpError> {
+    if buf.len() % 2 == 1 {
+        return Err(SlmpError::MalformedFrame("odd length word data"));
     }
-    connection_info.send_cmd(timeout, SLMPCommand::DeviceWrite, s_cmd, buf.as_slice())
+    decode_values(&unpack_words_by_word(buf), order)
 }
 
-/// 指定したデバイスの読み取り要求送信(16bitアドレス版)
+/// 型付きの値を書き込むワードのリストを作成
+/// # 引数
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// # 返値
+/// 書き込むデバイスのリスト
+pub fn make_value_targets<T: SlmpCodec>(
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<Vec<SLMPDeviceData<u16>>, SlmpError> {
+    Ok(encode_values(values, order)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| SLMPDeviceData {
+            dev: offset_device(target, i),
+            value,
+        })
+        .collect())
+}
+
+/// 型付きの値を単一の連続したワードデバイスへ書き込む要求作成(16bitアドレス版)
+/// # 引数
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// # 返値
+/// 要求内容
+pub fn make_write_value_cmd_16<T: SlmpCodec>(
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<SLMPRequest, SlmpError> {
+    make_write_word_cmd_16(&make_value_targets(target, values, order)?)
+}
+/// 型付きの値を単一の連続したワードデバイスへ書き込む要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
-/// * `target_word` - ワードアクセスするデバイスのリスト
-/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_read_random_cmd_16(
+pub fn send_write_value_cmd_16<T: SlmpCodec>(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_value_cmd_16(target, values, order)?)
+}
+
+/// 型付きの値を単一の連続したワードデバイスへ書き込む要求作成(32bitアドレス版)
+/// # 引数
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// # 返値
+/// 要求内容
+pub fn make_write_value_cmd_32<T: SlmpCodec>(
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<SLMPRequest, SlmpError> {
+    make_write_word_cmd_32(&make_value_targets(target, values, order)?)
+}
+/// 型付きの値を単一の連続したワードデバイスへ書き込む要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_value_cmd_32<T: SlmpCodec>(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_value_cmd_32(target, values, order)?)
+}
+
+/// 指定したデバイスの読み取り要求作成(16bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_random_cmd_16(
     target_word: &[SLMPDevice],
     target_dword: &[SLMPDevice],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 0;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
@@ -294,15 +448,13 @@ pub fn send_read_random_cmd_16(
             buf.push(*d);
         }
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceReadRandom,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-
-/// 指定したデバイスの読み取り要求送信(32bitアドレス版)
+/// 指定したデバイスの読み取り要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -310,21 +462,35 @@ pub fn send_read_random_cmd_16(
 /// * `target_dword` - ダブルワードアクセスするデバイスのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_read_random_cmd_32(
+pub fn send_read_random_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target_word: &[SLMPDevice],
     target_dword: &[SLMPDevice],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_read_random_cmd_16(target_word, target_dword)?,
+    )
+}
+
+/// 指定したデバイスの読み取り要求作成(32bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_random_cmd_32(
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 2;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
@@ -338,11 +504,29 @@ pub fn send_read_random_cmd_32(
             buf.push(*d);
         }
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceReadRandom,
         s_cmd,
         buf.as_slice(),
+    ))
+}
+/// 指定したデバイスの読み取り要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_random_cmd_32(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_read_random_cmd_32(target_word, target_dword)?,
     )
 }
 
@@ -353,57 +537,45 @@ pub fn send_read_random_cmd_32(
 /// * `target_dword` - 要求時に指定したダブルワードアクセスするデバイスのリスト
 /// # 返値
 /// ワードデータとダブルワードそれぞれのデバイスリスト
+#[allow(clippy::type_complexity)]
 pub fn decode_read_random_response(
     buf: &[u8],
     target_word: &[SLMPDevice],
     target_dword: &[SLMPDevice],
-) -> (Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>) {
+) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+    if buf.len() != target_word.len() * 2 + target_dword.len() * 4 {
+        return Err(SlmpError::MalformedFrame(
+            "wrong random read response length",
+        ));
+    }
+    let (buf_word, buf_dword) = buf.split_at(target_word.len() * 2);
     let mut ret_word = Vec::new();
     let mut ret_dword = Vec::new();
-    let mut tmp_buf = Vec::new();
-    tmp_buf.extend_from_slice(buf);
-    for t in target_word {
-        let d_low = tmp_buf.remove(0) as u16;
-        let d_high = tmp_buf.remove(0) as u16;
-        ret_word.push(SLMPDeviceData {
-            dev: *t,
-            value: d_low + (d_high << 8),
-        });
+    for (t, w) in target_word.iter().zip(unpack_words_by_word(buf_word)) {
+        ret_word.push(SLMPDeviceData { dev: *t, value: w });
     }
-    for t in target_dword {
-        let d_1 = tmp_buf.remove(0) as u32;
-        let d_2 = tmp_buf.remove(0) as u32;
-        let d_3 = tmp_buf.remove(0) as u32;
-        let d_4 = tmp_buf.remove(0) as u32;
-        ret_dword.push(SLMPDeviceData {
-            dev: *t,
-            value: d_1 + (d_2 << 8) + (d_3 << 16) + (d_4 << 24),
-        });
+    for (t, dw) in target_dword.iter().zip(unpack_dwords_by_dword(buf_dword)) {
+        ret_dword.push(SLMPDeviceData { dev: *t, value: dw });
     }
-    (ret_word, ret_dword)
+    Ok((ret_word, ret_dword))
 }
 
-/// 指定したビットデバイスの書込み要求送信(16bitアドレス版)
+/// 指定したビットデバイスの書込み要求作成(16bitアドレス版)
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `targets` - ビットデバイスデータのリスト
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_write_random_bits_cmd_16(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
+/// 要求内容
+pub fn make_write_random_bits_cmd_16(
     targets: &[SLMPDeviceData<bool>],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 1;
     let mut buf = Vec::new();
     if targets.len() > 0xff {
-        eprintln!("Too many targets");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many targets"));
     }
     buf.push(targets.len() as u8);
     for dd in targets {
-        for d in dd.pack16().iter() {
+        for d in dd.dev.pack16().iter() {
             buf.push(*d);
         }
         match dd.value {
@@ -411,34 +583,42 @@ pub fn send_write_random_bits_cmd_16(
             false => buf.push(0),
         }
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceWriteRandom,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// 指定したビットデバイスの書込み要求送信(32bitアドレス版)
+/// 指定したビットデバイスの書込み要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
 /// * `targets` - ビットデバイスデータのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_write_random_bits_cmd_32(
+pub fn send_write_random_bits_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     targets: &[SLMPDeviceData<bool>],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_random_bits_cmd_16(targets)?)
+}
+/// 指定したビットデバイスの書込み要求作成(32bitアドレス版)
+/// # 引数
+/// * `targets` - ビットデバイスデータのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_random_bits_cmd_32(
+    targets: &[SLMPDeviceData<bool>],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 3;
     let mut buf = Vec::new();
     if targets.len() > 0xff {
-        eprintln!("Too many targets");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many targets"));
     }
     buf.push(targets.len() as u8);
     for dd in targets {
-        for d in dd.pack32().iter() {
+        for d in dd.dev.pack32().iter() {
             buf.push(*d);
         }
         match dd.value {
@@ -447,48 +627,55 @@ pub fn send_write_random_bits_cmd_32(
         }
         buf.push(0);
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceWriteRandom,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// 指定したワードデバイスの書込み要求送信(16bitアドレス版)
+/// 指定したビットデバイスの書込み要求送信(32bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
-/// * `target_word` - ワードデバイスデータのリスト
-/// * `target_dword` - ダブルワードデバイスデータのリスト
+/// * `targets` - ビットデバイスデータのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_write_random_words_cmd_16(
+pub fn send_write_random_bits_cmd_32(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
+    targets: &[SLMPDeviceData<bool>],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_random_bits_cmd_32(targets)?)
+}
+/// 指定したワードデバイスの書込み要求作成(16bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードデバイスデータのリスト
+/// * `target_dword` - ダブルワードデバイスデータのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_random_words_cmd_16(
     target_word: &[SLMPDeviceData<u16>],
     target_dword: &[SLMPDeviceData<u32>],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 0;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
     for dd in target_word {
-        for d in dd.pack16().iter() {
+        for d in dd.dev.pack16().iter() {
             buf.push(*d);
         }
         buf.push(dd.value as u8);
         buf.push((dd.value >> 8) as u8);
     }
     for dd in target_dword {
-        for d in dd.pack16().iter() {
+        for d in dd.dev.pack16().iter() {
             buf.push(*d);
         }
         buf.push(dd.value as u8);
@@ -497,14 +684,13 @@ pub fn send_write_random_words_cmd_16(
         buf.push((dd.value >> 24) as u8);
     }
 
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceWriteRandom,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// 指定したワードデバイスの書込み要求送信(32bitアドレス版)
+/// 指定したワードデバイスの書込み要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -512,33 +698,46 @@ pub fn send_write_random_words_cmd_16(
 /// * `target_dword` - ダブルワードデバイスデータのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_write_random_words_cmd_32(
+pub fn send_write_random_words_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target_word: &[SLMPDeviceData<u16>],
     target_dword: &[SLMPDeviceData<u32>],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_write_random_words_cmd_16(target_word, target_dword)?,
+    )
+}
+/// 指定したワードデバイスの書込み要求作成(32bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードデバイスデータのリスト
+/// * `target_dword` - ダブルワードデバイスデータのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_random_words_cmd_32(
+    target_word: &[SLMPDeviceData<u16>],
+    target_dword: &[SLMPDeviceData<u32>],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 2;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
     for dd in target_word {
-        for d in dd.pack32().iter() {
+        for d in dd.dev.pack32().iter() {
             buf.push(*d);
         }
         buf.push(dd.value as u8);
         buf.push((dd.value >> 8) as u8);
     }
     for dd in target_dword {
-        for d in dd.pack32().iter() {
+        for d in dd.dev.pack32().iter() {
             buf.push(*d);
         }
         buf.push(dd.value as u8);
@@ -547,36 +746,48 @@ pub fn send_write_random_words_cmd_32(
         buf.push((dd.value >> 24) as u8);
     }
 
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::DeviceWriteRandom,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// モニタデバイスへ登録要求送信(16bitアドレス版)
+/// 指定したワードデバイスの書込み要求送信(32bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
-/// * `target_word` - ワードアクセスするデバイスのリスト
-/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// * `target_word` - ワードデバイスデータのリスト
+/// * `target_dword` - ダブルワードデバイスデータのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_entry_monitor_device_cmd_16(
+pub fn send_write_random_words_cmd_32(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
+    target_word: &[SLMPDeviceData<u16>],
+    target_dword: &[SLMPDeviceData<u32>],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_write_random_words_cmd_32(target_word, target_dword)?,
+    )
+}
+/// モニタデバイスへ登録要求作成(16bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_entry_monitor_device_cmd_16(
     target_word: &[SLMPDevice],
     target_dword: &[SLMPDevice],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 0;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
@@ -590,14 +801,13 @@ pub fn send_entry_monitor_device_cmd_16(
             buf.push(*d);
         }
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::EntryMonitorDevice,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// モニタデバイスへ登録要求送信(32bitアドレス版)
+/// モニタデバイスへ登録要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -605,21 +815,34 @@ pub fn send_entry_monitor_device_cmd_16(
 /// * `target_dword` - ダブルワードアクセスするデバイスのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_entry_monitor_device_cmd_32(
+pub fn send_entry_monitor_device_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target_word: &[SLMPDevice],
     target_dword: &[SLMPDevice],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_entry_monitor_device_cmd_16(target_word, target_dword)?,
+    )
+}
+/// モニタデバイスへ登録要求作成(32bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// 要求内容
+pub fn make_entry_monitor_device_cmd_32(
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 2;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_dword.len() > 0xff {
-        eprintln!("Too many dword target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many dword target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_dword.len() as u8);
@@ -633,48 +856,86 @@ pub fn send_entry_monitor_device_cmd_32(
             buf.push(*d);
         }
     }
-    connection_info.send_cmd(
-        timeout,
+    Ok(SLMPRequest::new(
         SLMPCommand::EntryMonitorDevice,
         s_cmd,
         buf.as_slice(),
-    )
+    ))
 }
-/// 登録したデバイスの読み取り要求送信
+/// モニタデバイスへ登録要求送信(32bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_execute_monitor_cmd(
+pub fn send_entry_monitor_device_cmd_32(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
-) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::ExecuteMonitor, 0, &[])
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_entry_monitor_device_cmd_32(target_word, target_dword)?,
+    )
 }
-/// 複数の連続したデバイス(デバイスブロック)の読み取り要求送信(16bitアドレス版)
+/// 登録したデバイスの読み取り要求作成
+/// # 返値
+/// 要求内容
+pub fn make_execute_monitor_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::ExecuteMonitor, 0, &[])
+}
+/// 登録したデバイスの読み取り要求送信
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
-/// * `target_word` - ワードアクセスするデバイスブロックのリスト
-/// * `target_bit` - ビットアクセスするデバイスブロックのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_read_block_cmd_16(
+pub fn send_execute_monitor_cmd(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_execute_monitor_cmd())
+}
+/// 登録したデバイスの読み取り応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// * `target_word` - モニタ登録したワードアクセスするデバイスのリスト
+/// * `target_dword` - モニタ登録したダブルワードアクセスするデバイスのリスト
+/// # 返値
+/// ワードデバイスの値とダブルワードデバイスの値
+#[allow(clippy::type_complexity)]
+pub fn decode_execute_monitor_response(
+    buf: &[u8],
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+    if buf.len() != target_word.len() * 2 + target_dword.len() * 4 {
+        return Err(SlmpError::MalformedFrame(
+            "wrong execute monitor response length",
+        ));
+    }
+    decode_read_random_response(buf, target_word, target_dword)
+}
+/// 複数の連続したデバイス(デバイスブロック)の読み取り要求作成(16bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_block_cmd_16(
     target_word: &[SLMPDeviceBlock],
     target_bit: &[SLMPDeviceBlock],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 0;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_bit.len() > 0xff {
-        eprintln!("Too many bit target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many bit target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_bit.len() as u8);
@@ -689,9 +950,9 @@ pub fn send_read_block_cmd_16(
         }
     }
 
-    connection_info.send_cmd(timeout, SLMPCommand::ReadBlock, s_cmd, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::ReadBlock, s_cmd, &buf))
 }
-/// 複数の連続したデバイス(デバイスブロック)の読み取り要求送信(32bitアドレス版)
+/// 複数の連続したデバイス(デバイスブロック)の読み取り要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -699,21 +960,31 @@ pub fn send_read_block_cmd_16(
 /// * `target_bit` - ビットアクセスするデバイスブロックのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_read_block_cmd_32(
+pub fn send_read_block_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target_word: &[SLMPDeviceBlock],
     target_bit: &[SLMPDeviceBlock],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_block_cmd_16(target_word, target_bit)?)
+}
+/// 複数の連続したデバイス(デバイスブロック)の読み取り要求作成(32bitアドレス版)
+/// # 引数
+/// * `target_word` - ワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_block_cmd_32(
+    target_word: &[SLMPDeviceBlock],
+    target_bit: &[SLMPDeviceBlock],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 2;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_bit.len() > 0xff {
-        eprintln!("Too many bit target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many bit target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_bit.len() as u8);
@@ -728,7 +999,23 @@ pub fn send_read_block_cmd_32(
         }
     }
 
-    connection_info.send_cmd(timeout, SLMPCommand::ReadBlock, s_cmd, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::ReadBlock, s_cmd, &buf))
+}
+/// 複数の連続したデバイス(デバイスブロック)の読み取り要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target_word` - ワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_block_cmd_32(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    target_word: &[SLMPDeviceBlock],
+    target_bit: &[SLMPDeviceBlock],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_block_cmd_32(target_word, target_bit)?)
 }
 /// デバイスブロック読み出し要求に対する応答の処理
 /// # 引数
@@ -737,61 +1024,65 @@ pub fn send_read_block_cmd_32(
 /// * `target_bit` - 要求時に指定したビットアクセスするデバイスブロックのリスト
 /// # 返値
 /// データを含んだデバイスブロックのリスト
+#[allow(clippy::type_complexity)]
 pub fn decode_read_block_response(
     buf: &[u8],
     target_word: &[SLMPDeviceBlock],
     target_bit: &[SLMPDeviceBlock],
-) -> (
-    Vec<SLMPDeviceBlockData<u16>>,
-    Vec<SLMPDeviceBlockData<bool>>,
-) {
+) -> Result<
+    (
+        Vec<SLMPDeviceBlockData<u16>>,
+        Vec<SLMPDeviceBlockData<bool>>,
+    ),
+    SlmpError,
+> {
+    let total: usize = target_word
+        .iter()
+        .chain(target_bit.iter())
+        .map(|db| db.count as usize * 2)
+        .sum();
+    if buf.len() != total {
+        return Err(SlmpError::MalformedFrame(
+            "wrong block read response length",
+        ));
+    }
     let mut ret_w = Vec::new();
     let mut ret_b = Vec::new();
-    let mut buf = Vec::from(buf);
+    let mut pos = 0;
     for db in target_word.iter() {
         let mut dbd = SLMPDeviceBlockData::<u16>::new(db.top_device, db.count);
-        let mut tmp = Vec::new();
-        for _ in 0..db.count * 2 {
-            tmp.push(buf.remove(0));
-        }
-        dbd.decode(&tmp);
+        let length = db.count as usize * 2;
+        dbd.decode(&buf[pos..pos + length]);
+        pos += length;
         ret_w.push(dbd);
     }
     for db in target_bit.iter() {
         let mut dbd = SLMPDeviceBlockData::<bool>::new(db.top_device, db.count);
-        let mut tmp = Vec::new();
-        for _ in 0..db.count * 2 {
-            tmp.push(buf.remove(0));
-        }
-        dbd.decode(&tmp);
+        let length = db.count as usize * 2;
+        dbd.decode(&buf[pos..pos + length]);
+        pos += length;
         ret_b.push(dbd);
     }
 
-    (ret_w, ret_b)
+    Ok((ret_w, ret_b))
 }
-/// 複数の連続したデバイス(デバイスブロック)の書込み要求送信(16bitアドレス版)
+/// 複数の連続したデバイス(デバイスブロック)の書込み要求作成(16bitアドレス版)
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
 /// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_write_block_cmd_16(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
+/// 要求内容
+pub fn make_write_block_cmd_16(
     target_word: &[SLMPDeviceBlockData<u16>],
     target_bit: &[SLMPDeviceBlockData<bool>],
-) -> Option<u16> {
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 0;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_bit.len() > 0xff {
-        eprintln!("Too many bit target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many bit target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_bit.len() as u8);
@@ -801,9 +1092,9 @@ pub fn send_write_block_cmd_16(
     for dbd in target_bit.iter() {
         buf.extend_from_slice(&dbd.pack16());
     }
-    connection_info.send_cmd(timeout, SLMPCommand::WriteBlock, s_cmd, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::WriteBlock, s_cmd, &buf))
 }
-/// 複数の連続したデバイス(デバイスブロック)の書込み要求送信(32bitアドレス版)
+/// 複数の連続したデバイス(デバイスブロック)の書込み要求送信(16bitアドレス版)
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
@@ -811,21 +1102,31 @@ pub fn send_write_block_cmd_16(
 /// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_write_block_cmd_32(
+pub fn send_write_block_cmd_16(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     target_word: &[SLMPDeviceBlockData<u16>],
     target_bit: &[SLMPDeviceBlockData<bool>],
-) -> Option<u16> {
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_block_cmd_16(target_word, target_bit)?)
+}
+/// 複数の連続したデバイス(デバイスブロック)の書込み要求作成(32bitアドレス版)
+/// # 引数
+/// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_block_cmd_32(
+    target_word: &[SLMPDeviceBlockData<u16>],
+    target_bit: &[SLMPDeviceBlockData<bool>],
+) -> Result<SLMPRequest, SlmpError> {
     let s_cmd = 2;
     let mut buf = Vec::new();
     if target_word.len() > 0xff {
-        eprintln!("Too many word target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many word target"));
     }
     if target_bit.len() > 0xff {
-        eprintln!("Too many bit target");
-        return None;
+        return Err(SlmpError::InvalidArgument("too many bit target"));
     }
     buf.push(target_word.len() as u8);
     buf.push(target_bit.len() as u8);
@@ -835,7 +1136,23 @@ pub fn send_write_block_cmd_32(
     for dbd in target_bit.iter() {
         buf.extend_from_slice(&dbd.pack32());
     }
-    connection_info.send_cmd(timeout, SLMPCommand::WriteBlock, s_cmd, &buf)
+    Ok(SLMPRequest::new(SLMPCommand::WriteBlock, s_cmd, &buf))
+}
+/// 複数の連続したデバイス(デバイスブロック)の書込み要求送信(32bitアドレス版)
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_block_cmd_32(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    target_word: &[SLMPDeviceBlockData<u16>],
+    target_bit: &[SLMPDeviceBlockData<bool>],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_block_cmd_32(target_word, target_bit)?)
 }
 #[cfg(test)]
 mod tests {
@@ -882,7 +1199,8 @@ mod tests {
             0x95, 0x19, 0x02, 0x12, 0x30, 0x20, 0x49, 0x48, 0x4e, 0x4f, 0x54, 0x4c, 0xaf, 0xb9,
             0xde, 0xc3, 0xb7, 0xbc, 0xdd, 0xba,
         ];
-        let (words, dwords) = decode_read_random_response(&buf, &word_devices, &dword_devices);
+        let (words, dwords) =
+            decode_read_random_response(&buf, &word_devices, &dword_devices).unwrap();
         assert_eq!(words.len(), 4);
         assert_eq!(words[0].value, 0x1995);
         assert_eq!(words[1].value, 0x1202);
@@ -902,14 +1220,14 @@ mod tests {
         };
         let ret = decode_read_bit_response(&buf, dev).unwrap();
         assert_eq!(ret.len(), 8);
-        assert_eq!(ret[0].value, false);
-        assert_eq!(ret[1].value, false);
-        assert_eq!(ret[2].value, false);
-        assert_eq!(ret[3].value, true);
-        assert_eq!(ret[4].value, false);
-        assert_eq!(ret[5].value, false);
-        assert_eq!(ret[6].value, true);
-        assert_eq!(ret[7].value, true);
+        assert!(!ret[0].value);
+        assert!(!ret[1].value);
+        assert!(!ret[2].value);
+        assert!(ret[3].value);
+        assert!(!ret[4].value);
+        assert!(!ret[5].value);
+        assert!(ret[6].value);
+        assert!(ret[7].value);
     }
     #[test]
     pub fn test_decode_read_word_response() {
@@ -918,7 +1236,7 @@ mod tests {
             d_code: SLMPDeviceCode::M,
             addr: 100,
         };
-        let ret = decode_read_word_response(&buf, dev);
+        let ret = decode_read_word_response(&buf, dev).unwrap();
         assert_eq!(ret.len(), 2);
         assert_eq!(ret[0].value, 0x1234);
         assert_eq!(ret[1].value, 0x2);
@@ -927,7 +1245,7 @@ mod tests {
             d_code: SLMPDeviceCode::TN,
             addr: 100,
         };
-        let ret = decode_read_word_response(&buf, dev);
+        let ret = decode_read_word_response(&buf, dev).unwrap();
         assert_eq!(ret.len(), 3);
         assert_eq!(ret[0].value, 0x1234);
         assert_eq!(ret[1].value, 0x2);
@@ -0,0 +1,667 @@
+use crate::{SLMPCommand, SLMPConnectionInfo, SLMPRequest, SlmpError, SlmpTagValue};
+use std::convert::TryInto;
+
+/// ラベルのデータ型
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpLabelDataType {
+    /// ビット
+    Bit,
+    /// 符号なし16bit整数、16bitのビット列
+    UWord,
+    /// 符号なし32bit整数、32bitのビット列
+    UDWord,
+    /// 符号付き16bit整数
+    Word,
+    /// 符号付き32bit整数
+    DWord,
+    /// 単精度実数
+    Float,
+    /// 倍精度実数
+    Double,
+    /// 時間、単位はms
+    Time,
+    /// 文字列
+    String,
+    /// 文字列(UTF-16)
+    WString,
+    /// その他のデータ型ID
+    Other(u8),
+}
+
+impl SlmpLabelDataType {
+    /// データ型IDから作成
+    pub fn from_id(id: u8) -> SlmpLabelDataType {
+        use SlmpLabelDataType::*;
+        match id {
+            1 => Bit,
+            2 => UWord,
+            3 => UDWord,
+            4 => Word,
+            5 => DWord,
+            6 => Float,
+            7 => Double,
+            8 => Time,
+            9 => String,
+            10 => WString,
+            id => Other(id),
+        }
+    }
+    /// データ型ID
+    pub fn id(&self) -> u8 {
+        use SlmpLabelDataType::*;
+        match self {
+            Bit => 1,
+            UWord => 2,
+            UDWord => 3,
+            Word => 4,
+            DWord => 5,
+            Float => 6,
+            Double => 7,
+            Time => 8,
+            String => 9,
+            WString => 10,
+            Other(id) => *id,
+        }
+    }
+}
+
+/// 配列ラベルのデータ長の単位
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpLabelUnit {
+    /// ビット単位、データは2バイト単位に詰める
+    Bit = 0,
+    /// バイト単位
+    Byte = 1,
+}
+
+impl SlmpLabelUnit {
+    fn from_u8(v: u8) -> Result<SlmpLabelUnit, SlmpError> {
+        match v {
+            0 => Ok(SlmpLabelUnit::Bit),
+            1 => Ok(SlmpLabelUnit::Byte),
+            _ => Err(SlmpError::MalformedFrame("unknown label unit")),
+        }
+    }
+}
+
+/// 配列ラベルの指定
+///
+/// ラベル名には配列の要素(`Arr[2]`、`Arr[1,3]`)、構造体のメンバ(`Str.member`)、
+/// 省略ラベル名(`%1.member`、1番目の省略ラベル名で置き換える)を含めることができる
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SlmpArrayLabel {
+    /// ラベル名
+    pub name: String,
+    /// データ長の単位
+    pub unit: SlmpLabelUnit,
+    /// データ長、単位は`unit`による
+    pub length: u16,
+}
+
+impl SlmpArrayLabel {
+    /// 作成
+    ///
+    /// # 引数
+    ///
+    /// * `name` - ラベル名
+    /// * `unit` - データ長の単位
+    /// * `length` - データ長
+    pub fn new(name: &str, unit: SlmpLabelUnit, length: u16) -> SlmpArrayLabel {
+        SlmpArrayLabel {
+            name: name.to_string(),
+            unit,
+            length,
+        }
+    }
+    /// データのバイト数
+    pub fn data_size(&self) -> usize {
+        data_size(self.unit, self.length)
+    }
+}
+
+/// 配列ラベルへ書き込むデータ
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SlmpArrayLabelData {
+    /// 配列ラベルの指定
+    pub label: SlmpArrayLabel,
+    /// 書き込むデータ
+    pub data: Vec<u8>,
+}
+
+/// 読み出したラベルのデータ
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SlmpLabelData {
+    /// データ型
+    pub data_type: SlmpLabelDataType,
+    /// データ長の単位、ランダム読出しでは常にバイト単位
+    pub unit: SlmpLabelUnit,
+    /// データ長、単位は`unit`による
+    pub length: u16,
+    /// データ
+    pub data: Vec<u8>,
+}
+
+impl SlmpLabelData {
+    /// データ型に従って値へ変換する
+    ///
+    /// 要素が2個以上の場合は[SlmpTagValue::Array]になる
+    pub fn value(&self) -> Result<SlmpTagValue, SlmpError> {
+        use SlmpLabelDataType::*;
+        let mut values: Vec<SlmpTagValue> = match self.data_type {
+            Bit => match self.unit {
+                SlmpLabelUnit::Bit => {
+                    if self.data.len() != data_size(self.unit, self.length) {
+                        return Err(SlmpError::MalformedFrame("wrong label data length"));
+                    }
+                    (0..self.length as usize)
+                        .map(|i| SlmpTagValue::Bit(self.data[i / 8] & (1 << (i % 8)) != 0))
+                        .collect()
+                }
+                SlmpLabelUnit::Byte => self
+                    .chunks::<2>()?
+                    .map(|c| SlmpTagValue::Bit(c[0] & 1 != 0))
+                    .collect(),
+            },
+            UWord => self
+                .chunks::<2>()?
+                .map(|c| SlmpTagValue::U16(u16::from_le_bytes(c)))
+                .collect(),
+            Word => self
+                .chunks::<2>()?
+                .map(|c| SlmpTagValue::I16(i16::from_le_bytes(c)))
+                .collect(),
+            UDWord => self
+                .chunks::<4>()?
+                .map(|c| SlmpTagValue::U32(u32::from_le_bytes(c)))
+                .collect(),
+            DWord | Time => self
+                .chunks::<4>()?
+                .map(|c| SlmpTagValue::I32(i32::from_le_bytes(c)))
+                .collect(),
+            Float => self
+                .chunks::<4>()?
+                .map(|c| SlmpTagValue::F32(f32::from_le_bytes(c)))
+                .collect(),
+            Double => self
+                .chunks::<8>()?
+                .map(|c| SlmpTagValue::F64(f64::from_le_bytes(c)))
+                .collect(),
+            String => {
+                let end = self
+                    .data
+                    .iter()
+                    .position(|c| *c == 0)
+                    .unwrap_or(self.data.len());
+                vec![SlmpTagValue::String(
+                    std::string::String::from_utf8_lossy(&self.data[..end]).into_owned(),
+                )]
+            }
+            WString => {
+                let chars: Vec<u16> = self
+                    .chunks::<2>()?
+                    .map(u16::from_le_bytes)
+                    .take_while(|c| *c != 0)
+                    .collect();
+                vec![SlmpTagValue::String(std::string::String::from_utf16_lossy(
+                    &chars,
+                ))]
+            }
+            Other(_) => return Err(SlmpError::InvalidArgument("unsupported label data type")),
+        };
+        match values.len() {
+            0 => Err(SlmpError::MalformedFrame("empty label data")),
+            1 => Ok(values.remove(0)),
+            _ => Ok(SlmpTagValue::Array(values)),
+        }
+    }
+    /// データを`N`バイトごとに区切る
+    fn chunks<const N: usize>(&self) -> Result<impl Iterator<Item = [u8; N]> + '_, SlmpError> {
+        if self.data.len() / N * N != self.data.len() {
+            return Err(SlmpError::MalformedFrame("wrong label data length"));
+        }
+        Ok(self.data.chunks_exact(N).map(|c| c.try_into().unwrap()))
+    }
+}
+
+/// 値をラベルへ書き込むデータに変換する
+///
+/// ビットは2バイト、文字列はNUL終端して2バイト単位に詰める。
+/// 配列は要素ごとに変換して連結し、ビットの配列は16点ごとに2バイトへ詰める
+///
+/// # 引数
+///
+/// * `value` - 値
+///
+/// # 返値
+///
+/// 書き込むデータ、ASCII以外を含む文字列や要素の型が揃っていない配列は
+/// [SlmpError::InvalidArgument]
+pub fn encode_label_value(value: &SlmpTagValue) -> Result<Vec<u8>, SlmpError> {
+    let buf = match value {
+        SlmpTagValue::Bit(b) => vec![*b as u8, 0],
+        SlmpTagValue::U16(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::I16(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::U32(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::I32(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::F32(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::F64(v) => v.to_le_bytes().to_vec(),
+        SlmpTagValue::String(s) => {
+            // STRING型のラベルはシフトJISのため、ASCII以外は正しく書き込めない
+            if !s.is_ascii() {
+                return Err(SlmpError::InvalidArgument("non-ASCII string"));
+            }
+            let mut buf = s.as_bytes().to_vec();
+            buf.push(0);
+            buf.resize(buf.len().div_ceil(2) * 2, 0);
+            buf
+        }
+        SlmpTagValue::Array(values) => {
+            // 要素の区切りが失われるため、配列の要素は同じ型に限る
+            if let Some(first) = values.first() {
+                let kind = std::mem::discriminant(first);
+                if matches!(first, SlmpTagValue::Array(_))
+                    || values.iter().any(|v| std::mem::discriminant(v) != kind)
+                {
+                    return Err(SlmpError::InvalidArgument("mixed types in array"));
+                }
+            }
+            if let Some(SlmpTagValue::Bit(_)) = values.first() {
+                let mut buf = vec![0u8; values.len().div_ceil(16) * 2];
+                for (i, v) in values.iter().enumerate() {
+                    if *v == SlmpTagValue::Bit(true) {
+                        buf[i / 8] |= 1 << (i % 8);
+                    }
+                }
+                buf
+            } else {
+                let mut buf = Vec::new();
+                for v in values {
+                    buf.extend_from_slice(&encode_label_value(v)?);
+                }
+                buf
+            }
+        }
+    };
+    Ok(buf)
+}
+
+/// 配列ラベルのデータのバイト数
+fn data_size(unit: SlmpLabelUnit, length: u16) -> usize {
+    match unit {
+        SlmpLabelUnit::Bit => (length as usize).div_ceil(16) * 2,
+        SlmpLabelUnit::Byte => length as usize,
+    }
+}
+
+/// ラベル名を文字数とUTF-16で追加する
+fn push_label_name(buf: &mut Vec<u8>, name: &str) -> Result<(), SlmpError> {
+    let chars: Vec<u16> = name.encode_utf16().collect();
+    if chars.is_empty() {
+        return Err(SlmpError::InvalidArgument("empty label name"));
+    }
+    if chars.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too long label name"));
+    }
+    buf.extend_from_slice(&(chars.len() as u16).to_le_bytes());
+    for c in chars {
+        buf.extend_from_slice(&c.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// ラベル点数、省略ラベル点数と省略ラベル名を追加する
+fn push_label_header(
+    buf: &mut Vec<u8>,
+    count: usize,
+    abbreviations: &[&str],
+) -> Result<(), SlmpError> {
+    if count == 0 {
+        return Err(SlmpError::InvalidArgument("no label"));
+    }
+    if count > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many labels"));
+    }
+    if abbreviations.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many abbreviations"));
+    }
+    buf.extend_from_slice(&(count as u16).to_le_bytes());
+    buf.extend_from_slice(&(abbreviations.len() as u16).to_le_bytes());
+    for name in abbreviations {
+        push_label_name(buf, name)?;
+    }
+    Ok(())
+}
+
+/// 応答を読み進める
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SlmpError> {
+        if self.buf.len() < n {
+            return Err(SlmpError::MalformedFrame("label response too short"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+    fn u8(&mut self) -> Result<u8, SlmpError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SlmpError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+}
+
+/// 配列ラベルの一括読出し要求を作成
+/// # 引数
+/// * `labels` - 読み出す配列ラベルのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_array_label_cmd(
+    labels: &[SlmpArrayLabel],
+    abbreviations: &[&str],
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_label_header(&mut buf, labels.len(), abbreviations)?;
+    for label in labels {
+        push_label_name(&mut buf, &label.name)?;
+        buf.push(label.unit as u8);
+        buf.push(0);
+        buf.extend_from_slice(&label.length.to_le_bytes());
+    }
+    Ok(SLMPRequest::new(SLMPCommand::ArrayLabelRead, 0, &buf))
+}
+/// 配列ラベルの一括読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `labels` - 読み出す配列ラベルのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_array_label_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    labels: &[SlmpArrayLabel],
+    abbreviations: &[&str],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_array_label_cmd(labels, abbreviations)?)
+}
+/// 配列ラベルの一括読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// * `labels` - 要求時に指定した配列ラベルのリスト
+/// # 返値
+/// 指定した順のラベルのデータ
+pub fn decode_read_array_label_response(
+    buf: &[u8],
+    labels: &[SlmpArrayLabel],
+) -> Result<Vec<SlmpLabelData>, SlmpError> {
+    let mut r = Reader { buf };
+    if r.u16()? as usize != labels.len() {
+        return Err(SlmpError::MalformedFrame("wrong number of labels"));
+    }
+    let mut ret = Vec::new();
+    for _ in labels {
+        let data_type = SlmpLabelDataType::from_id(r.u8()?);
+        let unit = SlmpLabelUnit::from_u8(r.u8()?)?;
+        let length = r.u16()?;
+        let data = r.take(data_size(unit, length))?.to_vec();
+        ret.push(SlmpLabelData {
+            data_type,
+            unit,
+            length,
+            data,
+        });
+    }
+    if !r.buf.is_empty() {
+        return Err(SlmpError::MalformedFrame("unexpected label response data"));
+    }
+    Ok(ret)
+}
+/// 配列ラベルの一括書込み要求を作成
+/// # 引数
+/// * `labels` - 書き込む配列ラベルとデータのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_array_label_cmd(
+    labels: &[SlmpArrayLabelData],
+    abbreviations: &[&str],
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_label_header(&mut buf, labels.len(), abbreviations)?;
+    for ld in labels {
+        if ld.data.len() != ld.label.data_size() {
+            return Err(SlmpError::InvalidArgument("wrong label data length"));
+        }
+        push_label_name(&mut buf, &ld.label.name)?;
+        buf.push(ld.label.unit as u8);
+        buf.push(0);
+        buf.extend_from_slice(&ld.label.length.to_le_bytes());
+        buf.extend_from_slice(&ld.data);
+    }
+    Ok(SLMPRequest::new(SLMPCommand::ArrayLabelWrite, 0, &buf))
+}
+/// 配列ラベルの一括書込み要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `labels` - 書き込む配列ラベルとデータのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_array_label_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    labels: &[SlmpArrayLabelData],
+    abbreviations: &[&str],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_write_array_label_cmd(labels, abbreviations)?)
+}
+/// ラベルのランダム読出し要求を作成
+/// # 引数
+/// * `names` - 読み出すラベル名のリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 要求内容
+pub fn make_read_random_label_cmd(
+    names: &[&str],
+    abbreviations: &[&str],
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_label_header(&mut buf, names.len(), abbreviations)?;
+    for name in names {
+        push_label_name(&mut buf, name)?;
+    }
+    Ok(SLMPRequest::new(SLMPCommand::LabelReadRandom, 0, &buf))
+}
+/// ラベルのランダム読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `names` - 読み出すラベル名のリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_random_label_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    names: &[&str],
+    abbreviations: &[&str],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_random_label_cmd(names, abbreviations)?)
+}
+/// ラベルのランダム読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// * `names` - 要求時に指定したラベル名のリスト
+/// # 返値
+/// 指定した順のラベルのデータ
+pub fn decode_read_random_label_response(
+    buf: &[u8],
+    names: &[&str],
+) -> Result<Vec<SlmpLabelData>, SlmpError> {
+    let mut r = Reader { buf };
+    if r.u16()? as usize != names.len() {
+        return Err(SlmpError::MalformedFrame("wrong number of labels"));
+    }
+    let mut ret = Vec::new();
+    for _ in names {
+        let data_type = SlmpLabelDataType::from_id(r.u8()?);
+        r.u8()?;
+        let length = r.u16()?;
+        let data = r.take(length as usize)?.to_vec();
+        ret.push(SlmpLabelData {
+            data_type,
+            unit: SlmpLabelUnit::Byte,
+            length,
+            data,
+        });
+    }
+    if !r.buf.is_empty() {
+        return Err(SlmpError::MalformedFrame("unexpected label response data"));
+    }
+    Ok(ret)
+}
+/// ラベルのランダム書込み要求を作成
+/// # 引数
+/// * `labels` - 書き込むラベル名とデータのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 要求内容
+pub fn make_write_random_label_cmd(
+    labels: &[(&str, &[u8])],
+    abbreviations: &[&str],
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_label_header(&mut buf, labels.len(), abbreviations)?;
+    for (name, data) in labels {
+        if data.len() > 0xffff {
+            return Err(SlmpError::InvalidArgument("too long label data"));
+        }
+        push_label_name(&mut buf, name)?;
+        buf.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        buf.extend_from_slice(data);
+    }
+    Ok(SLMPRequest::new(SLMPCommand::LabelWriteRandom, 0, &buf))
+}
+/// ラベルのランダム書込み要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `labels` - 書き込むラベル名とデータのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_write_random_label_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    labels: &[(&str, &[u8])],
+    abbreviations: &[&str],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_write_random_label_cmd(labels, abbreviations)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_random_label() {
+        let req = make_read_random_label_cmd(&["Lbl", "%1.x"], &["Str"]).unwrap();
+        assert_eq!(req.command, SLMPCommand::LabelReadRandom);
+        assert_eq!(
+            req.data,
+            vec![
+                0x02, 0x00, 0x01, 0x00, // 点数
+                0x03, 0x00, b'S', 0, b't', 0, b'r', 0, // 省略ラベル名
+                0x03, 0x00, b'L', 0, b'b', 0, b'l', 0, // ラベル名
+                0x04, 0x00, b'%', 0, b'1', 0, b'.', 0, b'x', 0,
+            ]
+        );
+        let res = [
+            0x02, 0x00, // 点数
+            0x05, 0x00, 0x04, 0x00, 0xfe, 0xff, 0xff, 0xff, // DWord
+            0x0a, 0x00, 0x06, 0x00, b'A', 0, 0x42, 0x30, 0, 0, // WString
+        ];
+        let ret = decode_read_random_label_response(&res, &["Lbl", "%1.x"]).unwrap();
+        assert_eq!(ret[0].data_type, SlmpLabelDataType::DWord);
+        assert_eq!(ret[0].value().unwrap(), SlmpTagValue::I32(-2));
+        assert_eq!(
+            ret[1].value().unwrap(),
+            SlmpTagValue::String("A\u{3042}".to_string())
+        );
+        assert!(decode_read_random_label_response(&res[..8], &["Lbl", "%1.x"]).is_err());
+    }
+
+    #[test]
+    fn test_array_label() {
+        let labels = [
+            SlmpArrayLabel::new("Flags", SlmpLabelUnit::Bit, 18),
+            SlmpArrayLabel::new("Arr[1]", SlmpLabelUnit::Byte, 4),
+        ];
+        let req = make_read_array_label_cmd(&labels, &[]).unwrap();
+        assert_eq!(&req.data[..4], &[0x02, 0x00, 0x00, 0x00]);
+        assert_eq!(&req.data[16..20], &[0x00, 0x00, 0x12, 0x00]);
+        let res = [
+            0x02, 0x00, // 点数
+            0x01, 0x00, 0x12, 0x00, 0x05, 0x00, 0x02, 0x00, // ビット18点
+            0x02, 0x01, 0x04, 0x00, 0x34, 0x12, 0x78, 0x56, // ワード2点
+        ];
+        let ret = decode_read_array_label_response(&res, &labels).unwrap();
+        let bits = match ret[0].value().unwrap() {
+            SlmpTagValue::Array(bits) => bits,
+            v => panic!("{:?}", v),
+        };
+        assert_eq!(bits.len(), 18);
+        assert_eq!(bits[0], SlmpTagValue::Bit(true));
+        assert_eq!(bits[1], SlmpTagValue::Bit(false));
+        assert_eq!(bits[2], SlmpTagValue::Bit(true));
+        assert_eq!(bits[17], SlmpTagValue::Bit(true));
+        assert_eq!(
+            ret[1].value().unwrap(),
+            SlmpTagValue::Array(vec![SlmpTagValue::U16(0x1234), SlmpTagValue::U16(0x5678)])
+        );
+        assert_eq!(
+            encode_label_value(&ret[0].value().unwrap()).unwrap(),
+            ret[0].data
+        );
+
+        let write = [SlmpArrayLabelData {
+            label: labels[1].clone(),
+            data: vec![0x01, 0x02],
+        }];
+        assert!(make_write_array_label_cmd(&write, &[]).is_err());
+    }
+    #[test]
+    fn test_encode_label_value_rejects_lossy() {
+        assert_eq!(
+            encode_label_value(&SlmpTagValue::String(String::from("ABC"))).unwrap(),
+            vec![0x41, 0x42, 0x43, 0x00]
+        );
+        assert!(matches!(
+            encode_label_value(&SlmpTagValue::String(String::from("温度"))),
+            Err(SlmpError::InvalidArgument(_))
+        ));
+        let mixed = SlmpTagValue::Array(vec![SlmpTagValue::U16(1), SlmpTagValue::U32(2)]);
+        assert!(matches!(
+            encode_label_value(&mixed),
+            Err(SlmpError::InvalidArgument(_))
+        ));
+        let nested = SlmpTagValue::Array(vec![SlmpTagValue::Array(vec![SlmpTagValue::U16(1)])]);
+        assert!(encode_label_value(&nested).is_err());
+        let words = SlmpTagValue::Array(vec![SlmpTagValue::I16(-1), SlmpTagValue::I16(2)]);
+        assert_eq!(
+            encode_label_value(&words).unwrap(),
+            vec![0xff, 0xff, 0x02, 0x00]
+        );
+    }
+}
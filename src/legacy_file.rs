@@ -0,0 +1,675 @@
+use crate::file::Reader;
+use crate::{
+    SLMPCommand, SLMPConnectionInfo, SLMPRequest, SlmpError, SlmpFileEntry, SlmpTimestamp,
+};
+
+/// ディレクトリエントリのバイト数
+const ENTRY_SIZE: usize = 32;
+/// ファイルのタイトルのバイト数
+const TITLE_SIZE: usize = 32;
+
+/// ファイルのロック操作
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpFileLockMode {
+    /// ロックを解除する
+    Unlock = 0,
+    /// ロックする
+    Lock = 1,
+}
+
+/// ファイル情報の変更内容
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpFileInfoChange {
+    /// 属性を変更する
+    Attribute(u8),
+    /// 更新日時を変更する
+    Timestamp(SlmpTimestamp),
+}
+
+/// キーワードを追加する
+///
+/// キーワードはASCII4文字、空文字列はキーワードなし
+fn push_keyword(buf: &mut Vec<u8>, keyword: &str) -> Result<(), SlmpError> {
+    match keyword.len() {
+        0 => buf.extend_from_slice(&[0; 4]),
+        4 if keyword.is_ascii() => buf.extend_from_slice(keyword.as_bytes()),
+        _ => {
+            return Err(SlmpError::InvalidArgument(
+                "keyword must be 4 ASCII characters",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// ファイル名を文字数とASCIIで追加する
+fn push_file_name(buf: &mut Vec<u8>, name: &str) -> Result<(), SlmpError> {
+    if name.is_empty() || !name.is_ascii() {
+        return Err(SlmpError::InvalidArgument("file name must be ASCII"));
+    }
+    if name.len() > 12 {
+        return Err(SlmpError::InvalidArgument("too long file name"));
+    }
+    buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    Ok(())
+}
+
+/// 空白を除いたASCII文字列
+fn trim_ascii(buf: &[u8]) -> String {
+    String::from_utf8_lossy(buf)
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+/// FATと同じ32バイトのディレクトリエントリを解析する
+///
+/// # 引数
+///
+/// * `buf` - ディレクトリエントリ
+fn decode_entry(buf: &[u8]) -> SlmpFileEntry {
+    let name = trim_ascii(&buf[0..8]);
+    let ext = trim_ascii(&buf[8..11]);
+    let le16 = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+    SlmpFileEntry {
+        name: match ext.is_empty() {
+            true => name,
+            false => format!("{}.{}", name, ext),
+        },
+        attribute: buf[11] as u16,
+        modified: SlmpTimestamp::from_fat(le16(24), le16(22)),
+        size: u32::from_le_bytes([buf[28], buf[29], buf[30], buf[31]]),
+    }
+}
+
+/// ファイル情報の一覧の読出し要求を作成
+///
+/// # 引数
+///
+/// * `command` - タイトルの有無によるコマンド
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭のファイル番号、1始まり
+/// * `count` - 読み出すファイル数
+fn make_file_info_cmd(
+    command: SLMPCommand,
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    if head == 0 {
+        return Err(SlmpError::InvalidArgument("file number starts from 1"));
+    }
+    let mut buf = vec![0; 4];
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&head.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    Ok(SLMPRequest::new(command, 0, &buf))
+}
+
+/// ファイル情報の一覧の読出し要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号、0はプログラムメモリ、3は標準RAM、4は標準ROM
+/// * `head` - 先頭のファイル番号、1始まり
+/// * `count` - 読み出すファイル数
+/// # 返値
+/// 要求内容
+pub fn make_read_file_info_cmd(
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    make_file_info_cmd(SLMPCommand::ReadFileInfo, drive, head, count)
+}
+/// ファイル情報の一覧の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭のファイル番号、1始まり
+/// * `count` - 読み出すファイル数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_file_info_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_file_info_cmd(drive, head, count)?)
+}
+/// ファイル情報の一覧の読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ファイルの情報のリスト
+pub fn decode_read_file_info_response(buf: &[u8]) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+    let mut r = Reader { buf };
+    let count = r.u16()? as usize;
+    let ret = (0..count)
+        .map(|_| Ok(decode_entry(r.take(ENTRY_SIZE)?)))
+        .collect::<Result<_, SlmpError>>()?;
+    r.finish()?;
+    Ok(ret)
+}
+/// タイトル付きのファイル情報の一覧の読出し要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭のファイル番号、1始まり
+/// * `count` - 読み出すファイル数
+/// # 返値
+/// 要求内容
+pub fn make_read_file_info_with_title_cmd(
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    make_file_info_cmd(SLMPCommand::ReadFileInfoWithTitle, drive, head, count)
+}
+/// タイトル付きのファイル情報の一覧の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭のファイル番号、1始まり
+/// * `count` - 読み出すファイル数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_file_info_with_title_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_read_file_info_with_title_cmd(drive, head, count)?,
+    )
+}
+/// タイトル付きのファイル情報の一覧の読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ファイルの情報とタイトルのリスト
+pub fn decode_read_file_info_with_title_response(
+    buf: &[u8],
+) -> Result<Vec<(SlmpFileEntry, String)>, SlmpError> {
+    let mut r = Reader { buf };
+    let count = r.u16()? as usize;
+    let ret = (0..count)
+        .map(|_| {
+            let entry = decode_entry(r.take(ENTRY_SIZE)?);
+            Ok((entry, trim_ascii(r.take(TITLE_SIZE)?)))
+        })
+        .collect::<Result<_, SlmpError>>()?;
+    r.finish()?;
+    Ok(ret)
+}
+/// ファイル番号の使用状況の読出し要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// # 返値
+/// 要求内容
+pub fn make_read_file_no_info_cmd(drive: u16) -> SLMPRequest {
+    let mut buf = vec![0; 4];
+    buf.extend_from_slice(&drive.to_le_bytes());
+    SLMPRequest::new(SLMPCommand::ReadFileNoInfo, 0, &buf)
+}
+/// ファイル番号の使用状況の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_file_no_info_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_file_no_info_cmd(drive))
+}
+/// ファイル番号の使用状況の読出し応答の解析
+///
+/// 応答はファイル番号1から順に1ビットずつ、使用中であれば1となる
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// 使用中のファイル番号のリスト
+pub fn decode_read_file_no_info_response(buf: &[u8]) -> Result<Vec<u16>, SlmpError> {
+    if buf.len() > 0xffff / 8 {
+        return Err(SlmpError::MalformedFrame("too long file number info"));
+    }
+    Ok((0..buf.len() * 8)
+        .filter(|i| buf[i / 8] & (1 << (i % 8)) != 0)
+        .map(|i| i as u16 + 1)
+        .collect())
+}
+/// ファイルの検索要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `name` - ファイル名、`MAIN.QPG`のような8.3形式
+/// # 返値
+/// 要求内容
+pub fn make_file_search_cmd(
+    keyword: &str,
+    drive: u16,
+    name: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    push_file_name(&mut buf, name)?;
+    Ok(SLMPRequest::new(SLMPCommand::FileSearch, 0, &buf))
+}
+/// ファイルの検索要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `name` - ファイル名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_search_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    name: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_file_search_cmd(keyword, drive, name)?)
+}
+/// ファイルの検索応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// ファイル番号とファイルサイズ
+pub fn decode_file_search_response(buf: &[u8]) -> Result<(u16, u32), SlmpError> {
+    let mut r = Reader { buf };
+    let number = r.u16()?;
+    let size = r.u32()?;
+    r.finish()?;
+    Ok((number, size))
+}
+/// ファイル番号を指定した読出し要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `offset` - 読み出す位置、単位はバイト
+/// * `size` - 読み出すバイト数
+/// # 返値
+/// 要求内容
+pub fn make_file_read_cmd(
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    offset: u32,
+    size: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    if size as usize > crate::MAX_FILE_CHUNK {
+        return Err(SlmpError::InvalidArgument("too large file read"));
+    }
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&number.to_le_bytes());
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    Ok(SLMPRequest::new(SLMPCommand::FileRead, 0, &buf))
+}
+/// ファイル番号を指定した読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `offset` - 読み出す位置、単位はバイト
+/// * `size` - 読み出すバイト数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_read_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    offset: u32,
+    size: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_file_read_cmd(keyword, drive, number, offset, size)?,
+    )
+}
+/// ファイル番号を指定した読出し応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// 読み出したデータ
+pub fn decode_file_read_response(buf: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    crate::decode_read_file_response(buf)
+}
+/// ファイル番号を指定した書込み要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `offset` - 書き込む位置、単位はバイト
+/// * `data` - 書き込むデータ
+/// # 返値
+/// 要求内容
+pub fn make_file_write_cmd(
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    offset: u32,
+    data: &[u8],
+) -> Result<SLMPRequest, SlmpError> {
+    if data.len() > crate::MAX_FILE_CHUNK {
+        return Err(SlmpError::InvalidArgument("too large file write"));
+    }
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&number.to_le_bytes());
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    buf.extend_from_slice(data);
+    Ok(SLMPRequest::new(SLMPCommand::FileWrite, 0, &buf))
+}
+/// ファイル番号を指定した書込み要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `offset` - 書き込む位置、単位はバイト
+/// * `data` - 書き込むデータ
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_write_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    offset: u32,
+    data: &[u8],
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_file_write_cmd(keyword, drive, number, offset, data)?,
+    )
+}
+/// ファイルのロック要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `mode` - ロックするか解除するか
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// # 返値
+/// 要求内容
+pub fn make_file_lock_cmd(
+    keyword: &str,
+    mode: SlmpFileLockMode,
+    drive: u16,
+    number: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&(mode as u16).to_le_bytes());
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&number.to_le_bytes());
+    Ok(SLMPRequest::new(SLMPCommand::FileLock, 0, &buf))
+}
+/// ファイルのロック要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `mode` - ロックするか解除するか
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_lock_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    mode: SlmpFileLockMode,
+    drive: u16,
+    number: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_file_lock_cmd(keyword, mode, drive, number)?)
+}
+/// ファイル名を指定したファイルの新規作成要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `size` - ファイルサイズ、単位はバイト
+/// * `name` - ファイル名
+/// # 返値
+/// 要求内容
+pub fn make_new_file_a_cmd(
+    keyword: &str,
+    drive: u16,
+    size: u32,
+    name: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    push_file_name(&mut buf, name)?;
+    Ok(SLMPRequest::new(SLMPCommand::NewFileA, 0, &buf))
+}
+/// ファイル名を指定したファイルの新規作成要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `size` - ファイルサイズ、単位はバイト
+/// * `name` - ファイル名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_new_file_a_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    size: u32,
+    name: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_new_file_a_cmd(keyword, drive, size, name)?)
+}
+/// ファイル名を指定したファイルの新規作成応答の解析
+/// # 引数
+/// * `buf` - 受信ペイロード
+/// # 返値
+/// 作成したファイルのファイル番号
+pub fn decode_new_file_a_response(buf: &[u8]) -> Result<u16, SlmpError> {
+    let mut r = Reader { buf };
+    let number = r.u16()?;
+    r.finish()?;
+    Ok(number)
+}
+/// ファイル名を指定したファイルのコピー要求を作成
+/// # 引数
+/// * `dst` - コピー先のキーワード、ドライブ番号、ファイル名
+/// * `src` - コピー元のキーワード、ドライブ番号、ファイル名
+/// # 返値
+/// 要求内容
+pub fn make_file_copy_cmd(
+    dst: (&str, u16, &str),
+    src: (&str, u16, &str),
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    for (keyword, drive, name) in [dst, src].iter() {
+        push_keyword(&mut buf, keyword)?;
+        buf.extend_from_slice(&drive.to_le_bytes());
+        push_file_name(&mut buf, name)?;
+    }
+    Ok(SLMPRequest::new(SLMPCommand::FileCopy, 0, &buf))
+}
+/// ファイル名を指定したファイルのコピー要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `dst` - コピー先のキーワード、ドライブ番号、ファイル名
+/// * `src` - コピー元のキーワード、ドライブ番号、ファイル名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_copy_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    dst: (&str, u16, &str),
+    src: (&str, u16, &str),
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_file_copy_cmd(dst, src)?)
+}
+/// ファイル名を指定したファイルの削除要求を作成
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `name` - ファイル名
+/// # 返値
+/// 要求内容
+pub fn make_file_delete_cmd(
+    keyword: &str,
+    drive: u16,
+    name: &str,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    push_file_name(&mut buf, name)?;
+    Ok(SLMPRequest::new(SLMPCommand::FileDelete, 0, &buf))
+}
+/// ファイル名を指定したファイルの削除要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `name` - ファイル名
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_file_delete_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    name: &str,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_file_delete_cmd(keyword, drive, name)?)
+}
+/// ファイル情報の変更要求を作成
+///
+/// 属性の変更はサブコマンド0000、更新日時の変更はサブコマンド0001となる
+/// # 引数
+/// * `keyword` - キーワード、ASCII4文字、空文字列はキーワードなし
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `change` - 変更内容
+/// # 返値
+/// 要求内容
+pub fn make_change_file_info_cmd(
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    change: SlmpFileInfoChange,
+) -> Result<SLMPRequest, SlmpError> {
+    let mut buf = Vec::new();
+    push_keyword(&mut buf, keyword)?;
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&number.to_le_bytes());
+    let s_cmd = match change {
+        SlmpFileInfoChange::Attribute(attribute) => {
+            buf.extend_from_slice(&(attribute as u16).to_le_bytes());
+            0
+        }
+        SlmpFileInfoChange::Timestamp(modified) => {
+            let (date, time) = modified.to_fat()?;
+            buf.extend_from_slice(&time.to_le_bytes());
+            buf.extend_from_slice(&date.to_le_bytes());
+            1
+        }
+    };
+    Ok(SLMPRequest::new(SLMPCommand::ChangeFileInfo, s_cmd, &buf))
+}
+/// ファイル情報の変更要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `keyword` - キーワード
+/// * `drive` - ドライブ番号
+/// * `number` - ファイル番号
+/// * `change` - 変更内容
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_change_file_info_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    keyword: &str,
+    drive: u16,
+    number: u16,
+    change: SlmpFileInfoChange,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(
+        timeout,
+        &make_change_file_info_cmd(keyword, drive, number, change)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_file_info() {
+        let req = make_read_file_info_cmd(0, 1, 36).unwrap();
+        assert_eq!(
+            req.data,
+            vec![0, 0, 0, 0, 0x00, 0x00, 0x01, 0x00, 0x24, 0x00]
+        );
+        assert!(make_read_file_info_cmd(0, 0, 36).is_err());
+
+        let mut res = vec![0x01, 0x00];
+        res.extend_from_slice(b"MAIN    QPG");
+        res.push(0x01);
+        res.extend_from_slice(&[0; 10]);
+        res.extend_from_slice(&[0x8c, 0x58, 0x52, 0x5b, 0x00, 0x00]);
+        res.extend_from_slice(&0x1234u32.to_le_bytes());
+        let ret = decode_read_file_info_response(&res).unwrap();
+        assert_eq!(ret[0].name, "MAIN.QPG");
+        assert!(ret[0].is_read_only());
+        assert_eq!(ret[0].modified.year, 2025);
+        assert_eq!(ret[0].size, 0x1234);
+        assert!(decode_read_file_info_response(&res[..20]).is_err());
+
+        res.extend_from_slice(b"LINE 1 PROGRAM");
+        res.extend_from_slice(&[b' '; 18]);
+        let ret = decode_read_file_info_with_title_response(&res).unwrap();
+        assert_eq!(ret[0].1, "LINE 1 PROGRAM");
+    }
+
+    #[test]
+    fn test_keyword() {
+        let req = make_file_read_cmd("ABCD", 4, 3, 0x100, 16).unwrap();
+        assert_eq!(&req.data[..8], b"ABCD\x04\x00\x03\x00");
+        assert!(make_file_read_cmd("ABC", 4, 3, 0, 16).is_err());
+        assert_eq!(
+            decode_read_file_no_info_response(&[0x05, 0x80]).unwrap(),
+            vec![1, 3, 16]
+        );
+        let req = make_change_file_info_cmd("", 0, 1, SlmpFileInfoChange::Attribute(0x01)).unwrap();
+        assert_eq!(req.sub_command, 0);
+    }
+}
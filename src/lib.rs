@@ -1,16 +1,71 @@
+// deriveマクロが生成する`::slmp_client`のパスをクレート内でも解決できるようにする
+extern crate self as slmp_client;
+
+#[cfg(feature = "tokio")]
+mod async_client;
+mod client;
+mod codec;
+mod device_name;
+mod device_struct;
 mod dual_port_memory;
 mod enums;
+mod error;
+mod event_history;
+mod file;
 mod internal_memory;
+mod label;
+mod legacy_file;
+mod mc1e;
+mod monitor;
+mod operation;
 mod packing;
+mod pipeline;
+mod read_plan;
+mod reconnect;
 mod remote_control;
+mod serial;
+#[cfg(any(test, feature = "simulator"))]
+mod server;
+mod split;
 mod structs;
+mod subscription;
+mod tag;
+mod transport;
 
+#[cfg(feature = "tokio")]
+pub use async_client::*;
+pub use client::*;
+pub use codec::*;
+pub use device_name::*;
+pub use device_struct::*;
 pub use dual_port_memory::*;
 pub use enums::*;
+pub use error::*;
+pub use event_history::*;
+pub use file::*;
 pub use internal_memory::*;
+pub use label::*;
+pub use legacy_file::*;
+pub use mc1e::*;
+pub use monitor::*;
 pub use packing::*;
+pub use pipeline::*;
+pub use read_plan::*;
+pub use reconnect::*;
 pub use remote_control::*;
+pub use serial::*;
+#[cfg(any(test, feature = "simulator"))]
+pub use server::*;
+#[cfg(feature = "derive")]
+pub use slmp_client_derive::SlmpStruct;
+pub use split::{
+    MAX_BATCH_BITS, MAX_BATCH_BITS_ASCII, MAX_BATCH_WORDS, MAX_BLOCKS, MAX_BLOCK_POINTS,
+    MAX_RANDOM_READ_POINTS, MAX_RANDOM_WRITE_BITS, MAX_RANDOM_WRITE_SIZE,
+};
 pub use structs::*;
+pub use subscription::*;
+pub use tag::*;
+pub use transport::*;
 
 /// デバイス読み書き時のデバイス指定32bit版
 ///
@@ -24,10 +79,8 @@ pub use structs::*;
 ///
 /// パックしたバイト列
 pub fn make_cmd_rw_devices32(d_type: SLMPDeviceCode, first_addr: u32, count: u16) -> Vec<u8> {
-    let mut buf = Vec::new();
     let d_code = d_type as u16;
-    buf.push(d_code as u8);
-    buf.push((d_code >> 8) as u8);
+    let mut buf = vec![d_code as u8, (d_code >> 8) as u8];
     buf.push(first_addr as u8);
     buf.push((first_addr >> 8) as u8);
     buf.push((first_addr >> 16) as u8);
@@ -49,9 +102,8 @@ pub fn make_cmd_rw_devices32(d_type: SLMPDeviceCode, first_addr: u32, count: u16
 ///
 /// パックしたバイト列
 pub fn make_cmd_rw_devices16(d_type: SLMPDeviceCode, first_addr: u16, count: u16) -> Vec<u8> {
-    let mut buf = Vec::new();
     let d_code = d_type as u8;
-    buf.push(d_code);
+    let mut buf = vec![d_code];
     buf.push(first_addr as u8);
     buf.push((first_addr >> 8) as u8);
     buf.push(0u8);
@@ -59,6 +111,24 @@ pub fn make_cmd_rw_devices16(d_type: SLMPDeviceCode, first_addr: u16, count: u16
     buf.push((count >> 8) as u8);
     buf
 }
+/// セルフチェック要求の作成
+///
+/// # 引数
+///
+/// * `data` - 折り返しチェックのための文字列データ、0-9もしくはA-Zのみが使用可能
+///
+/// # 返値
+///
+/// 要求内容
+pub fn make_self_test_cmd(data: &[u8]) -> Result<SLMPRequest, SlmpError> {
+    let length = data.len();
+    if length > 960 {
+        return Err(SlmpError::InvalidArgument("too long data"));
+    }
+    let mut buf = vec![length as u8, (length >> 8) as u8];
+    buf.extend_from_slice(data);
+    Ok(SLMPRequest::new(SLMPCommand::SelfTest, 0, &buf))
+}
 /// セルフチェック
 ///
 /// # 引数
@@ -74,52 +144,71 @@ pub fn send_self_test_cmd(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     data: &[u8],
-) -> Option<u16> {
-    let mut buf = Vec::new();
-    let length = data.len();
-    if length > 960 {
-        eprintln!("too long data");
-        return None;
-    }
-    buf.push(length as u8);
-    buf.push((length >> 8) as u8);
-    buf.extend_from_slice(data);
-    connection_info.send_cmd(timeout, SLMPCommand::SelfTest, 0, &buf)
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_self_test_cmd(data)?)
 }
 /// セルフテストの応答を処理
 /// # 引数
 /// * `buf` - セルフテストの応答を含んだバッファ
 /// # 返値
 /// セルフテストで送信したデータ
-pub fn decode_self_test_response(buf: &[u8]) -> Vec<u8> {
-    let mut ret = Vec::new();
-    for d in buf[2..].iter() {
-        ret.push(*d);
+pub fn decode_self_test_response(buf: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    if buf.len() < 2 {
+        return Err(SlmpError::MalformedFrame("too short self test response"));
+    }
+    let length = (buf[0] as usize) + ((buf[1] as usize) << 8);
+    if buf.len() != length + 2 {
+        return Err(SlmpError::MalformedFrame("wrong self test response length"));
     }
-    ret
+    Ok(buf[2..].to_vec())
 }
 
+/// エラークリア要求の作成
+/// # 返値
+/// 要求内容
+pub fn make_clear_error_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::ClearErrorCode, 0, &[])
+}
 /// エラークリア
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト, 250msec単位
 /// # 返値
 /// コマンド発行時のシリアル番号
-pub fn send_clear_error_cmd(connection_info: &mut SLMPConnectionInfo, timeout: u16) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::ClearErrorCode, 0, &[])
+pub fn send_clear_error_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_clear_error_cmd())
+}
+/// エラー履歴クリア要求の作成
+/// # 返値
+/// 要求内容
+pub fn make_clear_error_history_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::ClearErrorHistory, 0, &[])
+}
+/// エラー履歴クリア
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト, 250msec単位
+/// # 返値
+/// コマンド発行時のシリアル番号
+pub fn send_clear_error_history_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_clear_error_history_cmd())
 }
 /// 受信したオンデマンドデータの処理
 /// # 引数
 /// * `buf` - 受信したオンデマンドデータの入ったバッファ
 /// # 返値
 /// オンデマンドデータ
-pub fn decode_on_demand_data(buf: &[u8]) -> Option<Vec<u8>> {
-    if buf[0] != 1 || buf[1] != 0x21 || buf[2] != 0 || buf[3] != 0 {
-        None
+pub fn decode_on_demand_data(buf: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    if buf.len() < 4 || buf[0] != 1 || buf[1] != 0x21 || buf[2] != 0 || buf[3] != 0 {
+        Err(SlmpError::MalformedFrame("not on-demand data"))
     } else {
-        let mut ret = Vec::new();
-        ret.copy_from_slice(&buf[3..]);
-        Some(ret)
+        Ok(buf[4..].to_vec())
     }
 }
 
@@ -134,16 +223,29 @@ mod tests {
         let mut connection_info = SLMPConnectionInfo::new_udp(SocketAddr::new(
             IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
             5000,
-        ));
+        ))
+        .unwrap();
         let buf = [0x31, 0x32, 0x34];
-        let seq = send_self_test_cmd(&mut connection_info, 40, &buf);
-        let (seq_r, buf_r, end_code) = connection_info.recv_cmd();
-        assert_eq!(seq.unwrap(), seq_r);
-        assert_eq!(end_code, Some(SLMPEndCode::Success));
-        let ret = decode_self_test_response(&buf_r);
+        let seq = send_self_test_cmd(&mut connection_info, 40, &buf).unwrap();
+        let (seq_r, buf_r) = connection_info.recv_cmd().unwrap();
+        assert_eq!(seq, seq_r);
+        let ret = decode_self_test_response(&buf_r).unwrap();
         assert_eq!(ret.len(), 3);
-        assert_eq!(ret[0] as u8, buf[0] as u8);
-        assert_eq!(ret[1] as u8, buf[1] as u8);
-        assert_eq!(ret[2] as u8, buf[2] as u8);
+        assert_eq!(ret[0], buf[0]);
+        assert_eq!(ret[1], buf[1]);
+        assert_eq!(ret[2], buf[2]);
+    }
+
+    #[test]
+    pub fn test_udp_self_test_local() {
+        let server =
+            SlmpServer::start_udp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let mut connection_info = SLMPConnectionInfo::new_udp(server.local_addr()).unwrap();
+        let buf = [0x31, 0x32, 0x34];
+        let seq = send_self_test_cmd(&mut connection_info, 4, &buf).unwrap();
+        let (seq_r, buf_r) = connection_info.recv_cmd().unwrap();
+        assert_eq!(seq, seq_r);
+        let ret = decode_self_test_response(&buf_r).unwrap();
+        assert_eq!(ret, buf.to_vec());
     }
 }
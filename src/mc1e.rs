@@ -0,0 +1,599 @@
+use crate::enums::TCPorUDP;
+use crate::transport::SlmpTransport;
+use crate::{
+    decode_read_bit_response, decode_read_word_response, pack_bits_by_bit, pack_words_by_word,
+    SLMPDevice, SLMPDeviceCode, SLMPDeviceData, SlmpError,
+};
+use std::io;
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+
+/// MCプロトコル1Eフレームのコマンド
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MC1ECommand {
+    /// ビット単位の一括読出し
+    BatchReadBit = 0x00,
+    /// ワード単位の一括読出し
+    BatchReadWord = 0x01,
+    /// ビット単位の一括書込み
+    BatchWriteBit = 0x02,
+    /// ワード単位の一括書込み
+    BatchWriteWord = 0x03,
+    /// ビット単位のテスト(ランダム書込み)
+    TestBit = 0x04,
+    /// ワード単位のテスト(ランダム書込み)
+    TestWord = 0x05,
+    /// 折返しテスト
+    LoopbackTest = 0x16,
+}
+
+/// 1Eフレームの要求内容
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MC1ERequest {
+    /// コマンド
+    pub command: MC1ECommand,
+    /// 要求データ
+    pub data: Vec<u8>,
+    /// 正常終了時の応答データ長
+    pub response_len: usize,
+}
+
+/// 1Eフレームでのデバイスコード
+///
+/// # 引数
+///
+/// * `d_code` - デバイス
+///
+/// # 返値
+///
+/// 2文字のデバイス名からなるデバイスコード
+pub fn mc1e_device_code(d_code: SLMPDeviceCode) -> Result<u16, SlmpError> {
+    use SLMPDeviceCode::*;
+    let name = match d_code {
+        X => b"X ",
+        Y => b"Y ",
+        M => b"M ",
+        L => b"L ",
+        F => b"F ",
+        B => b"B ",
+        D => b"D ",
+        W => b"W ",
+        R => b"R ",
+        TS => b"TS",
+        TC => b"TC",
+        TN => b"TN",
+        CS => b"CS",
+        CC => b"CC",
+        CN => b"CN",
+        _ => {
+            return Err(SlmpError::InvalidArgument(
+                "device not supported in 1E frame",
+            ))
+        }
+    };
+    Ok(((name[0] as u16) << 8) + name[1] as u16)
+}
+
+/// 1Eフレームでのデバイス指定
+fn pack_1e_device(dev: &SLMPDevice) -> Result<[u8; 6], SlmpError> {
+    let d_code = mc1e_device_code(dev.d_code)?;
+    Ok([
+        dev.addr as u8,
+        (dev.addr >> 8) as u8,
+        (dev.addr >> 16) as u8,
+        (dev.addr >> 24) as u8,
+        d_code as u8,
+        (d_code >> 8) as u8,
+    ])
+}
+
+/// 一括読み書きの点数、256点は0で表す
+fn pack_1e_count(count: usize) -> Result<u8, SlmpError> {
+    match count {
+        0 => Err(SlmpError::InvalidArgument("no target")),
+        1..=256 => Ok(count as u8),
+        _ => Err(SlmpError::InvalidArgument("too many targets")),
+    }
+}
+
+/// ビット単位の一括読出し要求作成
+/// # 引数
+/// * `target` - 先頭デバイス
+/// * `count` - デバイス個数、最大256点
+/// # 返値
+/// 要求内容
+pub fn make_1e_read_bit_cmd(target: SLMPDevice, count: u16) -> Result<MC1ERequest, SlmpError> {
+    let mut data = Vec::from(pack_1e_device(&target)?);
+    data.push(pack_1e_count(count as usize)?);
+    data.push(0);
+    Ok(MC1ERequest {
+        command: MC1ECommand::BatchReadBit,
+        data,
+        response_len: (count as usize).div_ceil(2),
+    })
+}
+
+/// ワード単位の一括読出し要求作成
+/// # 引数
+/// * `target` - 先頭デバイス
+/// * `count` - ワード数、最大256点
+/// # 返値
+/// 要求内容
+pub fn make_1e_read_word_cmd(target: SLMPDevice, count: u16) -> Result<MC1ERequest, SlmpError> {
+    let mut data = Vec::from(pack_1e_device(&target)?);
+    data.push(pack_1e_count(count as usize)?);
+    data.push(0);
+    Ok(MC1ERequest {
+        command: MC1ECommand::BatchReadWord,
+        data,
+        response_len: count as usize * 2,
+    })
+}
+
+/// ビット単位の一括書込み要求作成
+/// # 引数
+/// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+/// # 返値
+/// 要求内容
+pub fn make_1e_write_bit_cmd(targets: &[SLMPDeviceData<bool>]) -> Result<MC1ERequest, SlmpError> {
+    let count = pack_1e_count(targets.len())?;
+    let mut data = Vec::from(pack_1e_device(&targets[0].dev)?);
+    data.push(count);
+    data.push(0);
+    let d_buf: Vec<bool> = targets.iter().map(|d| d.value).collect();
+    data.extend_from_slice(&pack_bits_by_bit(&d_buf));
+    Ok(MC1ERequest {
+        command: MC1ECommand::BatchWriteBit,
+        data,
+        response_len: 0,
+    })
+}
+
+/// ワード単位の一括書込み要求作成
+/// # 引数
+/// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+/// # 返値
+/// 要求内容
+pub fn make_1e_write_word_cmd(targets: &[SLMPDeviceData<u16>]) -> Result<MC1ERequest, SlmpError> {
+    let count = pack_1e_count(targets.len())?;
+    let mut data = Vec::from(pack_1e_device(&targets[0].dev)?);
+    data.push(count);
+    data.push(0);
+    let d_buf: Vec<u16> = targets.iter().map(|d| d.value).collect();
+    data.extend_from_slice(&pack_words_by_word(&d_buf));
+    Ok(MC1ERequest {
+        command: MC1ECommand::BatchWriteWord,
+        data,
+        response_len: 0,
+    })
+}
+
+/// ビット単位のテスト(ランダム書込み)要求作成
+/// # 引数
+/// * `targets` - 書き込むデバイスのリスト、最大80点
+/// # 返値
+/// 要求内容
+pub fn make_1e_test_bit_cmd(targets: &[SLMPDeviceData<bool>]) -> Result<MC1ERequest, SlmpError> {
+    if targets.len() > 80 {
+        return Err(SlmpError::InvalidArgument("too many targets"));
+    }
+    let mut data = vec![pack_1e_count(targets.len())?, 0];
+    for dd in targets {
+        data.extend_from_slice(&pack_1e_device(&dd.dev)?);
+        data.push(dd.value as u8);
+    }
+    Ok(MC1ERequest {
+        command: MC1ECommand::TestBit,
+        data,
+        response_len: 0,
+    })
+}
+
+/// ワード単位のテスト(ランダム書込み)要求作成
+/// # 引数
+/// * `targets` - 書き込むデバイスのリスト、最大40点
+/// # 返値
+/// 要求内容
+pub fn make_1e_test_word_cmd(targets: &[SLMPDeviceData<u16>]) -> Result<MC1ERequest, SlmpError> {
+    if targets.len() > 40 {
+        return Err(SlmpError::InvalidArgument("too many targets"));
+    }
+    let mut data = vec![pack_1e_count(targets.len())?, 0];
+    for dd in targets {
+        data.extend_from_slice(&pack_1e_device(&dd.dev)?);
+        data.push(dd.value as u8);
+        data.push((dd.value >> 8) as u8);
+    }
+    Ok(MC1ERequest {
+        command: MC1ECommand::TestWord,
+        data,
+        response_len: 0,
+    })
+}
+
+/// 折返しテスト要求作成
+/// # 引数
+/// * `data` - 折り返すデータ、1から254バイト
+/// # 返値
+/// 要求内容
+pub fn make_1e_loopback_cmd(data: &[u8]) -> Result<MC1ERequest, SlmpError> {
+    if data.is_empty() || data.len() > 254 {
+        return Err(SlmpError::InvalidArgument("wrong loopback data length"));
+    }
+    let mut buf = vec![data.len() as u8];
+    buf.extend_from_slice(data);
+    Ok(MC1ERequest {
+        command: MC1ECommand::LoopbackTest,
+        data: buf,
+        response_len: data.len() + 1,
+    })
+}
+/// 折返しテストの応答を処理
+/// # 引数
+/// * `buf` - 応答データ
+/// # 返値
+/// 折り返されたデータ
+pub fn decode_1e_loopback_response(buf: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    if buf.is_empty() || buf[0] as usize + 1 != buf.len() {
+        return Err(SlmpError::MalformedFrame("wrong loopback response length"));
+    }
+    Ok(buf[1..].to_vec())
+}
+
+/// MCプロトコル1Eフレームの接続管理
+pub struct MC1EConnectionInfo {
+    /// PC番号
+    pc_no: u8,
+    /// 通信路
+    socket: Box<dyn SlmpTransport>,
+    /// 受信バッファ
+    buf: Vec<u8>,
+}
+
+impl MC1EConnectionInfo {
+    /// TCPにて1Eフレームの接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    pub fn new_tcp(addr: SocketAddr) -> Result<MC1EConnectionInfo, SlmpError> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(MC1EConnectionInfo {
+            pc_no: 0xff,
+            socket: Box::new(TCPorUDP::TCP(stream)),
+            buf: Vec::new(),
+        })
+    }
+    /// UDPにて1Eフレームの接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    pub fn new_udp(addr: SocketAddr) -> Result<MC1EConnectionInfo, SlmpError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(MC1EConnectionInfo {
+            pc_no: 0xff,
+            socket: Box::new(TCPorUDP::UDP(socket, addr)),
+            buf: Vec::new(),
+        })
+    }
+    /// 任意の通信路にて1Eフレームの接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `transport` - 通信路
+    pub fn from_transport<T: SlmpTransport + 'static>(transport: T) -> MC1EConnectionInfo {
+        MC1EConnectionInfo {
+            pc_no: 0xff,
+            socket: Box::new(transport),
+            buf: Vec::new(),
+        }
+    }
+    pub fn get_pc_no(&self) -> u8 {
+        self.pc_no
+    }
+    pub fn set_pc_no(&mut self, pc_no: u8) {
+        self.pc_no = pc_no
+    }
+    /// 要求を送信する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - 監視タイマ、単位は250ms
+    /// * `req` - 要求内容
+    pub fn send_request(&mut self, timeout: u16, req: &MC1ERequest) -> Result<(), SlmpError> {
+        let mut buf = vec![
+            req.command as u8,
+            self.pc_no,
+            timeout as u8,
+            (timeout >> 8) as u8,
+        ];
+        buf.extend_from_slice(&req.data);
+        // 1Eフレームは応答を要求と対応付けられないため、古い受信データは破棄する
+        self.buf.clear();
+        self.socket.set_timeout(timeout as u64 * 250)?;
+        let send_size = self.socket.send(&buf)?;
+        if send_size != buf.len() {
+            return Err(SlmpError::Io(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "write size error",
+            )));
+        }
+        Ok(())
+    }
+    /// 要求に対する応答を受信する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 送信した要求内容
+    ///
+    /// # 返値
+    ///
+    /// 応答データ
+    pub fn recv_response(&mut self, req: &MC1ERequest) -> Result<Vec<u8>, SlmpError> {
+        let mut buf = [0u8; 2048];
+        loop {
+            if self.buf.len() >= 2 {
+                if self.buf[0] != req.command as u8 | 0x80 {
+                    self.buf.clear();
+                    return Err(SlmpError::MalformedFrame("wrong subheader"));
+                }
+                match self.buf[1] {
+                    0x00 => {
+                        if self.buf.len() >= 2 + req.response_len {
+                            let data = self.buf[2..2 + req.response_len].to_vec();
+                            self.buf.drain(..2 + req.response_len);
+                            return Ok(data);
+                        }
+                    }
+                    // 異常コードが続く
+                    0x5B => {
+                        if self.buf.len() >= 3 {
+                            let code = 0x5B00 + self.buf[2] as u16;
+                            self.buf.drain(..3);
+                            return Err(SlmpError::EndCode {
+                                serial: 0,
+                                code,
+                                info: None,
+                            });
+                        }
+                    }
+                    code => {
+                        self.buf.drain(..2);
+                        return Err(SlmpError::EndCode {
+                            serial: 0,
+                            code: code as u16,
+                            info: None,
+                        });
+                    }
+                }
+            }
+            let recv_size = match self.socket.recv(&mut buf) {
+                Ok(recv_size) => recv_size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if recv_size == 0 {
+                return Err(SlmpError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                )));
+            }
+            self.buf.extend_from_slice(&buf[..recv_size]);
+        }
+    }
+    /// 要求を送信し、応答を受信する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - 監視タイマ、単位は250ms
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 応答データ
+    pub fn request(&mut self, timeout: u16, req: &MC1ERequest) -> Result<Vec<u8>, SlmpError> {
+        self.send_request(timeout, req)?;
+        self.recv_response(req)
+    }
+}
+
+/// MCプロトコル1Eフレームのクライアント
+pub struct MC1EClient {
+    /// 1Eフレームの接続情報
+    connection_info: MC1EConnectionInfo,
+    /// 監視タイマ、単位は250ms
+    timeout: u16,
+}
+
+impl MC1EClient {
+    /// 接続情報からクライアントを作成
+    ///
+    /// # 引数
+    ///
+    /// * `connection_info` - 1Eフレームの接続情報
+    pub fn new(connection_info: MC1EConnectionInfo) -> MC1EClient {
+        MC1EClient {
+            connection_info,
+            timeout: 40,
+        }
+    }
+    pub fn get_timeout(&self) -> u16 {
+        self.timeout
+    }
+    pub fn set_timeout(&mut self, timeout: u16) {
+        self.timeout = timeout
+    }
+    pub fn connection_info_mut(&mut self) -> &mut MC1EConnectionInfo {
+        &mut self.connection_info
+    }
+    /// 単一の連続したビットデバイスを読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - デバイス個数、最大256点
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub fn read_bits(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<bool>>, SlmpError> {
+        let req = make_1e_read_bit_cmd(target, count)?;
+        let buf = self.connection_info.request(self.timeout, &req)?;
+        let mut ret = decode_read_bit_response(&buf, target)?;
+        ret.truncate(count as usize);
+        Ok(ret)
+    }
+    /// 単一の連続したデバイスをワード単位で読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `target` - 先頭デバイス
+    /// * `count` - ワード数、最大256点
+    ///
+    /// # 返値
+    ///
+    /// 読み取ったデバイスのリスト
+    pub fn read_words(
+        &mut self,
+        target: SLMPDevice,
+        count: u16,
+    ) -> Result<Vec<SLMPDeviceData<u16>>, SlmpError> {
+        let req = make_1e_read_word_cmd(target, count)?;
+        let buf = self.connection_info.request(self.timeout, &req)?;
+        decode_read_word_response(&buf, target)
+    }
+    /// 単一の連続したビットデバイスへ書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub fn write_bits(&mut self, targets: &[SLMPDeviceData<bool>]) -> Result<(), SlmpError> {
+        let req = make_1e_write_bit_cmd(targets)?;
+        self.connection_info.request(self.timeout, &req)?;
+        Ok(())
+    }
+    /// 単一の連続したデバイスへワード単位で書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+    pub fn write_words(&mut self, targets: &[SLMPDeviceData<u16>]) -> Result<(), SlmpError> {
+        let req = make_1e_write_word_cmd(targets)?;
+        self.connection_info.request(self.timeout, &req)?;
+        Ok(())
+    }
+    /// 指定したビットデバイスへ書き込む(テスト)
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - ビットデバイスデータのリスト
+    pub fn write_random_bits(&mut self, targets: &[SLMPDeviceData<bool>]) -> Result<(), SlmpError> {
+        let req = make_1e_test_bit_cmd(targets)?;
+        self.connection_info.request(self.timeout, &req)?;
+        Ok(())
+    }
+    /// 指定したワードデバイスへ書き込む(テスト)
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - ワードデバイスデータのリスト
+    pub fn write_random_words(&mut self, targets: &[SLMPDeviceData<u16>]) -> Result<(), SlmpError> {
+        let req = make_1e_test_word_cmd(targets)?;
+        self.connection_info.request(self.timeout, &req)?;
+        Ok(())
+    }
+    /// 折返しテスト
+    ///
+    /// # 引数
+    ///
+    /// * `data` - 折り返すデータ
+    ///
+    /// # 返値
+    ///
+    /// 折り返されたデータ
+    pub fn loopback_test(&mut self, data: &[u8]) -> Result<Vec<u8>, SlmpError> {
+        let req = make_1e_loopback_cmd(data)?;
+        let buf = self.connection_info.request(self.timeout, &req)?;
+        decode_1e_loopback_response(&buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_peer(expected: Vec<u8>, response: Vec<u8>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            let (size, from) = socket.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..size], expected.as_slice());
+            socket.send_to(&response, from).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_1e_read_words() {
+        let addr = udp_peer(
+            vec![
+                0x01, 0xff, 0x04, 0x00, 0x64, 0x00, 0x00, 0x00, 0x20, 0x44, 0x02, 0x00,
+            ],
+            vec![0x81, 0x00, 0x34, 0x12, 0x02, 0x00],
+        );
+        let mut client = MC1EClient::new(MC1EConnectionInfo::new_udp(addr).unwrap());
+        client.set_timeout(4);
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let ret = client.read_words(dev, 2).unwrap();
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+        assert_eq!(ret[1].dev.addr, 101);
+    }
+
+    #[test]
+    fn test_1e_read_bits() {
+        let addr = udp_peer(
+            vec![
+                0x00, 0xff, 0x04, 0x00, 0x10, 0x00, 0x00, 0x00, 0x20, 0x58, 0x03, 0x00,
+            ],
+            vec![0x80, 0x00, 0x10, 0x10],
+        );
+        let mut client = MC1EClient::new(MC1EConnectionInfo::new_udp(addr).unwrap());
+        client.set_timeout(4);
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::X,
+            addr: 0x10,
+        };
+        let ret = client.read_bits(dev, 3).unwrap();
+        let values: Vec<bool> = ret.iter().map(|d| d.value).collect();
+        assert_eq!(values, vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_1e_abnormal_code() {
+        let addr = udp_peer(
+            vec![0x16, 0xff, 0x04, 0x00, 0x02, b'A', b'B'],
+            vec![0x96, 0x5b, 0x10],
+        );
+        let mut client = MC1EClient::new(MC1EConnectionInfo::new_udp(addr).unwrap());
+        client.set_timeout(4);
+        match client.loopback_test(b"AB") {
+            Err(SlmpError::EndCode { code, .. }) => assert_eq!(code, 0x5b10),
+            _ => panic!("end code error expected"),
+        }
+    }
+
+    #[test]
+    fn test_1e_device_code() {
+        assert_eq!(mc1e_device_code(SLMPDeviceCode::TN).unwrap(), 0x544e);
+        assert!(mc1e_device_code(SLMPDeviceCode::ZR).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::split::MAX_RANDOM_READ_POINTS;
+use crate::{
+    decode_execute_monitor_response, make_entry_monitor_device_cmd_16,
+    make_entry_monitor_device_cmd_32, make_execute_monitor_cmd, SLMPDevice, SLMPDeviceData,
+    SLMPRequest, SlmpClient, SlmpError,
+};
+
+/// モニタ登録するデバイスの組
+///
+/// 登録したデバイスを覚えておき、未登録の場合や、再接続もしくは同じ接続での別の組の登録によって
+/// 登録が失われた場合は、モニタ実行の前に自動で登録し直す。
+/// 登録の有無は接続ごとのモニタ登録の世代([SLMPConnectionInfo::get_monitor_generation])で判定する。
+/// モニタ実行が異常終了した場合も、別の接続での登録とみなして一度だけ登録し直す
+///
+/// [SLMPConnectionInfo::get_monitor_generation]: crate::SLMPConnectionInfo::get_monitor_generation
+///
+/// # 例
+///
+/// ```no_run
+/// use slmp_client::*;
+///
+/// let connection_info = SLMPConnectionInfo::new_tcp("192.168.1.10:5000".parse().unwrap()).unwrap();
+/// let mut client = SlmpClient::new(connection_info);
+/// let mut monitor = SlmpMonitorSet::new(
+///     &["D100".parse().unwrap(), "D105".parse().unwrap()],
+///     &["D200".parse().unwrap()],
+/// )
+/// .unwrap();
+/// let (word, dword) = monitor.execute(&mut client).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlmpMonitorSet {
+    /// ワードアクセスするデバイス
+    word: Vec<SLMPDevice>,
+    /// ダブルワードアクセスするデバイス
+    dword: Vec<SLMPDevice>,
+    /// 登録した直後の接続のモニタ登録の世代、未登録は`None`
+    registered: Option<u64>,
+}
+
+impl SlmpMonitorSet {
+    /// 作成
+    ///
+    /// # 引数
+    ///
+    /// * `target_word` - ワードアクセスするデバイスのリスト
+    /// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+    pub fn new(
+        target_word: &[SLMPDevice],
+        target_dword: &[SLMPDevice],
+    ) -> Result<SlmpMonitorSet, SlmpError> {
+        if target_word.is_empty() && target_dword.is_empty() {
+            return Err(SlmpError::InvalidArgument("no monitor target"));
+        }
+        if target_word.len() + target_dword.len() > MAX_RANDOM_READ_POINTS {
+            return Err(SlmpError::InvalidArgument("too many monitor target"));
+        }
+        Ok(SlmpMonitorSet {
+            word: target_word.to_vec(),
+            dword: target_dword.to_vec(),
+            registered: None,
+        })
+    }
+    /// ワードアクセスするデバイス
+    pub fn word_devices(&self) -> &[SLMPDevice] {
+        &self.word
+    }
+    /// ダブルワードアクセスするデバイス
+    pub fn dword_devices(&self) -> &[SLMPDevice] {
+        &self.dword
+    }
+    /// 登録したことがあるか
+    ///
+    /// 登録が有効かどうかは接続のモニタ登録の世代と比較して判定する
+    pub fn is_registered(&self) -> bool {
+        self.registered.is_some()
+    }
+    /// 登録を無効にする
+    ///
+    /// 次のモニタ実行の前に登録し直す
+    pub fn invalidate(&mut self) {
+        self.registered = None;
+    }
+    /// モニタ登録の要求
+    fn entry_request(&self, addr_32bit: bool) -> Result<SLMPRequest, SlmpError> {
+        if addr_32bit {
+            make_entry_monitor_device_cmd_32(&self.word, &self.dword)
+        } else {
+            make_entry_monitor_device_cmd_16(&self.word, &self.dword)
+        }
+    }
+    /// デバイスをモニタ登録する
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 登録に使うクライアント
+    pub fn register(&mut self, client: &mut SlmpClient) -> Result<(), SlmpError> {
+        self.registered = None;
+        client.request(&self.entry_request(client.get_addr_32bit())?)?;
+        self.registered = Some(client.connection_info().get_monitor_generation());
+        Ok(())
+    }
+    /// モニタを実行して登録したデバイスを読み取る
+    ///
+    /// 未登録の場合や、登録後に再接続もしくは同じ接続で別の登録をした場合は先に登録する。
+    /// 要求中に再接続した場合や異常終了した場合は登録し直して一度だけやり直す
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読取りに使うクライアント
+    ///
+    /// # 返値
+    ///
+    /// ワードデバイスの値とダブルワードデバイスの値
+    #[allow(clippy::type_complexity)]
+    pub fn execute(
+        &mut self,
+        client: &mut SlmpClient,
+    ) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+        if self.registered != Some(client.connection_info().get_monitor_generation()) {
+            self.register(client)?;
+        }
+        let buf = match client.request(&make_execute_monitor_cmd()) {
+            Ok(buf) => buf,
+            Err(e) => {
+                let lost =
+                    self.registered != Some(client.connection_info().get_monitor_generation());
+                if !lost && !matches!(e, SlmpError::EndCode { .. }) {
+                    return Err(e);
+                }
+                self.register(client)?;
+                client.request(&make_execute_monitor_cmd())?
+            }
+        };
+        decode_execute_monitor_response(&buf, &self.word, &self.dword)
+    }
+    /// デバイスをモニタ登録する(tokio版)
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 登録に使うクライアント
+    #[cfg(feature = "tokio")]
+    pub async fn register_async(
+        &mut self,
+        client: &mut crate::AsyncSlmpClient,
+    ) -> Result<(), SlmpError> {
+        self.registered = None;
+        client
+            .request(&self.entry_request(client.get_addr_32bit())?)
+            .await?;
+        self.registered = Some(client.connection_info().get_monitor_generation());
+        Ok(())
+    }
+    /// モニタを実行して登録したデバイスを読み取る(tokio版)
+    ///
+    /// 未登録の場合や、登録後に再接続もしくは同じ接続で別の登録をした場合は先に登録する。
+    /// 要求中に再接続した場合や、クライアントを作り直した場合などで登録が失われて
+    /// 異常終了した場合は登録し直して一度だけやり直す
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読取りに使うクライアント
+    ///
+    /// # 返値
+    ///
+    /// ワードデバイスの値とダブルワードデバイスの値
+    #[cfg(feature = "tokio")]
+    #[allow(clippy::type_complexity)]
+    pub async fn execute_async(
+        &mut self,
+        client: &mut crate::AsyncSlmpClient,
+    ) -> Result<(Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>), SlmpError> {
+        if self.registered != Some(client.connection_info().get_monitor_generation()) {
+            self.register_async(client).await?;
+        }
+        let buf = match client.request(&make_execute_monitor_cmd()).await {
+            Ok(buf) => buf,
+            Err(e) => {
+                let lost =
+                    self.registered != Some(client.connection_info().get_monitor_generation());
+                if !lost && !matches!(e, SlmpError::EndCode { .. }) {
+                    return Err(e);
+                }
+                self.register_async(client).await?;
+                client.request(&make_execute_monitor_cmd()).await?
+            }
+        };
+        decode_execute_monitor_response(&buf, &self.word, &self.dword)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReconnectPolicy, SLMPConnectionInfo, SlmpServer};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    fn dev(s: &str) -> SLMPDevice {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_execute_and_reregister() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().devices.set_word(dev("D10"), 0x1234);
+        server.state().devices.set_word(dev("W1F"), 0x5678);
+        server.state().devices.set_dword(dev("D20"), 0x89abcdef);
+        let mut connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        connection_info.set_reconnect_policy(ReconnectPolicy::new(1));
+        let mut client = SlmpClient::new(connection_info);
+        let mut monitor = SlmpMonitorSet::new(&[dev("D10"), dev("W1F")], &[dev("D20")]).unwrap();
+        assert!(!monitor.is_registered());
+
+        let (word, dword) = monitor.execute(&mut client).unwrap();
+        assert!(monitor.is_registered());
+        assert_eq!(
+            word,
+            vec![
+                SLMPDeviceData {
+                    dev: dev("D10"),
+                    value: 0x1234
+                },
+                SLMPDeviceData {
+                    dev: dev("W1F"),
+                    value: 0x5678
+                },
+            ]
+        );
+        assert_eq!(dword[0].value, 0x89abcdef);
+
+        // 再接続すると新しい接続で登録し直す
+        client.connection_info_mut().reconnect().unwrap();
+        server.state().devices.set_word(dev("D10"), 0x4321);
+        let (word, _) = monitor.execute(&mut client).unwrap();
+        assert_eq!(word[0].value, 0x4321);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_execute_async_after_new_client() {
+        use crate::{AsyncSLMPConnectionInfo, AsyncSlmpClient};
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().devices.set_word(dev("D10"), 0x1234);
+        let connection_info = AsyncSLMPConnectionInfo::new_tcp(server.local_addr())
+            .await
+            .unwrap();
+        let mut client = AsyncSlmpClient::new(connection_info);
+        let mut monitor = SlmpMonitorSet::new(&[dev("D10")], &[]).unwrap();
+        let (word, _) = monitor.execute_async(&mut client).await.unwrap();
+        assert_eq!(word[0].value, 0x1234);
+
+        // 作り直したクライアントの接続では登録が失われている
+        drop(client);
+        let connection_info = AsyncSLMPConnectionInfo::new_tcp(server.local_addr())
+            .await
+            .unwrap();
+        let mut client = AsyncSlmpClient::new(connection_info);
+        server.state().devices.set_word(dev("D10"), 0x4321);
+        let (word, _) = monitor.execute_async(&mut client).await.unwrap();
+        assert_eq!(word[0].value, 0x4321);
+    }
+
+    #[test]
+    fn test_two_sets_on_one_client() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server.state().devices.set_word(dev("D10"), 10);
+        server.state().devices.set_word(dev("D20"), 20);
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let mut first = SlmpMonitorSet::new(&[dev("D10")], &[]).unwrap();
+        let mut second = SlmpMonitorSet::new(&[dev("D20")], &[]).unwrap();
+
+        // 交互に実行しても、それぞれ自分のデバイスを読み取る
+        for _ in 0..2 {
+            let (word, _) = first.execute(&mut client).unwrap();
+            assert_eq!(word[0].value, 10);
+            let (word, _) = second.execute(&mut client).unwrap();
+            assert_eq!(word[0].value, 20);
+        }
+        let generation = client.connection_info().get_monitor_generation();
+        second.execute(&mut client).unwrap();
+        assert_eq!(
+            client.connection_info().get_monitor_generation(),
+            generation
+        );
+    }
+
+    #[test]
+    fn test_decode_wrong_length() {
+        assert!(decode_execute_monitor_response(&[0x00, 0x01, 0x02], &[dev("D0")], &[]).is_err());
+    }
+}
@@ -0,0 +1,1568 @@
+use crate::device_struct::{struct_blocks, struct_from_blocks, struct_to_blocks};
+use crate::split::{
+    block_pieces, chunk_ranges, offset_device, random_chunks, MAX_BATCH_WORDS,
+    MAX_RANDOM_READ_POINTS, MAX_RANDOM_WRITE_BITS, MAX_RANDOM_WRITE_SIZE,
+};
+use crate::{
+    decode_file_read_response, decode_file_search_response, decode_read_file_info_response,
+    make_file_read_cmd, make_file_search_cmd, make_read_file_info_cmd,
+};
+use crate::{
+    decode_get_event_history_response, decode_get_event_num_response, make_clear_error_history_cmd,
+    make_clear_event_history_cmd, make_get_event_history_cmd, make_get_event_num_cmd, SlmpEvent,
+    MAX_EVENT_HISTORY,
+};
+use crate::{
+    decode_open_file_response, decode_read_dir_response, decode_read_file_response,
+    decode_write_file_response, make_close_file_cmd, make_copy_file_cmd, make_delete_file_cmd,
+    make_new_file_cmd, make_open_file_cmd, make_read_dir_cmd, make_read_file_cmd,
+    make_write_file_cmd, SlmpFileCloseType, SlmpFileEntry, SlmpFileOpenMode, MAX_FILE_CHUNK,
+    UPLOAD_BACKUP_SUFFIX, UPLOAD_TEMP_SUFFIX,
+};
+use crate::{
+    decode_read_array_label_response, decode_read_random_label_response, encode_label_value,
+    make_read_array_label_cmd, make_read_random_label_cmd, make_write_array_label_cmd,
+    make_write_random_label_cmd, SlmpArrayLabel, SlmpArrayLabelData, SlmpLabelData, SlmpTagValue,
+};
+use crate::{
+    decode_read_bit_response, decode_read_block_response, decode_read_memory,
+    decode_read_random_response, decode_read_type_name_response, decode_read_word_response,
+    decode_self_test_response, make_clear_error_cmd, make_read_block_cmd_16,
+    make_read_block_cmd_32, make_read_cmd_16, make_read_cmd_32, make_read_memory,
+    make_read_random_cmd_16, make_read_random_cmd_32, make_read_type_name_cmd,
+    make_remote_latch_clear, make_remote_pause_cmd, make_remote_reset_cmd, make_remote_run_cmd,
+    make_remote_stop_cmd, make_self_test_cmd, make_write_bit_cmd_16, make_write_bit_cmd_32,
+    make_write_block_cmd_16, make_write_block_cmd_32, make_write_memory,
+    make_write_random_bits_cmd_16, make_write_random_bits_cmd_32, make_write_random_words_cmd_16,
+    make_write_random_words_cmd_32, make_write_word_cmd_16, make_write_word_cmd_32, SLMPClearMode,
+    SLMPDevice, SLMPDeviceBlock, SLMPDeviceBlockData, SLMPDeviceData, SLMPRequest, SlmpError,
+};
+use crate::{
+    decode_read_disk_state_response, make_defrag_cmd, make_read_disk_state_cmd, SlmpDiskState,
+};
+use crate::{
+    decode_string, decode_values, encode_string, make_value_targets, SlmpCodec,
+    SlmpStringTermination, SlmpStruct, SlmpWordOrder,
+};
+use crate::{make_remote_password_lock_cmd, make_remote_password_unlock_cmd, SlmpPasswordFormat};
+
+/// 要求を順に発行し、応答から結果を得る処理
+///
+/// 入出力は行わず、同期版と非同期版のクライアントが要求の送信と応答の受信を受け持つ
+pub(crate) trait Operation {
+    /// 処理の結果
+    type Output;
+    /// 次に送信する要求
+    ///
+    /// # 返値
+    ///
+    /// 要求、全ての要求を送信済みであれば`None`
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError>;
+    /// 直前の要求に対する応答を処理する
+    ///
+    /// 失敗した応答の後も後始末の要求を続ける場合は`Ok`を返す
+    ///
+    /// # 引数
+    ///
+    /// * `response` - 応答のペイロード、もしくは要求の失敗
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError>;
+    /// 全ての応答を処理した後の結果
+    fn finish(self) -> Result<Self::Output, SlmpError>;
+}
+
+/// 全ての応答を受けてからまとめてデコードする
+type Decoder<'a, T> = Box<dyn FnOnce(Vec<Vec<u8>>) -> Result<T, SlmpError> + Send + 'a>;
+
+/// 前もって決まった要求を順に発行し、全ての応答をまとめてデコードする処理
+///
+/// いずれかの要求が失敗した場合はそこで中断する
+pub(crate) struct Plan<'a, T> {
+    requests: Vec<SLMPRequest>,
+    responses: Vec<Vec<u8>>,
+    decode: Decoder<'a, T>,
+}
+
+impl<'a, T: 'a> Plan<'a, T> {
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `requests` - 発行する要求
+    /// * `decode` - 要求の順の応答から結果を得る関数
+    pub(crate) fn new<F>(requests: Vec<SLMPRequest>, decode: F) -> Plan<'a, T>
+    where
+        F: FnOnce(Vec<Vec<u8>>) -> Result<T, SlmpError> + Send + 'a,
+    {
+        Plan {
+            requests,
+            responses: Vec::new(),
+            decode: Box::new(decode),
+        }
+    }
+    /// 一つの要求を発行する処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `request` - 発行する要求
+    /// * `decode` - 応答から結果を得る関数
+    pub(crate) fn single<F>(request: SLMPRequest, decode: F) -> Plan<'a, T>
+    where
+        F: FnOnce(&[u8]) -> Result<T, SlmpError> + Send + 'a,
+    {
+        Plan::new(vec![request], move |responses| decode(&responses[0]))
+    }
+    /// 結果を変換する
+    ///
+    /// # 引数
+    ///
+    /// * `f` - 変換する関数
+    pub(crate) fn map<U: 'a, F>(self, f: F) -> Plan<'a, U>
+    where
+        F: FnOnce(T) -> Result<U, SlmpError> + Send + 'a,
+    {
+        let decode = self.decode;
+        Plan::new(self.requests, move |responses| f(decode(responses)?))
+    }
+    /// 続けて別の処理の要求を発行し、両方の結果を得る
+    ///
+    /// # 引数
+    ///
+    /// * `other` - 続けて発行する処理
+    pub(crate) fn join<U: 'a>(self, other: Plan<'a, U>) -> Plan<'a, (T, U)> {
+        let n = self.requests.len();
+        let (decode, decode_other) = (self.decode, other.decode);
+        let mut requests = self.requests;
+        requests.extend(other.requests);
+        Plan::new(requests, move |mut responses| {
+            let rest = responses.split_off(n);
+            Ok((decode(responses)?, decode_other(rest)?))
+        })
+    }
+}
+
+impl<'a> Plan<'a, ()> {
+    /// 応答のペイロードを使わない要求を発行する処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `requests` - 発行する要求
+    pub(crate) fn requests(requests: Vec<SLMPRequest>) -> Plan<'a, ()> {
+        Plan::new(requests, |_| Ok(()))
+    }
+}
+
+impl<'a, T> Operation for Plan<'a, T> {
+    type Output = T;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        Ok(self.requests.get(self.responses.len()).cloned())
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        self.responses.push(response?);
+        Ok(())
+    }
+    fn finish(self) -> Result<T, SlmpError> {
+        (self.decode)(self.responses)
+    }
+}
+
+/// 単一の連続したワードデバイスの読取り
+///
+/// 一度に読み出せる点数を超える場合は複数の要求に分割する
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `count` - デバイス個数
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn read_words<'a>(
+    target: SLMPDevice,
+    count: u16,
+    addr_32bit: bool,
+) -> Plan<'a, Vec<SLMPDeviceData<u16>>> {
+    let ranges = chunk_ranges(count as usize, MAX_BATCH_WORDS);
+    let requests = ranges
+        .iter()
+        .map(|range| {
+            let dev = offset_device(target, range.start);
+            let n = range.len() as u16;
+            match addr_32bit {
+                true => make_read_cmd_32(dev, n, false),
+                false => make_read_cmd_16(dev, n, false),
+            }
+        })
+        .collect();
+    Plan::new(requests, move |responses| {
+        let mut buf = Vec::new();
+        for (range, res) in ranges.iter().zip(responses) {
+            if res.len() != range.len() * 2 {
+                return Err(SlmpError::MalformedFrame("wrong number of words"));
+            }
+            buf.extend_from_slice(&res);
+        }
+        let ret = decode_read_word_response(&buf, target)?;
+        if ret.len() != count as usize {
+            return Err(SlmpError::MalformedFrame("wrong number of words"));
+        }
+        Ok(ret)
+    })
+}
+
+/// 単一の連続したビットデバイスの読取り
+///
+/// 一度に読み出せる点数を超える場合は複数の要求に分割する
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `count` - デバイス個数
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+/// * `max_bits` - 一度に読み出せる点数
+pub(crate) fn read_bits<'a>(
+    target: SLMPDevice,
+    count: u16,
+    addr_32bit: bool,
+    max_bits: usize,
+) -> Result<Plan<'a, Vec<SLMPDeviceData<bool>>>, SlmpError> {
+    // ビットデバイスの位置はビット単位のため、末尾のデバイス番号が桁あふれしないことを確認する
+    if target.addr.checked_add(count as u32).is_none() {
+        return Err(SlmpError::InvalidArgument("too large device number"));
+    }
+    let requests = chunk_ranges(count as usize, max_bits)
+        .into_iter()
+        .map(|range| {
+            let dev = SLMPDevice {
+                d_code: target.d_code,
+                addr: target.addr + range.start as u32,
+            };
+            let n = range.len() as u16;
+            match addr_32bit {
+                true => make_read_cmd_32(dev, n, true),
+                false => make_read_cmd_16(dev, n, true),
+            }
+        })
+        .collect();
+    Ok(Plan::new(requests, move |responses| {
+        // 分割した途中の要求の点数は偶数のため、応答を連結できる
+        let buf: Vec<u8> = responses.concat();
+        let mut ret = decode_read_bit_response(&buf, target)?;
+        // ビットデータは偶数個で要求しているため、余分な1点を取り除く
+        if ret.len() < count as usize {
+            return Err(SlmpError::MalformedFrame("wrong number of bits"));
+        }
+        ret.truncate(count as usize);
+        Ok(ret)
+    }))
+}
+
+/// 単一の連続したワードデバイスへの書込み
+///
+/// # 引数
+///
+/// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_words<'a>(
+    targets: &[SLMPDeviceData<u16>],
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let requests = targets
+        .chunks(MAX_BATCH_WORDS)
+        .map(|chunk| match addr_32bit {
+            true => make_write_word_cmd_32(chunk),
+            false => make_write_word_cmd_16(chunk),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Plan::requests(requests))
+}
+
+/// 単一の連続したビットデバイスへの書込み
+///
+/// # 引数
+///
+/// * `targets` - 書き込むデバイスのリスト、先頭から連続している必要がある
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+/// * `max_bits` - 一度に書き込める点数
+pub(crate) fn write_bits<'a>(
+    targets: &[SLMPDeviceData<bool>],
+    addr_32bit: bool,
+    max_bits: usize,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let requests = targets
+        .chunks(max_bits)
+        .map(|chunk| match addr_32bit {
+            true => make_write_bit_cmd_32(chunk),
+            false => make_write_bit_cmd_16(chunk),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Plan::requests(requests))
+}
+
+/// 単一の連続したワードデバイスの型付きの値としての読取り
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `count` - 値の個数
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn read_values<'a, T: SlmpCodec + 'a>(
+    target: SLMPDevice,
+    count: u16,
+    order: SlmpWordOrder,
+    addr_32bit: bool,
+) -> Result<Plan<'a, Vec<T>>, SlmpError> {
+    let words = count as usize * T::WORDS;
+    if words > 0xffff {
+        return Err(SlmpError::InvalidArgument("too many values"));
+    }
+    Ok(
+        read_words(target, words as u16, addr_32bit).map(move |ret| {
+            let ws: Vec<u16> = ret.iter().map(|d| d.value).collect();
+            decode_values(&ws, order)
+        }),
+    )
+}
+
+/// 単一の連続したワードデバイスへの型付きの値の書込み
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `values` - 書き込む値
+/// * `order` - 複数ワードにまたがる値のワードの並び
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_values<'a, T: SlmpCodec>(
+    target: SLMPDevice,
+    values: &[T],
+    order: SlmpWordOrder,
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    write_words(&make_value_targets(target, values, order)?, addr_32bit)
+}
+
+/// 1ワードに2文字ずつ格納した文字列の読取り
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `words` - 文字列を格納した領域のワード数
+/// * `termination` - 終端の扱い
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn read_string<'a>(
+    target: SLMPDevice,
+    words: u16,
+    termination: SlmpStringTermination,
+    addr_32bit: bool,
+) -> Plan<'a, String> {
+    read_words(target, words, addr_32bit).map(move |ret| {
+        let ws: Vec<u16> = ret.iter().map(|d| d.value).collect();
+        decode_string(&ws, termination)
+    })
+}
+
+/// 1ワードに2文字ずつの文字列の書込み
+///
+/// # 引数
+///
+/// * `target` - 先頭デバイス
+/// * `s` - ASCII文字列
+/// * `words` - 文字列を格納する領域のワード数
+/// * `termination` - 終端の扱い
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_string<'a>(
+    target: SLMPDevice,
+    s: &str,
+    words: u16,
+    termination: SlmpStringTermination,
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let ws = encode_string(s, words as usize, termination)?;
+    write_values(target, &ws, SlmpWordOrder::LowFirst, addr_32bit)
+}
+
+/// フィールドをデバイスに対応付けた構造体の読取り
+///
+/// # 引数
+///
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn read_struct<'a, T: SlmpStruct + 'a>(
+    addr_32bit: bool,
+) -> Result<Plan<'a, T>, SlmpError> {
+    let (word, bit) = struct_blocks::<T>()?;
+    Ok(read_blocks(&word, &bit, addr_32bit)?
+        .map(|(ret_word, ret_bit)| struct_from_blocks(&ret_word, &ret_bit)))
+}
+
+/// フィールドをデバイスに対応付けた構造体の書込み
+///
+/// ワードのフィールドは複数ブロック一括書込み、ビットのフィールドはランダム書込みで書き込む
+///
+/// # 引数
+///
+/// * `value` - 書き込む構造体
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_struct<'a, T: SlmpStruct>(
+    value: &T,
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let (word, bit) = struct_to_blocks(value)?;
+    let mut plan = Plan::requests(Vec::new());
+    if !word.is_empty() {
+        plan = plan
+            .join(write_blocks(&word, &[], addr_32bit)?)
+            .map(|_| Ok(()));
+    }
+    if !bit.is_empty() {
+        plan = plan
+            .join(write_random_bits(&bit, addr_32bit)?)
+            .map(|_| Ok(()));
+    }
+    Ok(plan)
+}
+
+/// ワード単位、ダブルワード単位のランダム読出し
+///
+/// # 引数
+///
+/// * `target_word` - ワードアクセスするデバイスのリスト
+/// * `target_dword` - ダブルワードアクセスするデバイスのリスト
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+#[allow(clippy::type_complexity)]
+pub(crate) fn read_random<'a>(
+    target_word: &[SLMPDevice],
+    target_dword: &[SLMPDevice],
+    addr_32bit: bool,
+) -> Result<Plan<'a, (Vec<SLMPDeviceData<u16>>, Vec<SLMPDeviceData<u32>>)>, SlmpError> {
+    let chunks: Vec<(Vec<SLMPDevice>, Vec<SLMPDevice>)> = random_chunks(
+        target_word.len(),
+        target_dword.len(),
+        1,
+        1,
+        MAX_RANDOM_READ_POINTS,
+    )
+    .into_iter()
+    .map(|(w, d)| (target_word[w].to_vec(), target_dword[d].to_vec()))
+    .collect();
+    let requests = chunks
+        .iter()
+        .map(|(word, dword)| match addr_32bit {
+            true => make_read_random_cmd_32(word, dword),
+            false => make_read_random_cmd_16(word, dword),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Plan::new(requests, move |responses| {
+        let mut ret_word = Vec::new();
+        let mut ret_dword = Vec::new();
+        for ((word, dword), buf) in chunks.iter().zip(responses) {
+            let (rw, rd) = decode_read_random_response(&buf, word, dword)?;
+            ret_word.extend(rw);
+            ret_dword.extend(rd);
+        }
+        Ok((ret_word, ret_dword))
+    }))
+}
+
+/// ワードデバイス、ダブルワードデバイスへのランダム書込み
+///
+/// # 引数
+///
+/// * `target_word` - ワードデバイスデータのリスト
+/// * `target_dword` - ダブルワードデバイスデータのリスト
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_random_words<'a>(
+    target_word: &[SLMPDeviceData<u16>],
+    target_dword: &[SLMPDeviceData<u32>],
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let requests = random_chunks(
+        target_word.len(),
+        target_dword.len(),
+        12,
+        14,
+        MAX_RANDOM_WRITE_SIZE,
+    )
+    .into_iter()
+    .map(|(w, d)| match addr_32bit {
+        true => make_write_random_words_cmd_32(&target_word[w], &target_dword[d]),
+        false => make_write_random_words_cmd_16(&target_word[w], &target_dword[d]),
+    })
+    .collect::<Result<_, _>>()?;
+    Ok(Plan::requests(requests))
+}
+
+/// ビットデバイスへのランダム書込み
+///
+/// # 引数
+///
+/// * `targets` - ビットデバイスデータのリスト
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_random_bits<'a>(
+    targets: &[SLMPDeviceData<bool>],
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let requests = targets
+        .chunks(MAX_RANDOM_WRITE_BITS)
+        .map(|chunk| match addr_32bit {
+            true => make_write_random_bits_cmd_32(chunk),
+            false => make_write_random_bits_cmd_16(chunk),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Plan::requests(requests))
+}
+
+/// 複数ブロック一括読出し
+///
+/// 一度に読み出せるブロック数、点数を超える場合は複数の要求に分割する
+///
+/// # 引数
+///
+/// * `target_word` - ワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - ビットアクセスするデバイスブロックのリスト
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+#[allow(clippy::type_complexity)]
+pub(crate) fn read_blocks<'a>(
+    target_word: &[SLMPDeviceBlock],
+    target_bit: &[SLMPDeviceBlock],
+    addr_32bit: bool,
+) -> Result<
+    Plan<
+        'a,
+        (
+            Vec<SLMPDeviceBlockData<u16>>,
+            Vec<SLMPDeviceBlockData<bool>>,
+        ),
+    >,
+    SlmpError,
+> {
+    let counts = |blocks: &[SLMPDeviceBlock]| -> Vec<usize> {
+        blocks.iter().map(|b| b.count as usize).collect()
+    };
+    let all_pieces = block_pieces(&counts(target_word), &counts(target_bit));
+    let mut requests = Vec::new();
+    let mut chunks = Vec::new();
+    for pieces in all_pieces {
+        let word: Vec<SLMPDeviceBlock> = pieces
+            .iter()
+            .filter(|p| !p.bit)
+            .map(|p| p.block(&target_word[p.index]))
+            .collect();
+        let bit: Vec<SLMPDeviceBlock> = pieces
+            .iter()
+            .filter(|p| p.bit)
+            .map(|p| p.block(&target_bit[p.index]))
+            .collect();
+        requests.push(match addr_32bit {
+            true => make_read_block_cmd_32(&word, &bit)?,
+            false => make_read_block_cmd_16(&word, &bit)?,
+        });
+        chunks.push((pieces, word, bit));
+    }
+    let target_word = target_word.to_vec();
+    let target_bit = target_bit.to_vec();
+    Ok(Plan::new(requests, move |responses| {
+        let mut word_buf = vec![Vec::new(); target_word.len()];
+        let mut bit_buf = vec![Vec::new(); target_bit.len()];
+        for ((pieces, word, bit), buf) in chunks.iter().zip(responses) {
+            // 応答の長さを確認する
+            decode_read_block_response(&buf, word, bit)?;
+            let mut pos = 0;
+            for p in pieces.iter() {
+                let dst = if p.bit {
+                    &mut bit_buf[p.index]
+                } else {
+                    &mut word_buf[p.index]
+                };
+                dst.extend_from_slice(&buf[pos..pos + p.count * 2]);
+                pos += p.count * 2;
+            }
+        }
+        let buf: Vec<u8> = word_buf.into_iter().chain(bit_buf).flatten().collect();
+        decode_read_block_response(&buf, &target_word, &target_bit)
+    }))
+}
+
+/// 複数ブロック一括書込み
+///
+/// 一度に書き込めるブロック数、点数を超える場合は複数の要求に分割する
+///
+/// # 引数
+///
+/// * `target_word` - データを含んだワードアクセスするデバイスブロックのリスト
+/// * `target_bit` - データを含んだビットアクセスするデバイスブロックのリスト
+/// * `addr_32bit` - 32bitアドレス版のコマンドを使用するか
+pub(crate) fn write_blocks<'a>(
+    target_word: &[SLMPDeviceBlockData<u16>],
+    target_bit: &[SLMPDeviceBlockData<bool>],
+    addr_32bit: bool,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let word_counts: Vec<usize> = target_word
+        .iter()
+        .map(|b| b.device_block.count as usize)
+        .collect();
+    let bit_counts: Vec<usize> = target_bit
+        .iter()
+        .map(|b| b.device_block.count as usize)
+        .collect();
+    let mut requests = Vec::new();
+    for pieces in block_pieces(&word_counts, &bit_counts) {
+        let mut word = Vec::new();
+        let mut bit = Vec::new();
+        for p in pieces.iter() {
+            if p.bit {
+                let src = &target_bit[p.index];
+                let block = p.block(&src.device_block);
+                let mut dbd = SLMPDeviceBlockData::<bool>::new(block.top_device, block.count);
+                dbd.sets(&src.values()[p.offset * 16..(p.offset + p.count) * 16]);
+                bit.push(dbd);
+            } else {
+                let src = &target_word[p.index];
+                let block = p.block(&src.device_block);
+                let mut dbd = SLMPDeviceBlockData::<u16>::new(block.top_device, block.count);
+                dbd.sets(&src.values()[p.offset..p.offset + p.count]);
+                word.push(dbd);
+            }
+        }
+        requests.push(match addr_32bit {
+            true => make_write_block_cmd_32(&word, &bit)?,
+            false => make_write_block_cmd_16(&word, &bit)?,
+        });
+    }
+    Ok(Plan::requests(requests))
+}
+
+/// ラベルのランダム読出し
+///
+/// # 引数
+///
+/// * `names` - ラベル名のリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+pub(crate) fn read_labels<'a>(
+    names: &'a [&'a str],
+    abbreviations: &[&str],
+) -> Result<Plan<'a, Vec<SlmpLabelData>>, SlmpError> {
+    let req = make_read_random_label_cmd(names, abbreviations)?;
+    Ok(Plan::single(req, move |buf| {
+        decode_read_random_label_response(buf, names)
+    }))
+}
+
+/// ラベルのランダム読出しとデータ型に従った値への変換
+///
+/// # 引数
+///
+/// * `names` - ラベル名のリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+pub(crate) fn read_label_values<'a>(
+    names: &'a [&'a str],
+    abbreviations: &[&str],
+) -> Result<Plan<'a, Vec<SlmpTagValue>>, SlmpError> {
+    Ok(read_labels(names, abbreviations)?.map(|ret| ret.iter().map(|d| d.value()).collect()))
+}
+
+/// ラベルへのランダム書込み
+///
+/// # 引数
+///
+/// * `labels` - ラベル名と値のリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+pub(crate) fn write_labels<'a>(
+    labels: &[(&str, SlmpTagValue)],
+    abbreviations: &[&str],
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let data = labels
+        .iter()
+        .map(|(_, v)| encode_label_value(v))
+        .collect::<Result<Vec<Vec<u8>>, SlmpError>>()?;
+    let targets: Vec<(&str, &[u8])> = labels
+        .iter()
+        .zip(data.iter())
+        .map(|((name, _), d)| (*name, d.as_slice()))
+        .collect();
+    let req = make_write_random_label_cmd(&targets, abbreviations)?;
+    Ok(Plan::requests(vec![req]))
+}
+
+/// 配列ラベルの一括読出し
+///
+/// # 引数
+///
+/// * `labels` - 配列ラベルのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+pub(crate) fn read_array_labels<'a>(
+    labels: &'a [SlmpArrayLabel],
+    abbreviations: &[&str],
+) -> Result<Plan<'a, Vec<SlmpLabelData>>, SlmpError> {
+    let req = make_read_array_label_cmd(labels, abbreviations)?;
+    Ok(Plan::single(req, move |buf| {
+        decode_read_array_label_response(buf, labels)
+    }))
+}
+
+/// 配列ラベルへの一括書込み
+///
+/// # 引数
+///
+/// * `labels` - 配列ラベルと書き込むデータのリスト
+/// * `abbreviations` - 省略ラベル名のリスト
+pub(crate) fn write_array_labels<'a>(
+    labels: &[SlmpArrayLabelData],
+    abbreviations: &[&str],
+) -> Result<Plan<'a, ()>, SlmpError> {
+    let req = make_write_array_label_cmd(labels, abbreviations)?;
+    Ok(Plan::requests(vec![req]))
+}
+
+/// ファイル全体の読出しの段階
+enum DownloadStep {
+    Open,
+    Read,
+    Close,
+    Done,
+}
+
+/// ファイル全体の読出し
+///
+/// ファイルを開き、一度に扱えるバイト数ごとに読み出した後で閉じる。
+/// 読出しに失敗した場合もファイルを閉じる
+pub(crate) struct FileDownload {
+    step: DownloadStep,
+    open: SLMPRequest,
+    pointer: u16,
+    data: Vec<u8>,
+    error: Option<SlmpError>,
+}
+
+impl FileDownload {
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    pub(crate) fn new(drive: u16, path: &str, password: &str) -> Result<FileDownload, SlmpError> {
+        Ok(FileDownload {
+            step: DownloadStep::Open,
+            open: make_open_file_cmd(password, SlmpFileOpenMode::Read, drive, path)?,
+            pointer: 0,
+            data: Vec::new(),
+            error: None,
+        })
+    }
+}
+
+impl Operation for FileDownload {
+    type Output = Vec<u8>;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        Ok(match self.step {
+            DownloadStep::Open => Some(self.open.clone()),
+            DownloadStep::Read => Some(make_read_file_cmd(
+                self.pointer,
+                self.data.len() as u32,
+                MAX_FILE_CHUNK as u16,
+            )?),
+            DownloadStep::Close => Some(make_close_file_cmd(
+                self.pointer,
+                SlmpFileCloseType::Pointer,
+            )),
+            DownloadStep::Done => None,
+        })
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        match self.step {
+            DownloadStep::Open => {
+                self.pointer = decode_open_file_response(&response?)?;
+                self.step = DownloadStep::Read;
+            }
+            DownloadStep::Read => match response.and_then(|buf| decode_read_file_response(&buf)) {
+                Ok(chunk) => {
+                    self.data.extend_from_slice(&chunk);
+                    if chunk.len() < MAX_FILE_CHUNK {
+                        self.step = DownloadStep::Close;
+                    }
+                }
+                Err(e) => {
+                    self.error = Some(e);
+                    self.step = DownloadStep::Close;
+                }
+            },
+            DownloadStep::Close => {
+                self.step = DownloadStep::Done;
+                // 読出しのエラーを優先する
+                if let (None, Err(e)) = (&self.error, response) {
+                    self.error = Some(e);
+                }
+            }
+            DownloadStep::Done => (),
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<Vec<u8>, SlmpError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.data),
+        }
+    }
+}
+
+/// ファイル全体の書込みの段階
+enum UploadStep {
+    /// 既存のファイルを調べるためのディレクトリの読出し
+    List,
+    /// 前回の失敗で残ったファイルの削除
+    RemoveStale,
+    NewFile,
+    Open,
+    /// 何番目の書込みか
+    Write(usize),
+    Close,
+    /// 元のファイルのバックアップへのコピー
+    Backup,
+    RemoveOriginal,
+    Copy,
+    /// バックアップから元のファイルへの書戻し
+    Restore,
+    /// 一時ファイルとバックアップの削除
+    RemoveWork,
+    /// 失敗した後の一時ファイルとバックアップの削除
+    Cleanup,
+    Done,
+}
+
+/// ファイル全体の書込み
+///
+/// 一時ファイルに一度に扱えるバイト数ごとに書き込んだ後、同名のファイルをバックアップに
+/// コピーしてから置き換える。置換えに失敗した場合はバックアップから書き戻す
+pub(crate) struct FileUpload<'a> {
+    step: UploadStep,
+    drive: u16,
+    path: &'a str,
+    temp: String,
+    backup: String,
+    password: &'a str,
+    data: &'a [u8],
+    pointer: u16,
+    list: ReadDir<'a>,
+    /// 同名のファイルが存在するか
+    exists: bool,
+    /// 削除するファイルのパス名、末尾から削除する
+    removals: Vec<String>,
+    error: Option<SlmpError>,
+}
+
+impl<'a> FileUpload<'a> {
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ファイルのパス名
+    /// * `password` - ファイルパスワード、空文字列はパスワードなし
+    /// * `data` - ファイルの内容
+    pub(crate) fn new(
+        drive: u16,
+        path: &'a str,
+        password: &'a str,
+        data: &'a [u8],
+    ) -> Result<FileUpload<'a>, SlmpError> {
+        if data.len() > u32::MAX as usize {
+            return Err(SlmpError::InvalidArgument("too large file"));
+        }
+        let dir = match path.rfind(['/', '\\']) {
+            Some(i) => &path[..i],
+            None => "",
+        };
+        Ok(FileUpload {
+            step: UploadStep::List,
+            drive,
+            path,
+            temp: format!("{}{}", path, UPLOAD_TEMP_SUFFIX),
+            backup: format!("{}{}", path, UPLOAD_BACKUP_SUFFIX),
+            password,
+            data,
+            pointer: 0,
+            list: ReadDir::new(drive, dir),
+            exists: false,
+            removals: Vec::new(),
+            error: None,
+        })
+    }
+    /// ディレクトリの読出し結果から、パス名のファイルが存在するか
+    fn listed(&self, path: &str) -> bool {
+        let name = match path.rfind(['/', '\\']) {
+            Some(i) => &path[i + 1..],
+            None => path,
+        };
+        self.list
+            .entries
+            .iter()
+            .any(|e| !e.is_dir() && e.name.eq_ignore_ascii_case(name))
+    }
+    /// 失敗を記録し、一時ファイルを削除してから終える
+    fn fail(&mut self, e: SlmpError) {
+        self.error = Some(e);
+        self.removals = vec![self.temp.clone()];
+        self.step = UploadStep::Cleanup;
+    }
+}
+
+impl<'a> Operation for FileUpload<'a> {
+    type Output = ();
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        let (password, drive) = (self.password, self.drive);
+        let req = match self.step {
+            UploadStep::List => return self.list.next_request(),
+            UploadStep::RemoveStale | UploadStep::RemoveWork | UploadStep::Cleanup => {
+                match self.removals.last() {
+                    Some(path) => make_delete_file_cmd(password, drive, path)?,
+                    None => return Ok(None),
+                }
+            }
+            UploadStep::NewFile => {
+                make_new_file_cmd(password, drive, self.data.len() as u32, &self.temp)?
+            }
+            UploadStep::Open => {
+                make_open_file_cmd(password, SlmpFileOpenMode::Write, drive, &self.temp)?
+            }
+            UploadStep::Write(i) => {
+                let chunk = self.data.chunks(MAX_FILE_CHUNK).nth(i).unwrap_or(&[]);
+                make_write_file_cmd(self.pointer, (i * MAX_FILE_CHUNK) as u32, chunk)?
+            }
+            UploadStep::Close => make_close_file_cmd(self.pointer, SlmpFileCloseType::Pointer),
+            UploadStep::Backup => make_copy_file_cmd(
+                (password, drive, &self.backup),
+                (password, drive, self.path),
+            )?,
+            UploadStep::RemoveOriginal => make_delete_file_cmd(password, drive, self.path)?,
+            UploadStep::Copy => {
+                make_copy_file_cmd((password, drive, self.path), (password, drive, &self.temp))?
+            }
+            UploadStep::Restore => make_copy_file_cmd(
+                (password, drive, self.path),
+                (password, drive, &self.backup),
+            )?,
+            UploadStep::Done => return Ok(None),
+        };
+        Ok(Some(req))
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        match self.step {
+            UploadStep::List => {
+                self.list.on_response(response)?;
+                if !self.list.done {
+                    return Ok(());
+                }
+                self.exists = self.listed(self.path);
+                let backup = self.listed(&self.backup);
+                // 元のファイルがなくバックアップのみ残る場合は、前回の書戻しの失敗で
+                // バックアップが唯一の内容のため削除しない
+                if backup && !self.exists {
+                    return Err(SlmpError::InvalidArgument(
+                        "backup file left without original",
+                    ));
+                }
+                if backup {
+                    self.removals.push(self.backup.clone());
+                }
+                if self.listed(&self.temp) {
+                    self.removals.push(self.temp.clone());
+                }
+                self.step = match self.removals.is_empty() {
+                    true => UploadStep::NewFile,
+                    false => UploadStep::RemoveStale,
+                };
+            }
+            UploadStep::RemoveStale => {
+                response?;
+                self.removals.pop();
+                if self.removals.is_empty() {
+                    self.step = UploadStep::NewFile;
+                }
+            }
+            UploadStep::NewFile => match response {
+                Ok(_) => self.step = UploadStep::Open,
+                // 一時ファイルを作成できていないため削除しない
+                Err(e) => {
+                    self.error = Some(e);
+                    self.step = UploadStep::Done;
+                }
+            },
+            UploadStep::Open => match response.and_then(|buf| decode_open_file_response(&buf)) {
+                Ok(pointer) => {
+                    self.pointer = pointer;
+                    self.step = match self.data.is_empty() {
+                        true => UploadStep::Close,
+                        false => UploadStep::Write(0),
+                    };
+                }
+                Err(e) => self.fail(e),
+            },
+            UploadStep::Write(i) => {
+                let size = self.data.len().min((i + 1) * MAX_FILE_CHUNK) - i * MAX_FILE_CHUNK;
+                let written = response
+                    .and_then(|buf| decode_write_file_response(&buf))
+                    .and_then(|n| match n as usize == size {
+                        true => Ok(()),
+                        false => Err(SlmpError::MalformedFrame("short file write")),
+                    });
+                match written {
+                    Ok(()) if (i + 1) * MAX_FILE_CHUNK < self.data.len() => {
+                        self.step = UploadStep::Write(i + 1)
+                    }
+                    Ok(()) => self.step = UploadStep::Close,
+                    // 書込みに失敗した場合もファイルを閉じる
+                    Err(e) => {
+                        self.error = Some(e);
+                        self.step = UploadStep::Close;
+                    }
+                }
+            }
+            UploadStep::Close => match (self.error.take(), response) {
+                (Some(e), _) | (None, Err(e)) => self.fail(e),
+                (None, Ok(_)) if self.exists => self.step = UploadStep::Backup,
+                (None, Ok(_)) => self.step = UploadStep::Copy,
+            },
+            UploadStep::Backup => match response {
+                Ok(_) => self.step = UploadStep::RemoveOriginal,
+                Err(e) => self.fail(e),
+            },
+            UploadStep::RemoveOriginal => match response {
+                Ok(_) => self.step = UploadStep::Copy,
+                Err(e) => {
+                    self.fail(e);
+                    self.removals.push(self.backup.clone());
+                }
+            },
+            UploadStep::Copy => match response {
+                Ok(_) => {
+                    self.removals = vec![self.temp.clone()];
+                    if self.exists {
+                        self.removals.push(self.backup.clone());
+                    }
+                    self.step = UploadStep::RemoveWork;
+                }
+                Err(e) if self.exists => {
+                    self.error = Some(e);
+                    self.step = UploadStep::Restore;
+                }
+                Err(e) => self.fail(e),
+            },
+            UploadStep::Restore => match response {
+                Ok(_) => {
+                    self.removals = vec![self.temp.clone(), self.backup.clone()];
+                    self.step = UploadStep::Cleanup;
+                }
+                // 書き戻せない場合は一時ファイルとバックアップを残す
+                Err(_) => self.step = UploadStep::Done,
+            },
+            UploadStep::RemoveWork => {
+                response?;
+                self.removals.pop();
+                if self.removals.is_empty() {
+                    self.step = UploadStep::Done;
+                }
+            }
+            // 元のエラーを返すため、削除の結果は問わない
+            UploadStep::Cleanup => {
+                self.removals.pop();
+                if self.removals.is_empty() {
+                    self.step = UploadStep::Done;
+                }
+            }
+            UploadStep::Done => (),
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<(), SlmpError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// ディレクトリ内の全てのファイルの情報の読出し
+pub(crate) struct ReadDir<'a> {
+    drive: u16,
+    path: &'a str,
+    entries: Vec<SlmpFileEntry>,
+    done: bool,
+}
+
+impl<'a> ReadDir<'a> {
+    /// 長いファイル名でも一度の応答に収まる数
+    const COUNT: u16 = 3;
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `path` - ディレクトリのパス名、空文字列はルートディレクトリ
+    pub(crate) fn new(drive: u16, path: &'a str) -> ReadDir<'a> {
+        ReadDir {
+            drive,
+            path,
+            entries: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+impl<'a> Operation for ReadDir<'a> {
+    type Output = Vec<SlmpFileEntry>;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        if self.done {
+            return Ok(None);
+        }
+        let head = self.entries.len() as u32;
+        Ok(Some(make_read_dir_cmd(
+            self.drive,
+            head,
+            Self::COUNT,
+            self.path,
+        )?))
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        let entries = decode_read_dir_response(&response?)?;
+        self.done = entries.len() < Self::COUNT as usize;
+        self.entries.extend(entries);
+        Ok(())
+    }
+    fn finish(self) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        Ok(self.entries)
+    }
+}
+
+/// ドライブ内の全てのファイルの情報の読出し(Qシリーズ用)
+pub(crate) struct ReadFileInfo {
+    drive: u16,
+    entries: Vec<SlmpFileEntry>,
+    done: bool,
+}
+
+impl ReadFileInfo {
+    /// 一度に読み出す件数
+    const COUNT: u16 = 36;
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    pub(crate) fn new(drive: u16) -> ReadFileInfo {
+        ReadFileInfo {
+            drive,
+            entries: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+impl Operation for ReadFileInfo {
+    type Output = Vec<SlmpFileEntry>;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        if self.done {
+            return Ok(None);
+        }
+        let head = self.entries.len() as u16 + 1;
+        Ok(Some(make_read_file_info_cmd(
+            self.drive,
+            head,
+            Self::COUNT,
+        )?))
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        let entries = decode_read_file_info_response(&response?)?;
+        self.done = entries.len() < Self::COUNT as usize;
+        self.entries.extend(entries);
+        if self.entries.len() + Self::COUNT as usize > u16::MAX as usize {
+            self.done = true;
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<Vec<SlmpFileEntry>, SlmpError> {
+        Ok(self.entries)
+    }
+}
+
+/// ファイル名を指定したファイル全体の読出し(Qシリーズ用)
+///
+/// ファイル番号を検索し、一度に扱えるバイト数ごとに読み出す
+pub(crate) struct LegacyFileDownload<'a> {
+    drive: u16,
+    name: &'a str,
+    keyword: &'a str,
+    /// ファイル番号とファイルサイズ、検索前は`None`
+    file: Option<(u16, u32)>,
+    data: Vec<u8>,
+}
+
+impl<'a> LegacyFileDownload<'a> {
+    /// 処理を作成
+    ///
+    /// # 引数
+    ///
+    /// * `drive` - ドライブ番号
+    /// * `name` - ファイル名
+    /// * `keyword` - キーワード、空文字列はキーワードなし
+    pub(crate) fn new(drive: u16, name: &'a str, keyword: &'a str) -> LegacyFileDownload<'a> {
+        LegacyFileDownload {
+            drive,
+            name,
+            keyword,
+            file: None,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Operation for LegacyFileDownload<'a> {
+    type Output = Vec<u8>;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        match self.file {
+            None => Ok(Some(make_file_search_cmd(
+                self.keyword,
+                self.drive,
+                self.name,
+            )?)),
+            Some((number, size)) if self.data.len() < size as usize => {
+                let chunk = (size as usize - self.data.len()).min(MAX_FILE_CHUNK) as u16;
+                let offset = self.data.len() as u32;
+                Ok(Some(make_file_read_cmd(
+                    self.keyword,
+                    self.drive,
+                    number,
+                    offset,
+                    chunk,
+                )?))
+            }
+            Some(_) => Ok(None),
+        }
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        let buf = response?;
+        match self.file {
+            None => self.file = Some(decode_file_search_response(&buf)?),
+            Some(_) => {
+                let ret = decode_file_read_response(&buf)?;
+                if ret.is_empty() {
+                    return Err(SlmpError::MalformedFrame("short file read"));
+                }
+                self.data.extend_from_slice(&ret);
+            }
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<Vec<u8>, SlmpError> {
+        Ok(self.data)
+    }
+}
+
+/// イベント履歴の全件の読出し
+///
+/// 件数を読み出した後、一度に扱える件数ごとに読み出す
+pub(crate) struct ReadEventHistory {
+    /// 履歴の件数、読出し前は`None`
+    total: Option<usize>,
+    events: Vec<SlmpEvent>,
+    done: bool,
+}
+
+impl ReadEventHistory {
+    /// 処理を作成
+    pub(crate) fn new() -> ReadEventHistory {
+        ReadEventHistory {
+            total: None,
+            events: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+impl Operation for ReadEventHistory {
+    type Output = Vec<SlmpEvent>;
+    fn next_request(&mut self) -> Result<Option<SLMPRequest>, SlmpError> {
+        match self.total {
+            None => Ok(Some(make_get_event_num_cmd())),
+            Some(total) if !self.done && self.events.len() < total => {
+                let count = (total - self.events.len()).min(MAX_EVENT_HISTORY) as u16;
+                let head = self.events.len() as u32;
+                Ok(Some(make_get_event_history_cmd(head, count)?))
+            }
+            Some(_) => Ok(None),
+        }
+    }
+    fn on_response(&mut self, response: Result<Vec<u8>, SlmpError>) -> Result<(), SlmpError> {
+        let buf = response?;
+        match self.total {
+            None => self.total = Some(decode_get_event_num_response(&buf)? as usize),
+            Some(_) => {
+                let events = decode_get_event_history_response(&buf)?;
+                // 空であれば読出し中に履歴がクリアされた
+                self.done = events.is_empty();
+                self.events.extend(events);
+            }
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<Vec<SlmpEvent>, SlmpError> {
+        Ok(self.events)
+    }
+}
+
+/// ファイルの削除
+///
+/// # 引数
+///
+/// * `drive` - ドライブ番号
+/// * `path` - ファイルのパス名
+/// * `password` - ファイルパスワード、空文字列はパスワードなし
+pub(crate) fn delete_file<'a>(
+    drive: u16,
+    path: &str,
+    password: &str,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    Ok(Plan::requests(vec![make_delete_file_cmd(
+        password, drive, path,
+    )?]))
+}
+
+/// 自局のデュアルポートメモリの読取り
+///
+/// # 引数
+///
+/// * `start_addr` - 先頭アドレス
+/// * `word_length` - ワード長
+pub(crate) fn read_memory<'a>(
+    start_addr: u32,
+    word_length: u16,
+) -> Result<Plan<'a, Vec<u16>>, SlmpError> {
+    Ok(Plan::single(
+        make_read_memory(start_addr, word_length)?,
+        decode_read_memory,
+    ))
+}
+
+/// 自局のデュアルポートメモリへの書込み
+///
+/// # 引数
+///
+/// * `start_addr` - 先頭アドレス
+/// * `data` - 書き込みデータ
+pub(crate) fn write_memory<'a>(start_addr: u32, data: &[u16]) -> Result<Plan<'a, ()>, SlmpError> {
+    if data.len() > 0xffff {
+        return Err(SlmpError::InvalidArgument("too long data"));
+    }
+    let req = make_write_memory(start_addr, data.len() as u16, data)?;
+    Ok(Plan::requests(vec![req]))
+}
+
+/// リモートRUN
+///
+/// # 引数
+///
+/// * `force` - 強制実行するかどうか
+/// * `clear_mode` - クリアモード
+pub(crate) fn remote_run<'a>(force: bool, clear_mode: SLMPClearMode) -> Plan<'a, ()> {
+    Plan::requests(vec![make_remote_run_cmd(force, clear_mode)])
+}
+
+/// リモートSTOP
+pub(crate) fn remote_stop<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_remote_stop_cmd()])
+}
+
+/// リモートPAUSE
+///
+/// # 引数
+///
+/// * `force` - 強制実行するかどうか
+pub(crate) fn remote_pause<'a>(force: bool) -> Plan<'a, ()> {
+    Plan::requests(vec![make_remote_pause_cmd(force)])
+}
+
+/// リモートラッチクリア
+pub(crate) fn remote_latch_clear<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_remote_latch_clear()])
+}
+
+/// リモートRESET
+pub(crate) fn remote_reset<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_remote_reset_cmd()])
+}
+
+/// リモートパスワードによるロックの解除
+///
+/// # 引数
+///
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+pub(crate) fn remote_password_unlock<'a>(
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    Ok(Plan::requests(vec![make_remote_password_unlock_cmd(
+        password, format,
+    )?]))
+}
+
+/// リモートパスワードによるロック
+///
+/// # 引数
+///
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+pub(crate) fn remote_password_lock<'a>(
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<Plan<'a, ()>, SlmpError> {
+    Ok(Plan::requests(vec![make_remote_password_lock_cmd(
+        password, format,
+    )?]))
+}
+
+/// ドライブのメモリ使用状況の読出し
+///
+/// # 引数
+///
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭クラスタ番号
+/// * `count` - クラスタ数、16の倍数
+pub(crate) fn read_disk_state<'a>(
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<Plan<'a, SlmpDiskState>, SlmpError> {
+    Ok(Plan::single(
+        make_read_disk_state_cmd(drive, head, count)?,
+        move |buf| decode_read_disk_state_response(buf, count),
+    ))
+}
+
+/// ドライブのデフラグ
+///
+/// # 引数
+///
+/// * `drive` - ドライブ番号
+pub(crate) fn defrag<'a>(drive: u16) -> Plan<'a, ()> {
+    Plan::requests(vec![make_defrag_cmd(drive)])
+}
+
+/// 形名読出し
+pub(crate) fn read_type_name<'a>() -> Plan<'a, (String, u16)> {
+    Plan::single(make_read_type_name_cmd(), decode_read_type_name_response)
+}
+
+/// セルフテスト
+///
+/// # 引数
+///
+/// * `data` - 折り返しチェックのための文字列データ
+pub(crate) fn self_test<'a>(data: &[u8]) -> Result<Plan<'a, Vec<u8>>, SlmpError> {
+    Ok(Plan::single(
+        make_self_test_cmd(data)?,
+        decode_self_test_response,
+    ))
+}
+
+/// エラークリア
+pub(crate) fn clear_error<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_clear_error_cmd()])
+}
+
+/// エラー履歴のクリア
+pub(crate) fn clear_error_history<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_clear_error_history_cmd()])
+}
+
+/// イベント履歴の件数の読出し
+pub(crate) fn read_event_count<'a>() -> Plan<'a, u32> {
+    Plan::single(make_get_event_num_cmd(), decode_get_event_num_response)
+}
+
+/// イベント履歴のクリア
+pub(crate) fn clear_event_history<'a>() -> Plan<'a, ()> {
+    Plan::requests(vec![make_clear_event_history_cmd()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SLMPDeviceCode;
+
+    /// 要求を記録し、用意した応答を順に返して処理を実行する
+    fn run<O: Operation>(
+        mut op: O,
+        mut responses: Vec<Result<Vec<u8>, SlmpError>>,
+    ) -> (Vec<SLMPRequest>, Result<O::Output, SlmpError>) {
+        let mut requests = Vec::new();
+        responses.reverse();
+        let ret = (|| {
+            while let Some(req) = op.next_request()? {
+                requests.push(req);
+                op.on_response(responses.pop().expect("unexpected request"))?;
+            }
+            op.finish()
+        })();
+        (requests, ret)
+    }
+
+    #[test]
+    fn test_plan_join() {
+        let d = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let plan = read_words(d, 1, false).join(read_event_count());
+        let (requests, ret) = run(plan, vec![Ok(vec![0x34, 0x12]), Ok(vec![3, 0, 0, 0])]);
+        assert_eq!(requests.len(), 2);
+        let (words, count) = ret.unwrap();
+        assert_eq!(words[0].value, 0x1234);
+        assert_eq!(count, 3);
+
+        // 失敗した要求で中断する
+        let plan = read_words(d, 1, false).join(read_event_count());
+        let (requests, ret) = run(plan, vec![Err(SlmpError::Timeout)]);
+        assert_eq!(requests.len(), 1);
+        assert!(matches!(ret, Err(SlmpError::Timeout)));
+    }
+
+    #[test]
+    fn test_read_bits_address_overflow() {
+        let m = |addr| SLMPDevice {
+            d_code: SLMPDeviceCode::M,
+            addr,
+        };
+        assert!(matches!(
+            read_bits(m(u32::MAX - 10), 20, true, 7168),
+            Err(SlmpError::InvalidArgument(_))
+        ));
+        let plan = read_bits(m(u32::MAX - 20), 20, true, 8).unwrap();
+        let (requests, ret) = run(
+            plan,
+            vec![Ok(vec![0x10; 4]), Ok(vec![0x10; 4]), Ok(vec![0x10; 2])],
+        );
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2], make_read_cmd_32(m(u32::MAX - 4), 4, true));
+        let ret = ret.unwrap();
+        assert_eq!(ret.len(), 20);
+        assert_eq!(ret[19].dev, m(u32::MAX - 1));
+    }
+
+    #[test]
+    fn test_download_closes_on_error() {
+        let op = FileDownload::new(2, "A.CSV", "").unwrap();
+        let (requests, ret) = run(
+            op,
+            vec![
+                Ok(vec![5, 0]),
+                Err(SlmpError::MalformedFrame("test")),
+                Ok(Vec::new()),
+            ],
+        );
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[2],
+            make_close_file_cmd(5, SlmpFileCloseType::Pointer)
+        );
+        assert!(matches!(ret, Err(SlmpError::MalformedFrame("test"))));
+    }
+}
@@ -301,7 +301,7 @@ pub fn pack_bits_by_word(data: &[bool]) -> Vec<u8> {
             buf.push(0u8);
         }
         let mut tmp = buf.pop().unwrap();
-        if let true = d {
+        if *d {
             tmp += 1u8 << (i % 8);
         }
         buf.push(tmp);
@@ -509,11 +509,9 @@ pub fn unpack_dwords_by_dword(data: &[u8]) -> Vec<u32> {
 /// ```
 pub fn unpack_bits_in_byte(data: u8) -> [bool; 8] {
     let mut buf = [false; 8];
-    for i in 0..8 {
+    for (i, b) in buf.iter_mut().enumerate() {
         let tmp = 1u8 << i;
-        if data & tmp == tmp {
-            buf[i] = true;
-        }
+        *b = data & tmp == tmp;
     }
     buf
 }
@@ -595,7 +593,7 @@ pub fn unpack_bits_in_dword(data: u32) -> [bool; 32] {
 }
 
 /// SLMPにおけるデバイス
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct SLMPDevice {
     /// デバイス種別
     pub d_code: SLMPDeviceCode,
@@ -622,41 +620,21 @@ impl SLMPDevice {
         buf
     }
 }
-impl PartialEq for SLMPDevice {
-    fn eq(&self, other: &Self) -> bool {
-        if self.d_code == other.d_code && self.addr == other.addr {
-            true
-        } else {
-            false
-        }
-    }
-}
 
 /// SLMPにおけるデバイスの値
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct SLMPDeviceData<T: PartialEq + Copy + Clone> {
     /// 対象デバイス
     pub dev: SLMPDevice,
     /// デバイスの持っている値
     pub value: T,
 }
-impl<T: PartialEq + Copy> PartialEq for SLMPDeviceData<T> {
-    fn eq(&self, other: &Self) -> bool {
-        if self.dev == other.dev && self.value == other.value {
-            true
-        } else {
-            false
-        }
-    }
-}
 
 impl SLMPDeviceData<bool> {
     pub fn pack32(&self) -> [u8; 8] {
         let mut buf = [0u8; 8];
         let dev = self.dev.pack32();
-        for i in 0..6 {
-            buf[i] = dev[i];
-        }
+        buf[..6].copy_from_slice(&dev);
         if self.value {
             buf[7] = 1;
         }
@@ -665,9 +643,7 @@ impl SLMPDeviceData<bool> {
     pub fn pack16(&self) -> [u8; 5] {
         let mut buf = [0u8; 5];
         let dev = self.dev.pack16();
-        for i in 0..4 {
-            buf[i] = dev[i];
-        }
+        buf[..4].copy_from_slice(&dev);
         if self.value {
             buf[4] = 1;
         }
@@ -679,9 +655,7 @@ impl SLMPDeviceData<u16> {
     pub fn pack32(&self) -> [u8; 8] {
         let mut buf = [0u8; 8];
         let dev = self.dev.pack32();
-        for i in 0..6 {
-            buf[i] = dev[i];
-        }
+        buf[..6].copy_from_slice(&dev);
         buf[6] = self.value as u8;
         buf[7] = (self.value >> 8) as u8;
         buf
@@ -689,9 +663,7 @@ impl SLMPDeviceData<u16> {
     pub fn pack16(&self) -> [u8; 6] {
         let mut buf = [0u8; 6];
         let dev = self.dev.pack16();
-        for i in 0..4 {
-            buf[i] = dev[i];
-        }
+        buf[..4].copy_from_slice(&dev);
         buf[4] = self.value as u8;
         buf[5] = (self.value >> 8) as u8;
         buf
@@ -705,9 +677,7 @@ impl SLMPDeviceData<u32> {
     pub fn pack32(&self) -> [u8; 10] {
         let mut buf = [0u8; 10];
         let dev = self.dev.pack32();
-        for i in 0..6 {
-            buf[i] = dev[i];
-        }
+        buf[..6].copy_from_slice(&dev);
         buf[6] = self.value as u8;
         buf[7] = (self.value >> 8) as u8;
         buf[8] = (self.value >> 16) as u8;
@@ -717,9 +687,7 @@ impl SLMPDeviceData<u32> {
     pub fn pack16(&self) -> [u8; 8] {
         let mut buf = [0u8; 8];
         let dev = self.dev.pack16();
-        for i in 0..4 {
-            buf[i] = dev[i];
-        }
+        buf[..4].copy_from_slice(&dev);
         buf[4] = self.value as u8;
         buf[5] = (self.value >> 8) as u8;
         buf[6] = (self.value >> 16) as u8;
@@ -842,7 +810,7 @@ fn unpack_dword2word(src: &SLMPDeviceData<u32>) -> [SLMPDeviceData<u16>; 2] {
 /// 先頭デバイスと点数で表される
 ///
 /// ここで点数はビットデバイスであろうとワード単位となる
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct SLMPDeviceBlock {
     /// 先頭デバイス
     pub top_device: SLMPDevice,
@@ -853,9 +821,7 @@ impl SLMPDeviceBlock {
     pub fn pack16(&self) -> [u8; 6] {
         let packed = self.top_device.pack16();
         let mut buf = [0; 6];
-        for i in 0..4 {
-            buf[i] = packed[i];
-        }
+        buf[..4].copy_from_slice(&packed);
         buf[4] = self.count as u8;
         buf[5] = (self.count >> 8) as u8;
 
@@ -864,23 +830,12 @@ impl SLMPDeviceBlock {
     pub fn pack32(&self) -> [u8; 8] {
         let packed = self.top_device.pack32();
         let mut buf = [0u8; 8];
-        for i in 0..6 {
-            buf[i] = packed[i];
-        }
+        buf[..6].copy_from_slice(&packed);
         buf[6] = self.count as u8;
         buf[7] = (self.count >> 8) as u8;
         buf
     }
 }
-impl PartialEq for SLMPDeviceBlock {
-    fn eq(&self, other: &Self) -> bool {
-        if self.top_device == other.top_device && self.count == other.count {
-            true
-        } else {
-            false
-        }
-    }
-}
 impl PartialOrd for SLMPDevice {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self.d_code == other.d_code {
@@ -923,6 +878,12 @@ pub struct SLMPDeviceBlockData<T: PartialEq + Copy + Clone> {
     pub device_block: SLMPDeviceBlock,
     value: Vec<T>,
 }
+impl<T: PartialEq + Copy + Clone> SLMPDeviceBlockData<T> {
+    /// 保持している値、ビットデバイスブロックでは点数の16倍
+    pub fn values(&self) -> &[T] {
+        &self.value
+    }
+}
 impl SLMPDeviceBlockData<bool> {
     /// データを含むデバイスブロックを新規作成
     ///
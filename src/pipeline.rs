@@ -0,0 +1,340 @@
+use crate::structs::SLMPFrame;
+use crate::{SLMPConnectionInfo, SLMPFrameType, SLMPRequest, SlmpError};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// 待ち受けている要求のいずれにも対応しない受信データ
+#[derive(Debug)]
+pub enum SlmpUnmatched {
+    /// オンデマンドデータ
+    OnDemand {
+        /// シリアル番号
+        serial: u16,
+        /// コマンド、サブコマンドとそれに続くデータ
+        data: Vec<u8>,
+    },
+    /// タイムアウト後、もしくは要求していないシリアル番号の応答
+    Late {
+        /// シリアル番号
+        serial: u16,
+        /// 応答の内容
+        result: Result<Vec<u8>, SlmpError>,
+    },
+}
+
+/// 複数の要求を応答を待たずに送信し、シリアル番号で応答を振り分ける
+pub struct SlmpPipeline {
+    /// SLMP接続情報
+    connection_info: SLMPConnectionInfo,
+    /// SLMPコマンドのタイムアウト、単位は250ms
+    timeout: u16,
+    /// 応答待ちの要求のシリアル番号と期限
+    outstanding: HashMap<u16, Option<Instant>>,
+    /// 受け取られていない応答
+    completed: HashMap<u16, Result<Vec<u8>, SlmpError>>,
+    /// 完了した順のシリアル番号
+    completed_order: VecDeque<u16>,
+    /// 対応する要求のない受信データ
+    unmatched: VecDeque<SlmpUnmatched>,
+}
+
+impl SlmpPipeline {
+    /// SLMP接続情報からパイプラインを作成
+    ///
+    /// # 引数
+    ///
+    /// * `connection_info` - SLMP接続情報
+    pub fn new(connection_info: SLMPConnectionInfo) -> SlmpPipeline {
+        SlmpPipeline {
+            connection_info,
+            timeout: 40,
+            outstanding: HashMap::new(),
+            completed: HashMap::new(),
+            completed_order: VecDeque::new(),
+            unmatched: VecDeque::new(),
+        }
+    }
+    pub fn get_timeout(&self) -> u16 {
+        self.timeout
+    }
+    pub fn set_timeout(&mut self, timeout: u16) {
+        self.timeout = timeout
+    }
+    pub fn connection_info(&self) -> &SLMPConnectionInfo {
+        &self.connection_info
+    }
+    /// パイプラインを破棄してSLMP接続情報を取り出す
+    ///
+    /// 応答待ちの要求は破棄される
+    pub fn into_inner(self) -> SLMPConnectionInfo {
+        self.connection_info
+    }
+    /// 応答待ちの要求数
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+    /// 既定のタイムアウトで要求を送信する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信した要求のシリアル番号
+    pub fn submit(&mut self, req: &SLMPRequest) -> Result<u16, SlmpError> {
+        self.submit_with_timeout(req, self.timeout)
+    }
+    /// タイムアウトを指定して要求を送信する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms、0で無期限
+    ///
+    /// # 返値
+    ///
+    /// 送信した要求のシリアル番号
+    pub fn submit_with_timeout(
+        &mut self,
+        req: &SLMPRequest,
+        timeout: u16,
+    ) -> Result<u16, SlmpError> {
+        // 3Eフレームはシリアル番号を持たないため、応答を振り分けられない
+        if self.connection_info.get_frame_type() == SLMPFrameType::Frame3E
+            && !self.outstanding.is_empty()
+        {
+            return Err(SlmpError::InvalidArgument(
+                "3E frame allows only one outstanding request",
+            ));
+        }
+        let serial = self.connection_info.send_request(timeout, req)?;
+        let deadline = match timeout {
+            0 => None,
+            t => Some(Instant::now() + Duration::from_millis(t as u64 * 250)),
+        };
+        // 同じシリアル番号の古い結果は無効
+        if self.completed.remove(&serial).is_some() {
+            self.completed_order.retain(|s| *s != serial);
+        }
+        self.outstanding.insert(serial, deadline);
+        Ok(serial)
+    }
+    /// 指定した要求の応答を待つ
+    ///
+    /// 待っている間に受信した他の要求の応答は保持され、後で受け取れる
+    ///
+    /// # 引数
+    ///
+    /// * `serial` - 要求のシリアル番号
+    ///
+    /// # 返値
+    ///
+    /// 応答のペイロード
+    pub fn wait(&mut self, serial: u16) -> Result<Vec<u8>, SlmpError> {
+        loop {
+            if let Some(result) = self.completed.remove(&serial) {
+                self.completed_order.retain(|s| *s != serial);
+                return result;
+            }
+            let deadline = match self.outstanding.get(&serial) {
+                Some(deadline) => *deadline,
+                None => return Err(SlmpError::InvalidArgument("unknown serial")),
+            };
+            self.poll(deadline)?;
+        }
+    }
+    /// いずれかの要求の応答を待つ
+    ///
+    /// # 返値
+    ///
+    /// 完了した要求のシリアル番号と応答のペイロード
+    #[allow(clippy::type_complexity)]
+    pub fn wait_any(&mut self) -> Result<(u16, Result<Vec<u8>, SlmpError>), SlmpError> {
+        loop {
+            if let Some(serial) = self.completed_order.pop_front() {
+                if let Some(result) = self.completed.remove(&serial) {
+                    return Ok((serial, result));
+                }
+                continue;
+            }
+            if self.outstanding.is_empty() {
+                return Err(SlmpError::InvalidArgument("no outstanding request"));
+            }
+            let deadline = if self.outstanding.values().any(|d| d.is_none()) {
+                None
+            } else {
+                self.outstanding.values().flatten().min().copied()
+            };
+            self.poll(deadline)?;
+        }
+    }
+    /// 要求を送信して応答を待つ
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 応答のペイロード
+    pub fn request(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let serial = self.submit(req)?;
+        self.wait(serial)
+    }
+    /// 対応する要求のない受信データを取り出す
+    pub fn take_unmatched(&mut self) -> Option<SlmpUnmatched> {
+        self.unmatched.pop_front()
+    }
+    /// 期限を過ぎた要求をタイムアウトとして完了させる
+    fn expire(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<u16> = self
+            .outstanding
+            .iter()
+            .filter(|(_, deadline)| matches!(deadline, Some(d) if *d <= now))
+            .map(|(serial, _)| *serial)
+            .collect();
+        for serial in expired {
+            self.outstanding.remove(&serial);
+            self.complete(serial, Err(SlmpError::Timeout));
+        }
+    }
+    fn complete(&mut self, serial: u16, result: Result<Vec<u8>, SlmpError>) {
+        self.completed.insert(serial, result);
+        self.completed_order.push_back(serial);
+    }
+    /// 期限まで通信文を一つ受信し、対応する要求へ振り分ける
+    fn poll(&mut self, deadline: Option<Instant>) -> Result<(), SlmpError> {
+        let timeout = match deadline {
+            None => 0,
+            Some(d) => {
+                let remain = d.saturating_duration_since(Instant::now()).as_millis() as u64;
+                if remain == 0 {
+                    self.expire();
+                    return Ok(());
+                }
+                remain
+            }
+        };
+        self.connection_info.set_timeout(timeout)?;
+        let frame = match self.connection_info.recv_frame() {
+            Ok(frame) => frame,
+            Err(SlmpError::Timeout) => {
+                self.expire();
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let serial = frame.serial();
+        match frame {
+            SLMPFrame::OnDemand { data, .. } => {
+                self.unmatched
+                    .push_back(SlmpUnmatched::OnDemand { serial, data });
+            }
+            frame => {
+                let result = frame
+                    .into_result(
+                        self.connection_info.get_network(),
+                        self.connection_info.get_node(),
+                        self.connection_info.get_dst_proc(),
+                    )
+                    .map(|(_, data)| data);
+                if self.outstanding.remove(&serial).is_some() {
+                    self.complete(serial, result);
+                } else {
+                    self.unmatched
+                        .push_back(SlmpUnmatched::Late { serial, result });
+                }
+            }
+        }
+        self.expire();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SLMPCommand;
+    use std::net::{SocketAddr, UdpSocket};
+
+    fn response(serial: [u8; 2], data: &[u8]) -> Vec<u8> {
+        let dl = data.len() + 2;
+        let mut res = vec![
+            0xd4, 0x00, serial[0], serial[1], 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00,
+        ];
+        res.push(dl as u8);
+        res.push((dl >> 8) as u8);
+        res.extend_from_slice(&[0x00, 0x00]);
+        res.extend_from_slice(data);
+        res
+    }
+
+    /// `count`個の要求を受信した後、オンデマンドデータを送り、逆順に`answer`個応答する
+    fn reverse_peer(count: usize, answer: usize) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            let mut serials = Vec::new();
+            let mut from = addr;
+            for _ in 0..count {
+                let (_, f) = socket.recv_from(&mut buf).unwrap();
+                serials.push([buf[2], buf[3]]);
+                from = f;
+            }
+            let od = [
+                0x54, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x06, 0x00, 0x00,
+                0x00, 0x01, 0x16, 0x00, 0x00,
+            ];
+            socket.send_to(&od, from).unwrap();
+            for (i, serial) in serials.iter().enumerate().rev().take(answer) {
+                socket
+                    .send_to(&response(*serial, &[i as u8]), from)
+                    .unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_out_of_order_responses() {
+        let addr = reverse_peer(3, 3);
+        let mut pipeline = SlmpPipeline::new(SLMPConnectionInfo::new_udp(addr).unwrap());
+        pipeline.set_timeout(8);
+        let req = SLMPRequest::new(SLMPCommand::ClearErrorCode, 0, &[]);
+        let serials: Vec<u16> = (0..3).map(|_| pipeline.submit(&req).unwrap()).collect();
+        assert_eq!(pipeline.outstanding(), 3);
+        for (i, serial) in serials.iter().enumerate() {
+            assert_eq!(pipeline.wait(*serial).unwrap(), vec![i as u8]);
+        }
+        assert_eq!(pipeline.outstanding(), 0);
+        match pipeline.take_unmatched() {
+            Some(SlmpUnmatched::OnDemand { data, .. }) => {
+                assert_eq!(data, vec![0x01, 0x16, 0x00, 0x00])
+            }
+            _ => panic!("on-demand data expected"),
+        }
+    }
+
+    #[test]
+    fn test_partial_timeout() {
+        let addr = reverse_peer(2, 1);
+        let mut pipeline = SlmpPipeline::new(SLMPConnectionInfo::new_udp(addr).unwrap());
+        pipeline.set_timeout(2);
+        let req = SLMPRequest::new(SLMPCommand::ClearErrorCode, 0, &[]);
+        let first = pipeline.submit(&req).unwrap();
+        let second = pipeline.submit(&req).unwrap();
+        let (serial, result) = pipeline.wait_any().unwrap();
+        assert_eq!(serial, second);
+        assert_eq!(result.unwrap(), vec![1]);
+        let (serial, result) = pipeline.wait_any().unwrap();
+        assert_eq!(serial, first);
+        assert!(matches!(result, Err(SlmpError::Timeout)));
+        assert!(matches!(
+            pipeline.wait(first),
+            Err(SlmpError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,460 @@
+use crate::split::{
+    block_pieces, offset_device, random_chunks, MAX_BLOCK_POINTS, MAX_RANDOM_READ_POINTS,
+};
+use crate::{
+    SLMPDevice, SLMPDeviceBlock, SLMPDeviceBlockData, SLMPDeviceData, SlmpClient, SlmpError,
+};
+use std::collections::HashMap;
+
+/// 読み出す際のアクセス単位
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpAccessWidth {
+    /// ビット単位、ビットデバイスのみ
+    Bit,
+    /// ワード単位
+    Word,
+    /// ダブルワード単位
+    DWord,
+}
+
+/// アクセス単位に応じた型変換前の値、型変換後の値は[SlmpTagValue](crate::SlmpTagValue)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SlmpRawValue {
+    /// ビット単位の値
+    Bit(bool),
+    /// ワード単位の値
+    Word(u16),
+    /// ダブルワード単位の値
+    DWord(u32),
+}
+
+/// 読出し計画を構成する一つの要求
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlmpPlannedRead {
+    /// 複数ブロック一括読出し
+    Block {
+        /// ワードデバイスのブロック
+        word: Vec<SLMPDeviceBlock>,
+        /// ビットデバイスのブロック
+        bit: Vec<SLMPDeviceBlock>,
+    },
+    /// ランダム読出し
+    Random {
+        /// ワード単位で読み出すデバイス
+        word: Vec<SLMPDevice>,
+        /// ダブルワード単位で読み出すデバイス
+        dword: Vec<SLMPDevice>,
+    },
+}
+
+/// 散在するデバイスをまとめて読み出す計画
+///
+/// 近いアドレスのデバイスはデバイスブロックにまとめ、孤立したデバイスはランダム読出しとする
+///
+/// # 例
+///
+/// ```
+/// use slmp_client::*;
+/// let targets = [
+///     ("D100".parse().unwrap(), SlmpAccessWidth::Word),
+///     ("D102".parse().unwrap(), SlmpAccessWidth::DWord),
+///     ("M5".parse().unwrap(), SlmpAccessWidth::Bit),
+///     ("W1F0".parse().unwrap(), SlmpAccessWidth::Word),
+/// ];
+/// let plan = SlmpReadPlan::new(&targets, 4).unwrap();
+/// assert_eq!(plan.requests().len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SlmpReadPlan {
+    targets: Vec<(SLMPDevice, SlmpAccessWidth)>,
+    requests: Vec<SlmpPlannedRead>,
+}
+
+/// ワード単位に換算した読出し範囲
+#[derive(Copy, Clone, Debug)]
+struct Span {
+    /// 先頭デバイス
+    top: SLMPDevice,
+    /// ワード単位の点数
+    count: usize,
+    /// 単一のダブルワード単位のデバイスのみを含むか
+    dword: bool,
+}
+
+/// 読み出すデバイスを近いもの同士でまとめる
+///
+/// # 引数
+///
+/// * `targets` - 読み出すデバイスとアクセス単位
+/// * `max_gap` - 同じブロックにまとめるデバイスの間隔の上限(ワード単位)
+fn make_spans(
+    targets: &[(SLMPDevice, SlmpAccessWidth)],
+    max_gap: usize,
+) -> Result<Vec<Span>, SlmpError> {
+    // ビットデバイスは16点ごとの区切りの位置が揃うものだけをまとめる
+    let mut groups: HashMap<SLMPDevice, Vec<(u32, u32, bool)>> = HashMap::new();
+    for (dev, width) in targets.iter() {
+        // ビットデバイスは16点を1ワードとする
+        let step = if dev.d_code.is_bit() { 16 } else { 1 };
+        let (addr, count) = match width {
+            SlmpAccessWidth::Bit if !dev.d_code.is_bit() => {
+                return Err(SlmpError::InvalidArgument("bit access to word device"))
+            }
+            SlmpAccessWidth::Bit => (dev.addr - dev.addr % 16, 1),
+            SlmpAccessWidth::Word => (dev.addr, 1),
+            SlmpAccessWidth::DWord => (dev.addr, 2),
+        };
+        let key = SLMPDevice {
+            d_code: dev.d_code,
+            addr: addr % step,
+        };
+        let unit = addr / step;
+        groups
+            .entry(key)
+            .or_default()
+            .push((unit, unit + count, *width == SlmpAccessWidth::DWord));
+    }
+    let mut keys: Vec<SLMPDevice> = groups.keys().copied().collect();
+    keys.sort_by_key(|dev| (dev.d_code as u16, dev.addr));
+
+    let mut ret = Vec::new();
+    for key in keys {
+        let mut units = groups.remove(&key).unwrap();
+        units.sort();
+        units.dedup();
+        let mut current: Option<(u32, u32, bool)> = None;
+        for (start, end, dword) in units {
+            current = match current {
+                Some((s, e, _))
+                    if start as usize <= e as usize + max_gap
+                        && (end.max(e) - s) as usize <= MAX_BLOCK_POINTS =>
+                {
+                    Some((s, end.max(e), false))
+                }
+                _ => {
+                    if let Some(span) = current {
+                        ret.push(span_of(key, span));
+                    }
+                    Some((start, end, dword))
+                }
+            };
+        }
+        if let Some(span) = current {
+            ret.push(span_of(key, span));
+        }
+    }
+    Ok(ret)
+}
+
+/// ワード単位の範囲からデバイスの範囲を作成
+fn span_of(base: SLMPDevice, (start, end, dword): (u32, u32, bool)) -> Span {
+    Span {
+        top: offset_device(base, start as usize),
+        count: (end - start) as usize,
+        dword,
+    }
+}
+
+/// デバイスブロックのみで読み出す要求
+fn block_requests(spans: &[Span]) -> Vec<SlmpPlannedRead> {
+    let (bit, word): (Vec<&Span>, Vec<&Span>) = spans.iter().partition(|s| s.top.d_code.is_bit());
+    let counts = |spans: &[&Span]| -> Vec<usize> { spans.iter().map(|s| s.count).collect() };
+    block_pieces(&counts(&word), &counts(&bit))
+        .into_iter()
+        .map(|pieces| {
+            let block = |p: &crate::split::BlockPiece, spans: &[&Span]| {
+                p.block(&SLMPDeviceBlock {
+                    top_device: spans[p.index].top,
+                    count: spans[p.index].count as u16,
+                })
+            };
+            SlmpPlannedRead::Block {
+                word: pieces
+                    .iter()
+                    .filter(|p| !p.bit)
+                    .map(|p| block(p, &word))
+                    .collect(),
+                bit: pieces
+                    .iter()
+                    .filter(|p| p.bit)
+                    .map(|p| block(p, &bit))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// ランダム読出しのみで読み出す要求
+fn random_requests(spans: &[Span]) -> Vec<SlmpPlannedRead> {
+    let (dword, word): (Vec<&Span>, Vec<&Span>) = spans.iter().partition(|s| s.dword);
+    let word: Vec<SLMPDevice> = word.iter().map(|s| s.top).collect();
+    let dword: Vec<SLMPDevice> = dword.iter().map(|s| s.top).collect();
+    random_chunks(word.len(), dword.len(), 1, 1, MAX_RANDOM_READ_POINTS)
+        .into_iter()
+        .map(|(w, d)| SlmpPlannedRead::Random {
+            word: word[w].to_vec(),
+            dword: dword[d].to_vec(),
+        })
+        .collect()
+}
+
+impl SlmpReadPlan {
+    /// 読出し計画を作成
+    ///
+    /// # 引数
+    ///
+    /// * `targets` - 読み出すデバイスとアクセス単位
+    /// * `max_gap` - 同じブロックにまとめるデバイスの間隔の上限(ワード単位)
+    ///
+    /// # 返値
+    ///
+    /// ワードデバイスへビット単位のアクセスを指定した場合はエラー
+    pub fn new(
+        targets: &[(SLMPDevice, SlmpAccessWidth)],
+        max_gap: usize,
+    ) -> Result<SlmpReadPlan, SlmpError> {
+        let spans = make_spans(targets, max_gap)?;
+        // 1ワードまたは単一のダブルワードの範囲は孤立したデバイスとする
+        let (single, blocks): (Vec<Span>, Vec<Span>) = spans
+            .iter()
+            .copied()
+            .partition(|s| s.count == 1 || (s.count == 2 && s.dword));
+        let mut requests = block_requests(&blocks);
+        requests.extend(random_requests(&single));
+        // 孤立したデバイスもブロックとした方が要求数が少なければそちらを選ぶ
+        let all_blocks = block_requests(&spans);
+        if all_blocks.len() < requests.len() {
+            requests = all_blocks;
+        }
+        Ok(SlmpReadPlan {
+            targets: targets.to_vec(),
+            requests,
+        })
+    }
+    /// 読み出すデバイスとアクセス単位
+    pub fn targets(&self) -> &[(SLMPDevice, SlmpAccessWidth)] {
+        &self.targets
+    }
+    /// 計画した要求
+    pub fn requests(&self) -> &[SlmpPlannedRead] {
+        &self.requests
+    }
+    /// 計画に従って読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読出しに使うクライアント
+    ///
+    /// # 返値
+    ///
+    /// 指定したデバイスの順の値
+    pub fn execute(
+        &self,
+        client: &mut SlmpClient,
+    ) -> Result<Vec<SLMPDeviceData<SlmpRawValue>>, SlmpError> {
+        let mut words = HashMap::new();
+        for request in self.requests.iter() {
+            match request {
+                SlmpPlannedRead::Block { word, bit } => {
+                    let (ret_word, ret_bit) = client.read_blocks(word, bit)?;
+                    store_blocks(&mut words, &ret_word, &ret_bit);
+                }
+                SlmpPlannedRead::Random { word, dword } => {
+                    let (ret_word, ret_dword) = client.read_random(word, dword)?;
+                    store_random(&mut words, &ret_word, &ret_dword);
+                }
+            }
+        }
+        self.map_values(&words)
+    }
+    /// 計画に従って読み出す(tokio版)
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読出しに使うクライアント
+    ///
+    /// # 返値
+    ///
+    /// 指定したデバイスの順の値
+    #[cfg(feature = "tokio")]
+    pub async fn execute_async(
+        &self,
+        client: &mut crate::AsyncSlmpClient,
+    ) -> Result<Vec<SLMPDeviceData<SlmpRawValue>>, SlmpError> {
+        let mut words = HashMap::new();
+        for request in self.requests.iter() {
+            match request {
+                SlmpPlannedRead::Block { word, bit } => {
+                    let (ret_word, ret_bit) = client.read_blocks(word, bit).await?;
+                    store_blocks(&mut words, &ret_word, &ret_bit);
+                }
+                SlmpPlannedRead::Random { word, dword } => {
+                    let (ret_word, ret_dword) = client.read_random(word, dword).await?;
+                    store_random(&mut words, &ret_word, &ret_dword);
+                }
+            }
+        }
+        self.map_values(&words)
+    }
+    /// 読み出したワード値から指定したデバイスの値を取り出す
+    ///
+    /// # 引数
+    ///
+    /// * `words` - デバイスごとのワード値、ビットデバイスは先頭から16点分の値
+    fn map_values(
+        &self,
+        words: &HashMap<SLMPDevice, u16>,
+    ) -> Result<Vec<SLMPDeviceData<SlmpRawValue>>, SlmpError> {
+        let get = |dev: SLMPDevice| {
+            words
+                .get(&dev)
+                .copied()
+                .ok_or(SlmpError::MalformedFrame("device not read"))
+        };
+        self.targets
+            .iter()
+            .map(|(dev, width)| {
+                let value = match width {
+                    SlmpAccessWidth::Bit => {
+                        let top = SLMPDevice {
+                            d_code: dev.d_code,
+                            addr: dev.addr - dev.addr % 16,
+                        };
+                        SlmpRawValue::Bit((get(top)? >> (dev.addr % 16)) & 1 != 0)
+                    }
+                    SlmpAccessWidth::Word => SlmpRawValue::Word(get(*dev)?),
+                    SlmpAccessWidth::DWord => SlmpRawValue::DWord(
+                        get(*dev)? as u32 | ((get(offset_device(*dev, 1))? as u32) << 16),
+                    ),
+                };
+                Ok(SLMPDeviceData { dev: *dev, value })
+            })
+            .collect()
+    }
+}
+
+/// デバイスブロックの読出し結果をワード単位で記録
+fn store_blocks(
+    words: &mut HashMap<SLMPDevice, u16>,
+    ret_word: &[SLMPDeviceBlockData<u16>],
+    ret_bit: &[SLMPDeviceBlockData<bool>],
+) {
+    for block in ret_word.iter() {
+        for (i, v) in block.values().iter().enumerate() {
+            words.insert(offset_device(block.device_block.top_device, i), *v);
+        }
+    }
+    for block in ret_bit.iter() {
+        for (i, bits) in block.values().chunks(16).enumerate() {
+            let v = bits
+                .iter()
+                .enumerate()
+                .fold(0u16, |w, (j, b)| w | ((*b as u16) << j));
+            words.insert(offset_device(block.device_block.top_device, i), v);
+        }
+    }
+}
+
+/// ランダム読出しの結果をワード単位で記録
+fn store_random(
+    words: &mut HashMap<SLMPDevice, u16>,
+    ret_word: &[SLMPDeviceData<u16>],
+    ret_dword: &[SLMPDeviceData<u32>],
+) {
+    for d in ret_word.iter() {
+        words.insert(d.dev, d.value);
+    }
+    for d in ret_dword.iter() {
+        words.insert(d.dev, d.value as u16);
+        words.insert(offset_device(d.dev, 1), (d.value >> 16) as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SLMPConnectionInfo, SLMPDeviceCode, SlmpServer};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    fn dev(s: &str) -> SLMPDevice {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut targets = vec![
+            (dev("D100"), SlmpAccessWidth::Word),
+            (dev("D101"), SlmpAccessWidth::Word),
+            (dev("D104"), SlmpAccessWidth::DWord),
+            (dev("M3"), SlmpAccessWidth::Bit),
+            (dev("M20"), SlmpAccessWidth::Bit),
+        ];
+        // 孤立したデバイスが多ければランダム読出しにする
+        for i in 0..150 {
+            targets.push((dev(&format!("W{:X}", i * 100)), SlmpAccessWidth::Word));
+        }
+        targets.push((dev("ZR5000"), SlmpAccessWidth::DWord));
+        let plan = SlmpReadPlan::new(&targets, 4).unwrap();
+        let requests = plan.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0],
+            SlmpPlannedRead::Block {
+                word: vec![SLMPDeviceBlock {
+                    top_device: dev("D100"),
+                    count: 6
+                }],
+                bit: vec![SLMPDeviceBlock {
+                    top_device: dev("M0"),
+                    count: 2
+                }],
+            }
+        );
+        match &requests[1] {
+            SlmpPlannedRead::Random { word, dword } => {
+                assert_eq!(word.len(), 150);
+                assert_eq!(dword, &vec![dev("ZR5000")]);
+            }
+            _ => panic!("random read expected"),
+        }
+        assert!(SlmpReadPlan::new(&[(dev("D0"), SlmpAccessWidth::Bit)], 4).is_err());
+    }
+
+    #[test]
+    fn test_execute() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        {
+            let mut state = server.state();
+            state.devices.set_word(dev("D10"), 0x1234);
+            state.devices.set_dword(dev("D20"), 0x89abcdef);
+            state.devices.set_bit(dev("M17"), true);
+            state.devices.set_word(dev("M40"), 0x5a5a);
+        }
+        let targets = [
+            (dev("D10"), SlmpAccessWidth::Word),
+            (dev("D11"), SlmpAccessWidth::Word),
+            (dev("D20"), SlmpAccessWidth::DWord),
+            (dev("M17"), SlmpAccessWidth::Bit),
+            (dev("M18"), SlmpAccessWidth::Bit),
+            (dev("M40"), SlmpAccessWidth::Word),
+        ];
+        let plan = SlmpReadPlan::new(&targets, 2).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let ret = plan.execute(&mut client).unwrap();
+        let values: Vec<SlmpRawValue> = ret.iter().map(|d| d.value).collect();
+        assert_eq!(
+            values,
+            vec![
+                SlmpRawValue::Word(0x1234),
+                SlmpRawValue::Word(0),
+                SlmpRawValue::DWord(0x89abcdef),
+                SlmpRawValue::Bit(true),
+                SlmpRawValue::Bit(false),
+                SlmpRawValue::Word(0x5a5a),
+            ]
+        );
+        assert_eq!(ret[5].dev.d_code, SLMPDeviceCode::M);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{SLMPRequest, SlmpError};
+use std::time::Duration;
+
+/// 接続状態の変化
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpConnectionState {
+    /// 再接続に成功した
+    Connected,
+    /// 切断を検出した
+    Disconnected,
+    /// 再接続を試行中、何回目の試行か
+    Reconnecting { attempt: u32 },
+    /// 規定回数の再接続に失敗した
+    ReconnectFailed,
+}
+
+/// TCP接続が切断された際の再接続方針
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// 再接続の最大試行回数、0で再接続しない
+    pub max_attempts: u32,
+    /// 2回目の試行前の待ち時間
+    pub initial_backoff: Duration,
+    /// 待ち時間の上限
+    pub max_backoff: Duration,
+    /// 試行ごとに待ち時間へ掛ける倍率
+    pub multiplier: u32,
+    /// 接続のタイムアウト
+    pub connect_timeout: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_attempts: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2,
+            connect_timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 指定回数まで再接続する方針を作成、その他は既定値
+    ///
+    /// # 引数
+    ///
+    /// * `max_attempts` - 再接続の最大試行回数
+    pub fn new(max_attempts: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts,
+            ..ReconnectPolicy::default()
+        }
+    }
+    /// 試行前の待ち時間
+    ///
+    /// # 引数
+    ///
+    /// * `attempt` - 何回目の試行か、1から始まる
+    ///
+    /// # 返値
+    ///
+    /// 待ち時間、初回は待たない
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+        let factor = self.multiplier.saturating_pow(attempt - 2);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// 冪等な要求を再送する方針
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 再送の最大回数、0で再送しない
+    pub max_retries: u32,
+    /// タイムアウトした場合も再送するか
+    pub retry_on_timeout: bool,
+}
+
+impl RetryPolicy {
+    /// 失敗した要求を再送するか
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 失敗した要求
+    /// * `err` - 発生したエラー
+    /// * `retries` - これまでに再送した回数
+    pub(crate) fn should_retry(&self, req: &SLMPRequest, err: &SlmpError, retries: u32) -> bool {
+        let retriable = match err {
+            SlmpError::Io(_) | SlmpError::NotConnected => true,
+            SlmpError::Timeout => self.retry_on_timeout,
+            _ => false,
+        };
+        retriable && req.command.is_idempotent() && retries < self.max_retries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReconnectPolicy {
+            max_backoff: Duration::from_millis(500),
+            ..ReconnectPolicy::new(5)
+        };
+        assert_eq!(policy.backoff(1), Duration::ZERO);
+        assert_eq!(policy.backoff(2), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_millis(500));
+    }
+}
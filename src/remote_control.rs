@@ -1,19 +1,48 @@
-use crate::{SLMPClearMode, SLMPCommand, SLMPConnectionInfo};
+use crate::{SLMPClearMode, SLMPCommand, SLMPConnectionInfo, SLMPRequest, SlmpError};
 
-/// リモートRUN要求を送信
+/// リモートパスワードの形式
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpPasswordFormat {
+    /// Qシリーズ、4文字固定
+    QSeries,
+    /// iQ-Rシリーズ、文字数を付けた6～32文字
+    IqR,
+}
+
+/// ドライブのメモリ使用状況
+///
+/// ドライブのメモリ使用状況の読出しはドライブの容量やファイル数を返さない。
+/// クラスタ数は読み出した範囲のみ分かる
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlmpDiskState {
+    /// ドライブ全体の空きクラスタ数
+    pub free_clusters: u16,
+    /// 読み出したクラスタごとの使用状況、使用中であれば`true`
+    pub clusters: Vec<bool>,
+}
+
+impl SlmpDiskState {
+    /// 読み出した範囲のクラスタ数
+    pub fn total_clusters(&self) -> usize {
+        self.clusters.len()
+    }
+    /// 読み出した範囲で使用中のクラスタ数
+    pub fn used_clusters(&self) -> usize {
+        self.clusters.iter().filter(|c| **c).count()
+    }
+    /// 読み出した範囲で未使用のクラスタ数
+    pub fn unused_clusters(&self) -> usize {
+        self.total_clusters() - self.used_clusters()
+    }
+}
+
+/// リモートRUN要求を作成
 /// # 引数
-/// * `connection_info` - SLMP接続情報
-/// * `timeout` - SLMPコマンドのタイムアウト
 /// * `force` - 強制実行するかどうか
 /// * `clear_mode` - クリアモード
 /// # 返値
-/// 発行したコマンドのシリアル
-pub fn send_remote_run_cmd(
-    connection_info: &mut SLMPConnectionInfo,
-    timeout: u16,
-    force: bool,
-    clear_mode: SLMPClearMode,
-) -> Option<u16> {
+/// 要求内容
+pub fn make_remote_run_cmd(force: bool, clear_mode: SLMPClearMode) -> SLMPRequest {
     let mut buf = Vec::new();
     match force {
         true => buf.push(0x03),
@@ -23,17 +52,54 @@ pub fn send_remote_run_cmd(
     buf.push(clear_mode as u8);
     buf.push(0);
 
-    connection_info.send_cmd(timeout, SLMPCommand::RemoteRun, 0, &buf)
+    SLMPRequest::new(SLMPCommand::RemoteRun, 0, &buf)
+}
+/// リモートRUN要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `force` - 強制実行するかどうか
+/// * `clear_mode` - クリアモード
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_remote_run_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    force: bool,
+    clear_mode: SLMPClearMode,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_run_cmd(force, clear_mode))
 }
 
+/// リモートSTOP要求の作成
+/// # 返値
+/// 要求内容
+pub fn make_remote_stop_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::RemoteStop, 0, &[1u8, 0])
+}
 /// リモートSTOP要求の送信
 /// # 引数
 /// * `connection_info` - SLMP接続情報
 /// * `timeout` - SLMPコマンドのタイムアウト
 /// # 返値
 /// 発行したコマンドのシリアル
-pub fn send_remote_stop_cmd(connection_info: &mut SLMPConnectionInfo, timeout: u16) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::RemoteStop, 0, &[1u8, 0])
+pub fn send_remote_stop_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_stop_cmd())
+}
+/// リモートPAUSE要求を作成
+/// # 引数
+/// * `force` - 強制実行するかどうか
+/// # 返値
+/// 要求内容
+pub fn make_remote_pause_cmd(force: bool) -> SLMPRequest {
+    let buf = match force {
+        true => [1, 0],
+        false => [3, 0],
+    };
+    SLMPRequest::new(SLMPCommand::RemotePause, 0, &buf)
 }
 /// リモートPAUSE要求を送信
 /// # 引数
@@ -46,12 +112,14 @@ pub fn send_remote_pause_cmd(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
     force: bool,
-) -> Option<u16> {
-    let buf = match force {
-        true => [1, 0],
-        false => [3, 0],
-    };
-    connection_info.send_cmd(timeout, SLMPCommand::RemotePause, 0, &buf)
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_pause_cmd(force))
+}
+/// リモートラッチクリア要求を作成
+/// # 返値
+/// 要求内容
+pub fn make_remote_latch_clear() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::RemoteLatchClear, 0, &[1, 0])
 }
 /// リモートラッチクリア要求を送信
 /// # 引数
@@ -62,8 +130,14 @@ pub fn send_remote_pause_cmd(
 pub fn send_remote_latch_clear(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
-) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::RemoteLatchClear, 0, &[1, 0])
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_latch_clear())
+}
+/// リモートRESET要求を作成
+/// # 返値
+/// 要求内容
+pub fn make_remote_reset_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::RemoteReset, 0, &[1, 0])
 }
 /// リモートRESET要求を送信
 /// # 引数
@@ -74,8 +148,14 @@ pub fn send_remote_latch_clear(
 pub fn send_remote_reset_cmd(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
-) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::RemoteReset, 0, &[1, 0])
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_reset_cmd())
+}
+/// 形名要求を作成
+/// # 返値
+/// 要求内容
+pub fn make_read_type_name_cmd() -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::ReadTypeName, 0, &[])
 }
 /// 形名要求を送信
 /// # 引数
@@ -86,19 +166,233 @@ pub fn send_remote_reset_cmd(
 pub fn send_read_type_name_cmd(
     connection_info: &mut SLMPConnectionInfo,
     timeout: u16,
-) -> Option<u16> {
-    connection_info.send_cmd(timeout, SLMPCommand::ReadTypeName, 0, &[])
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_type_name_cmd())
 }
 /// 形名要求に対する応答の処理
 /// # 引数
 /// * `buf` - 応答内容の入ったバッファ
 /// # 返値
 /// 形名と形名コード
-pub fn decode_read_type_name_response(buf: &[u8]) -> (String, u16) {
+pub fn decode_read_type_name_response(buf: &[u8]) -> Result<(String, u16), SlmpError> {
+    if buf.len() < 18 {
+        return Err(SlmpError::MalformedFrame("too short type name response"));
+    }
     let mut s = String::new();
-    for i in 0..16 {
-        s.push(buf[i] as char);
+    for d in buf[..16].iter() {
+        s.push(*d as char);
+    }
+    let code = buf[16] as u16 + ((buf[17] as u16) << 8);
+    Ok((s, code))
+}
+/// メモリ使用状況の読出し要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭クラスタ番号
+/// * `count` - クラスタ数、16の倍数
+/// # 返値
+/// 要求内容
+pub fn make_read_disk_state_cmd(
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<SLMPRequest, SlmpError> {
+    if count == 0 || count / 16 * 16 != count {
+        return Err(SlmpError::InvalidArgument(
+            "cluster count must be a multiple of 16",
+        ));
+    }
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&drive.to_le_bytes());
+    buf.extend_from_slice(&head.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    Ok(SLMPRequest::new(SLMPCommand::ReadDiskState, 0, &buf))
+}
+/// メモリ使用状況の読出し要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// * `head` - 先頭クラスタ番号
+/// * `count` - クラスタ数、16の倍数
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_read_disk_state_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+    head: u16,
+    count: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_read_disk_state_cmd(drive, head, count)?)
+}
+/// メモリ使用状況の読出し応答の処理
+///
+/// 応答は空きクラスタ数に続き、先頭クラスタから順に1ビットずつ使用中であれば1となる
+/// # 引数
+/// * `buf` - 応答内容の入ったバッファ
+/// * `count` - 要求したクラスタ数
+/// # 返値
+/// メモリ使用状況
+pub fn decode_read_disk_state_response(buf: &[u8], count: u16) -> Result<SlmpDiskState, SlmpError> {
+    let count = count as usize;
+    if buf.len() != 2 + count / 8 {
+        return Err(SlmpError::MalformedFrame(
+            "wrong disk state response length",
+        ));
+    }
+    Ok(SlmpDiskState {
+        free_clusters: buf[0] as u16 + ((buf[1] as u16) << 8),
+        clusters: (0..count)
+            .map(|i| buf[2 + i / 8] & (1 << (i % 8)) != 0)
+            .collect(),
+    })
+}
+/// デフラグ要求を作成
+/// # 引数
+/// * `drive` - ドライブ番号
+/// # 返値
+/// 要求内容
+pub fn make_defrag_cmd(drive: u16) -> SLMPRequest {
+    SLMPRequest::new(SLMPCommand::Defrag, 0, &drive.to_le_bytes())
+}
+/// デフラグ要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `drive` - ドライブ番号
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_defrag_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    drive: u16,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_defrag_cmd(drive))
+}
+/// リモートパスワードの要求データを作成
+/// # 引数
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+fn remote_password_data(password: &str, format: SlmpPasswordFormat) -> Result<Vec<u8>, SlmpError> {
+    if !password.is_ascii() {
+        return Err(SlmpError::InvalidArgument("non-ASCII remote password"));
+    }
+    let mut buf = Vec::new();
+    match format {
+        SlmpPasswordFormat::QSeries => {
+            if password.len() != 4 {
+                return Err(SlmpError::InvalidArgument(
+                    "remote password must be 4 characters",
+                ));
+            }
+        }
+        SlmpPasswordFormat::IqR => {
+            if !(6..=32).contains(&password.len()) {
+                return Err(SlmpError::InvalidArgument(
+                    "remote password must be 6 to 32 characters",
+                ));
+            }
+            buf.extend_from_slice(&(password.len() as u16).to_le_bytes());
+        }
+    }
+    buf.extend_from_slice(password.as_bytes());
+    Ok(buf)
+}
+/// リモートパスワードのロック解除要求を作成
+/// # 引数
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+/// # 返値
+/// 要求内容
+pub fn make_remote_password_unlock_cmd(
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<SLMPRequest, SlmpError> {
+    Ok(SLMPRequest::new(
+        SLMPCommand::RemotePasswordUnlock,
+        0,
+        &remote_password_data(password, format)?,
+    ))
+}
+/// リモートパスワードのロック解除要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_remote_password_unlock_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_password_unlock_cmd(password, format)?)
+}
+/// リモートパスワードのロック要求を作成
+/// # 引数
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+/// # 返値
+/// 要求内容
+pub fn make_remote_password_lock_cmd(
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<SLMPRequest, SlmpError> {
+    Ok(SLMPRequest::new(
+        SLMPCommand::RemotePasswordLock,
+        0,
+        &remote_password_data(password, format)?,
+    ))
+}
+/// リモートパスワードのロック要求を送信
+/// # 引数
+/// * `connection_info` - SLMP接続情報
+/// * `timeout` - SLMPコマンドのタイムアウト
+/// * `password` - リモートパスワード
+/// * `format` - リモートパスワードの形式
+/// # 返値
+/// 発行したコマンドのシリアル
+pub fn send_remote_password_lock_cmd(
+    connection_info: &mut SLMPConnectionInfo,
+    timeout: u16,
+    password: &str,
+    format: SlmpPasswordFormat,
+) -> Result<u16, SlmpError> {
+    connection_info.send_request(timeout, &make_remote_password_lock_cmd(password, format)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_password() {
+        let req = make_remote_password_unlock_cmd("ABCD", SlmpPasswordFormat::QSeries).unwrap();
+        assert_eq!(req.data, b"ABCD".to_vec());
+        let req = make_remote_password_lock_cmd("secret1", SlmpPasswordFormat::IqR).unwrap();
+        assert_eq!(req.command, SLMPCommand::RemotePasswordLock);
+        assert_eq!(req.data, b"\x07\x00secret1".to_vec());
+        assert!(make_remote_password_unlock_cmd("ABCDE", SlmpPasswordFormat::QSeries).is_err());
+        assert!(make_remote_password_unlock_cmd("short", SlmpPasswordFormat::IqR).is_err());
+    }
+    #[test]
+    fn test_disk_state() {
+        let req = make_read_disk_state_cmd(4, 0, 32).unwrap();
+        assert_eq!(req.command, SLMPCommand::ReadDiskState);
+        assert_eq!(req.data, vec![4, 0, 0, 0, 32, 0]);
+        assert!(make_read_disk_state_cmd(4, 0, 20).is_err());
+        let state =
+            decode_read_disk_state_response(&[0x1d, 0x00, 0x07, 0x00, 0x00, 0x80], 32).unwrap();
+        assert_eq!(state.free_clusters, 29);
+        assert_eq!(state.total_clusters(), 32);
+        assert_eq!(state.used_clusters(), 4);
+        assert_eq!(state.unused_clusters(), 28);
+        assert!(state.clusters[0] && state.clusters[2] && !state.clusters[3]);
+        assert!(state.clusters[31]);
+        assert!(decode_read_disk_state_response(&[0x1d, 0x00, 0x07], 32).is_err());
+        assert_eq!(make_defrag_cmd(3).data, vec![3, 0]);
     }
-    let code = buf[16] as u16 + (buf[17] as u16) << 8;
-    (s, code)
 }
@@ -0,0 +1,878 @@
+use crate::structs::{decode_response, encode_request, parse_hex, push_hex};
+use crate::{unpack_bits_by_bit, SLMPCommand, SLMPDevice, SLMPDeviceCode, SLMPRequest, SlmpError};
+use std::io;
+use std::io::{Read, Write};
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const ENQ: u8 = 0x05;
+const ACK: u8 = 0x06;
+const DLE: u8 = 0x10;
+const NAK: u8 = 0x15;
+const CRLF: &[u8] = b"\r\n";
+
+/// シリアル通信でのMCプロトコルのフレーム
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MCSerialFrame {
+    /// QnA互換4Cフレーム
+    Frame4C,
+    /// QnA互換3Cフレーム
+    Frame3C,
+    /// QnA互換2Cフレーム、ネットワーク番号とPC番号を持たないQnA簡易フレーム
+    Frame2C,
+    /// A互換1Cフレーム
+    Frame1C,
+}
+
+/// シリアル通信でのMCプロトコルの交信形式
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MCSerialFormat {
+    /// 形式1、ENQで始まるASCIIコード
+    Format1,
+    /// 形式2、ブロック番号付きのASCIIコード
+    Format2,
+    /// 形式3、STXとETXで囲まれたASCIIコード
+    Format3,
+    /// 形式4、形式1の末尾にCR、LFを付加
+    Format4,
+    /// 形式5、4Cフレームのみのバイナリコード
+    Format5,
+}
+
+/// 受信した応答の種類
+enum Reply {
+    /// 応答データ
+    Data(Vec<u8>),
+    /// データを伴わない正常応答
+    Ack,
+    /// 異常応答とエラーコード
+    Nak(u16),
+}
+
+/// サムチェックコードを計算
+fn sum_check(buf: &[u8]) -> u8 {
+    buf.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+/// 1Cフレームでのデバイス指定
+fn device_1c(dev: &SLMPDevice) -> Result<Vec<u8>, SlmpError> {
+    use SLMPDeviceCode::*;
+    let s = match dev.d_code {
+        X => format!("X{:04X}", dev.addr),
+        Y => format!("Y{:04X}", dev.addr),
+        B => format!("B{:04X}", dev.addr),
+        W => format!("W{:04X}", dev.addr),
+        M => format!("M{:04}", dev.addr),
+        L => format!("L{:04}", dev.addr),
+        F => format!("F{:04}", dev.addr),
+        D => format!("D{:04}", dev.addr),
+        R => format!("R{:04}", dev.addr),
+        // 特殊リレー、特殊レジスタはM9000、D9000以降
+        SM => format!("M{:04}", 9000 + dev.addr),
+        SD => format!("D{:04}", 9000 + dev.addr),
+        TS => format!("TS{:03}", dev.addr),
+        TC => format!("TC{:03}", dev.addr),
+        TN => format!("TN{:03}", dev.addr),
+        CS => format!("CS{:03}", dev.addr),
+        CC => format!("CC{:03}", dev.addr),
+        CN => format!("CN{:03}", dev.addr),
+        _ => {
+            return Err(SlmpError::InvalidArgument(
+                "device not supported in 1C frame",
+            ))
+        }
+    };
+    if s.len() != 5 {
+        return Err(SlmpError::InvalidArgument("too large device number"));
+    }
+    Ok(s.into_bytes())
+}
+
+/// バイナリの16bitアドレス版デバイス指定を読み取る
+fn read_device16(buf: &[u8]) -> Result<SLMPDevice, SlmpError> {
+    if buf.len() < 4 {
+        return Err(SlmpError::InvalidArgument("too short request data"));
+    }
+    let addr = (buf[0] as u32) + ((buf[1] as u32) << 8) + ((buf[2] as u32) << 16);
+    let d_code = SLMPDeviceCode::get(buf[3] as u16)
+        .ok_or(SlmpError::InvalidArgument("unknown device code"))?;
+    Ok(SLMPDevice { d_code, addr })
+}
+
+/// 1Cフレームでの点数、256点は0で表す
+fn push_count_1c(out: &mut Vec<u8>, count: usize) -> Result<(), SlmpError> {
+    if count == 0 || count > 256 {
+        return Err(SlmpError::InvalidArgument("wrong number of points"));
+    }
+    push_hex(out, count as u32 & 0xff, 2);
+    Ok(())
+}
+
+/// 要求内容を1Cフレームのコマンド、メッセージウェイト、文字部へ変換
+///
+/// # 引数
+///
+/// * `req` - 要求内容
+/// * `message_wait` - メッセージウェイト、単位は10ms
+///
+/// # 返値
+///
+/// ASCIIでの要求
+fn encode_1c(req: &SLMPRequest, message_wait: u8) -> Result<Vec<u8>, SlmpError> {
+    if req.sub_command & 0x0002 != 0 {
+        return Err(SlmpError::InvalidArgument(
+            "32bit address not supported in 1C frame",
+        ));
+    }
+    let is_bit = req.sub_command & 0x0001 != 0;
+    let data = &req.data;
+    let mut out = Vec::new();
+    let push_command = |out: &mut Vec<u8>, command: &[u8]| {
+        out.extend_from_slice(command);
+        push_hex(out, message_wait as u32, 1);
+    };
+    match req.command {
+        SLMPCommand::DeviceRead | SLMPCommand::DeviceWrite => {
+            let dev = read_device16(data)?;
+            if data.len() < 6 {
+                return Err(SlmpError::InvalidArgument("too short request data"));
+            }
+            let count = (data[4] as usize) + ((data[5] as usize) << 8);
+            let command: &[u8] = match (req.command, is_bit) {
+                (SLMPCommand::DeviceRead, true) => b"BR",
+                (SLMPCommand::DeviceRead, false) => b"WR",
+                (_, true) => b"BW",
+                (_, false) => b"WW",
+            };
+            push_command(&mut out, command);
+            out.extend_from_slice(&device_1c(&dev)?);
+            push_count_1c(&mut out, count)?;
+            if req.command == SLMPCommand::DeviceWrite {
+                if is_bit {
+                    let bits =
+                        unpack_bits_by_bit(&data[6..]).map_err(SlmpError::InvalidArgument)?;
+                    if bits.len() < count {
+                        return Err(SlmpError::InvalidArgument("too short request data"));
+                    }
+                    for b in &bits[..count] {
+                        out.push(if *b { b'1' } else { b'0' });
+                    }
+                } else {
+                    if data.len() != 6 + count * 2 {
+                        return Err(SlmpError::InvalidArgument("wrong request data length"));
+                    }
+                    for w in data[6..].chunks(2) {
+                        push_hex(&mut out, (w[0] as u32) + ((w[1] as u32) << 8), 4);
+                    }
+                }
+            }
+        }
+        SLMPCommand::DeviceWriteRandom => {
+            if is_bit {
+                let count = *data
+                    .first()
+                    .ok_or(SlmpError::InvalidArgument("no target"))?;
+                if data.len() != 1 + count as usize * 5 {
+                    return Err(SlmpError::InvalidArgument("wrong request data length"));
+                }
+                push_command(&mut out, b"BT");
+                push_count_1c(&mut out, count as usize)?;
+                for d in data[1..].chunks(5) {
+                    out.extend_from_slice(&device_1c(&read_device16(d)?)?);
+                    out.push(if d[4] != 0 { b'1' } else { b'0' });
+                }
+            } else {
+                if data.len() < 2 || data[1] != 0 {
+                    return Err(SlmpError::InvalidArgument(
+                        "double word not supported in 1C frame",
+                    ));
+                }
+                let count = data[0];
+                if data.len() != 2 + count as usize * 6 {
+                    return Err(SlmpError::InvalidArgument("wrong request data length"));
+                }
+                push_command(&mut out, b"WT");
+                push_count_1c(&mut out, count as usize)?;
+                for d in data[2..].chunks(6) {
+                    out.extend_from_slice(&device_1c(&read_device16(d)?)?);
+                    push_hex(&mut out, (d[4] as u32) + ((d[5] as u32) << 8), 4);
+                }
+            }
+        }
+        SLMPCommand::SelfTest => {
+            if data.len() < 2 || data.len() > 256 {
+                return Err(SlmpError::InvalidArgument("wrong loopback data length"));
+            }
+            push_command(&mut out, b"TT");
+            push_hex(&mut out, (data.len() - 2) as u32, 2);
+            out.extend_from_slice(&data[2..]);
+        }
+        _ => {
+            return Err(SlmpError::InvalidArgument(
+                "command not supported in 1C frame",
+            ))
+        }
+    }
+    Ok(out)
+}
+
+/// 1Cフレームの応答データをバイナリコードでの応答と同じ形式へ変換
+fn decode_1c(req: &SLMPRequest, data: &[u8]) -> Result<Vec<u8>, SlmpError> {
+    if req.command == SLMPCommand::SelfTest {
+        if data.len() < 2 {
+            return Err(SlmpError::MalformedFrame("too short response data"));
+        }
+        let length = parse_hex(&data[..2])? as usize;
+        if data.len() != length + 2 {
+            return Err(SlmpError::MalformedFrame("wrong self test response length"));
+        }
+        let mut out = vec![length as u8, 0];
+        out.extend_from_slice(&data[2..]);
+        return Ok(out);
+    }
+    // 読出しの文字部はSLMPのASCIIコードと同じ
+    decode_response(req, None, data)
+}
+
+/// シリアル通信でのMCプロトコルの接続管理
+///
+/// 任意の`Read + Write`なストリーム上で動作する。受信のタイムアウトはストリーム側で設定すること
+pub struct MCSerialConnectionInfo<S: Read + Write> {
+    /// 通信ストリーム
+    stream: S,
+    /// フレーム
+    frame: MCSerialFrame,
+    /// 交信形式
+    format: MCSerialFormat,
+    /// 局番
+    station_no: u8,
+    /// ネットワーク番号
+    network: u8,
+    /// PC番号
+    pc_no: u8,
+    /// 要求先ユニットI/O番号
+    dst_proc: u16,
+    /// 要求先ユニット局番
+    dst_station: u8,
+    /// 自局番号
+    self_station: u8,
+    /// ブロック番号
+    block_no: u8,
+    /// メッセージウェイト、単位は10ms
+    message_wait: u8,
+    /// サムチェックの有無
+    sum_check: bool,
+    /// 直前のモニタ登録要求
+    monitor: Option<SLMPRequest>,
+    /// 受信バッファ
+    buf: Vec<u8>,
+}
+
+impl<S: Read + Write> MCSerialConnectionInfo<S> {
+    /// ストリームからシリアル通信の接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `stream` - 通信ストリーム
+    /// * `frame` - フレーム
+    /// * `format` - 交信形式
+    pub fn new(
+        stream: S,
+        frame: MCSerialFrame,
+        format: MCSerialFormat,
+    ) -> Result<MCSerialConnectionInfo<S>, SlmpError> {
+        if format == MCSerialFormat::Format5 && frame != MCSerialFrame::Frame4C {
+            return Err(SlmpError::InvalidArgument(
+                "format 5 is available only with 4C frame",
+            ));
+        }
+        Ok(MCSerialConnectionInfo {
+            stream,
+            frame,
+            format,
+            station_no: 0,
+            network: 0,
+            pc_no: 0xff,
+            dst_proc: 0x3ff,
+            dst_station: 0,
+            self_station: 0,
+            block_no: 0,
+            message_wait: 0,
+            sum_check: true,
+            monitor: None,
+            buf: Vec::new(),
+        })
+    }
+    pub fn get_station_no(&self) -> u8 {
+        self.station_no
+    }
+    pub fn set_station_no(&mut self, station_no: u8) {
+        self.station_no = station_no
+    }
+    pub fn get_network(&self) -> u8 {
+        self.network
+    }
+    pub fn set_network(&mut self, network: u8) {
+        self.network = network
+    }
+    pub fn get_pc_no(&self) -> u8 {
+        self.pc_no
+    }
+    pub fn set_pc_no(&mut self, pc_no: u8) {
+        self.pc_no = pc_no
+    }
+    pub fn get_dst_proc(&self) -> u16 {
+        self.dst_proc
+    }
+    pub fn set_dst_proc(&mut self, dst_proc: u16) {
+        self.dst_proc = dst_proc
+    }
+    pub fn get_dst_station(&self) -> u8 {
+        self.dst_station
+    }
+    pub fn set_dst_station(&mut self, dst_station: u8) {
+        self.dst_station = dst_station
+    }
+    pub fn get_self_station(&self) -> u8 {
+        self.self_station
+    }
+    pub fn set_self_station(&mut self, self_station: u8) {
+        self.self_station = self_station
+    }
+    pub fn get_block_no(&self) -> u8 {
+        self.block_no
+    }
+    pub fn set_block_no(&mut self, block_no: u8) {
+        self.block_no = block_no
+    }
+    pub fn get_message_wait(&self) -> u8 {
+        self.message_wait
+    }
+    /// メッセージウェイトを設定する、1Cフレームでのみ使用
+    ///
+    /// # 引数
+    ///
+    /// * `message_wait` - メッセージウェイト、単位は10ms、0から15
+    pub fn set_message_wait(&mut self, message_wait: u8) {
+        self.message_wait = message_wait & 0x0f
+    }
+    pub fn get_sum_check(&self) -> bool {
+        self.sum_check
+    }
+    pub fn set_sum_check(&mut self, sum_check: bool) {
+        self.sum_check = sum_check
+    }
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+    /// 接続を破棄してストリームを取り出す
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+    /// ASCIIコードでのフレームIDから自局番号まで
+    fn header_ascii(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self.frame {
+            MCSerialFrame::Frame4C => {
+                buf.extend_from_slice(b"F8");
+                push_hex(&mut buf, self.station_no as u32, 2);
+                push_hex(&mut buf, self.network as u32, 2);
+                push_hex(&mut buf, self.pc_no as u32, 2);
+                push_hex(&mut buf, self.dst_proc as u32, 4);
+                push_hex(&mut buf, self.dst_station as u32, 2);
+                push_hex(&mut buf, self.self_station as u32, 2);
+            }
+            MCSerialFrame::Frame3C => {
+                buf.extend_from_slice(b"F9");
+                push_hex(&mut buf, self.station_no as u32, 2);
+                push_hex(&mut buf, self.network as u32, 2);
+                push_hex(&mut buf, self.pc_no as u32, 2);
+                push_hex(&mut buf, self.self_station as u32, 2);
+            }
+            MCSerialFrame::Frame2C => {
+                buf.extend_from_slice(b"FB");
+                push_hex(&mut buf, self.station_no as u32, 2);
+                push_hex(&mut buf, self.self_station as u32, 2);
+            }
+            MCSerialFrame::Frame1C => {
+                push_hex(&mut buf, self.station_no as u32, 2);
+                push_hex(&mut buf, self.pc_no as u32, 2);
+            }
+        }
+        buf
+    }
+    /// バイナリコードでのフレームIDから自局番号まで
+    fn header_binary(&self) -> [u8; 8] {
+        [
+            0xf8,
+            self.station_no,
+            self.network,
+            self.pc_no,
+            self.dst_proc as u8,
+            (self.dst_proc >> 8) as u8,
+            self.dst_station,
+            self.self_station,
+        ]
+    }
+    /// 要求の伝文を作成
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信するバイト列
+    fn make_message(&self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        if self.format == MCSerialFormat::Format5 {
+            let mut raw = vec![0u8; 2];
+            raw.extend_from_slice(&self.header_binary());
+            let command = req.command as u16;
+            raw.extend_from_slice(&[
+                command as u8,
+                (command >> 8) as u8,
+                req.sub_command as u8,
+                (req.sub_command >> 8) as u8,
+            ]);
+            raw.extend_from_slice(&req.data);
+            let length = raw.len() - 2;
+            if length > 0xffff {
+                return Err(SlmpError::InvalidArgument("too long data"));
+            }
+            raw[0] = length as u8;
+            raw[1] = (length >> 8) as u8;
+            let mut buf = vec![DLE, STX];
+            for b in &raw {
+                // DLEは2重にして送る
+                if *b == DLE {
+                    buf.push(DLE);
+                }
+                buf.push(*b);
+            }
+            buf.extend_from_slice(&[DLE, ETX]);
+            if self.sum_check {
+                push_hex(&mut buf, sum_check(&raw) as u32, 2);
+            }
+            return Ok(buf);
+        }
+        // サムチェックの対象
+        let mut body = Vec::new();
+        if self.format == MCSerialFormat::Format2 {
+            push_hex(&mut body, self.block_no as u32, 2);
+        }
+        body.extend_from_slice(&self.header_ascii());
+        match self.frame {
+            MCSerialFrame::Frame1C => body.extend_from_slice(&encode_1c(req, self.message_wait)?),
+            _ => {
+                push_hex(&mut body, req.command as u32, 4);
+                push_hex(&mut body, req.sub_command as u32, 4);
+                body.extend_from_slice(&encode_request(req)?);
+            }
+        }
+        let mut buf = Vec::with_capacity(body.len() + 6);
+        if self.format == MCSerialFormat::Format3 {
+            buf.push(STX);
+            body.push(ETX);
+        } else {
+            buf.push(ENQ);
+        }
+        buf.extend_from_slice(&body);
+        if self.sum_check {
+            push_hex(&mut buf, sum_check(&body) as u32, 2);
+        }
+        if self.format == MCSerialFormat::Format4 {
+            buf.extend_from_slice(CRLF);
+        }
+        Ok(buf)
+    }
+    /// 要求を送信する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    pub fn send_request(&mut self, req: &SLMPRequest) -> Result<(), SlmpError> {
+        let buf = self.make_message(req)?;
+        if req.command == SLMPCommand::EntryMonitorDevice {
+            self.monitor = Some(req.clone());
+        }
+        // 要求と応答は1対1のため、古い受信データは破棄する
+        self.buf.clear();
+        self.stream.write_all(&buf)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+    /// サムチェックコードを確認する
+    fn verify_sum(&self, body: &[u8], sum: &[u8]) -> Result<(), SlmpError> {
+        if self.sum_check && parse_hex(sum)? as u8 != sum_check(body) {
+            return Err(SlmpError::MalformedFrame("sum check error"));
+        }
+        Ok(())
+    }
+    /// ブロック番号とフレームIDから自局番号までを確認し、残りを返す
+    fn strip_header<'a>(&self, body: &'a [u8]) -> Result<&'a [u8], SlmpError> {
+        let mut header = Vec::new();
+        if self.format == MCSerialFormat::Format2 {
+            push_hex(&mut header, self.block_no as u32, 2);
+        }
+        header.extend_from_slice(&self.header_ascii());
+        if !body.starts_with(&header) {
+            return Err(SlmpError::MalformedFrame("unexpected response header"));
+        }
+        Ok(&body[header.len()..])
+    }
+    /// 受信バッファの先頭からASCIIコードの応答を一つ取り出す
+    fn parse_ascii(&self) -> Result<Option<(usize, Reply)>, SlmpError> {
+        let buf = &self.buf;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        let sum_len = if self.sum_check { 2 } else { 0 };
+        let tail_len = if self.format == MCSerialFormat::Format4 {
+            2
+        } else {
+            0
+        };
+        let error_len = if self.frame == MCSerialFrame::Frame1C {
+            2
+        } else {
+            4
+        };
+        let mut prefix_len = 1 + self.header_ascii().len();
+        if self.format == MCSerialFormat::Format2 {
+            prefix_len += 2;
+        }
+        let (size, reply) = match buf[0] {
+            STX => {
+                let etx = match buf.iter().position(|c| *c == ETX) {
+                    Some(etx) => etx,
+                    None => return Ok(None),
+                };
+                let size = etx + 1 + sum_len + tail_len;
+                if buf.len() < size {
+                    return Ok(None);
+                }
+                self.verify_sum(&buf[1..=etx], &buf[etx + 1..etx + 1 + sum_len])?;
+                let rest = self.strip_header(&buf[1..etx])?;
+                if self.format != MCSerialFormat::Format3 {
+                    (size, Reply::Data(rest.to_vec()))
+                } else {
+                    let (ack, nak): (&[u8], &[u8]) = match self.frame {
+                        MCSerialFrame::Frame1C => (b"GG", b"NN"),
+                        _ => (b"QACK", b"QNAK"),
+                    };
+                    if rest.starts_with(ack) {
+                        (size, Reply::Data(rest[ack.len()..].to_vec()))
+                    } else if rest.starts_with(nak) && rest.len() == nak.len() + error_len {
+                        (size, Reply::Nak(parse_hex(&rest[nak.len()..])? as u16))
+                    } else {
+                        return Err(SlmpError::MalformedFrame("wrong response"));
+                    }
+                }
+            }
+            ACK if self.format != MCSerialFormat::Format3 => {
+                let size = prefix_len + tail_len;
+                if buf.len() < size {
+                    return Ok(None);
+                }
+                self.strip_header(&buf[1..prefix_len])?;
+                (size, Reply::Ack)
+            }
+            NAK if self.format != MCSerialFormat::Format3 => {
+                let size = prefix_len + error_len + tail_len;
+                if buf.len() < size {
+                    return Ok(None);
+                }
+                self.strip_header(&buf[1..prefix_len])?;
+                let code = parse_hex(&buf[prefix_len..prefix_len + error_len])? as u16;
+                (size, Reply::Nak(code))
+            }
+            _ => return Err(SlmpError::MalformedFrame("unexpected control code")),
+        };
+        if buf[size - tail_len..size] != CRLF[..tail_len] {
+            return Err(SlmpError::MalformedFrame("no CR LF"));
+        }
+        Ok(Some((size, reply)))
+    }
+    /// 受信バッファの先頭からバイナリコード(形式5)の応答を一つ取り出す
+    fn parse_binary(&self) -> Result<Option<(usize, Reply)>, SlmpError> {
+        let buf = &self.buf;
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        if buf[0] != DLE || buf[1] != STX {
+            return Err(SlmpError::MalformedFrame("unexpected control code"));
+        }
+        // 2重になったDLEを戻す
+        let mut raw = Vec::new();
+        let mut pos = 2;
+        loop {
+            match (buf.get(pos), buf.get(pos + 1)) {
+                (Some(&DLE), Some(&DLE)) => raw.push(DLE),
+                (Some(&DLE), Some(&ETX)) => break,
+                (Some(&DLE), Some(_)) => {
+                    return Err(SlmpError::MalformedFrame("unexpected DLE"));
+                }
+                (Some(&DLE), None) | (None, _) => return Ok(None),
+                (Some(b), _) => {
+                    raw.push(*b);
+                    pos += 1;
+                    continue;
+                }
+            }
+            pos += 2;
+        }
+        let size = pos + 2 + if self.sum_check { 2 } else { 0 };
+        if buf.len() < size {
+            return Ok(None);
+        }
+        self.verify_sum(&raw, &buf[pos + 2..size])?;
+        // データ長、フレームIDから自局番号、応答ID、終了コード
+        if raw.len() < 14 || (raw[0] as usize) + ((raw[1] as usize) << 8) != raw.len() - 2 {
+            return Err(SlmpError::MalformedFrame("wrong response length"));
+        }
+        if raw[2..10] != self.header_binary() || raw[10] != 0xff || raw[11] != 0xff {
+            return Err(SlmpError::MalformedFrame("unexpected response header"));
+        }
+        let code = (raw[12] as u16) + ((raw[13] as u16) << 8);
+        if code != 0 {
+            return Ok(Some((size, Reply::Nak(code))));
+        }
+        Ok(Some((size, Reply::Data(raw[14..].to_vec()))))
+    }
+    /// 要求に対する応答を受信
+    ///
+    /// 正常終了以外の応答を受信した場合は[SlmpError::EndCode]を返す
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 送信した要求内容
+    ///
+    /// # 返値
+    ///
+    /// バイナリコードでの応答と同じ形式の応答データ
+    pub fn recv_response(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        let mut buf = [0u8; 1024];
+        loop {
+            let parsed = if self.format == MCSerialFormat::Format5 {
+                self.parse_binary()
+            } else {
+                self.parse_ascii()
+            };
+            match parsed {
+                Ok(Some((size, reply))) => {
+                    self.buf.drain(..size);
+                    return match reply {
+                        Reply::Ack => Ok(Vec::new()),
+                        Reply::Nak(code) => Err(SlmpError::EndCode {
+                            serial: 0,
+                            code,
+                            info: None,
+                        }),
+                        Reply::Data(data) => match (self.format, self.frame) {
+                            (MCSerialFormat::Format5, _) => Ok(data),
+                            (_, MCSerialFrame::Frame1C) => decode_1c(req, &data),
+                            _ => decode_response(req, self.monitor.as_ref(), &data),
+                        },
+                    };
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    self.buf.clear();
+                    return Err(e);
+                }
+            }
+            let recv_size = match self.stream.read(&mut buf) {
+                Ok(recv_size) => recv_size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if recv_size == 0 {
+                return Err(SlmpError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                )));
+            }
+            self.buf.extend_from_slice(&buf[..recv_size]);
+        }
+    }
+    /// 要求を送信し、応答を受信する
+    ///
+    /// # 引数
+    ///
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// バイナリコードでの応答と同じ形式の応答データ
+    pub fn request(&mut self, req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+        self.send_request(req)?;
+        self.recv_response(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_read_word_response, make_read_cmd_16, make_write_bit_cmd_16, SLMPDeviceData,
+    };
+    use std::io::Cursor;
+
+    /// 受信内容をあらかじめ用意したストリーム
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn connect(
+        frame: MCSerialFrame,
+        format: MCSerialFormat,
+        input: &[u8],
+    ) -> MCSerialConnectionInfo<MockStream> {
+        let stream = MockStream {
+            input: Cursor::new(input.to_vec()),
+            output: Vec::new(),
+        };
+        MCSerialConnectionInfo::new(stream, frame, format).unwrap()
+    }
+
+    fn d100() -> SLMPDevice {
+        SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        }
+    }
+
+    #[test]
+    fn test_4c_format1_read_words() {
+        let mut conn = connect(
+            MCSerialFrame::Frame4C,
+            MCSerialFormat::Format1,
+            b"\x02F80000FF03FF000012340002\x0308",
+        );
+        let req = make_read_cmd_16(d100(), 2, false);
+        let buf = conn.request(&req).unwrap();
+        let ret = decode_read_word_response(&buf, d100()).unwrap();
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+        assert_eq!(
+            conn.get_ref().output,
+            b"\x05F80000FF03FF000004010000D*00010000024F".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_4c_format4_nak() {
+        let mut conn = connect(
+            MCSerialFrame::Frame4C,
+            MCSerialFormat::Format4,
+            b"\x15F80000FF03FF00007155\r\n",
+        );
+        let req = make_read_cmd_16(d100(), 2, false);
+        match conn.request(&req) {
+            Err(SlmpError::EndCode { code, .. }) => assert_eq!(code, 0x7155),
+            _ => panic!("end code error expected"),
+        }
+        assert!(conn.get_ref().output.ends_with(b"\r\n"));
+    }
+
+    #[test]
+    fn test_4c_format5_dle() {
+        // データ長、ヘッダ、応答ID、終了コード、データ0x1010
+        let raw = [
+            0x0e, 0x00, 0xf8, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00,
+            0x10, 0x10,
+        ];
+        let mut input = vec![DLE, STX];
+        for b in raw.iter() {
+            if *b == DLE {
+                input.push(DLE);
+            }
+            input.push(*b);
+        }
+        input.extend_from_slice(&[DLE, ETX]);
+        push_hex(&mut input, sum_check(&raw) as u32, 2);
+        let mut conn = connect(MCSerialFrame::Frame4C, MCSerialFormat::Format5, &input);
+        let req = make_read_cmd_16(d100(), 1, false);
+        assert_eq!(conn.request(&req).unwrap(), vec![0x10, 0x10]);
+        let output = &conn.get_ref().output;
+        // データ長0x0012
+        assert_eq!(&output[..4], &[DLE, STX, 0x12, 0x00]);
+        assert_eq!(&output[output.len() - 4..output.len() - 2], &[DLE, ETX]);
+    }
+
+    #[test]
+    fn test_2c_format1_read_words() {
+        let mut conn = connect(
+            MCSerialFrame::Frame2C,
+            MCSerialFormat::Format1,
+            b"\x02FB000012340002\x03D7",
+        );
+        let req = make_read_cmd_16(d100(), 2, false);
+        let buf = conn.request(&req).unwrap();
+        let ret = decode_read_word_response(&buf, d100()).unwrap();
+        assert_eq!(ret[0].value, 0x1234);
+        assert_eq!(ret[1].value, 0x0002);
+        assert_eq!(
+            conn.get_ref().output,
+            b"\x05FB000004010000D*00010000021E".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_1c_format3_write_bits() {
+        let mut conn = connect(
+            MCSerialFrame::Frame1C,
+            MCSerialFormat::Format3,
+            b"\x0200FFGG\x037D",
+        );
+        let targets: Vec<SLMPDeviceData<bool>> = [true, false, true]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| SLMPDeviceData {
+                dev: SLMPDevice {
+                    d_code: SLMPDeviceCode::M,
+                    addr: 10 + i as u32,
+                },
+                value: *value,
+            })
+            .collect();
+        let req = make_write_bit_cmd_16(&targets).unwrap();
+        assert!(conn.request(&req).unwrap().is_empty());
+        assert!(conn
+            .get_ref()
+            .output
+            .starts_with(b"\x0200FFBW0M001003101\x03"));
+    }
+
+    #[test]
+    fn test_format5_requires_4c() {
+        let stream = MockStream {
+            input: Cursor::new(Vec::new()),
+            output: Vec::new(),
+        };
+        assert!(MCSerialConnectionInfo::new(
+            stream,
+            MCSerialFrame::Frame3C,
+            MCSerialFormat::Format5
+        )
+        .is_err());
+    }
+}
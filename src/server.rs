@@ -0,0 +1,988 @@
+use crate::event_history::push_event;
+use crate::{
+    pack_bits_by_bit, unpack_bits_by_bit, SLMPCommand, SLMPDevice, SLMPDeviceCode, SLMPEndCode,
+    SLMPFrameType, SlmpError, SlmpEvent, SlmpEventType,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// 停止要求を確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// シミュレータのCPUユニットの動作状態
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpCpuState {
+    Run,
+    Stop,
+    Pause,
+}
+
+/// デバイスの値を保持するメモリ
+///
+/// 全てのデバイスは0で初期化されている。ビットデバイスをワード単位で読み書きした場合は、
+/// 指定したアドレスから16点をまとめて扱う
+#[derive(Clone, Debug, Default)]
+pub struct SlmpDeviceStore {
+    /// デバイスごとの値、ビットデバイスは1点ごとに0か1を持つ
+    devices: HashMap<SLMPDeviceCode, BTreeMap<u32, u16>>,
+}
+
+impl SlmpDeviceStore {
+    pub fn new() -> SlmpDeviceStore {
+        SlmpDeviceStore::default()
+    }
+    fn raw(&self, d_code: SLMPDeviceCode, addr: u32) -> u16 {
+        self.devices
+            .get(&d_code)
+            .and_then(|m| m.get(&addr))
+            .copied()
+            .unwrap_or(0)
+    }
+    fn set_raw(&mut self, d_code: SLMPDeviceCode, addr: u32, value: u16) {
+        let m = self.devices.entry(d_code).or_default();
+        if value == 0 {
+            m.remove(&addr);
+        } else {
+            m.insert(addr, value);
+        }
+    }
+    /// 全てのデバイスを0にする
+    pub fn clear(&mut self) {
+        self.devices.clear()
+    }
+    pub fn get_bit(&self, dev: SLMPDevice) -> bool {
+        self.raw(dev.d_code, dev.addr) != 0
+    }
+    pub fn set_bit(&mut self, dev: SLMPDevice, value: bool) {
+        self.set_raw(dev.d_code, dev.addr, value as u16)
+    }
+    pub fn get_word(&self, dev: SLMPDevice) -> u16 {
+        if dev.d_code.is_bit() {
+            (0..16).fold(0, |w, i| {
+                w | ((self.raw(dev.d_code, dev.addr.wrapping_add(i)) & 1) << i)
+            })
+        } else {
+            self.raw(dev.d_code, dev.addr)
+        }
+    }
+    pub fn set_word(&mut self, dev: SLMPDevice, value: u16) {
+        if dev.d_code.is_bit() {
+            for i in 0..16 {
+                self.set_raw(dev.d_code, dev.addr.wrapping_add(i), (value >> i) & 1);
+            }
+        } else {
+            self.set_raw(dev.d_code, dev.addr, value)
+        }
+    }
+    /// ダブルワードの上位側のデバイス
+    fn upper(dev: SLMPDevice) -> SLMPDevice {
+        let step = if dev.d_code.is_bit() { 16 } else { 1 };
+        SLMPDevice {
+            d_code: dev.d_code,
+            addr: dev.addr.wrapping_add(step),
+        }
+    }
+    pub fn get_dword(&self, dev: SLMPDevice) -> u32 {
+        (self.get_word(dev) as u32) + ((self.get_word(Self::upper(dev)) as u32) << 16)
+    }
+    pub fn set_dword(&mut self, dev: SLMPDevice, value: u32) {
+        self.set_word(dev, value as u16);
+        self.set_word(Self::upper(dev), (value >> 16) as u16);
+    }
+    /// 連続したデバイスを読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `top` - 先頭デバイス
+    /// * `count` - 点数
+    pub fn read_words(&self, top: SLMPDevice, count: usize) -> Vec<u16> {
+        let step = if top.d_code.is_bit() { 16 } else { 1 };
+        (0..count as u32)
+            .map(|i| {
+                self.get_word(SLMPDevice {
+                    d_code: top.d_code,
+                    addr: top.addr.wrapping_add(i * step),
+                })
+            })
+            .collect()
+    }
+    /// 連続したデバイスへ書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `top` - 先頭デバイス
+    /// * `values` - 書き込む値
+    pub fn write_words(&mut self, top: SLMPDevice, values: &[u16]) {
+        let step = if top.d_code.is_bit() { 16 } else { 1 };
+        for (i, v) in values.iter().enumerate() {
+            let dev = SLMPDevice {
+                d_code: top.d_code,
+                addr: top.addr.wrapping_add(i as u32 * step),
+            };
+            self.set_word(dev, *v);
+        }
+    }
+    pub fn read_bits(&self, top: SLMPDevice, count: usize) -> Vec<bool> {
+        (0..count as u32)
+            .map(|i| self.raw(top.d_code, top.addr.wrapping_add(i)) != 0)
+            .collect()
+    }
+    pub fn write_bits(&mut self, top: SLMPDevice, values: &[bool]) {
+        for (i, v) in values.iter().enumerate() {
+            self.set_raw(top.d_code, top.addr.wrapping_add(i as u32), *v as u16);
+        }
+    }
+}
+
+/// リモートパスワードでロックされている時の終了コード
+const REMOTE_PASSWORD_LOCKED: u16 = 0xC201;
+/// リモートパスワードが一致しない時の終了コード
+const REMOTE_PASSWORD_MISMATCH: u16 = 0xC810;
+
+/// シミュレータの状態
+#[derive(Clone, Debug)]
+pub struct SlmpServerState {
+    /// デバイスメモリ
+    pub devices: SlmpDeviceStore,
+    /// CPUユニットの動作状態
+    pub cpu_state: SlmpCpuState,
+    /// 形名
+    pub type_name: String,
+    /// 形名コード
+    pub type_code: u16,
+    /// ドライブ番号とパス名ごとのファイルの内容
+    pub files: BTreeMap<(u16, String), Vec<u8>>,
+    /// リモートパスワード、設定すると接続ごとに解除するまで他のコマンドを受け付けない
+    pub remote_password: Option<String>,
+    /// 新しい順のイベント履歴
+    pub events: Vec<SlmpEvent>,
+    /// 書込みを受け付けるファイルの最大サイズ、超える書込みは失敗する
+    pub file_size_limit: Option<usize>,
+    /// ドライブごとのファイルの合計サイズの上限、超えるファイルの作成とコピーは失敗する
+    pub drive_capacity: Option<usize>,
+    /// 応答せずに破棄する要求の数、通信異常の試験に使う
+    pub drop_requests: usize,
+    /// 応答を遅らせる要求の数、通信異常の試験に使う
+    pub delay_requests: usize,
+    /// 応答を遅らせる時間
+    pub response_delay: Duration,
+}
+
+impl Default for SlmpServerState {
+    fn default() -> Self {
+        SlmpServerState {
+            devices: SlmpDeviceStore::new(),
+            cpu_state: SlmpCpuState::Run,
+            type_name: String::from("R04CPU"),
+            type_code: 0x4800,
+            files: BTreeMap::new(),
+            remote_password: None,
+            events: Vec::new(),
+            file_size_limit: None,
+            drive_capacity: None,
+            drop_requests: 0,
+            delay_requests: 0,
+            response_delay: Duration::ZERO,
+        }
+    }
+}
+
+/// 要求データの読み取り
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader { buf, pos: 0 }
+    }
+    fn bytes(&mut self, size: usize) -> Result<&'a [u8], u16> {
+        if self.buf.len() < self.pos + size {
+            return Err(SLMPEndCode::WrongLength as u16);
+        }
+        let ret = &self.buf[self.pos..self.pos + size];
+        self.pos += size;
+        Ok(ret)
+    }
+    fn u8(&mut self) -> Result<u8, u16> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, u16> {
+        let b = self.bytes(2)?;
+        Ok((b[0] as u16) + ((b[1] as u16) << 8))
+    }
+    fn u32(&mut self) -> Result<u32, u16> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    /// デバイス指定を読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `addr_32bit` - 32bitアドレス版か
+    fn device(&mut self, addr_32bit: bool) -> Result<SLMPDevice, u16> {
+        let (addr, code) = if addr_32bit {
+            (self.u32()?, self.u16()?)
+        } else {
+            let b = self.bytes(4)?;
+            let addr = (b[0] as u32) + ((b[1] as u32) << 8) + ((b[2] as u32) << 16);
+            (addr, b[3] as u16)
+        };
+        let d_code = SLMPDeviceCode::get(code).ok_or(SLMPEndCode::WrongFormat as u16)?;
+        Ok(SLMPDevice { d_code, addr })
+    }
+    /// ファイルパスワードを読み飛ばす
+    fn password(&mut self) -> Result<(), u16> {
+        let length = self.u16()? as usize;
+        self.bytes(length)?;
+        Ok(())
+    }
+    /// UTF-16のパス名を読み取る
+    fn path(&mut self) -> Result<String, u16> {
+        let length = self.u16()? as usize;
+        let chars: Vec<u16> = (0..length).map(|_| self.u16()).collect::<Result<_, _>>()?;
+        String::from_utf16(&chars).map_err(|_| SLMPEndCode::WrongFormat as u16)
+    }
+    /// リモートパスワードを読み取る、Qシリーズ形式は4バイト固定で長さを持たない
+    fn remote_password(&mut self) -> Result<&'a [u8], u16> {
+        if self.buf.len() - self.pos == 4 {
+            return self.bytes(4);
+        }
+        let length = self.u16()? as usize;
+        self.bytes(length)
+    }
+    fn rest(&mut self) -> &'a [u8] {
+        let ret = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        ret
+    }
+    /// 要求データを全て読み取ったことを確認する
+    fn finish(&self) -> Result<(), u16> {
+        if self.pos != self.buf.len() {
+            return Err(SLMPEndCode::WrongLength as u16);
+        }
+        Ok(())
+    }
+}
+
+/// 接続ごとの状態
+#[derive(Default)]
+struct Session {
+    /// モニタ登録されたサブコマンドと要求データ
+    monitor: Option<(u16, Vec<u8>)>,
+    /// ファイルポインタごとの開いているファイル
+    files: HashMap<u16, (u16, String)>,
+    /// 次に割り当てるファイルポインタ
+    next_pointer: u16,
+    /// リモートパスワードのロックを解除済みか
+    unlocked: bool,
+}
+
+impl SlmpServerState {
+    /// ドライブに指定したサイズのファイルを追加できるか
+    fn has_space(&self, drive: u16, size: usize) -> bool {
+        let used: usize = self
+            .files
+            .iter()
+            .filter(|((d, _), _)| *d == drive)
+            .map(|(_, data)| data.len())
+            .sum();
+        !matches!(self.drive_capacity, Some(c) if c < used + size)
+    }
+    /// ランダム読出し、モニタの応答を作成
+    fn read_random(&self, sub_command: u16, data: &[u8]) -> Result<Vec<u8>, u16> {
+        let addr_32bit = sub_command & 0x0002 != 0;
+        let mut r = Reader::new(data);
+        let word_count = r.u8()?;
+        let dword_count = r.u8()?;
+        let mut out = Vec::new();
+        for _ in 0..word_count {
+            let dev = r.device(addr_32bit)?;
+            out.extend_from_slice(&self.devices.get_word(dev).to_le_bytes());
+        }
+        for _ in 0..dword_count {
+            let dev = r.device(addr_32bit)?;
+            out.extend_from_slice(&self.devices.get_dword(dev).to_le_bytes());
+        }
+        r.finish()?;
+        Ok(out)
+    }
+    /// 要求を処理する
+    ///
+    /// # 返値
+    ///
+    /// 応答データ、もしくは異常時の終了コード
+    fn handle(
+        &mut self,
+        session: &mut Session,
+        command: u16,
+        sub_command: u16,
+        data: &[u8],
+    ) -> Result<Vec<u8>, u16> {
+        use SLMPCommand::*;
+        let is_bit = sub_command & 0x0001 != 0;
+        let addr_32bit = sub_command & 0x0002 != 0;
+        let mut r = Reader::new(data);
+        let mut out = Vec::new();
+        if self.remote_password.is_some()
+            && !session.unlocked
+            && command != RemotePasswordUnlock as u16
+        {
+            return Err(REMOTE_PASSWORD_LOCKED);
+        }
+        match command {
+            c if c == DeviceRead as u16 => {
+                let dev = r.device(addr_32bit)?;
+                let count = r.u16()? as usize;
+                r.finish()?;
+                if is_bit {
+                    out = pack_bits_by_bit(&self.devices.read_bits(dev, count));
+                } else {
+                    for w in self.devices.read_words(dev, count) {
+                        out.extend_from_slice(&w.to_le_bytes());
+                    }
+                }
+            }
+            c if c == DeviceWrite as u16 => {
+                let dev = r.device(addr_32bit)?;
+                let count = r.u16()? as usize;
+                if is_bit {
+                    let bits = unpack_bits_by_bit(r.bytes(count.div_ceil(2))?)
+                        .map_err(|_| SLMPEndCode::WrongFormat as u16)?;
+                    r.finish()?;
+                    self.devices.write_bits(dev, &bits[..count]);
+                } else {
+                    let words: Vec<u16> = (0..count).map(|_| r.u16()).collect::<Result<_, _>>()?;
+                    r.finish()?;
+                    self.devices.write_words(dev, &words);
+                }
+            }
+            c if c == DeviceReadRandom as u16 => {
+                out = self.read_random(sub_command, data)?;
+            }
+            c if c == DeviceWriteRandom as u16 => {
+                if is_bit {
+                    let count = r.u8()?;
+                    for _ in 0..count {
+                        let dev = r.device(addr_32bit)?;
+                        let value = if addr_32bit { r.u16()? } else { r.u8()? as u16 };
+                        self.devices.set_bit(dev, value != 0);
+                    }
+                } else {
+                    let word_count = r.u8()?;
+                    let dword_count = r.u8()?;
+                    for _ in 0..word_count {
+                        let dev = r.device(addr_32bit)?;
+                        let value = r.u16()?;
+                        self.devices.set_word(dev, value);
+                    }
+                    for _ in 0..dword_count {
+                        let dev = r.device(addr_32bit)?;
+                        let value = r.u32()?;
+                        self.devices.set_dword(dev, value);
+                    }
+                }
+                r.finish()?;
+            }
+            c if c == ReadBlock as u16 => {
+                let blocks = r.u8()? as usize + r.u8()? as usize;
+                for _ in 0..blocks {
+                    let dev = r.device(addr_32bit)?;
+                    let count = r.u16()? as usize;
+                    for w in self.devices.read_words(dev, count) {
+                        out.extend_from_slice(&w.to_le_bytes());
+                    }
+                }
+                r.finish()?;
+            }
+            c if c == WriteBlock as u16 => {
+                let blocks = r.u8()? as usize + r.u8()? as usize;
+                for _ in 0..blocks {
+                    let dev = r.device(addr_32bit)?;
+                    let count = r.u16()? as usize;
+                    let words: Vec<u16> = (0..count).map(|_| r.u16()).collect::<Result<_, _>>()?;
+                    self.devices.write_words(dev, &words);
+                }
+                r.finish()?;
+            }
+            c if c == EntryMonitorDevice as u16 => {
+                // 登録内容の確認のため一度読み出す
+                self.read_random(sub_command, data)?;
+                session.monitor = Some((sub_command, data.to_vec()));
+            }
+            c if c == ExecuteMonitor as u16 => {
+                r.finish()?;
+                let (sub, entry) = session
+                    .monitor
+                    .as_ref()
+                    .ok_or(SLMPEndCode::WrongFormat as u16)?;
+                out = self.read_random(*sub, entry)?;
+            }
+            c if c == SelfTest as u16 => {
+                let length = r.u16()? as usize;
+                let echo = r.bytes(length)?;
+                r.finish()?;
+                out.extend_from_slice(&(length as u16).to_le_bytes());
+                out.extend_from_slice(echo);
+            }
+            c if c == ReadTypeName as u16 => {
+                r.finish()?;
+                let mut name = self.type_name.clone().into_bytes();
+                name.resize(16, b' ');
+                out.extend_from_slice(&name[..16]);
+                out.extend_from_slice(&self.type_code.to_le_bytes());
+            }
+            c if c == RemoteRun as u16 => {
+                r.bytes(4)?;
+                r.finish()?;
+                self.cpu_state = SlmpCpuState::Run;
+            }
+            c if c == RemoteStop as u16 => {
+                r.bytes(2)?;
+                r.finish()?;
+                self.cpu_state = SlmpCpuState::Stop;
+            }
+            c if c == RemotePause as u16 => {
+                r.bytes(2)?;
+                r.finish()?;
+                self.cpu_state = SlmpCpuState::Pause;
+            }
+            c if c == RemoteLatchClear as u16 => {
+                r.bytes(2)?;
+                r.finish()?;
+                // シミュレータではラッチ範囲を区別せず全てのデバイスを0にする
+                self.devices.clear();
+            }
+            c if c == RemoteReset as u16 => {
+                r.rest();
+                self.cpu_state = SlmpCpuState::Stop;
+                self.devices.clear();
+            }
+            c if c == GetEventNum as u16 => {
+                r.finish()?;
+                out.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+            }
+            c if c == GetEventHistory as u16 => {
+                let head = r.u32()? as usize;
+                let count = r.u16()? as usize;
+                r.finish()?;
+                let events: Vec<&SlmpEvent> = self.events.iter().skip(head).take(count).collect();
+                out.extend_from_slice(&(events.len() as u16).to_le_bytes());
+                for event in events {
+                    push_event(&mut out, event);
+                }
+            }
+            c if c == ClearEventHistory as u16 => {
+                r.finish()?;
+                self.events.clear();
+            }
+            c if c == ClearErrorHistory as u16 => {
+                r.finish()?;
+                self.events.retain(|e| e.event_type != SlmpEventType::Error);
+            }
+            c if c == RemotePasswordUnlock as u16 || c == RemotePasswordLock as u16 => {
+                let password = r.remote_password()?;
+                r.finish()?;
+                if let Some(expected) = &self.remote_password {
+                    if password != expected.as_bytes() {
+                        return Err(REMOTE_PASSWORD_MISMATCH);
+                    }
+                }
+                session.unlocked = c == RemotePasswordUnlock as u16;
+            }
+            c if c == NewFileB as u16 => {
+                r.password()?;
+                let drive = r.u16()?;
+                let size = r.u32()? as usize;
+                let path = r.path()?;
+                r.finish()?;
+                if self.files.contains_key(&(drive, path.clone())) || !self.has_space(drive, size) {
+                    return Err(SLMPEndCode::WrongFormat as u16);
+                }
+                self.files.insert((drive, path), vec![0; size]);
+            }
+            c if c == DeleteFile as u16 => {
+                r.password()?;
+                let drive = r.u16()?;
+                let path = r.path()?;
+                r.finish()?;
+                self.files
+                    .remove(&(drive, path))
+                    .ok_or(SLMPEndCode::WrongFormat as u16)?;
+            }
+            c if c == CopyFile as u16 => {
+                r.password()?;
+                let dst = (r.u16()?, r.path()?);
+                r.password()?;
+                let src = (r.u16()?, r.path()?);
+                r.finish()?;
+                if self.files.contains_key(&dst) {
+                    return Err(SLMPEndCode::WrongFormat as u16);
+                }
+                let data = self
+                    .files
+                    .get(&src)
+                    .ok_or(SLMPEndCode::WrongFormat as u16)?
+                    .clone();
+                if !self.has_space(dst.0, data.len()) {
+                    return Err(SLMPEndCode::WrongFormat as u16);
+                }
+                self.files.insert(dst, data);
+            }
+            c if c == OpenFile as u16 => {
+                r.password()?;
+                r.u16()?;
+                let drive = r.u16()?;
+                let path = r.path()?;
+                r.finish()?;
+                if !self.files.contains_key(&(drive, path.clone())) {
+                    return Err(SLMPEndCode::WrongFormat as u16);
+                }
+                let pointer = session.next_pointer;
+                session.next_pointer = session.next_pointer.wrapping_add(1);
+                session.files.insert(pointer, (drive, path));
+                out.extend_from_slice(&pointer.to_le_bytes());
+            }
+            c if c == ReadFile as u16 || c == WriteFile as u16 => {
+                let pointer = r.u16()?;
+                let offset = r.u32()? as usize;
+                let size = r.u16()? as usize;
+                let key = session
+                    .files
+                    .get(&pointer)
+                    .ok_or(SLMPEndCode::WrongFormat as u16)?;
+                let file = self
+                    .files
+                    .get_mut(key)
+                    .ok_or(SLMPEndCode::WrongFormat as u16)?;
+                if c == ReadFile as u16 {
+                    r.finish()?;
+                    let start = offset.min(file.len());
+                    let end = (offset + size).min(file.len());
+                    out.extend_from_slice(&((end - start) as u16).to_le_bytes());
+                    out.extend_from_slice(&file[start..end]);
+                } else {
+                    let data = r.bytes(size)?;
+                    r.finish()?;
+                    if matches!(self.file_size_limit, Some(l) if l < offset + size) {
+                        return Err(SLMPEndCode::WrongFormat as u16);
+                    }
+                    if file.len() < offset + size {
+                        file.resize(offset + size, 0);
+                    }
+                    file[offset..offset + size].copy_from_slice(data);
+                    out.extend_from_slice(&(size as u16).to_le_bytes());
+                }
+            }
+            c if c == CloseFile as u16 => {
+                let pointer = r.u16()?;
+                let close_type = r.u16()?;
+                r.finish()?;
+                if close_type == 0 {
+                    session
+                        .files
+                        .remove(&pointer)
+                        .ok_or(SLMPEndCode::WrongFormat as u16)?;
+                } else {
+                    session.files.clear();
+                }
+            }
+            c if c == ReadDir as u16 => {
+                let drive = r.u16()?;
+                let head = r.u32()? as usize;
+                let count = r.u16()? as usize;
+                let dir = r.path()?;
+                r.finish()?;
+                // シミュレータではディレクトリを区別せず、パス名が一致するファイルを返す
+                let prefix = match dir.is_empty() {
+                    true => String::new(),
+                    false => format!("{}/", dir.trim_end_matches('/')),
+                };
+                let entries = self
+                    .files
+                    .iter()
+                    .filter(|((d, path), _)| *d == drive && path.starts_with(&prefix))
+                    .map(|((_, path), data)| (&path[prefix.len()..], data.len()))
+                    .filter(|(name, _)| !name.contains('/'))
+                    .skip(head)
+                    .take(count);
+                for (name, size) in entries {
+                    let chars: Vec<u16> = name.encode_utf16().collect();
+                    out.extend_from_slice(&(chars.len() as u16).to_le_bytes());
+                    for c in chars {
+                        out.extend_from_slice(&c.to_le_bytes());
+                    }
+                    // 属性なし、更新日時は1980年1月1日
+                    out.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x21, 0x00]);
+                    out.extend_from_slice(&(size as u32).to_le_bytes());
+                }
+            }
+            c if c == ClearErrorCode as u16 => {
+                r.finish()?;
+            }
+            _ => return Err(SLMPEndCode::WrongCommand as u16),
+        }
+        Ok(out)
+    }
+}
+
+/// 受信した要求
+struct RequestFrame {
+    frame_type: SLMPFrameType,
+    serial: u16,
+    /// ネットワーク番号から要求先マルチドロップ局番まで
+    route: [u8; 5],
+    command: u16,
+    sub_command: u16,
+    data: Vec<u8>,
+}
+
+/// バッファの先頭から要求を一つ取り出す
+///
+/// # 返値
+///
+/// 使用したバイト数と要求、データが不足している場合はNone
+fn parse_request(buf: &[u8]) -> Result<Option<(usize, RequestFrame)>, SlmpError> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let (frame_type, head) = match (buf[0], buf[1]) {
+        (0x54, 0x00) => (SLMPFrameType::Frame4E, 6),
+        (0x50, 0x00) => (SLMPFrameType::Frame3E, 2),
+        _ => return Err(SlmpError::MalformedFrame("unknown sub header")),
+    };
+    if buf.len() < head + 7 {
+        return Ok(None);
+    }
+    let dl = (buf[head + 5] as usize) + ((buf[head + 6] as usize) << 8);
+    if dl < 6 {
+        return Err(SlmpError::MalformedFrame("too short request"));
+    }
+    let size = head + 7 + dl;
+    if buf.len() < size {
+        return Ok(None);
+    }
+    let serial = match frame_type {
+        SLMPFrameType::Frame4E => (buf[2] as u16) + ((buf[3] as u16) << 8),
+        SLMPFrameType::Frame3E => 0,
+    };
+    let mut route = [0u8; 5];
+    route.copy_from_slice(&buf[head..head + 5]);
+    let body = &buf[head + 9..size];
+    Ok(Some((
+        size,
+        RequestFrame {
+            frame_type,
+            serial,
+            route,
+            command: (body[0] as u16) + ((body[1] as u16) << 8),
+            sub_command: (body[2] as u16) + ((body[3] as u16) << 8),
+            data: body[4..].to_vec(),
+        },
+    )))
+}
+
+/// 要求を処理して応答の通信文を作成
+///
+/// # 返値
+///
+/// 応答の通信文、要求を破棄する場合は`None`
+fn respond(
+    state: &Mutex<SlmpServerState>,
+    session: &mut Session,
+    req: &RequestFrame,
+) -> Option<Vec<u8>> {
+    let (result, delay) = {
+        let mut state = state.lock().unwrap();
+        if state.drop_requests > 0 {
+            state.drop_requests -= 1;
+            return None;
+        }
+        let delay = if state.delay_requests > 0 {
+            state.delay_requests -= 1;
+            state.response_delay
+        } else {
+            Duration::ZERO
+        };
+        (
+            state.handle(session, req.command, req.sub_command, &req.data),
+            delay,
+        )
+    };
+    thread::sleep(delay);
+    let mut buf = match req.frame_type {
+        SLMPFrameType::Frame4E => {
+            let mut buf = vec![0xd4, 0x00];
+            buf.extend_from_slice(&req.serial.to_le_bytes());
+            buf.extend_from_slice(&[0, 0]);
+            buf
+        }
+        SLMPFrameType::Frame3E => vec![0xd0, 0x00],
+    };
+    buf.extend_from_slice(&req.route);
+    let body = match result {
+        Ok(data) => {
+            let mut body = vec![0, 0];
+            body.extend_from_slice(&data);
+            body
+        }
+        Err(code) => {
+            // 終了コードとエラー情報
+            let mut body = code.to_le_bytes().to_vec();
+            body.extend_from_slice(&req.route);
+            body.extend_from_slice(&req.command.to_le_bytes());
+            body.extend_from_slice(&req.sub_command.to_le_bytes());
+            body
+        }
+    };
+    buf.extend_from_slice(&(body.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&body);
+    Some(buf)
+}
+
+/// 結合試験用のSLMPサーバ
+///
+/// バイナリコードの4Eフレームと3Eフレームの要求に、メモリ上のデバイスで応答する。
+/// 破棄すると停止する。`simulator`フィーチャを有効にした場合のみ使える
+pub struct SlmpServer {
+    /// 待ち受けているアドレス
+    local_addr: SocketAddr,
+    /// シミュレータの状態
+    state: Arc<Mutex<SlmpServerState>>,
+    /// 動作中か
+    running: Arc<AtomicBool>,
+}
+
+impl SlmpServer {
+    /// TCPで待ち受けを開始する
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 待ち受けるアドレス、ポート番号0で空いているポートを使用する
+    pub fn start_tcp(addr: SocketAddr) -> Result<SlmpServer, SlmpError> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let server = SlmpServer::new(listener.local_addr()?);
+        let state = server.state.clone();
+        let running = server.running.clone();
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let state = state.clone();
+                        let running = running.clone();
+                        thread::spawn(move || serve_tcp(stream, state, running));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(server)
+    }
+    /// UDPで待ち受けを開始する
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 待ち受けるアドレス、ポート番号0で空いているポートを使用する
+    pub fn start_udp(addr: SocketAddr) -> Result<SlmpServer, SlmpError> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let server = SlmpServer::new(socket.local_addr()?);
+        let state = server.state.clone();
+        let running = server.running.clone();
+        thread::spawn(move || {
+            let mut sessions: HashMap<SocketAddr, Session> = HashMap::new();
+            let mut buf = [0u8; 8194];
+            while running.load(Ordering::Relaxed) {
+                let (size, from) = match socket.recv_from(&mut buf) {
+                    Ok(r) => r,
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        continue
+                    }
+                    Err(_) => break,
+                };
+                // 不正な要求は応答せずに破棄する
+                if let Ok(Some((_, req))) = parse_request(&buf[..size]) {
+                    let session = sessions.entry(from).or_default();
+                    if let Some(res) = respond(&state, session, &req) {
+                        let _ = socket.send_to(&res, from);
+                    }
+                }
+            }
+        });
+        Ok(server)
+    }
+    fn new(local_addr: SocketAddr) -> SlmpServer {
+        SlmpServer {
+            local_addr,
+            state: Arc::new(Mutex::new(SlmpServerState::default())),
+            running: Arc::new(AtomicBool::new(true)),
+        }
+    }
+    /// 待ち受けているアドレス
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+    /// シミュレータの状態を参照、変更する
+    pub fn state(&self) -> MutexGuard<'_, SlmpServerState> {
+        self.state.lock().unwrap()
+    }
+    /// 待ち受けを停止する
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for SlmpServer {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
+/// TCP接続一つ分の要求を処理する
+fn serve_tcp(mut stream: TcpStream, state: Arc<Mutex<SlmpServerState>>, running: Arc<AtomicBool>) {
+    if stream.set_nonblocking(false).is_err()
+        || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err()
+    {
+        return;
+    }
+    let mut session = Session::default();
+    let mut pending = Vec::new();
+    let mut buf = [0u8; 8194];
+    while running.load(Ordering::Relaxed) {
+        match stream.read(&mut buf) {
+            Ok(0) => return,
+            Ok(size) => pending.extend_from_slice(&buf[..size]),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(_) => return,
+        }
+        loop {
+            match parse_request(&pending) {
+                Ok(Some((size, req))) => {
+                    pending.drain(..size);
+                    if let Some(res) = respond(&state, &mut session, &req) {
+                        if stream.write_all(&res).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // 区切りが分からないため、受信済みのデータは破棄する
+                    pending.clear();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SLMPConnectionInfo, SLMPDeviceData, SlmpClient};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn localhost() -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)
+    }
+
+    fn dev(d_code: SLMPDeviceCode, addr: u32) -> SLMPDevice {
+        SLMPDevice { d_code, addr }
+    }
+
+    #[test]
+    fn test_device_store() {
+        let mut store = SlmpDeviceStore::new();
+        store.set_word(dev(SLMPDeviceCode::M, 0), 0x8001);
+        assert!(store.get_bit(dev(SLMPDeviceCode::M, 0)));
+        assert!(store.get_bit(dev(SLMPDeviceCode::M, 15)));
+        assert!(!store.get_bit(dev(SLMPDeviceCode::M, 1)));
+        store.set_dword(dev(SLMPDeviceCode::D, 10), 0x12345678);
+        assert_eq!(store.get_word(dev(SLMPDeviceCode::D, 11)), 0x1234);
+        assert_eq!(store.get_dword(dev(SLMPDeviceCode::D, 10)), 0x12345678);
+    }
+
+    #[test]
+    fn test_tcp_server() {
+        let server = SlmpServer::start_tcp(localhost()).unwrap();
+        server
+            .state()
+            .devices
+            .write_words(dev(SLMPDeviceCode::D, 100), &[1, 2, 3]);
+        let mut client = SlmpClient::new(SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap());
+        client.set_timeout(4);
+        let ret = client.read_words(dev(SLMPDeviceCode::D, 100), 3).unwrap();
+        assert_eq!(ret.iter().map(|d| d.value).collect::<Vec<_>>(), [1, 2, 3]);
+        let bits: Vec<_> = [true, false, true]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| SLMPDeviceData {
+                dev: dev(SLMPDeviceCode::Y, 0x20 + i as u32),
+                value: *value,
+            })
+            .collect();
+        client.write_bits(&bits).unwrap();
+        let ret = client.read_bits(dev(SLMPDeviceCode::Y, 0x20), 3).unwrap();
+        assert_eq!(
+            ret.iter().map(|d| d.value).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+        let (name, code) = client.read_type_name().unwrap();
+        assert_eq!(name.trim_end(), "R04CPU");
+        assert_eq!(code, 0x4800);
+        client.remote_stop().unwrap();
+        assert_eq!(server.state().cpu_state, SlmpCpuState::Stop);
+    }
+
+    #[test]
+    fn test_udp_server_3e() {
+        let server = SlmpServer::start_udp(localhost()).unwrap();
+        let mut connection_info = SLMPConnectionInfo::new_udp(server.local_addr()).unwrap();
+        connection_info.set_frame_type(SLMPFrameType::Frame3E);
+        let mut client = SlmpClient::new(connection_info);
+        client.set_timeout(4);
+        assert_eq!(client.self_test(b"ABC123").unwrap(), b"ABC123".to_vec());
+        let words = [SLMPDeviceData {
+            dev: dev(SLMPDeviceCode::W, 0x10),
+            value: 0xbeef,
+        }];
+        let dwords = [SLMPDeviceData {
+            dev: dev(SLMPDeviceCode::D, 0),
+            value: 0x0001_0002,
+        }];
+        client.write_random_words(&words, &dwords).unwrap();
+        let (w, dw) = client
+            .read_random(
+                &[dev(SLMPDeviceCode::W, 0x10)],
+                &[dev(SLMPDeviceCode::D, 0)],
+            )
+            .unwrap();
+        assert_eq!(w[0].value, 0xbeef);
+        assert_eq!(dw[0].value, 0x0001_0002);
+        assert_eq!(
+            server.state().devices.get_word(dev(SLMPDeviceCode::D, 1)),
+            0x0001
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{SLMPDataCode, SLMPDevice, SLMPDeviceBlock};
+use std::ops::Range;
+
+/// 一括読出し、一括書込みで一度に扱えるワード点数
+pub const MAX_BATCH_WORDS: usize = 960;
+/// 一括読出し、一括書込みで一度に扱えるビット点数(バイナリコード)
+pub const MAX_BATCH_BITS: usize = 7168;
+/// 一括読出し、一括書込みで一度に扱えるビット点数(ASCIIコード)
+pub const MAX_BATCH_BITS_ASCII: usize = 3584;
+/// ランダム読出しで一度に扱えるワードとダブルワードの合計点数
+pub const MAX_RANDOM_READ_POINTS: usize = 192;
+/// ビット単位のランダム書込みで一度に扱える点数
+pub const MAX_RANDOM_WRITE_BITS: usize = 188;
+/// ワード単位のランダム書込みの上限、ワード点数×12+ダブルワード点数×14
+pub const MAX_RANDOM_WRITE_SIZE: usize = 1920;
+/// 複数ブロック一括読出し、書込みで一度に扱えるブロック数
+pub const MAX_BLOCKS: usize = 120;
+/// 複数ブロック一括読出し、書込みで一度に扱える合計ワード点数
+pub const MAX_BLOCK_POINTS: usize = 960;
+
+/// 一括読出し、一括書込みで一度に扱えるビット点数
+pub(crate) fn max_batch_bits(data_code: SLMPDataCode) -> usize {
+    match data_code {
+        SLMPDataCode::Binary => MAX_BATCH_BITS,
+        SLMPDataCode::Ascii => MAX_BATCH_BITS_ASCII,
+    }
+}
+
+/// 連続した範囲を一定の大きさごとに分割
+///
+/// # 引数
+///
+/// * `total` - 全体の点数
+/// * `size` - 一度に扱える点数
+pub(crate) fn chunk_ranges(total: usize, size: usize) -> Vec<Range<usize>> {
+    (0..total)
+        .step_by(size)
+        .map(|start| start..total.min(start + size))
+        .collect()
+}
+
+/// ワードとダブルワードのリストを要求ごとに分割
+///
+/// # 引数
+///
+/// * `word_count` - ワード点数
+/// * `dword_count` - ダブルワード点数
+/// * `word_cost` - ワード1点あたりの消費量
+/// * `dword_cost` - ダブルワード1点あたりの消費量
+/// * `budget` - 一つの要求で消費できる量
+///
+/// # 返値
+///
+/// 要求ごとのワードとダブルワードの範囲
+pub(crate) fn random_chunks(
+    word_count: usize,
+    dword_count: usize,
+    word_cost: usize,
+    dword_cost: usize,
+    budget: usize,
+) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut ret = Vec::new();
+    let (mut w, mut d) = (0, 0);
+    while w < word_count || d < dword_count {
+        let nw = (word_count - w).min(budget / word_cost);
+        let nd = (dword_count - d).min((budget - nw * word_cost) / dword_cost);
+        ret.push((w..w + nw, d..d + nd));
+        w += nw;
+        d += nd;
+    }
+    ret
+}
+
+/// 分割したデバイスブロックの一部
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BlockPiece {
+    /// ビットアクセスするブロックか
+    pub(crate) bit: bool,
+    /// 元のリストでの位置
+    pub(crate) index: usize,
+    /// ブロック先頭からのワード単位の位置
+    pub(crate) offset: usize,
+    /// ワード単位の点数
+    pub(crate) count: usize,
+}
+
+impl BlockPiece {
+    /// 分割した部分のデバイスブロック
+    ///
+    /// # 引数
+    ///
+    /// * `block` - 元のデバイスブロック
+    pub(crate) fn block(&self, block: &SLMPDeviceBlock) -> SLMPDeviceBlock {
+        SLMPDeviceBlock {
+            top_device: offset_device(block.top_device, self.offset),
+            count: self.count as u16,
+        }
+    }
+}
+
+/// ワード単位で指定した位置のデバイス、ビットデバイスは16点を1ワードとする
+///
+/// # 引数
+///
+/// * `top` - 先頭デバイス
+/// * `words` - ワード単位の位置
+pub(crate) fn offset_device(top: SLMPDevice, words: usize) -> SLMPDevice {
+    let step = if top.d_code.is_bit() { 16 } else { 1 };
+    SLMPDevice {
+        d_code: top.d_code,
+        addr: top.addr.wrapping_add((words * step) as u32),
+    }
+}
+
+/// デバイスブロックのリストを要求ごとに分割
+///
+/// 上限を超える大きさのブロックは複数に分ける
+///
+/// # 引数
+///
+/// * `word_blocks` - ワードアクセスするブロックの点数
+/// * `bit_blocks` - ビットアクセスするブロックの点数
+///
+/// # 返値
+///
+/// 要求ごとの分割したブロック、ワードアクセスするブロックが先になる
+pub(crate) fn block_pieces(word_blocks: &[usize], bit_blocks: &[usize]) -> Vec<Vec<BlockPiece>> {
+    let mut ret = Vec::new();
+    let mut current: Vec<BlockPiece> = Vec::new();
+    let mut points = 0;
+    let blocks = word_blocks
+        .iter()
+        .enumerate()
+        .map(|(index, count)| (false, index, *count))
+        .chain(
+            bit_blocks
+                .iter()
+                .enumerate()
+                .map(|(index, count)| (true, index, *count)),
+        );
+    for (bit, index, count) in blocks {
+        let mut offset = 0;
+        while offset < count {
+            if current.len() == MAX_BLOCKS || points == MAX_BLOCK_POINTS {
+                ret.push(std::mem::take(&mut current));
+                points = 0;
+            }
+            let n = (count - offset).min(MAX_BLOCK_POINTS - points);
+            current.push(BlockPiece {
+                bit,
+                index,
+                offset,
+                count: n,
+            });
+            points += n;
+            offset += n;
+        }
+    }
+    if !current.is_empty() {
+        ret.push(current);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunk_ranges(2000, 960), vec![0..960, 960..1920, 1920..2000]);
+        assert!(chunk_ranges(0, 960).is_empty());
+        assert_eq!(
+            random_chunks(100, 150, 1, 1, 192),
+            vec![(0..100, 0..92), (100..100, 92..150)]
+        );
+        assert_eq!(
+            random_chunks(200, 10, 12, 14, 1920),
+            vec![(0..160, 0..0), (160..200, 0..10)]
+        );
+    }
+
+    #[test]
+    fn test_block_pieces() {
+        let pieces = block_pieces(&[1000, 10], &[5]);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[0],
+            vec![BlockPiece {
+                bit: false,
+                index: 0,
+                offset: 0,
+                count: 960
+            }]
+        );
+        assert_eq!(pieces[1].len(), 3);
+        assert_eq!(pieces[1][0].offset, 960);
+        assert_eq!(pieces[1][0].count, 40);
+        assert!(pieces[1][2].bit);
+    }
+}
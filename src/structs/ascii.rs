@@ -0,0 +1,658 @@
+use super::{SLMPFrame, SLMPRequest, SlmpSubHeaderReq, SlmpSubHeaderRes};
+use crate::{SLMPCommand, SLMPDeviceCode, SLMPFrameType, SlmpError};
+use std::collections::VecDeque;
+
+/// 応答待ちとして記録しておく要求の最大数
+const MAX_PENDING: usize = 256;
+
+/// 数値を指定桁数の16進数文字列として追加
+pub(crate) fn push_hex(out: &mut Vec<u8>, value: u32, digits: usize) {
+    for i in (0..digits).rev() {
+        let n = ((value >> (i * 4)) & 0xf) as u8;
+        out.push(if n < 10 { b'0' + n } else { b'A' + n - 10 });
+    }
+}
+
+/// 数値を指定桁数の10進数文字列として追加
+pub(crate) fn push_dec(out: &mut Vec<u8>, value: u32, digits: usize) -> Result<(), SlmpError> {
+    let s = format!("{:0width$}", value, width = digits);
+    if s.len() > digits {
+        return Err(SlmpError::InvalidArgument("too large device number"));
+    }
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+/// 16進数文字列を数値へ変換
+pub(crate) fn parse_hex(buf: &[u8]) -> Result<u32, SlmpError> {
+    let mut value = 0u32;
+    for c in buf {
+        let n = match c {
+            b'0'..=b'9' => c - b'0',
+            b'A'..=b'F' => c - b'A' + 10,
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => return Err(SlmpError::MalformedFrame("not hexadecimal character")),
+        };
+        value = (value << 4) | n as u32;
+    }
+    Ok(value)
+}
+
+/// バイナリの要求データを先頭から読み進める
+struct BinReader<'a> {
+    buf: &'a [u8],
+}
+impl<'a> BinReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SlmpError> {
+        if self.buf.len() < n {
+            return Err(SlmpError::InvalidArgument("too short request data"));
+        }
+        let (ret, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(ret)
+    }
+    fn u8(&mut self) -> Result<u8, SlmpError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SlmpError> {
+        let b = self.take(2)?;
+        Ok((b[0] as u16) + ((b[1] as u16) << 8))
+    }
+    fn u32(&mut self) -> Result<u32, SlmpError> {
+        let b = self.take(4)?;
+        Ok((b[0] as u32) + ((b[1] as u32) << 8) + ((b[2] as u32) << 16) + ((b[3] as u32) << 24))
+    }
+}
+
+/// ASCIIの応答データを先頭から読み進める
+struct AsciiReader<'a> {
+    buf: &'a [u8],
+}
+impl<'a> AsciiReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SlmpError> {
+        if self.buf.len() < n {
+            return Err(SlmpError::MalformedFrame("too short response data"));
+        }
+        let (ret, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(ret)
+    }
+    fn hex(&mut self, digits: usize) -> Result<u32, SlmpError> {
+        parse_hex(self.take(digits)?)
+    }
+}
+
+/// デバイス指定をASCIIへ変換
+fn encode_device(r: &mut BinReader, out: &mut Vec<u8>, long: bool) -> Result<(), SlmpError> {
+    let (addr, code) = if long {
+        (r.u32()?, r.u16()?)
+    } else {
+        let b = r.take(4)?;
+        let addr = (b[0] as u32) + ((b[1] as u32) << 8) + ((b[2] as u32) << 16);
+        (addr, b[3] as u16)
+    };
+    let d_code =
+        SLMPDeviceCode::get(code).ok_or(SlmpError::InvalidArgument("unknown device code"))?;
+    let digits = if long {
+        out.extend_from_slice(d_code.ascii_name_long().as_bytes());
+        8
+    } else {
+        let name = d_code.ascii_name().ok_or(SlmpError::InvalidArgument(
+            "device not supported with 16bit address",
+        ))?;
+        out.extend_from_slice(name.as_bytes());
+        6
+    };
+    if d_code.is_hex_addr() {
+        push_hex(out, addr, digits);
+        Ok(())
+    } else {
+        push_dec(out, addr, digits)
+    }
+}
+
+/// ビット単位でパックされたビットデータをASCIIへ変換
+fn encode_bits(r: &mut BinReader, out: &mut Vec<u8>, count: usize) -> Result<(), SlmpError> {
+    let packed = r.take(count.div_ceil(2))?;
+    for i in 0..count {
+        let n = if i % 2 == 0 {
+            packed[i / 2] >> 4
+        } else {
+            packed[i / 2] & 0x0f
+        };
+        push_hex(out, n as u32, 1);
+    }
+    Ok(())
+}
+
+/// 要求データをバイナリからASCIIへ変換
+///
+/// # 引数
+///
+/// * `req` - 要求内容
+///
+/// # 返値
+///
+/// ASCIIでの要求データ
+pub(crate) fn encode_request(req: &SLMPRequest) -> Result<Vec<u8>, SlmpError> {
+    use SLMPCommand::*;
+    let long = req.sub_command & 0x0002 != 0;
+    let is_bit = req.sub_command & 0x0001 != 0;
+    let mut r = BinReader { buf: &req.data };
+    let mut out = Vec::new();
+    match req.command {
+        DeviceRead => {
+            encode_device(&mut r, &mut out, long)?;
+            push_hex(&mut out, r.u16()? as u32, 4);
+        }
+        DeviceWrite => {
+            encode_device(&mut r, &mut out, long)?;
+            let count = r.u16()?;
+            push_hex(&mut out, count as u32, 4);
+            if is_bit {
+                encode_bits(&mut r, &mut out, count as usize)?;
+            } else {
+                for _ in 0..count {
+                    push_hex(&mut out, r.u16()? as u32, 4);
+                }
+            }
+        }
+        DeviceReadRandom | EntryMonitorDevice => {
+            let word_count = r.u8()?;
+            let dword_count = r.u8()?;
+            push_hex(&mut out, word_count as u32, 2);
+            push_hex(&mut out, dword_count as u32, 2);
+            for _ in 0..(word_count as usize + dword_count as usize) {
+                encode_device(&mut r, &mut out, long)?;
+            }
+        }
+        DeviceWriteRandom => {
+            if is_bit {
+                let count = r.u8()?;
+                push_hex(&mut out, count as u32, 2);
+                for _ in 0..count {
+                    encode_device(&mut r, &mut out, long)?;
+                    if long {
+                        push_hex(&mut out, r.u16()? as u32, 4);
+                    } else {
+                        push_hex(&mut out, r.u8()? as u32, 2);
+                    }
+                }
+            } else {
+                let word_count = r.u8()?;
+                let dword_count = r.u8()?;
+                push_hex(&mut out, word_count as u32, 2);
+                push_hex(&mut out, dword_count as u32, 2);
+                for _ in 0..word_count {
+                    encode_device(&mut r, &mut out, long)?;
+                    push_hex(&mut out, r.u16()? as u32, 4);
+                }
+                for _ in 0..dword_count {
+                    encode_device(&mut r, &mut out, long)?;
+                    push_hex(&mut out, r.u32()?, 8);
+                }
+            }
+        }
+        ReadBlock => {
+            let word_count = r.u8()?;
+            let bit_count = r.u8()?;
+            push_hex(&mut out, word_count as u32, 2);
+            push_hex(&mut out, bit_count as u32, 2);
+            for _ in 0..(word_count as usize + bit_count as usize) {
+                encode_device(&mut r, &mut out, long)?;
+                push_hex(&mut out, r.u16()? as u32, 4);
+            }
+        }
+        WriteBlock => {
+            let word_count = r.u8()?;
+            let bit_count = r.u8()?;
+            push_hex(&mut out, word_count as u32, 2);
+            push_hex(&mut out, bit_count as u32, 2);
+            for _ in 0..(word_count as usize + bit_count as usize) {
+                encode_device(&mut r, &mut out, long)?;
+                let count = r.u16()?;
+                push_hex(&mut out, count as u32, 4);
+                // ビットデバイスブロックもワード単位
+                for _ in 0..count {
+                    push_hex(&mut out, r.u16()? as u32, 4);
+                }
+            }
+        }
+        MemoryRead => {
+            push_hex(&mut out, r.u32()?, 8);
+            push_hex(&mut out, r.u16()? as u32, 4);
+        }
+        MemoryWrite => {
+            push_hex(&mut out, r.u32()?, 8);
+            let count = r.u16()?;
+            push_hex(&mut out, count as u32, 4);
+            for _ in 0..count {
+                push_hex(&mut out, r.u16()? as u32, 4);
+            }
+        }
+        RemoteRun => {
+            push_hex(&mut out, r.u16()? as u32, 4);
+            push_hex(&mut out, r.u8()? as u32, 2);
+            push_hex(&mut out, r.u8()? as u32, 2);
+        }
+        RemoteStop | RemotePause | RemoteLatchClear | RemoteReset | Defrag => {
+            push_hex(&mut out, r.u16()? as u32, 4);
+        }
+        ReadDiskState => {
+            for _ in 0..3 {
+                push_hex(&mut out, r.u16()? as u32, 4);
+            }
+        }
+        SelfTest => {
+            let length = r.u16()?;
+            push_hex(&mut out, length as u32, 4);
+            out.extend_from_slice(r.take(length as usize)?);
+        }
+        RemotePasswordUnlock | RemotePasswordLock => {
+            // Qシリーズの形式は4文字固定、iQ-Rシリーズの形式は6文字以上で文字数が付く
+            if r.buf.len() != 4 {
+                let length = r.u16()?;
+                push_hex(&mut out, length as u32, 4);
+            }
+            out.extend_from_slice(r.take(r.buf.len())?);
+        }
+        ReadTypeName | ExecuteMonitor | ClearErrorCode | ClearErrorHistory | ClearEventHistory => {}
+        _ => {
+            return Err(SlmpError::InvalidArgument(
+                "command not supported in ASCII mode",
+            ))
+        }
+    }
+    if !r.buf.is_empty() {
+        return Err(SlmpError::InvalidArgument("unexpected request data"));
+    }
+    Ok(out)
+}
+
+/// ASCIIのワードデータをバイナリへ変換
+fn decode_words(r: &mut AsciiReader, out: &mut Vec<u8>, count: usize) -> Result<(), SlmpError> {
+    for _ in 0..count {
+        let v = r.hex(4)?;
+        out.push(v as u8);
+        out.push((v >> 8) as u8);
+    }
+    Ok(())
+}
+
+/// ASCIIのダブルワードデータをバイナリへ変換
+fn decode_dwords(r: &mut AsciiReader, out: &mut Vec<u8>, count: usize) -> Result<(), SlmpError> {
+    for _ in 0..count {
+        let v = r.hex(8)?;
+        out.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+    }
+    Ok(())
+}
+
+/// 要求内容から、乱数読み出し・モニタ登録のワード点数とダブルワード点数を得る
+fn random_counts(req: &SLMPRequest) -> Result<(usize, usize), SlmpError> {
+    if req.data.len() < 2 {
+        return Err(SlmpError::MalformedFrame("no matching request"));
+    }
+    Ok((req.data[0] as usize, req.data[1] as usize))
+}
+
+/// 応答データをASCIIからバイナリへ変換
+///
+/// # 引数
+///
+/// * `req` - 応答に対応する要求
+/// * `monitor` - 直前のモニタ登録要求
+/// * `data` - ASCIIでの応答データ
+///
+/// # 返値
+///
+/// バイナリでの応答データ
+pub(crate) fn decode_response(
+    req: &SLMPRequest,
+    monitor: Option<&SLMPRequest>,
+    data: &[u8],
+) -> Result<Vec<u8>, SlmpError> {
+    use SLMPCommand::*;
+    let mut r = AsciiReader { buf: data };
+    let mut out = Vec::new();
+    match req.command {
+        DeviceRead => {
+            // デバイス指定の後に点数
+            let pos = if req.sub_command & 0x0002 != 0 { 6 } else { 4 };
+            if req.data.len() < pos + 2 {
+                return Err(SlmpError::MalformedFrame("no matching request"));
+            }
+            let count = (req.data[pos] as usize) + ((req.data[pos + 1] as usize) << 8);
+            if req.sub_command & 0x0001 != 0 {
+                let bits = r.take(count)?;
+                for pair in bits.chunks(2) {
+                    let high = parse_hex(&pair[..1])? as u8;
+                    let low = match pair.get(1) {
+                        Some(c) => parse_hex(&[*c])? as u8,
+                        None => 0,
+                    };
+                    out.push((high << 4) | low);
+                }
+            } else {
+                decode_words(&mut r, &mut out, count)?;
+            }
+        }
+        DeviceReadRandom => {
+            let (word_count, dword_count) = random_counts(req)?;
+            decode_words(&mut r, &mut out, word_count)?;
+            decode_dwords(&mut r, &mut out, dword_count)?;
+        }
+        ExecuteMonitor => {
+            let monitor = monitor.ok_or(SlmpError::MalformedFrame("no monitor entry"))?;
+            let (word_count, dword_count) = random_counts(monitor)?;
+            decode_words(&mut r, &mut out, word_count)?;
+            decode_dwords(&mut r, &mut out, dword_count)?;
+        }
+        ReadBlock | MemoryRead | ReadDiskState => {
+            let count = data.len() / 4;
+            if count * 4 != data.len() {
+                return Err(SlmpError::MalformedFrame("wrong response length"));
+            }
+            decode_words(&mut r, &mut out, count)?;
+        }
+        ReadTypeName => {
+            out.extend_from_slice(r.take(16)?);
+            decode_words(&mut r, &mut out, 1)?;
+        }
+        SelfTest => {
+            let length = r.hex(4)?;
+            out.push(length as u8);
+            out.push((length >> 8) as u8);
+            out.extend_from_slice(r.take(length as usize)?);
+        }
+        _ => (),
+    }
+    if !r.buf.is_empty() {
+        return Err(SlmpError::MalformedFrame("unexpected response data"));
+    }
+    Ok(out)
+}
+
+/// ASCIIコードでの通信のため、送信した要求を記録しておく
+pub(crate) struct AsciiContext {
+    /// 応答待ちの要求
+    pending: VecDeque<(u16, SLMPRequest)>,
+    /// 直前のモニタ登録要求
+    monitor: Option<SLMPRequest>,
+}
+
+impl AsciiContext {
+    pub(crate) fn new() -> AsciiContext {
+        AsciiContext {
+            pending: VecDeque::new(),
+            monitor: None,
+        }
+    }
+    /// 要求の通信文をASCIIコードで作成
+    ///
+    /// # 引数
+    ///
+    /// * `frame_type` - フレームの種類
+    /// * `slmp_header` - 要求のサブヘッダ、データ長は再計算される
+    /// * `seq_no` - シリアル番号、3Eフレームでは使用しない
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信するバイト列
+    pub(crate) fn make_frame(
+        &mut self,
+        frame_type: SLMPFrameType,
+        slmp_header: &SlmpSubHeaderReq,
+        seq_no: u16,
+        req: &SLMPRequest,
+    ) -> Result<Vec<u8>, SlmpError> {
+        let data = encode_request(req)?;
+        // データ長はタイマ、コマンド、サブコマンドを含む
+        let dl = data.len() + 12;
+        if dl > 0xffff {
+            return Err(SlmpError::InvalidArgument("too long data"));
+        }
+        let mut buf = Vec::with_capacity(42 + data.len());
+        match frame_type {
+            SLMPFrameType::Frame4E => {
+                buf.extend_from_slice(b"5400");
+                push_hex(&mut buf, seq_no as u32, 4);
+                buf.extend_from_slice(b"0000");
+            }
+            SLMPFrameType::Frame3E => buf.extend_from_slice(b"5000"),
+        }
+        push_hex(&mut buf, slmp_header.net_no as u32, 2);
+        push_hex(&mut buf, slmp_header.node_no as u32, 2);
+        push_hex(&mut buf, slmp_header.dst_proc_no as u32, 4);
+        push_hex(&mut buf, slmp_header.reserved1 as u32, 2);
+        push_hex(&mut buf, dl as u32, 4);
+        push_hex(&mut buf, slmp_header.timer as u32, 4);
+        push_hex(&mut buf, req.command as u32, 4);
+        push_hex(&mut buf, req.sub_command as u32, 4);
+        buf.extend_from_slice(&data);
+
+        if req.command == SLMPCommand::EntryMonitorDevice {
+            self.monitor = Some(req.clone());
+        }
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((seq_no, req.clone()));
+        Ok(buf)
+    }
+    /// 受信バッファの先頭からASCIIコードの通信文を一つ取り出す
+    ///
+    /// 正常応答のデータはバイナリコードでの応答と同じ形式へ変換する
+    ///
+    /// # 引数
+    ///
+    /// * `buf` - 受信バッファ
+    /// * `frame_type` - フレームの種類
+    /// * `serial` - 3Eフレームの場合に代わりに使用するシリアル番号
+    ///
+    /// # 返値
+    ///
+    /// 通信文が揃っていなければ`None`、揃っていれば消費したバイト数と通信文
+    pub(crate) fn decode_frame(
+        &mut self,
+        buf: &[u8],
+        frame_type: SLMPFrameType,
+        serial: u16,
+    ) -> Result<Option<(usize, SLMPFrame)>, SlmpError> {
+        let head = match frame_type {
+            SLMPFrameType::Frame4E => 12,
+            SLMPFrameType::Frame3E => 4,
+        };
+        if buf.len() < head + 18 {
+            return Ok(None);
+        }
+        let is_response = match &buf[..4] {
+            b"D400" | b"D000" => true,
+            b"5400" | b"5000" => false,
+            _ => return Err(SlmpError::MalformedFrame("unknown frame type")),
+        };
+        let serial = match frame_type {
+            SLMPFrameType::Frame4E => {
+                if buf[1] != b'4' {
+                    return Err(SlmpError::MalformedFrame("wrong frame header"));
+                }
+                parse_hex(&buf[4..8])? as u16
+            }
+            SLMPFrameType::Frame3E => {
+                if buf[1] != b'0' {
+                    return Err(SlmpError::MalformedFrame("wrong frame header"));
+                }
+                serial
+            }
+        };
+        let mut r = AsciiReader { buf: &buf[head..] };
+        let net_no = r.hex(2)? as u8;
+        let node_no = r.hex(2)? as u8;
+        let dst_proc_no = r.hex(4)? as u16;
+        let reserved1 = r.hex(2)? as u8;
+        let dl = r.hex(4)? as usize;
+        if dl < 4 {
+            return Err(SlmpError::MalformedFrame("wrong response header"));
+        }
+        let size = head + 14 + dl;
+        if buf.len() < size {
+            return Ok(None);
+        }
+        let code = r.hex(4)? as u16;
+        let data = &buf[head + 18..size];
+        if !is_response {
+            // 予約の後にコマンド、サブコマンド、データが続く
+            let mut r = AsciiReader { buf: data };
+            let command = r.hex(4)?;
+            let sub_command = r.hex(4)?;
+            let mut out = vec![
+                command as u8,
+                (command >> 8) as u8,
+                sub_command as u8,
+                (sub_command >> 8) as u8,
+            ];
+            out.extend_from_slice(r.buf);
+            return Ok(Some((size, SLMPFrame::OnDemand { serial, data: out })));
+        }
+        let req = self
+            .pending
+            .iter()
+            .position(|(s, _)| *s == serial)
+            .and_then(|i| self.pending.remove(i))
+            .map(|(_, req)| req);
+        let data = if code != 0 {
+            // エラー情報
+            let mut r = AsciiReader { buf: data };
+            let mut out = Vec::with_capacity(9);
+            out.push(r.hex(2)? as u8);
+            out.push(r.hex(2)? as u8);
+            decode_words(&mut r, &mut out, 1)?;
+            out.push(r.hex(2)? as u8);
+            decode_words(&mut r, &mut out, 2)?;
+            out
+        } else {
+            match req {
+                Some(req) => decode_response(&req, self.monitor.as_ref(), data)?,
+                None => return Err(SlmpError::MalformedFrame("no matching request")),
+            }
+        };
+        Ok(Some((
+            size,
+            SLMPFrame::Response {
+                serial,
+                header: SlmpSubHeaderRes {
+                    net_no,
+                    node_no,
+                    dst_proc_no,
+                    reserved1,
+                    dl: data.len() as u16 + 2,
+                    end_code: code,
+                },
+                data,
+            },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        make_read_cmd_16, make_read_cmd_32, make_write_bit_cmd_16, SLMPDevice, SLMPDeviceData,
+    };
+
+    fn header() -> SlmpSubHeaderReq {
+        SlmpSubHeaderReq {
+            net_no: 0,
+            node_no: 0xff,
+            dst_proc_no: 0x3ff,
+            reserved1: 0,
+            dl: 0,
+            timer: 0x10,
+        }
+    }
+
+    #[test]
+    fn test_make_ascii_frame() {
+        let mut ctx = AsciiContext::new();
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::D,
+            addr: 100,
+        };
+        let req = make_read_cmd_16(dev, 3, false);
+        let buf = ctx
+            .make_frame(SLMPFrameType::Frame3E, &header(), 0, &req)
+            .unwrap();
+        assert_eq!(buf, b"500000FF03FF000018001004010000D*0001000003".to_vec());
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::X,
+            addr: 0x1a0,
+        };
+        let req = make_read_cmd_32(dev, 2, true);
+        let buf = ctx
+            .make_frame(SLMPFrameType::Frame4E, &header(), 0x1234, &req)
+            .unwrap();
+        assert_eq!(
+            buf,
+            b"54001234000000FF03FF00001C001004010003X***000001A00002".to_vec()
+        );
+        let targets: Vec<SLMPDeviceData<bool>> = [true, false, true]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| SLMPDeviceData {
+                dev: SLMPDevice {
+                    d_code: SLMPDeviceCode::M,
+                    addr: 10 + i as u32,
+                },
+                value: *value,
+            })
+            .collect();
+        let req = make_write_bit_cmd_16(&targets).unwrap();
+        let buf = ctx
+            .make_frame(SLMPFrameType::Frame3E, &header(), 0, &req)
+            .unwrap();
+        assert!(buf.ends_with(b"14010001M*0000100003101"));
+    }
+
+    #[test]
+    fn test_decode_ascii_frame() {
+        let mut ctx = AsciiContext::new();
+        let dev = SLMPDevice {
+            d_code: SLMPDeviceCode::M,
+            addr: 0,
+        };
+        let req = make_read_cmd_16(dev, 3, true);
+        ctx.make_frame(SLMPFrameType::Frame4E, &header(), 0x0010, &req)
+            .unwrap();
+        let res = b"D4000010000000FF03FF000008000010";
+        assert!(ctx
+            .decode_frame(&res[..], SLMPFrameType::Frame4E, 0)
+            .unwrap()
+            .is_none());
+        // 奇数点の要求は偶数点へ切り上げられる
+        let res = b"D4000010000000FF03FF00000800001010";
+        let (size, frame) = ctx
+            .decode_frame(&res[..], SLMPFrameType::Frame4E, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(size, res.len());
+        let (serial, data) = frame.into_result(0, 0xff, 0x3ff).unwrap();
+        assert_eq!(serial, 0x0010);
+        assert_eq!(data, vec![0x10, 0x10]);
+
+        let res = b"D00000FF03FF000016C05900FF03FF0004010000";
+        let (_, frame) = ctx
+            .decode_frame(&res[..], SLMPFrameType::Frame3E, 0x20)
+            .unwrap()
+            .unwrap();
+        match frame.into_result(0, 0xff, 0x3ff) {
+            Err(SlmpError::EndCode { serial, code, info }) => {
+                assert_eq!(serial, 0x20);
+                assert_eq!(code, 0xc059);
+                assert_eq!(info.unwrap().command, 0x0401);
+            }
+            _ => panic!("end code error expected"),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use super::ascii::AsciiContext;
+use super::headers::*;
+use super::{ErrInfo, SLMPRequest};
+use crate::{SLMPDataCode, SLMPEndCode, SLMPFrameType, SlmpError};
+
+/// 受信したSLMPの通信文
+pub(crate) enum SLMPFrame {
+    /// 要求に対する応答
+    Response {
+        /// シリアル番号
+        serial: u16,
+        /// サブヘッダ
+        header: SlmpSubHeaderRes,
+        /// 終了コードに続くデータ
+        data: Vec<u8>,
+    },
+    /// オンデマンドデータ
+    OnDemand {
+        /// シリアル番号
+        serial: u16,
+        /// コマンド、サブコマンドとそれに続くデータ
+        data: Vec<u8>,
+    },
+}
+
+impl SLMPFrame {
+    /// シリアル番号
+    pub(crate) fn serial(&self) -> u16 {
+        match self {
+            SLMPFrame::Response { serial, .. } => *serial,
+            SLMPFrame::OnDemand { serial, .. } => *serial,
+        }
+    }
+    /// 応答の宛先と終了コードを確認し、シリアル番号とペイロードを取り出す
+    ///
+    /// # 引数
+    ///
+    /// * `network` - 要求時のネットワーク番号
+    /// * `node` - 要求時の局番
+    /// * `dst_proc` - 要求時のプロセッサ番号
+    ///
+    /// # 返値
+    ///
+    /// シリアル番号と受信ペイロード
+    pub(crate) fn into_result(
+        self,
+        network: u8,
+        node: u8,
+        dst_proc: u16,
+    ) -> Result<(u16, Vec<u8>), SlmpError> {
+        match self {
+            SLMPFrame::OnDemand { serial, data } => Ok((serial, data)),
+            SLMPFrame::Response {
+                serial,
+                header,
+                data,
+            } => {
+                if header.net_no != network {
+                    return Err(SlmpError::UnexpectedNetwork {
+                        expected: network,
+                        actual: header.net_no,
+                    });
+                }
+                if header.node_no != node {
+                    return Err(SlmpError::UnexpectedNode {
+                        expected: node,
+                        actual: header.node_no,
+                    });
+                }
+                if header.dst_proc_no != dst_proc {
+                    return Err(SlmpError::UnexpectedProcessor {
+                        expected: dst_proc,
+                        actual: header.dst_proc_no,
+                    });
+                }
+                if header.end_code != SLMPEndCode::Success as u16 {
+                    let info = if data.len() >= 9 {
+                        let mut buf = [0u8; 9];
+                        buf.copy_from_slice(&data[..9]);
+                        Some(ErrInfo::from(buf))
+                    } else {
+                        None
+                    };
+                    return Err(SlmpError::EndCode {
+                        serial,
+                        code: header.end_code,
+                        info,
+                    });
+                }
+                Ok((serial, data))
+            }
+        }
+    }
+}
+
+/// 要求の通信文を作成
+///
+/// # 引数
+///
+/// * `frame_type` - フレームの種類
+/// * `slmp_header` - 要求のサブヘッダ
+/// * `seq_no` - シリアル番号、3Eフレームでは使用しない
+/// * `req` - 要求内容
+///
+/// # 返値
+///
+/// 送信するバイト列
+pub(crate) fn make_frame(
+    frame_type: SLMPFrameType,
+    slmp_header: &SlmpSubHeaderReq,
+    seq_no: u16,
+    req: &SLMPRequest,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(19 + req.data.len());
+    match frame_type {
+        SLMPFrameType::Frame4E => {
+            // フレームタイプ0x0054
+            buf.push(0x54);
+            buf.push(0x00);
+            // シリアル番号
+            buf.push(seq_no as u8);
+            buf.push((seq_no >> 8) as u8);
+            // 拡張用
+            buf.push(0x00);
+            buf.push(0x00);
+        }
+        SLMPFrameType::Frame3E => {
+            // サブヘッダ0x5000
+            buf.push(0x50);
+            buf.push(0x00);
+        }
+    }
+    // ネットワーク番号
+    // ノード番号
+    // プロセッサ番号2byte
+    // マルチドロップ
+    // データ長2byte
+    // タイマ2byte
+    buf.extend_from_slice(&slmp_header.to());
+    // コマンド2byte
+    let command = req.command as u16;
+    buf.push(command as u8);
+    buf.push((command >> 8) as u8);
+    // サブコマンド2byte
+    buf.push(req.sub_command as u8);
+    buf.push((req.sub_command >> 8) as u8);
+    buf.extend_from_slice(&req.data);
+    buf
+}
+
+/// 受信バッファの先頭から通信文を一つ取り出す
+///
+/// # 引数
+///
+/// * `buf` - 受信バッファ
+/// * `frame_type` - フレームの種類
+/// * `serial` - 3Eフレームの場合に代わりに使用するシリアル番号
+///
+/// # 返値
+///
+/// 通信文が揃っていなければ`None`、揃っていれば消費したバイト数と通信文
+pub(crate) fn decode_frame(
+    buf: &[u8],
+    frame_type: SLMPFrameType,
+    serial: u16,
+) -> Result<Option<(usize, SLMPFrame)>, SlmpError> {
+    // サブヘッダ以降の長さ
+    let head = match frame_type {
+        SLMPFrameType::Frame4E => 6,
+        SLMPFrameType::Frame3E => 2,
+    };
+    if buf.len() < head + 9 {
+        return Ok(None);
+    }
+    let serial = match frame_type {
+        SLMPFrameType::Frame4E => {
+            let header = SlmpMTHeader::from(&buf[0..6]);
+            if header.f_type & 0xff0f != 0x0004 {
+                return Err(SlmpError::MalformedFrame("wrong frame header"));
+            }
+            header.serial_no
+        }
+        SLMPFrameType::Frame3E => {
+            if buf[0] & 0x0f != 0x00 || buf[1] != 0x00 {
+                return Err(SlmpError::MalformedFrame("wrong frame header"));
+            }
+            serial
+        }
+    };
+    match buf[0] & 0xf0 {
+        // odReqMT-PDU
+        0x50 => {
+            let target = SlmpSubHeaderOd::from(&buf[head..head + 9]);
+            if target.dl < 2 {
+                return Err(SlmpError::MalformedFrame("wrong on-demand header"));
+            }
+            // 予約2バイトの後にコマンド、サブコマンド、データが続く
+            let size = head + 9 + (target.dl - 2) as usize;
+            if buf.len() < size {
+                return Ok(None);
+            }
+            Ok(Some((
+                size,
+                SLMPFrame::OnDemand {
+                    serial,
+                    data: buf[head + 9..size].to_vec(),
+                },
+            )))
+        }
+        // rdResMT-PDU, wrResMT-PDU, rdErrMT-PDU, wrErrMT-PDU
+        0xD0 => {
+            let target = SlmpSubHeaderRes::from(&buf[head..head + 9]);
+            if target.dl < 2 {
+                return Err(SlmpError::MalformedFrame("wrong response header"));
+            }
+            let size = head + 9 + (target.dl - 2) as usize;
+            if buf.len() < size {
+                return Ok(None);
+            }
+            Ok(Some((
+                size,
+                SLMPFrame::Response {
+                    serial,
+                    header: target,
+                    data: buf[head + 9..size].to_vec(),
+                },
+            )))
+        }
+        // 上記以外
+        _ => Err(SlmpError::MalformedFrame("unknown frame type")),
+    }
+}
+
+/// 通信文の作成と解釈
+pub(crate) struct FrameCodec {
+    /// 通信フレームの種類
+    pub(crate) frame_type: SLMPFrameType,
+    /// 通信データコード
+    pub(crate) data_code: SLMPDataCode,
+    /// 最後に送信した要求のシリアル番号
+    pub(crate) last_serial: u16,
+    /// ASCIIコードでの通信状態
+    ascii: AsciiContext,
+}
+
+impl FrameCodec {
+    pub(crate) fn new() -> FrameCodec {
+        FrameCodec {
+            frame_type: SLMPFrameType::Frame4E,
+            data_code: SLMPDataCode::Binary,
+            last_serial: 0,
+            ascii: AsciiContext::new(),
+        }
+    }
+    /// 要求の通信文を作成
+    ///
+    /// # 引数
+    ///
+    /// * `slmp_header` - 要求のサブヘッダ
+    /// * `seq_no` - シリアル番号
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信するバイト列
+    pub(crate) fn encode(
+        &mut self,
+        slmp_header: &SlmpSubHeaderReq,
+        seq_no: u16,
+        req: &SLMPRequest,
+    ) -> Result<Vec<u8>, SlmpError> {
+        match self.data_code {
+            SLMPDataCode::Binary => Ok(make_frame(self.frame_type, slmp_header, seq_no, req)),
+            SLMPDataCode::Ascii => self
+                .ascii
+                .make_frame(self.frame_type, slmp_header, seq_no, req),
+        }
+    }
+    /// 受信バッファの先頭から通信文を一つ取り出す
+    ///
+    /// # 引数
+    ///
+    /// * `buf` - 受信バッファ
+    ///
+    /// # 返値
+    ///
+    /// 通信文が揃っていなければ`None`、揃っていれば消費したバイト数と通信文
+    pub(crate) fn decode(&mut self, buf: &[u8]) -> Result<Option<(usize, SLMPFrame)>, SlmpError> {
+        match self.data_code {
+            SLMPDataCode::Binary => decode_frame(buf, self.frame_type, self.last_serial),
+            SLMPDataCode::Ascii => self
+                .ascii
+                .decode_frame(buf, self.frame_type, self.last_serial),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SLMPCommand;
+
+    #[test]
+    fn test_make_frame_3e() {
+        let req = SLMPRequest::new(SLMPCommand::ClearErrorCode, 0, &[]);
+        let header = req.make_sub_header(0, 0xff, 0x3ff, 0, 4).unwrap();
+        let buf = make_frame(SLMPFrameType::Frame3E, &header, 0x1234, &req);
+        assert_eq!(
+            buf,
+            vec![
+                0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x06, 0x00, 0x04, 0x00, 0x17, 0x16, 0x00,
+                0x00
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_frame_3e() {
+        let buf = [
+            0xd0, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x04, 0x00, 0x00, 0x00, 0x34, 0x12,
+        ];
+        assert!(decode_frame(&buf[..12], SLMPFrameType::Frame3E, 7)
+            .unwrap()
+            .is_none());
+        let (size, frame) = decode_frame(&buf, SLMPFrameType::Frame3E, 7)
+            .unwrap()
+            .unwrap();
+        assert_eq!(size, buf.len());
+        assert_eq!(frame.serial(), 7);
+        let (_, data) = frame.into_result(0, 0xff, 0x3ff).unwrap();
+        assert_eq!(data, vec![0x34, 0x12]);
+        // 4Eフレームの応答は3Eとしては解釈できない
+        let buf = [
+            0xd4, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x02, 0x00, 0x00,
+            0x00,
+        ];
+        assert!(decode_frame(&buf, SLMPFrameType::Frame3E, 7).is_err());
+        assert!(decode_frame(&buf, SLMPFrameType::Frame4E, 7)
+            .unwrap()
+            .is_some());
+    }
+}
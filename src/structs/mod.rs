@@ -1,10 +1,17 @@
+mod ascii;
+mod frame;
 mod headers;
 
 use super::enums::SLMPCommand;
-use super::enums::SLMPEndCode;
-use headers::*;
+use crate::{
+    make_remote_password_lock_cmd, make_remote_password_unlock_cmd, SlmpError, SlmpPasswordFormat,
+};
+pub(crate) use ascii::{decode_response, encode_request, parse_hex, push_hex};
+pub(crate) use frame::*;
+pub use headers::*;
 
 /// エラー情報
+#[derive(Copy, Clone, Debug)]
 pub struct ErrInfo {
     /// ネットワーク番号
     pub net_no: u8,
@@ -38,9 +45,75 @@ impl ErrInfo {
     }
 }
 
-use crate::enums::TCPorUDP;
+/// SLMPの要求内容
+///
+/// コマンド、サブコマンドとそれに続く要求データからなる
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SLMPRequest {
+    /// コマンド
+    pub command: SLMPCommand,
+    /// サブコマンド
+    pub sub_command: u16,
+    /// 要求データ
+    pub data: Vec<u8>,
+}
+impl SLMPRequest {
+    /// 要求内容を作成
+    ///
+    /// # 引数
+    ///
+    /// * `command` - コマンド
+    /// * `sub_command` - サブコマンド
+    /// * `data` - 要求データ
+    pub fn new(command: SLMPCommand, sub_command: u16, data: &[u8]) -> SLMPRequest {
+        SLMPRequest {
+            command,
+            sub_command,
+            data: data.to_vec(),
+        }
+    }
+    /// 要求のサブヘッダを作成
+    ///
+    /// # 引数
+    ///
+    /// * `net_no` - ネットワーク番号
+    /// * `node_no` - 局番
+    /// * `dst_proc_no` - プロセッサ番号
+    /// * `m_drop` - マルチドロップ番号
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms
+    pub(crate) fn make_sub_header(
+        &self,
+        net_no: u8,
+        node_no: u8,
+        dst_proc_no: u16,
+        m_drop: u8,
+        timeout: u16,
+    ) -> Result<SlmpSubHeaderReq, SlmpError> {
+        // データ長はタイマ、コマンド、サブコマンドを含む
+        let dl = self.data.len() + 6;
+        if dl > 0xffff {
+            return Err(SlmpError::InvalidArgument("too long data"));
+        }
+        Ok(SlmpSubHeaderReq {
+            net_no,
+            node_no,
+            dst_proc_no,
+            reserved1: m_drop,
+            dl: dl as u16,
+            timer: timeout,
+        })
+    }
+}
+
+use crate::enums::{SLMPDataCode, SLMPFrameType, TCPorUDP};
+use crate::transport::SlmpTransport;
+use crate::{ReconnectPolicy, SlmpConnectionState};
 use std::io;
 use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// 接続状態の変化を通知するコールバック
+pub type SlmpStateCallback = Box<dyn FnMut(SlmpConnectionState) + Send>;
 
 /// SLMPの接続管理
 pub struct SLMPConnectionInfo {
@@ -52,12 +125,34 @@ pub struct SLMPConnectionInfo {
     dst_proc: u16,
     /// マルチドロップ番号
     m_drop: u8,
+    /// 通信文の作成と解釈
+    codec: FrameCodec,
     /// シリアル番号
     seq_no: u16,
-    /// 通信ソケット
-    socket: Option<TCPorUDP>,
+    /// 通信路
+    socket: Option<Box<dyn SlmpTransport>>,
     /// 受信バッファ
     buf: Vec<u8>,
+    /// 受信タイムアウト、単位はms
+    recv_timeout: u64,
+    /// 接続自身が送信する要求のタイムアウト、単位は250ms
+    request_timeout: u16,
+    /// 再接続先、TCPの場合のみ
+    remote: Option<SocketAddr>,
+    /// 再接続方針
+    reconnect_policy: ReconnectPolicy,
+    /// 接続状態の変化の通知先
+    state_callback: Option<SlmpStateCallback>,
+    /// 再接続に成功した回数
+    reconnect_count: u64,
+    /// モニタ登録の世代、モニタ登録の要求の送信と再接続のたびに増える
+    monitor_generation: u64,
+    /// 接続ごとにロックを解除するリモートパスワード
+    remote_password: Option<(String, SlmpPasswordFormat)>,
+    /// 通信エラー時に再接続しない要求の処理中か
+    no_reconnect: bool,
+    /// 3Eフレームで応答を受信する前にタイムアウトし、遅れた応答が届く可能性があるか
+    out_of_sync: bool,
 }
 
 use std::default::Default;
@@ -72,9 +167,20 @@ impl Default for SLMPConnectionInfo {
             node: 1,
             dst_proc: 0x3FF,
             m_drop: 0,
+            codec: FrameCodec::new(),
             seq_no: rnd as u16,
             socket: None,
             buf: Vec::new(),
+            recv_timeout: 0,
+            request_timeout: 40,
+            remote: None,
+            reconnect_policy: ReconnectPolicy::default(),
+            state_callback: None,
+            reconnect_count: 0,
+            monitor_generation: 0,
+            remote_password: None,
+            no_reconnect: false,
+            out_of_sync: false,
         }
     }
 }
@@ -82,26 +188,59 @@ impl Default for SLMPConnectionInfo {
 impl SLMPConnectionInfo {
     /// TCPにてSLMP接続を作成
     ///
+    /// 接続のタイムアウトはOSの既定値、指定するには[SLMPConnectionInfo::new_tcp_with_policy]を使う
+    ///
     /// # 引数
     ///
     /// * `addr` - 接続先
-    pub fn new_tcp(addr: SocketAddr) -> SLMPConnectionInfo {
-        let stream = TcpStream::connect(addr).unwrap();
-        SLMPConnectionInfo {
-            socket: Some(TCPorUDP::TCP(stream)),
-            ..SLMPConnectionInfo::default()
-        }
+    pub fn new_tcp(addr: SocketAddr) -> Result<SLMPConnectionInfo, SlmpError> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(SLMPConnectionInfo {
+            socket: Some(Box::new(TCPorUDP::TCP(stream))),
+            remote: Some(addr),
+            ..Default::default()
+        })
+    }
+    /// 再接続方針を指定し、TCPにてSLMP接続を作成
+    ///
+    /// 最初の接続にも再接続方針の接続タイムアウトを使う
+    ///
+    /// # 引数
+    ///
+    /// * `addr` - 接続先
+    /// * `policy` - 再接続方針
+    pub fn new_tcp_with_policy(
+        addr: SocketAddr,
+        policy: ReconnectPolicy,
+    ) -> Result<SLMPConnectionInfo, SlmpError> {
+        let stream = TcpStream::connect_timeout(&addr, policy.connect_timeout)?;
+        Ok(SLMPConnectionInfo {
+            socket: Some(Box::new(TCPorUDP::TCP(stream))),
+            remote: Some(addr),
+            reconnect_policy: policy,
+            ..Default::default()
+        })
     }
     /// UDPにてSLMP接続を作成
     ///
     /// # 引数
     ///
     /// * `addr` - 接続先
-    pub fn new_udp(addr: SocketAddr) -> SLMPConnectionInfo {
-        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    pub fn new_udp(addr: SocketAddr) -> Result<SLMPConnectionInfo, SlmpError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(SLMPConnectionInfo::from_transport(TCPorUDP::UDP(
+            socket, addr,
+        )))
+    }
+    /// 任意の通信路にてSLMP接続を作成
+    ///
+    /// # 引数
+    ///
+    /// * `transport` - 通信路
+    pub fn from_transport<T: SlmpTransport + 'static>(transport: T) -> SLMPConnectionInfo {
         SLMPConnectionInfo {
-            socket: Some(TCPorUDP::UDP(socket, addr)),
-            ..SLMPConnectionInfo::default()
+            socket: Some(Box::new(transport)),
+            ..Default::default()
         }
     }
     pub fn get_network(&self) -> u8 {
@@ -128,6 +267,244 @@ impl SLMPConnectionInfo {
     pub fn set_m_drop(&mut self, m_drop: u8) {
         self.m_drop = m_drop
     }
+    pub fn get_frame_type(&self) -> SLMPFrameType {
+        self.codec.frame_type
+    }
+    /// 通信フレームの種類を設定する
+    ///
+    /// 受信途中のデータは破棄される
+    ///
+    /// # 引数
+    ///
+    /// * `frame_type` - フレームの種類
+    pub fn set_frame_type(&mut self, frame_type: SLMPFrameType) {
+        self.codec.frame_type = frame_type;
+        self.buf.clear();
+    }
+    pub fn get_data_code(&self) -> SLMPDataCode {
+        self.codec.data_code
+    }
+    /// 通信データコードを設定する
+    ///
+    /// 受信途中のデータは破棄される
+    ///
+    /// # 引数
+    ///
+    /// * `data_code` - 通信データコード
+    pub fn set_data_code(&mut self, data_code: SLMPDataCode) {
+        self.codec.data_code = data_code;
+        self.buf.clear();
+    }
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.reconnect_policy
+    }
+    /// 再接続方針を設定する
+    ///
+    /// 再接続はTCPで接続した場合のみ行う
+    ///
+    /// # 引数
+    ///
+    /// * `policy` - 再接続方針
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy
+    }
+    /// 再接続に成功した回数
+    ///
+    /// 接続ごとに保持されるPLC側の状態(モニタ登録など)が失われたことの検出に使う
+    pub fn get_reconnect_count(&self) -> u64 {
+        self.reconnect_count
+    }
+    /// モニタ登録の世代
+    ///
+    /// モニタ登録の要求を送信するか再接続するたびに増える。
+    /// 登録時の値と異なれば、その登録は別のデバイスの登録で上書きされたか失われている
+    pub fn get_monitor_generation(&self) -> u64 {
+        self.monitor_generation
+    }
+    pub fn get_request_timeout(&self) -> u16 {
+        self.request_timeout
+    }
+    /// 接続自身が送信する要求(再接続時のロックの解除など)のタイムアウトを設定する
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms、0でタイムアウトなし
+    pub fn set_request_timeout(&mut self, timeout: u16) {
+        self.request_timeout = timeout
+    }
+    /// リモートパスワードを設定し、ロックを解除する
+    ///
+    /// 以降は再接続するごとにロックを解除する。
+    /// 破棄する際はロックしないため、[SLMPConnectionInfo::lock_and_close]を使う
+    ///
+    /// # 引数
+    ///
+    /// * `password` - リモートパスワード
+    /// * `format` - リモートパスワードの形式
+    pub fn set_remote_password(
+        &mut self,
+        password: &str,
+        format: SlmpPasswordFormat,
+    ) -> Result<(), SlmpError> {
+        self.request_once(&make_remote_password_unlock_cmd(password, format)?)?;
+        self.remote_password = Some((password.to_string(), format));
+        Ok(())
+    }
+    /// リモートパスワードの設定を消去する、ロックはしない
+    pub fn clear_remote_password(&mut self) {
+        self.remote_password = None;
+    }
+    /// 設定したリモートパスワードでロックを解除する
+    fn unlock_remote_password(&mut self) -> Result<(), SlmpError> {
+        let req = match &self.remote_password {
+            Some((password, format)) => make_remote_password_unlock_cmd(password, *format)?,
+            None => return Ok(()),
+        };
+        self.request_once(&req)
+    }
+    /// リモートパスワードを設定していればロックし、接続を閉じる
+    pub fn lock_and_close(mut self) -> Result<(), SlmpError> {
+        match self.remote_password.take() {
+            Some((password, format)) if self.socket.is_some() => {
+                self.request_once(&make_remote_password_lock_cmd(&password, format)?)
+            }
+            _ => Ok(()),
+        }
+    }
+    /// 再接続せずに要求を送信し、応答を待つ
+    fn request_once(&mut self, req: &SLMPRequest) -> Result<(), SlmpError> {
+        let no_reconnect = self.no_reconnect;
+        self.no_reconnect = true;
+        let timeout = self.request_timeout;
+        let deadline = Instant::now() + Duration::from_millis(timeout as u64 * 250);
+        let ret = self.send_request(timeout, req).and_then(|serial| loop {
+            match self.recv_response(serial) {
+                Err(SlmpError::SerialMismatch { .. }) => {
+                    if timeout != 0 && Instant::now() > deadline {
+                        break Err(SlmpError::Timeout);
+                    }
+                }
+                r => break r.map(|_| ()),
+            }
+        });
+        self.no_reconnect = no_reconnect;
+        ret
+    }
+    /// 接続状態の変化の通知先を設定する
+    ///
+    /// # 引数
+    ///
+    /// * `callback` - 状態の変化ごとに呼ばれる関数
+    pub fn set_state_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(SlmpConnectionState) + Send + 'static,
+    {
+        self.state_callback = Some(Box::new(callback))
+    }
+    fn notify(&mut self, state: SlmpConnectionState) {
+        if let Some(callback) = self.state_callback.as_mut() {
+            callback(state)
+        }
+    }
+    /// 再接続方針に従いTCP接続をやり直す
+    ///
+    /// 受信途中のデータは破棄される
+    pub fn reconnect(&mut self) -> Result<(), SlmpError> {
+        self.reconnect_with(self.reconnect_policy.max_attempts)
+    }
+    /// 試行回数を指定してTCP接続をやり直す
+    ///
+    /// # 引数
+    ///
+    /// * `max_attempts` - 最大試行回数
+    fn reconnect_with(&mut self, max_attempts: u32) -> Result<(), SlmpError> {
+        let addr = self.remote.ok_or(SlmpError::NotConnected)?;
+        self.socket = None;
+        self.buf.clear();
+        let policy = self.reconnect_policy;
+        let mut last_err = SlmpError::NotConnected;
+        for attempt in 1..=max_attempts {
+            std::thread::sleep(policy.backoff(attempt));
+            self.notify(SlmpConnectionState::Reconnecting { attempt });
+            match TcpStream::connect_timeout(&addr, policy.connect_timeout) {
+                Ok(stream) => {
+                    let mut socket = TCPorUDP::TCP(stream);
+                    socket.set_timeout(self.recv_timeout)?;
+                    self.socket = Some(Box::new(socket));
+                    self.out_of_sync = false;
+                    if let Err(e) = self.unlock_remote_password() {
+                        self.socket = None;
+                        last_err = e;
+                        continue;
+                    }
+                    self.reconnect_count += 1;
+                    self.monitor_generation += 1;
+                    self.notify(SlmpConnectionState::Connected);
+                    return Ok(());
+                }
+                Err(e) => last_err = e.into(),
+            }
+        }
+        self.notify(SlmpConnectionState::ReconnectFailed);
+        Err(last_err)
+    }
+    /// 遅れて届く応答を読み捨て、送受信を同期し直す
+    ///
+    /// TCPは接続し直し、その他の通信路は受信タイムアウトまでに届いた応答を読み捨てる
+    fn resync(&mut self) -> Result<(), SlmpError> {
+        self.buf.clear();
+        if self.remote.is_some() {
+            return self.reconnect_with(self.reconnect_policy.max_attempts.max(1));
+        }
+        let deadline = Instant::now() + Duration::from_millis(self.recv_timeout);
+        let mut buf = [0u8; 8194];
+        // 応答が途切れるまで読み捨てる、届き続ける場合は受信タイムアウトの時間で打ち切る
+        while Instant::now() < deadline {
+            match self.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => return Err(self.on_io_error(e)),
+            }
+        }
+        self.out_of_sync = false;
+        Ok(())
+    }
+    /// 入出力エラーが切断によるものであれば、再接続を試みる
+    ///
+    /// # 引数
+    ///
+    /// * `e` - 発生したエラー
+    ///
+    /// # 返値
+    ///
+    /// 呼び出し元へ返すエラー
+    fn on_io_error(&mut self, e: io::Error) -> SlmpError {
+        let lost = !matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+        );
+        if lost && self.remote.is_some() && !self.no_reconnect {
+            if self.socket.is_some() {
+                self.notify(SlmpConnectionState::Disconnected);
+            }
+            if self.reconnect_policy.max_attempts > 0 {
+                // 再接続の成否にかかわらず、元のエラーを返す
+                let _ = self.reconnect();
+            } else {
+                self.socket = None;
+            }
+        }
+        e.into()
+    }
     /// 指定したバイト列を送信する
     /// # 引数
     ///
@@ -137,10 +514,16 @@ impl SLMPConnectionInfo {
     ///
     /// 送信したバイト数
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.socket.as_mut().unwrap().write(buf)
+        match self.socket.as_mut() {
+            Some(socket) => socket.send(buf),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "no socket")),
+        }
     }
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.socket.as_mut().unwrap().read(buf)
+        match self.socket.as_mut() {
+            Some(socket) => socket.recv(buf),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "no socket")),
+        }
     }
     /// 新しい要求用のシリアル番号を取得する
     /// # 返値
@@ -148,163 +531,106 @@ impl SLMPConnectionInfo {
     fn get_new_serial(&mut self) -> u16 {
         self.seq_no += 0x10;
         if self.seq_no > 0xff00 {
+            // リトライ時に減算するため0x10以上から再開する
             let rnd: u8 = rand::thread_rng().gen();
-            self.seq_no = rnd as u16;
+            self.seq_no = 0x10 + rnd as u16;
         }
         self.seq_no
     }
-    fn set_timeout(&mut self, timeout: u64) {
-        self.socket.as_mut().unwrap().set_timeout(timeout);
+    /// 受信タイムアウトを設定する
+    /// # 引数
+    /// * `timeout` - タイムアウト時間、単位はms、0でタイムアウトなし
+    pub(crate) fn set_timeout(&mut self, timeout: u64) -> Result<(), SlmpError> {
+        self.recv_timeout = timeout;
+        match self.socket.as_mut() {
+            Some(socket) => Ok(socket.set_timeout(timeout)?),
+            None if self.remote.is_some() => Ok(()),
+            None => Err(SlmpError::NotConnected),
+        }
+    }
+    /// 通信文を一つ受信する
+    pub(crate) fn recv_frame(&mut self) -> Result<SLMPFrame, SlmpError> {
+        let mut buf = [0u8; 8194];
+        loop {
+            match self.codec.decode(&self.buf) {
+                Ok(Some((size, frame))) => {
+                    self.buf.drain(..size);
+                    return Ok(frame);
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    self.buf.clear();
+                    return Err(e);
+                }
+            }
+            let recv_size = match self.read(&mut buf) {
+                Ok(recv_size) => recv_size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    let timed_out = matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    );
+                    // 3Eフレームは遅れた応答を次の要求の応答と区別できない
+                    if timed_out && self.codec.frame_type == SLMPFrameType::Frame3E {
+                        self.out_of_sync = true;
+                    }
+                    return Err(self.on_io_error(e));
+                }
+            };
+            if recv_size == 0 {
+                return Err(self.on_io_error(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                )));
+            }
+            self.buf.extend_from_slice(&buf[..recv_size]);
+        }
     }
     /// SLMPの応答を受信
     ///
-    ///  # 引数
+    /// 正常終了以外の終了コードを受信した場合は[SlmpError::EndCode]を返す
+    ///
+    /// # 返値
+    ///
+    /// シリアル番号と受信ペイロード
+    pub fn recv_cmd(&mut self) -> Result<(u16, Vec<u8>), SlmpError> {
+        let frame = self.recv_frame()?;
+        frame.into_result(self.network, self.node, self.dst_proc)
+    }
+    /// 指定したシリアル番号に対するSLMPの応答を受信
     ///
-    /// * `connection_info` - 接続情報
+    /// # 引数
+    ///
+    /// * `serial` - 要求時のシリアル番号
     ///
     /// # 返値
     ///
-    /// シリアル番号と受信ペイロードと終了コード
-    pub fn recv_cmd(&mut self) -> (u16, Vec<u8>, Option<SLMPEndCode>) {
-        let ser_no;
-        let mut req_data = Vec::new();
-        let mut end_code: Option<SLMPEndCode> = None;
-        let mut buf = [0 as u8; 8194];
-        let mut count = 0;
-        while self.buf.len() < 15 {
-            if count > 3 {
-                eprintln!("3times retry finish");
-                return (0, req_data, end_code);
-            }
-            let recv_result = self.read(&mut buf);
-            match recv_result {
-                Ok(recv_size) => self.buf.extend_from_slice(&buf[..recv_size]),
-                Err(e) => eprintln!("{}", e.to_string()),
-            }
-            count += 1;
-        }
-        match self.buf.remove(0) {
-            // odReqMT-PDU
-            0x54 => {
-                assert_eq!(self.buf[0], 0x00);
-                self.buf.remove(0);
-                let ser_low = self.buf.remove(0) as u16;
-                let ser_high = self.buf.remove(0) as u16;
-                ser_no = ser_low + (ser_high << 8);
-                // reserved
-                self.buf.remove(0);
-                self.buf.remove(0);
-                // sub_header
-                assert_eq!(self.buf[0], self.get_network());
-                self.buf.remove(0); // netNo
-                assert_eq!(self.buf[0], self.get_node());
-                self.buf.remove(0); // nodeNo
-                assert_eq!(
-                    self.buf[0] as u16 + (self.buf[1] as u16) << 8,
-                    self.get_dst_proc()
-                );
-                self.buf.remove(0);
-                self.buf.remove(0); // dstProcNo
-                assert_eq!(self.buf[0], self.get_m_drop());
-                self.buf.remove(0); // reserved1
-                let dl_low = self.buf.remove(0) as u16;
-                let dl_high = self.buf.remove(0) as u16;
-                let dl = (dl_low + (dl_high << 8)) - 6;
-                // reserved3
-                self.buf.remove(0);
-                self.buf.remove(0);
-                count = 0;
-                while self.buf.len() < (dl + 4) as usize {
-                    if count > 3 {
-                        eprintln!("3times retry finish");
-                        return (ser_no, req_data, end_code);
-                    }
-                    let recv_result = self.read(&mut buf);
-                    match recv_result {
-                        Ok(recv_size) => self.buf.extend_from_slice(&buf[..recv_size]),
-                        Err(e) => eprintln!("{}", e.to_string()),
-                    }
-                    count += 1;
-                }
-                let cmd_low = self.buf.remove(0) as u16;
-                let cmd_high = self.buf.remove(0) as u16;
-                let cmd = cmd_low + (cmd_high << 8);
-                assert_eq!(cmd, 0x2101);
-                let sub_cmd_low = self.buf.remove(0) as u16;
-                let sub_cmd_high = self.buf.remove(0) as u16;
-                let sub_cmd = sub_cmd_low + (sub_cmd_high << 8);
-                assert_eq!(sub_cmd, 0x0000);
-                loop {
-                    req_data.push(self.buf[0]);
-                    self.buf.remove(0);
-                    if req_data.len() == dl as usize {
-                        break;
-                    }
-                }
-            }
-            // rdResMT-PDU, wrResMT-PDU, rdErrMT-PDU, wrErrMT-PDU
-            0xD4 => {
-                let buf_header = [
-                    0xd4u8,
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                ];
-                let sub_header = SlmpMTHeader::from(&buf_header);
-                ser_no = sub_header.serial_no;
-                let buf_target = [
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                    self.buf.remove(0),
-                ];
-                let target = SlmpSubHeaderRes::from(&buf_target);
-                let dl = target.dl - 2;
-                end_code = SLMPEndCode::get(target.end_code);
-                assert_eq!(target.net_no, self.network);
-                assert_eq!(target.node_no, self.node);
-                assert_eq!(target.dst_proc_no, self.dst_proc);
-                count = 0;
-                while self.buf.len() < dl as usize {
-                    if count > 3 {
-                        eprintln!("3times retry finish");
-                        return (ser_no, req_data, end_code);
-                    }
-                    let recv_result = self.read(&mut buf);
-                    match recv_result {
-                        Ok(recv_size) => self.buf.extend_from_slice(&buf[..recv_size]),
-                        Err(e) => eprintln!("{}", e.to_string()),
-                    }
-                    count += 1;
-                }
-                loop {
-                    req_data.push(self.buf.remove(0));
-                    if req_data.len() == dl as usize {
-                        break;
-                    }
+    /// 受信ペイロード、異なるシリアル番号の応答を受信した場合は[SlmpError::SerialMismatch]
+    pub fn recv_response(&mut self, serial: u16) -> Result<Vec<u8>, SlmpError> {
+        match self.recv_cmd() {
+            Ok((ser_no, data)) => {
+                if ser_no != serial {
+                    return Err(SlmpError::SerialMismatch {
+                        expected: serial,
+                        actual: ser_no,
+                    });
                 }
+                Ok(data)
             }
-            // 上記以外
-            _ => {
-                eprintln!("Wrong Data received");
-                return (0, req_data, None);
+            Err(SlmpError::EndCode { serial: ser_no, .. }) if ser_no != serial => {
+                Err(SlmpError::SerialMismatch {
+                    expected: serial,
+                    actual: ser_no,
+                })
             }
-        };
-
-        (ser_no, req_data, end_code)
+            Err(e) => Err(e),
+        }
     }
     /// SLMPコマンドを送信する
     ///
     /// # 引数
     ///
-    /// * `connection_info` - SLMPの接続情報
     /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms
     /// * `cmd` - SLMPコマンド
     /// * `sub_command` - サブコマンド
@@ -319,78 +645,182 @@ impl SLMPConnectionInfo {
         cmd: SLMPCommand,
         sub_command: u16,
         content_data: &[u8],
-    ) -> Option<u16> {
+    ) -> Result<u16, SlmpError> {
+        self.send_request(timeout, &SLMPRequest::new(cmd, sub_command, content_data))
+    }
+    /// SLMPの要求を送信する
+    ///
+    /// 3Eフレームで前の要求の応答を待つ間にタイムアウトしていた場合は、
+    /// 遅れて届く応答を取り違えないよう、先にTCPを接続し直すか届いた応答を読み捨てる
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - SLMPコマンドのタイムアウト時間、単位は250ms
+    /// * `req` - 要求内容
+    ///
+    /// # 返値
+    ///
+    /// 送信した要求に対応するシリアル番号
+    pub fn send_request(&mut self, timeout: u16, req: &SLMPRequest) -> Result<u16, SlmpError> {
+        let slmp_header =
+            req.make_sub_header(self.network, self.node, self.dst_proc, self.m_drop, timeout)?;
+        if self.out_of_sync {
+            self.resync()?;
+        }
+        if req.command == SLMPCommand::EntryMonitorDevice {
+            // 応答を受け取れなくても、前の登録は上書きされている可能性がある
+            self.monitor_generation += 1;
+        }
         let mut seq_no = self.get_new_serial();
-        let slmp_header = SlmpSubHeaderReq {
-            net_no: self.network,
-            node_no: self.node,
-            dst_proc_no: self.dst_proc,
-            reserved1: self.m_drop,
-            dl: (content_data.len() + 4) as u16,
-            timer: timeout,
-        };
-        self.set_timeout((timeout as u64) * 250);
+        self.set_timeout((timeout as u64) * 250)?;
+        let mut last_err = SlmpError::NotConnected;
         // 3回までリトライ
-        for i in 0..3 {
-            seq_no -= 1;
-            let header = make_frame_header(&slmp_header, seq_no, cmd, sub_command);
-            let mut write_buf: Vec<u8> = Vec::from(header);
-            write_buf.extend_from_slice(&content_data);
-            let send_result = self.write(&write_buf);
-            match send_result {
-                Ok(send_size) => {
-                    if send_size != write_buf.len() {
-                        eprintln!("write size error.");
-                        if i == 2 {
-                            return None;
-                        }
-                    } else {
-                        break;
-                    }
+        for _ in 0..3 {
+            seq_no = seq_no.wrapping_sub(1);
+            let write_buf = self.codec.encode(&slmp_header, seq_no, req)?;
+            match self.write(&write_buf) {
+                Ok(send_size) if send_size == write_buf.len() => {
+                    self.codec.last_serial = seq_no;
+                    return Ok(seq_no);
                 }
-                Err(e) => {
-                    eprintln!("{}", e.to_string());
-                    if i == 2 {
-                        return None;
-                    }
+                Ok(_) => {
+                    last_err =
+                        SlmpError::Io(io::Error::new(io::ErrorKind::WriteZero, "write size error"))
                 }
+                Err(e) => last_err = self.on_io_error(e),
             }
         }
-        Some(seq_no)
+        Err(last_err)
     }
 }
-fn make_frame_header(
-    slmp_header: &SlmpSubHeaderReq,
-    seq_no: u16,
-    command: SLMPCommand,
-    sub_command: u16,
-) -> [u8; 19] {
-    let mut buf = [0u8; 19];
-    let pre = slmp_header.to();
-    // フレームタイプ0x0054
-    buf[0] = 0x54;
-    buf[1] = 0x00;
-    // シリアル番号
-    buf[2] = seq_no as u8;
-    buf[3] = (seq_no >> 8) as u8;
-    // 拡張用
-    buf[4] = 0x00;
-    buf[5] = 0x00;
-    // ネットワーク番号
-    // ノード番号
-    // プロセッサ番号2byte
-    // マルチドロップ
-    // データ長2byte
-    // タイマ2byte
-    for i in 0..9 {
-        buf[6 + i] = pre[i];
-    }
-    // コマンド2byte
-    let command = command as u16;
-    buf[15] = command as u8;
-    buf[16] = (command >> 8) as u8;
-    // サブコマンド2byte
-    buf[17] = sub_command as u8;
-    buf[18] = (sub_command >> 8) as u8;
-    buf
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// 受信した要求に対して指定した応答を返すUDPの相手を用意する
+    fn udp_peer(responses: Vec<Vec<u8>>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            for mut res in responses {
+                let (_, from) = socket.recv_from(&mut buf).unwrap();
+                // 要求のシリアル番号を応答へ複写
+                res[2] = buf[2];
+                res[3] = buf[3];
+                socket.send_to(&res, from).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_recv_error_end_code() {
+        let res = vec![
+            0xd4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x0b, 0x00, 0x59,
+            0xc0, 0x01, 0x01, 0xff, 0x03, 0x00, 0x19, 0x06, 0x00, 0x00,
+        ];
+        let addr = udp_peer(vec![res]);
+        let mut connection_info = SLMPConnectionInfo::new_udp(addr).unwrap();
+        let seq = connection_info
+            .send_cmd(4, SLMPCommand::SelfTest, 0, &[0, 0])
+            .unwrap();
+        match connection_info.recv_response(seq) {
+            Err(SlmpError::EndCode { serial, code, info }) => {
+                assert_eq!(serial, seq);
+                assert_eq!(code, 0xc059);
+                assert_eq!(info.unwrap().command, SLMPCommand::SelfTest as u16);
+            }
+            _ => panic!("end code error expected"),
+        }
+    }
+
+    #[test]
+    fn test_serial_wrap_around() {
+        let mut connection_info = SLMPConnectionInfo::default();
+        for _ in 0..100 {
+            connection_info.seq_no = 0xff00;
+            let serial = connection_info.get_new_serial();
+            assert!((0x10..0x110).contains(&serial));
+        }
+    }
+
+    #[test]
+    fn test_recv_unexpected_node() {
+        let res = vec![
+            0xd4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0xff, 0x03, 0x00, 0x02, 0x00, 0x00,
+            0x00,
+        ];
+        let addr = udp_peer(vec![res]);
+        let mut connection_info = SLMPConnectionInfo::new_udp(addr).unwrap();
+        connection_info
+            .send_cmd(4, SLMPCommand::ClearErrorCode, 0, &[])
+            .unwrap();
+        match connection_info.recv_cmd() {
+            Err(SlmpError::UnexpectedNode { expected, actual }) => {
+                assert_eq!(expected, 1);
+                assert_eq!(actual, 2);
+            }
+            _ => panic!("node mismatch expected"),
+        }
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            socket.local_addr().unwrap().port(),
+        );
+        let mut connection_info = SLMPConnectionInfo::new_udp(addr).unwrap();
+        connection_info
+            .send_cmd(1, SLMPCommand::ClearErrorCode, 0, &[])
+            .unwrap();
+        assert!(matches!(
+            connection_info.recv_cmd(),
+            Err(SlmpError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_new_tcp_with_policy() {
+        use std::net::TcpListener;
+        use std::time::Duration;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let policy = ReconnectPolicy {
+            connect_timeout: Duration::from_millis(500),
+            ..ReconnectPolicy::new(2)
+        };
+        let connection_info = SLMPConnectionInfo::new_tcp_with_policy(addr, policy).unwrap();
+        assert_eq!(connection_info.get_reconnect_policy(), policy);
+        assert!(listener.accept().is_ok());
+
+        // 待ち受けていない接続先は失敗する
+        drop(listener);
+        assert!(SLMPConnectionInfo::new_tcp_with_policy(addr, policy).is_err());
+    }
+
+    #[test]
+    fn test_loopback_transport() {
+        let (client, mut peer) = crate::SlmpLoopback::pair();
+        let mut connection_info = SLMPConnectionInfo::from_transport(client);
+        let seq = connection_info
+            .send_cmd(4, SLMPCommand::ClearErrorCode, 0, &[])
+            .unwrap();
+        let mut buf = [0u8; 64];
+        let size = peer.recv(&mut buf).unwrap();
+        assert_eq!(size, 19);
+        assert_eq!(&buf[2..4], &seq.to_le_bytes());
+        let mut res = vec![
+            0xd4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0xff, 0x03, 0x00, 0x02, 0x00, 0x00,
+            0x00,
+        ];
+        res[2..4].copy_from_slice(&seq.to_le_bytes());
+        peer.send(&res).unwrap();
+        assert!(connection_info.recv_response(seq).unwrap().is_empty());
+    }
 }
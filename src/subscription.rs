@@ -0,0 +1,561 @@
+use crate::tag::{decode_tags, tags_read_plan};
+use crate::{
+    SLMPDevice, SlmpAccessWidth, SlmpClient, SlmpError, SlmpReadPlan, SlmpTag, SlmpTagDatabase,
+    SlmpTagType, SlmpTagValue,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+/// 購読の識別子
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SlmpSubscriptionId(pub u64);
+
+/// 値の変化の通知
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlmpChange {
+    /// 購読の識別子
+    pub id: SlmpSubscriptionId,
+    /// タグ名、デバイスを購読した場合はデバイス名
+    pub name: String,
+    /// 新しい値
+    pub value: SlmpTagValue,
+    /// 前回通知した値、初回は`None`
+    pub previous: Option<SlmpTagValue>,
+}
+
+/// 購読の通知
+#[derive(Clone, Debug)]
+pub enum SlmpPollEvent {
+    /// 値が変化した
+    Change(SlmpChange),
+    /// 読出しに失敗した、同じ周期の購読それぞれに通知する
+    Error {
+        /// 購読の識別子
+        id: SlmpSubscriptionId,
+        /// タグ名、デバイスを購読した場合はデバイス名
+        name: String,
+        /// 読出し時のエラー
+        error: Arc<SlmpError>,
+    },
+}
+
+/// 購読の通知を受け取るコールバック
+pub type SlmpPollCallback = Box<dyn FnMut(&SlmpPollEvent) + Send>;
+
+/// 通知先
+enum Sink {
+    Callback(SlmpPollCallback),
+    Channel(mpsc::Sender<SlmpPollEvent>),
+}
+
+/// 一つの購読
+struct Subscription {
+    id: SlmpSubscriptionId,
+    tag: SlmpTag,
+    deadband: f64,
+    last: Option<SlmpTagValue>,
+    /// 前回の読出しに失敗したか、復旧後は変化がなくても通知する
+    failed: bool,
+    sink: Sink,
+}
+
+impl Subscription {
+    /// 通知する
+    ///
+    /// # 返値
+    ///
+    /// 通知先のチャネルが閉じていなければ`true`
+    fn notify(&mut self, event: SlmpPollEvent) -> bool {
+        match &mut self.sink {
+            Sink::Callback(f) => {
+                f(&event);
+                true
+            }
+            Sink::Channel(tx) => tx.send(event).is_ok(),
+        }
+    }
+}
+
+/// 同じ周期で読み出す購読の集まり
+struct PollGroup {
+    interval: Duration,
+    next_due: Instant,
+    subscriptions: Vec<Subscription>,
+    /// 購読が変わったら作り直す
+    plan: Option<SlmpReadPlan>,
+}
+
+/// 値が変化したか
+///
+/// # 引数
+///
+/// * `old` - 前回通知した値
+/// * `new` - 新しい値
+/// * `deadband` - 数値の変化を無視する幅
+fn is_changed(old: &SlmpTagValue, new: &SlmpTagValue, deadband: f64) -> bool {
+    match (old, new) {
+        (SlmpTagValue::Array(old), SlmpTagValue::Array(new)) => {
+            old.len() != new.len()
+                || old
+                    .iter()
+                    .zip(new.iter())
+                    .any(|(o, n)| is_changed(o, n, deadband))
+        }
+        _ => match (old.as_f64(), new.as_f64()) {
+            (Some(o), Some(n)) if deadband > 0.0 => (n - o).abs() > deadband,
+            _ => old != new,
+        },
+    }
+}
+
+/// 周期的にデバイスを読み出し、値が変化した場合に通知する
+///
+/// 同じ周期の購読は一つの読出し計画にまとめ、複数ブロック一括読出しとランダム読出しで読み出す
+///
+/// # 例
+///
+/// ```no_run
+/// use slmp_client::*;
+/// use std::time::Duration;
+///
+/// let connection_info = SLMPConnectionInfo::new_tcp("192.168.1.10:5000".parse().unwrap()).unwrap();
+/// let mut poller = SlmpPoller::new(SlmpClient::new(connection_info));
+/// let (_, rx) = poller
+///     .subscribe_channel(
+///         SlmpPoller::device_tag("D100".parse().unwrap(), SlmpAccessWidth::Word),
+///         Duration::from_millis(100),
+///         0.0,
+///     )
+///     .unwrap();
+/// loop {
+///     let wait = poller.poll();
+///     for event in rx.try_iter() {
+///         match event {
+///             SlmpPollEvent::Change(change) => println!("{} = {:?}", change.name, change.value),
+///             SlmpPollEvent::Error { name, error, .. } => println!("{}: {}", name, error),
+///         }
+///     }
+///     std::thread::sleep(wait);
+/// }
+/// ```
+pub struct SlmpPoller {
+    client: SlmpClient,
+    groups: Vec<PollGroup>,
+    next_id: u64,
+    max_gap: usize,
+}
+
+impl SlmpPoller {
+    /// 作成
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読出しに使うクライアント
+    pub fn new(client: SlmpClient) -> SlmpPoller {
+        SlmpPoller {
+            client,
+            groups: Vec::new(),
+            next_id: 0,
+            max_gap: 4,
+        }
+    }
+    /// 読出しに使うクライアント
+    pub fn client(&self) -> &SlmpClient {
+        &self.client
+    }
+    /// 読出しに使うクライアント、接続の設定を変える場合などに使う
+    pub fn client_mut(&mut self) -> &mut SlmpClient {
+        &mut self.client
+    }
+    /// 購読を破棄してクライアントを取り出す
+    pub fn into_inner(self) -> SlmpClient {
+        self.client
+    }
+    /// 同じブロックにまとめるデバイスの間隔の上限(ワード単位)
+    pub fn get_max_gap(&self) -> usize {
+        self.max_gap
+    }
+    /// 同じブロックにまとめるデバイスの間隔の上限を設定する
+    ///
+    /// 購読中のグループの読出し計画は次の読出しで作り直す
+    ///
+    /// # 引数
+    ///
+    /// * `max_gap` - 間隔の上限(ワード単位)
+    pub fn set_max_gap(&mut self, max_gap: usize) {
+        self.max_gap = max_gap;
+        for group in self.groups.iter_mut() {
+            group.plan = None;
+        }
+    }
+    /// デバイスを購読するためのタグを作成
+    ///
+    /// ビットは`Bit`、ワードは`U16`、ダブルワードは`U32`のタグとなる
+    ///
+    /// # 引数
+    ///
+    /// * `dev` - デバイス
+    /// * `width` - アクセス単位
+    pub fn device_tag(dev: SLMPDevice, width: SlmpAccessWidth) -> SlmpTag {
+        let data_type = match width {
+            SlmpAccessWidth::Bit => SlmpTagType::Bit,
+            SlmpAccessWidth::Word => SlmpTagType::U16,
+            SlmpAccessWidth::DWord => SlmpTagType::U32,
+        };
+        SlmpTag::new(&dev.to_string(), dev, data_type)
+    }
+    /// タグ定義にあるタグを購読するためのタグを得る
+    ///
+    /// # 引数
+    ///
+    /// * `db` - タグ定義
+    /// * `name` - タグ名
+    pub fn database_tag(db: &SlmpTagDatabase, name: &str) -> Result<SlmpTag, SlmpError> {
+        db.get(name)
+            .cloned()
+            .ok_or(SlmpError::InvalidArgument("unknown tag"))
+    }
+    /// 購読を追加
+    fn add(
+        &mut self,
+        tag: SlmpTag,
+        interval: Duration,
+        deadband: f64,
+        sink: Sink,
+    ) -> Result<SlmpSubscriptionId, SlmpError> {
+        if interval.is_zero() {
+            return Err(SlmpError::InvalidArgument("zero interval"));
+        }
+        tag.validate().map_err(SlmpError::InvalidArgument)?;
+        let id = SlmpSubscriptionId(self.next_id);
+        self.next_id += 1;
+        let subscription = Subscription {
+            id,
+            tag,
+            deadband,
+            last: None,
+            failed: false,
+            sink,
+        };
+        match self.groups.iter_mut().find(|g| g.interval == interval) {
+            Some(group) => {
+                group.subscriptions.push(subscription);
+                group.plan = None;
+            }
+            None => self.groups.push(PollGroup {
+                interval,
+                next_due: Instant::now(),
+                subscriptions: vec![subscription],
+                plan: None,
+            }),
+        }
+        Ok(id)
+    }
+    /// 変化をコールバックで受け取る購読を追加
+    ///
+    /// # 引数
+    ///
+    /// * `tag` - 購読するタグ
+    /// * `interval` - 読出しの周期
+    /// * `deadband` - 数値の変化を無視する幅、0であれば全ての変化を通知する
+    /// * `callback` - 値が変化した際、読出しに失敗した際に呼ばれるコールバック
+    pub fn subscribe<F>(
+        &mut self,
+        tag: SlmpTag,
+        interval: Duration,
+        deadband: f64,
+        callback: F,
+    ) -> Result<SlmpSubscriptionId, SlmpError>
+    where
+        F: FnMut(&SlmpPollEvent) + Send + 'static,
+    {
+        self.add(tag, interval, deadband, Sink::Callback(Box::new(callback)))
+    }
+    /// 変化をチャネルで受け取る購読を追加
+    ///
+    /// 受信側を破棄すると購読を解除する
+    ///
+    /// # 引数
+    ///
+    /// * `tag` - 購読するタグ
+    /// * `interval` - 読出しの周期
+    /// * `deadband` - 数値の変化を無視する幅、0であれば全ての変化を通知する
+    pub fn subscribe_channel(
+        &mut self,
+        tag: SlmpTag,
+        interval: Duration,
+        deadband: f64,
+    ) -> Result<(SlmpSubscriptionId, mpsc::Receiver<SlmpPollEvent>), SlmpError> {
+        let (tx, rx) = mpsc::channel();
+        let id = self.add(tag, interval, deadband, Sink::Channel(tx))?;
+        Ok((id, rx))
+    }
+    /// 購読を解除
+    ///
+    /// # 返値
+    ///
+    /// 該当する購読があったか
+    pub fn unsubscribe(&mut self, id: SlmpSubscriptionId) -> bool {
+        let mut found = false;
+        for group in self.groups.iter_mut() {
+            let len = group.subscriptions.len();
+            group.subscriptions.retain(|s| s.id != id);
+            if group.subscriptions.len() != len {
+                group.plan = None;
+                found = true;
+            }
+        }
+        self.groups.retain(|g| !g.subscriptions.is_empty());
+        found
+    }
+    /// 周期が来た購読のデバイスを読み出し、変化を通知する
+    ///
+    /// 読出しに失敗した場合は同じ周期の購読にエラーを通知し、他の周期の読出しを続ける
+    ///
+    /// # 返値
+    ///
+    /// 次の周期までの時間
+    pub fn poll(&mut self) -> Duration {
+        let now = Instant::now();
+        for group in self.groups.iter_mut() {
+            if group.next_due > now {
+                continue;
+            }
+            group.next_due += group.interval;
+            if group.next_due <= now {
+                // 遅れた分は読み飛ばす
+                group.next_due = now + group.interval;
+            }
+            let mut closed = Vec::new();
+            match read_group(&mut self.client, group, self.max_gap) {
+                Ok(values) => {
+                    for (subscription, value) in group.subscriptions.iter_mut().zip(values) {
+                        let changed = match &subscription.last {
+                            Some(last) => {
+                                subscription.failed
+                                    || is_changed(last, &value, subscription.deadband)
+                            }
+                            None => true,
+                        };
+                        subscription.failed = false;
+                        if !changed {
+                            continue;
+                        }
+                        let change = SlmpChange {
+                            id: subscription.id,
+                            name: subscription.tag.name.clone(),
+                            value: value.clone(),
+                            previous: subscription.last.replace(value),
+                        };
+                        if !subscription.notify(SlmpPollEvent::Change(change)) {
+                            closed.push(subscription.id);
+                        }
+                    }
+                }
+                Err(e) => {
+                    let error = Arc::new(e);
+                    for subscription in group.subscriptions.iter_mut() {
+                        subscription.failed = true;
+                        let event = SlmpPollEvent::Error {
+                            id: subscription.id,
+                            name: subscription.tag.name.clone(),
+                            error: error.clone(),
+                        };
+                        if !subscription.notify(event) {
+                            closed.push(subscription.id);
+                        }
+                    }
+                }
+            }
+            if !closed.is_empty() {
+                group.subscriptions.retain(|s| !closed.contains(&s.id));
+                group.plan = None;
+            }
+        }
+        self.groups.retain(|g| !g.subscriptions.is_empty());
+        let now = Instant::now();
+        self.groups
+            .iter()
+            .map(|g| g.next_due.saturating_duration_since(now))
+            .min()
+            .unwrap_or(Duration::from_millis(100))
+    }
+    /// 停止が指示されるまで周期的に読み出す
+    ///
+    /// 読出しのエラーは購読に通知し、読出しを続ける
+    ///
+    /// # 引数
+    ///
+    /// * `stop` - trueにすると停止する
+    pub fn run(&mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            let wait = self.poll();
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// 同じ周期の購読のデバイスを読み出す
+///
+/// # 引数
+///
+/// * `client` - 読出しに使うクライアント
+/// * `group` - 読み出す購読の集まり
+/// * `max_gap` - 同じブロックにまとめるデバイスの間隔の上限
+fn read_group(
+    client: &mut SlmpClient,
+    group: &mut PollGroup,
+    max_gap: usize,
+) -> Result<Vec<SlmpTagValue>, SlmpError> {
+    let tags: Vec<&SlmpTag> = group.subscriptions.iter().map(|s| &s.tag).collect();
+    let plan = match group.plan.take() {
+        Some(plan) => plan,
+        None => tags_read_plan(&tags, max_gap)?,
+    };
+    let ret = plan.execute(client);
+    group.plan = Some(plan);
+    decode_tags(&tags, ret?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SLMPConnectionInfo, SlmpServer};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+
+    fn dev(s: &str) -> SLMPDevice {
+        s.parse().unwrap()
+    }
+
+    fn expect_change(event: SlmpPollEvent) -> SlmpChange {
+        match event {
+            SlmpPollEvent::Change(change) => change,
+            SlmpPollEvent::Error { error, .. } => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_is_changed() {
+        let a = SlmpTagValue::F64(10.0);
+        assert!(!is_changed(&a, &SlmpTagValue::F64(10.4), 0.5));
+        assert!(is_changed(&a, &SlmpTagValue::F64(10.6), 0.5));
+        assert!(is_changed(&a, &SlmpTagValue::F64(10.1), 0.0));
+        assert!(is_changed(
+            &SlmpTagValue::Bit(false),
+            &SlmpTagValue::Bit(true),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn test_poll_changes() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut poller = SlmpPoller::new(SlmpClient::new(connection_info));
+        let interval = Duration::from_millis(10);
+        let (id, rx) = poller
+            .subscribe_channel(
+                SlmpPoller::device_tag(dev("D10"), SlmpAccessWidth::Word),
+                interval,
+                5.0,
+            )
+            .unwrap();
+        let bits = Arc::new(Mutex::new(Vec::new()));
+        let log = bits.clone();
+        poller
+            .subscribe(
+                SlmpPoller::device_tag(dev("M3"), SlmpAccessWidth::Bit),
+                interval,
+                0.0,
+                move |event| log.lock().unwrap().push(expect_change(event.clone()).value),
+            )
+            .unwrap();
+        // 初回は必ず通知する
+        poller.poll();
+        let change = expect_change(rx.try_recv().unwrap());
+        assert_eq!(change.id, id);
+        assert_eq!(change.name, "D10");
+        assert_eq!(change.value, SlmpTagValue::U16(0));
+        assert_eq!(change.previous, None);
+
+        // 不感帯以内の変化は通知しない
+        server.state().devices.set_word(dev("D10"), 3);
+        std::thread::sleep(interval);
+        poller.poll();
+        assert!(rx.try_recv().is_err());
+
+        server.state().devices.set_word(dev("D10"), 8);
+        server.state().devices.set_bit(dev("M3"), true);
+        std::thread::sleep(interval);
+        poller.poll();
+        let change = expect_change(rx.try_recv().unwrap());
+        assert_eq!(change.value, SlmpTagValue::U16(8));
+        assert_eq!(change.previous, Some(SlmpTagValue::U16(0)));
+        assert_eq!(
+            *bits.lock().unwrap(),
+            vec![SlmpTagValue::Bit(false), SlmpTagValue::Bit(true)]
+        );
+
+        // 受信側を破棄すると購読を解除する
+        drop(rx);
+        server.state().devices.set_word(dev("D10"), 100);
+        std::thread::sleep(interval);
+        poller.poll();
+        assert!(!poller.unsubscribe(id));
+    }
+    #[test]
+    fn test_poll_error() {
+        use crate::RetryPolicy;
+
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        client.set_timeout(1);
+        client.set_retry_policy(RetryPolicy {
+            max_retries: 0,
+            retry_on_timeout: false,
+        });
+        let mut poller = SlmpPoller::new(client);
+        let (fast, fast_rx) = poller
+            .subscribe_channel(
+                SlmpPoller::device_tag(dev("D10"), SlmpAccessWidth::Word),
+                Duration::from_millis(10),
+                0.0,
+            )
+            .unwrap();
+        let (_, slow_rx) = poller
+            .subscribe_channel(
+                SlmpPoller::device_tag(dev("D20"), SlmpAccessWidth::Word),
+                Duration::from_millis(11),
+                0.0,
+            )
+            .unwrap();
+
+        // 最初の周期の読出しだけ応答しない
+        server.state().devices.set_word(dev("D20"), 5);
+        server.state().drop_requests = 1;
+        poller.poll();
+        match fast_rx.try_recv().unwrap() {
+            SlmpPollEvent::Error { id, error, .. } => {
+                assert_eq!(id, fast);
+                assert!(matches!(*error, SlmpError::Timeout));
+            }
+            e => panic!("unexpected event: {:?}", e),
+        }
+        assert_eq!(
+            expect_change(slow_rx.try_recv().unwrap()).value,
+            SlmpTagValue::U16(5)
+        );
+
+        // 復旧後は値が変化していなくても通知する
+        std::thread::sleep(Duration::from_millis(20));
+        poller.poll();
+        let recovered = expect_change(fast_rx.try_recv().unwrap());
+        assert_eq!(recovered.id, fast);
+        assert_eq!(recovered.value, SlmpTagValue::U16(0));
+    }
+}
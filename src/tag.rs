@@ -0,0 +1,1092 @@
+use crate::split::offset_device;
+use crate::{
+    decode_string, decode_values, encode_string, encode_values, SLMPDevice, SLMPDeviceData,
+    SlmpAccessWidth, SlmpBcd16, SlmpBcd32, SlmpClient, SlmpCodec, SlmpDeviceParseError, SlmpError,
+    SlmpRawValue, SlmpReadPlan, SlmpStringTermination, SlmpWordOrder,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// タグのデータ型
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SlmpTagType {
+    /// ビット、ビットデバイスのみ
+    Bit,
+    /// 符号なし16bit整数
+    U16,
+    /// 符号付き16bit整数
+    I16,
+    /// 符号なし32bit整数
+    U32,
+    /// 符号付き32bit整数
+    I32,
+    /// 単精度実数
+    F32,
+    /// 倍精度実数
+    F64,
+    /// BCD4桁
+    Bcd16,
+    /// BCD8桁
+    Bcd32,
+    /// NUL終端の文字列
+    String,
+}
+
+impl SlmpTagType {
+    /// データ型の名前と別名
+    const NAMES: [(&'static str, SlmpTagType); 22] = [
+        ("BOOL", SlmpTagType::Bit),
+        ("BIT", SlmpTagType::Bit),
+        ("UINT", SlmpTagType::U16),
+        ("WORD", SlmpTagType::U16),
+        ("U16", SlmpTagType::U16),
+        ("INT", SlmpTagType::I16),
+        ("I16", SlmpTagType::I16),
+        ("UDINT", SlmpTagType::U32),
+        ("DWORD", SlmpTagType::U32),
+        ("U32", SlmpTagType::U32),
+        ("DINT", SlmpTagType::I32),
+        ("I32", SlmpTagType::I32),
+        ("REAL", SlmpTagType::F32),
+        ("FLOAT", SlmpTagType::F32),
+        ("F32", SlmpTagType::F32),
+        ("LREAL", SlmpTagType::F64),
+        ("DOUBLE", SlmpTagType::F64),
+        ("F64", SlmpTagType::F64),
+        ("BCD", SlmpTagType::Bcd16),
+        ("BCD16", SlmpTagType::Bcd16),
+        ("BCD32", SlmpTagType::Bcd32),
+        ("STRING", SlmpTagType::String),
+    ];
+    /// 一つの値が占めるワード数、ビットは0
+    pub fn words(&self) -> usize {
+        match self {
+            SlmpTagType::Bit => 0,
+            SlmpTagType::U16 | SlmpTagType::I16 | SlmpTagType::Bcd16 => 1,
+            SlmpTagType::U32 | SlmpTagType::I32 | SlmpTagType::F32 | SlmpTagType::Bcd32 => 2,
+            SlmpTagType::F64 => 4,
+            SlmpTagType::String => 1,
+        }
+    }
+    /// 数値を表すデータ型か
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, SlmpTagType::Bit | SlmpTagType::String)
+    }
+}
+
+impl FromStr for SlmpTagType {
+    type Err = SlmpTagError;
+
+    /// IEC 61131-3の型名または別名から作成、大文字小文字は区別しない
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_ascii_uppercase();
+        SlmpTagType::NAMES
+            .iter()
+            .find(|(name, _)| *name == upper)
+            .map(|(_, t)| *t)
+            .ok_or_else(|| SlmpTagError::UnknownType(s.to_string()))
+    }
+}
+
+impl fmt::Display for SlmpTagType {
+    /// IEC 61131-3の型名
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SlmpTagType::Bit => "BOOL",
+            SlmpTagType::U16 => "UINT",
+            SlmpTagType::I16 => "INT",
+            SlmpTagType::U32 => "UDINT",
+            SlmpTagType::I32 => "DINT",
+            SlmpTagType::F32 => "REAL",
+            SlmpTagType::F64 => "LREAL",
+            SlmpTagType::Bcd16 => "BCD16",
+            SlmpTagType::Bcd32 => "BCD32",
+            SlmpTagType::String => "STRING",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// タグの値
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlmpTagValue {
+    /// ビット
+    Bit(bool),
+    /// 符号なし16bit整数、BCD4桁のタグの値もこれになる
+    U16(u16),
+    /// 符号付き16bit整数
+    I16(i16),
+    /// 符号なし32bit整数、BCD8桁のタグの値もこれになる
+    U32(u32),
+    /// 符号付き32bit整数
+    I32(i32),
+    /// 単精度実数
+    F32(f32),
+    /// 倍精度実数、スケーリングを指定したタグの値もこれになる
+    F64(f64),
+    /// 文字列
+    String(String),
+    /// 長さが2以上のタグの値
+    Array(Vec<SlmpTagValue>),
+}
+
+impl SlmpTagValue {
+    /// 数値であれば倍精度実数として得る
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SlmpTagValue::U16(v) => Some(*v as f64),
+            SlmpTagValue::I16(v) => Some(*v as f64),
+            SlmpTagValue::U32(v) => Some(*v as f64),
+            SlmpTagValue::I32(v) => Some(*v as f64),
+            SlmpTagValue::F32(v) => Some(*v as f64),
+            SlmpTagValue::F64(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+/// タグの定義
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlmpTag {
+    /// タグ名
+    pub name: String,
+    /// 先頭デバイス
+    pub device: SLMPDevice,
+    /// データ型
+    pub data_type: SlmpTagType,
+    /// 要素数、文字列では終端を除く最大文字数
+    pub length: usize,
+    /// 読み取った値に掛ける倍率
+    pub scale: f64,
+    /// 倍率を掛けた後に加える値
+    pub offset: f64,
+    /// 読み取り専用か
+    pub read_only: bool,
+}
+
+impl SlmpTag {
+    /// 長さ1、スケーリングなしのタグを作成
+    ///
+    /// # 引数
+    ///
+    /// * `name` - タグ名
+    /// * `device` - 先頭デバイス
+    /// * `data_type` - データ型
+    pub fn new(name: &str, device: SLMPDevice, data_type: SlmpTagType) -> SlmpTag {
+        SlmpTag {
+            name: name.to_string(),
+            device,
+            data_type,
+            length: 1,
+            scale: 1.0,
+            offset: 0.0,
+            read_only: false,
+        }
+    }
+    /// スケーリングを指定しているか
+    pub fn is_scaled(&self) -> bool {
+        self.scale != 1.0 || self.offset != 0.0
+    }
+    /// タグが占めるワード数、ビットのタグは0
+    pub fn words(&self) -> usize {
+        match self.data_type {
+            SlmpTagType::String => self.length / 2 + 1,
+            t => t.words() * self.length,
+        }
+    }
+    /// 定義が正しいか確認
+    pub(crate) fn validate(&self) -> Result<(), &'static str> {
+        if self.name.trim().is_empty() {
+            return Err("empty tag name");
+        }
+        if self.length == 0 {
+            return Err("zero length");
+        }
+        if self.words() > 0xffff || self.length > 0xffff {
+            return Err("too long");
+        }
+        if self.data_type == SlmpTagType::Bit && !self.device.d_code.is_bit() {
+            return Err("bit tag on word device");
+        }
+        if !self.scale.is_finite() || !self.offset.is_finite() || self.scale == 0.0 {
+            return Err("invalid scaling");
+        }
+        if self.is_scaled() && !self.data_type.is_numeric() {
+            return Err("scaling on non-numeric tag");
+        }
+        Ok(())
+    }
+    /// 読み取りに使うデバイスとアクセス単位、ワードは2点ずつダブルワードにまとめる
+    pub(crate) fn read_targets(&self) -> Vec<(SLMPDevice, SlmpAccessWidth)> {
+        if self.data_type == SlmpTagType::Bit {
+            return (0..self.length as u32)
+                .map(|i| {
+                    let dev = SLMPDevice {
+                        d_code: self.device.d_code,
+                        addr: self.device.addr.wrapping_add(i),
+                    };
+                    (dev, SlmpAccessWidth::Bit)
+                })
+                .collect();
+        }
+        let words = self.words();
+        (0..words)
+            .step_by(2)
+            .map(|i| {
+                let width = if i + 1 < words {
+                    SlmpAccessWidth::DWord
+                } else {
+                    SlmpAccessWidth::Word
+                };
+                (offset_device(self.device, i), width)
+            })
+            .collect()
+    }
+    /// 読み取ったワードまたはビットを値にする
+    ///
+    /// # 引数
+    ///
+    /// * `words` - タグが占めるワード
+    /// * `bits` - ビットのタグの値
+    pub(crate) fn decode(&self, words: &[u16], bits: &[bool]) -> Result<SlmpTagValue, SlmpError> {
+        let order = SlmpWordOrder::LowFirst;
+        let mut values: Vec<SlmpTagValue> = match self.data_type {
+            SlmpTagType::Bit => bits.iter().map(|b| SlmpTagValue::Bit(*b)).collect(),
+            SlmpTagType::String => {
+                return Ok(SlmpTagValue::String(decode_string(
+                    words,
+                    SlmpStringTermination::Nul,
+                )?))
+            }
+            SlmpTagType::U16 => decode_map(words, order, SlmpTagValue::U16)?,
+            SlmpTagType::I16 => decode_map(words, order, SlmpTagValue::I16)?,
+            SlmpTagType::U32 => decode_map(words, order, SlmpTagValue::U32)?,
+            SlmpTagType::I32 => decode_map(words, order, SlmpTagValue::I32)?,
+            SlmpTagType::F32 => decode_map(words, order, SlmpTagValue::F32)?,
+            SlmpTagType::F64 => decode_map(words, order, SlmpTagValue::F64)?,
+            SlmpTagType::Bcd16 => decode_map(words, order, |v: SlmpBcd16| SlmpTagValue::U16(v.0))?,
+            SlmpTagType::Bcd32 => decode_map(words, order, |v: SlmpBcd32| SlmpTagValue::U32(v.0))?,
+        };
+        if self.is_scaled() {
+            for v in values.iter_mut() {
+                *v = SlmpTagValue::F64(v.as_f64().unwrap() * self.scale + self.offset);
+            }
+        }
+        Ok(self.collect(values))
+    }
+    /// 長さに応じて値をまとめる
+    fn collect(&self, mut values: Vec<SlmpTagValue>) -> SlmpTagValue {
+        if self.length == 1 {
+            values.remove(0)
+        } else {
+            SlmpTagValue::Array(values)
+        }
+    }
+    /// 書き込む値をワードにする
+    ///
+    /// # 引数
+    ///
+    /// * `value` - 書き込む値
+    fn encode(&self, value: &SlmpTagValue) -> Result<Vec<u16>, SlmpError> {
+        let order = SlmpWordOrder::LowFirst;
+        if let SlmpTagValue::String(s) = value {
+            if self.data_type != SlmpTagType::String {
+                return Err(SlmpError::InvalidArgument("type mismatch"));
+            }
+            if s.len() > self.length {
+                return Err(SlmpError::InvalidArgument("too long string"));
+            }
+            return encode_string(s, self.words(), SlmpStringTermination::Nul);
+        }
+        let values = self.elements(value)?;
+        let mut raw = Vec::with_capacity(values.len());
+        for v in values.iter() {
+            let v = v
+                .as_f64()
+                .ok_or(SlmpError::InvalidArgument("type mismatch"))?;
+            raw.push((v - self.offset) / self.scale);
+        }
+        match self.data_type {
+            SlmpTagType::U16 => encode_map(&raw, 0.0, u16::MAX as f64, |v| v as u16),
+            SlmpTagType::I16 => encode_map(&raw, i16::MIN as f64, i16::MAX as f64, |v| v as i16),
+            SlmpTagType::U32 => encode_map(&raw, 0.0, u32::MAX as f64, |v| v as u32),
+            SlmpTagType::I32 => encode_map(&raw, i32::MIN as f64, i32::MAX as f64, |v| v as i32),
+            SlmpTagType::Bcd16 => encode_map(&raw, 0.0, 9999.0, |v| SlmpBcd16(v as u16)),
+            SlmpTagType::Bcd32 => encode_map(&raw, 0.0, 99999999.0, |v| SlmpBcd32(v as u32)),
+            SlmpTagType::F32 => {
+                let values: Vec<f32> = raw.iter().map(|v| *v as f32).collect();
+                encode_values(&values, order)
+            }
+            SlmpTagType::F64 => encode_values(&raw, order),
+            SlmpTagType::Bit | SlmpTagType::String => {
+                Err(SlmpError::InvalidArgument("type mismatch"))
+            }
+        }
+    }
+    /// 書き込む値を要素ごとに分け、要素数を確認
+    fn elements<'a>(&self, value: &'a SlmpTagValue) -> Result<Vec<&'a SlmpTagValue>, SlmpError> {
+        let values: Vec<&SlmpTagValue> = match value {
+            SlmpTagValue::Array(values) => values.iter().collect(),
+            v => vec![v],
+        };
+        if values.len() != self.length {
+            return Err(SlmpError::InvalidArgument("wrong number of elements"));
+        }
+        Ok(values)
+    }
+}
+
+/// タグのリストの読出し計画を作成
+///
+/// # 引数
+///
+/// * `tags` - タグのリスト
+/// * `max_gap` - 同じブロックにまとめるデバイスの間隔の上限(ワード単位)
+pub(crate) fn tags_read_plan(tags: &[&SlmpTag], max_gap: usize) -> Result<SlmpReadPlan, SlmpError> {
+    let mut targets = Vec::new();
+    for tag in tags.iter() {
+        targets.extend(tag.read_targets());
+    }
+    SlmpReadPlan::new(&targets, max_gap)
+}
+
+/// 読出し計画の結果をタグの値にする
+///
+/// # 引数
+///
+/// * `tags` - 読出し計画を作成したタグのリスト
+/// * `ret` - 読出し計画の結果
+pub(crate) fn decode_tags(
+    tags: &[&SlmpTag],
+    ret: Vec<SLMPDeviceData<SlmpRawValue>>,
+) -> Result<Vec<SlmpTagValue>, SlmpError> {
+    let mut ret = ret.into_iter();
+    let mut values = Vec::with_capacity(tags.len());
+    for tag in tags.iter() {
+        let mut words = Vec::new();
+        let mut bits = Vec::new();
+        for _ in 0..tag.read_targets().len() {
+            match ret.next().map(|d| d.value) {
+                Some(SlmpRawValue::Bit(b)) => bits.push(b),
+                Some(SlmpRawValue::Word(w)) => words.push(w),
+                Some(SlmpRawValue::DWord(d)) => {
+                    words.extend_from_slice(&[d as u16, (d >> 16) as u16])
+                }
+                None => return Err(SlmpError::MalformedFrame("missing value")),
+            }
+        }
+        values.push(tag.decode(&words, &bits)?);
+    }
+    Ok(values)
+}
+
+/// ワードを値のリストにする
+fn decode_map<T: SlmpCodec>(
+    words: &[u16],
+    order: SlmpWordOrder,
+    f: impl Fn(T) -> SlmpTagValue,
+) -> Result<Vec<SlmpTagValue>, SlmpError> {
+    Ok(decode_values::<T>(words, order)?
+        .into_iter()
+        .map(f)
+        .collect())
+}
+
+/// 値を丸めて範囲を確認し、ワードにする
+///
+/// # 引数
+///
+/// * `raw` - スケーリングを戻した値
+/// * `min` - 最小値
+/// * `max` - 最大値
+/// * `f` - 整数型への変換
+fn encode_map<T: SlmpCodec>(
+    raw: &[f64],
+    min: f64,
+    max: f64,
+    f: impl Fn(f64) -> T,
+) -> Result<Vec<u16>, SlmpError> {
+    let mut values = Vec::with_capacity(raw.len());
+    for v in raw.iter() {
+        let v = v.round();
+        if !(min..=max).contains(&v) {
+            return Err(SlmpError::InvalidArgument("value out of range"));
+        }
+        values.push(f(v));
+    }
+    encode_values(&values, SlmpWordOrder::LowFirst)
+}
+
+/// タグ定義の読み込みに失敗した
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlmpTagError {
+    /// 定義ファイルの書式が不正
+    Syntax {
+        /// 行番号、不明であれば`None`
+        line: Option<usize>,
+        /// 内容
+        message: String,
+    },
+    /// 該当するデータ型がない
+    UnknownType(String),
+    /// デバイス名が不正
+    Device {
+        /// タグ名
+        name: String,
+        /// デバイス名の解釈のエラー
+        error: SlmpDeviceParseError,
+    },
+    /// タグの定義が不正
+    InvalidTag {
+        /// タグ名
+        name: String,
+        /// 理由
+        reason: &'static str,
+    },
+    /// タグ名が重複している
+    DuplicateName(String),
+    /// 定義ファイルの指定した行でのエラー
+    Line {
+        /// 行番号
+        line: usize,
+        /// 内容
+        error: Box<SlmpTagError>,
+    },
+}
+
+impl fmt::Display for SlmpTagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlmpTagError::Syntax {
+                line: Some(line),
+                message,
+            } => write!(f, "line {}: {}", line, message),
+            SlmpTagError::Syntax {
+                line: None,
+                message,
+            } => write!(f, "{}", message),
+            SlmpTagError::UnknownType(s) => write!(f, "unknown data type: {}", s),
+            SlmpTagError::Device { name, error } => write!(f, "tag {}: {}", name, error),
+            SlmpTagError::InvalidTag { name, reason } => write!(f, "tag {}: {}", name, reason),
+            SlmpTagError::DuplicateName(name) => write!(f, "duplicate tag name: {}", name),
+            SlmpTagError::Line { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for SlmpTagError {}
+
+/// タグ名でデバイスを読み書きするためのタグ定義の集まり
+///
+/// CSVの各行は`タグ名,デバイス,データ型,長さ,倍率,オフセット,読み取り専用`とし、
+/// 長さ以降は省略できる。先頭が`name`の行は見出しとして、`#`で始まる行はコメントとして読み飛ばす。
+/// `,`を含む項目は`"`で囲み、項目中の`"`は`""`と記述する
+///
+/// # 例
+///
+/// ```
+/// use slmp_client::*;
+/// let csv = "name,device,type,length,scale,offset,read_only
+/// Line1.Speed,D1200,INT,1,0.1
+/// Line1.Running,M100,BOOL
+/// Line1.Recipe,D1300,STRING,20,,,true
+/// ";
+/// let db = SlmpTagDatabase::from_csv(csv).unwrap();
+/// let tag = db.get("Line1.Speed").unwrap();
+/// assert_eq!(tag.device, "D1200".parse().unwrap());
+/// assert_eq!(tag.data_type, SlmpTagType::I16);
+/// assert!(db.get("Line1.Recipe").unwrap().read_only);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SlmpTagDatabase {
+    tags: Vec<SlmpTag>,
+    index: HashMap<String, usize>,
+    max_gap: usize,
+}
+
+/// 省略可能な項目を解釈する
+fn parse_field<T: FromStr>(
+    field: Option<&str>,
+    default: T,
+    line: usize,
+    what: &str,
+) -> Result<T, SlmpTagError> {
+    match field.map(str::trim) {
+        None | Some("") => Ok(default),
+        Some(s) => s.parse().map_err(|_| SlmpTagError::Syntax {
+            line: Some(line),
+            message: format!("invalid {}: {}", what, s),
+        }),
+    }
+}
+
+/// CSVの1行を項目に分割する
+///
+/// `"`で囲んだ項目は`,`を含むことができ、`""`は`"`1文字として扱う
+///
+/// # 引数
+///
+/// * `line` - CSVの1行
+fn split_csv_line(line: &str) -> Result<Vec<String>, &'static str> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        while chars.peek() == Some(&' ') || chars.peek() == Some(&'\t') {
+            chars.next();
+        }
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    None => return Err("unterminated quoted field"),
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                }
+            }
+            while chars.peek() == Some(&' ') || chars.peek() == Some(&'\t') {
+                chars.next();
+            }
+            match chars.next() {
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                }
+                Some(',') => fields.push(field),
+                Some(_) => return Err("unexpected character after quoted field"),
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    None => {
+                        fields.push(field.trim().to_string());
+                        return Ok(fields);
+                    }
+                    Some(',') => break,
+                    Some('"') => return Err("unexpected quote in unquoted field"),
+                    Some(c) => field.push(c),
+                }
+            }
+            fields.push(field.trim().to_string());
+        }
+    }
+}
+
+/// タグを作成
+///
+/// # 引数
+///
+/// * `name` - タグ名
+/// * `device` - デバイス名
+/// * `data_type` - データ型の名前
+fn make_tag(name: &str, device: &str, data_type: &str) -> Result<SlmpTag, SlmpTagError> {
+    let device = device.parse().map_err(|error| SlmpTagError::Device {
+        name: name.to_string(),
+        error,
+    })?;
+    Ok(SlmpTag::new(name, device, data_type.parse()?))
+}
+
+impl SlmpTagDatabase {
+    /// 空のタグ定義を作成
+    pub fn new() -> SlmpTagDatabase {
+        SlmpTagDatabase {
+            max_gap: 4,
+            ..SlmpTagDatabase::default()
+        }
+    }
+    /// タグを追加する
+    ///
+    /// # 引数
+    ///
+    /// * `tag` - タグの定義
+    pub fn add(&mut self, tag: SlmpTag) -> Result<(), SlmpTagError> {
+        tag.validate().map_err(|reason| SlmpTagError::InvalidTag {
+            name: tag.name.clone(),
+            reason,
+        })?;
+        if self.index.contains_key(&tag.name) {
+            return Err(SlmpTagError::DuplicateName(tag.name));
+        }
+        self.index.insert(tag.name.clone(), self.tags.len());
+        self.tags.push(tag);
+        Ok(())
+    }
+    /// 名前からタグを得る
+    pub fn get(&self, name: &str) -> Option<&SlmpTag> {
+        self.index.get(name).map(|i| &self.tags[*i])
+    }
+    /// 全てのタグ
+    pub fn tags(&self) -> &[SlmpTag] {
+        &self.tags
+    }
+    /// 読み取り時に同じブロックにまとめるデバイスの間隔の上限(ワード単位)
+    pub fn get_max_gap(&self) -> usize {
+        self.max_gap
+    }
+    /// 読み取り時に同じブロックにまとめるデバイスの間隔の上限を設定する
+    ///
+    /// 大きくすると要求の数が減る代わりに、不要なデバイスも読み出す
+    ///
+    /// # 引数
+    ///
+    /// * `max_gap` - 間隔の上限(ワード単位)
+    pub fn set_max_gap(&mut self, max_gap: usize) {
+        self.max_gap = max_gap;
+    }
+    /// CSVからタグ定義を読み込む
+    ///
+    /// `#`で始まる行はコメントとする。
+    /// コメント以外の最初の行が`name,device`で始まる場合は見出し行として読み飛ばす
+    ///
+    /// # 引数
+    ///
+    /// * `text` - CSVの内容
+    pub fn from_csv(text: &str) -> Result<SlmpTagDatabase, SlmpTagError> {
+        let mut db = SlmpTagDatabase::new();
+        let mut first = true;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_csv_line(line).map_err(|message| SlmpTagError::Syntax {
+                line: Some(line_no),
+                message: message.to_string(),
+            })?;
+            // 見出し行はコメント以外の最初の行のみ
+            let header = first
+                && fields[0].eq_ignore_ascii_case("name")
+                && fields.len() > 1
+                && fields[1].eq_ignore_ascii_case("device");
+            first = false;
+            if header {
+                continue;
+            }
+            if fields.len() < 3 || fields.len() > 7 {
+                return Err(SlmpTagError::Syntax {
+                    line: Some(line_no),
+                    message: format!("expected 3 to 7 fields, found {}", fields.len()),
+                });
+            }
+            let field = |i: usize| fields.get(i).map(String::as_str);
+            let mut tag = make_tag(&fields[0], &fields[1], &fields[2]).map_err(|error| {
+                SlmpTagError::Line {
+                    line: line_no,
+                    error: Box::new(error),
+                }
+            })?;
+            tag.length = parse_field(field(3), 1, line_no, "length")?;
+            tag.scale = parse_field(field(4), 1.0, line_no, "scale")?;
+            tag.offset = parse_field(field(5), 0.0, line_no, "offset")?;
+            tag.read_only = parse_field(field(6), false, line_no, "read_only")?;
+            db.add(tag).map_err(|error| SlmpTagError::Line {
+                line: line_no,
+                error: Box::new(error),
+            })?;
+        }
+        Ok(db)
+    }
+    /// TOMLからタグ定義を読み込む
+    ///
+    /// `[[tag]]`の配列に`name`、`device`、`type`、`length`、`scale`、`offset`、`read_only`を
+    /// 記述する
+    ///
+    /// # 引数
+    ///
+    /// * `text` - TOMLの内容
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<SlmpTagDatabase, SlmpTagError> {
+        let syntax = |message: String| SlmpTagError::Syntax {
+            line: None,
+            message,
+        };
+        let value: toml::Value =
+            text.parse()
+                .map_err(|e: toml::de::Error| SlmpTagError::Syntax {
+                    line: e.line_col().map(|(line, _)| line + 1),
+                    message: e.to_string(),
+                })?;
+        let entries = match value.get("tag") {
+            None => return Ok(SlmpTagDatabase::new()),
+            Some(toml::Value::Array(entries)) => entries,
+            Some(_) => return Err(syntax(String::from("tag must be an array of tables"))),
+        };
+        let mut db = SlmpTagDatabase::new();
+        for entry in entries.iter() {
+            let str_field = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| syntax(format!("missing string field: {}", key)))
+            };
+            let float_field = |key: &str, default: f64| match entry.get(key) {
+                None => Ok(default),
+                Some(toml::Value::Float(v)) => Ok(*v),
+                Some(toml::Value::Integer(v)) => Ok(*v as f64),
+                Some(_) => Err(syntax(format!("invalid {}", key))),
+            };
+            let name = str_field("name")?;
+            let mut tag = make_tag(name, str_field("device")?, str_field("type")?)?;
+            tag.length = match entry.get("length") {
+                None => 1,
+                Some(toml::Value::Integer(v)) if *v >= 0 => *v as usize,
+                Some(_) => return Err(syntax(format!("invalid length of {}", name))),
+            };
+            tag.scale = float_field("scale", 1.0)?;
+            tag.offset = float_field("offset", 0.0)?;
+            tag.read_only = match entry.get("read_only") {
+                None => false,
+                Some(toml::Value::Boolean(v)) => *v,
+                Some(_) => return Err(syntax(format!("invalid read_only of {}", name))),
+            };
+            db.add(tag)?;
+        }
+        Ok(db)
+    }
+    /// 名前からタグを得る、なければエラー
+    fn tag(&self, name: &str) -> Result<&SlmpTag, SlmpError> {
+        self.get(name)
+            .ok_or(SlmpError::InvalidArgument("unknown tag"))
+    }
+    /// 名前のリストからタグのリストを得る
+    fn tags_of(&self, names: &[&str]) -> Result<Vec<&SlmpTag>, SlmpError> {
+        names.iter().map(|name| self.tag(name)).collect()
+    }
+    /// 指定したタグの読出し計画を作成
+    ///
+    /// # 引数
+    ///
+    /// * `names` - タグ名のリスト
+    pub fn read_plan(&self, names: &[&str]) -> Result<SlmpReadPlan, SlmpError> {
+        tags_read_plan(&self.tags_of(names)?, self.max_gap)
+    }
+    /// 書き込むデバイスのリストを作成
+    ///
+    /// # 返値
+    ///
+    /// ワード単位、ダブルワード単位、ビット単位で書き込むデバイスのリスト
+    #[allow(clippy::type_complexity)]
+    fn write_targets(
+        &self,
+        values: &[(&str, SlmpTagValue)],
+    ) -> Result<
+        (
+            Vec<SLMPDeviceData<u16>>,
+            Vec<SLMPDeviceData<u32>>,
+            Vec<SLMPDeviceData<bool>>,
+        ),
+        SlmpError,
+    > {
+        let mut word = Vec::new();
+        let mut dword = Vec::new();
+        let mut bit = Vec::new();
+        for (name, value) in values.iter() {
+            let tag = self.tag(name)?;
+            if tag.read_only {
+                return Err(SlmpError::InvalidArgument("read-only tag"));
+            }
+            if tag.data_type == SlmpTagType::Bit {
+                for (i, v) in tag.elements(value)?.into_iter().enumerate() {
+                    let value = match v {
+                        SlmpTagValue::Bit(b) => *b,
+                        _ => return Err(SlmpError::InvalidArgument("type mismatch")),
+                    };
+                    let dev = SLMPDevice {
+                        d_code: tag.device.d_code,
+                        addr: tag.device.addr.wrapping_add(i as u32),
+                    };
+                    bit.push(SLMPDeviceData { dev, value });
+                }
+                continue;
+            }
+            let words = tag.encode(value)?;
+            for (i, w) in words.chunks(2).enumerate() {
+                let dev = offset_device(tag.device, i * 2);
+                match w {
+                    [lo, hi] => dword.push(SLMPDeviceData {
+                        dev,
+                        value: *lo as u32 | ((*hi as u32) << 16),
+                    }),
+                    [value] => word.push(SLMPDeviceData { dev, value: *value }),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Ok((word, dword, bit))
+    }
+    /// タグの値を読み取る
+    ///
+    /// 近いデバイスは複数ブロック一括読出し、孤立したデバイスはランダム読出しでまとめて読み取る
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読出しに使うクライアント
+    /// * `names` - タグ名のリスト
+    ///
+    /// # 返値
+    ///
+    /// タグ名の順の値
+    pub fn read(
+        &self,
+        client: &mut SlmpClient,
+        names: &[&str],
+    ) -> Result<Vec<SlmpTagValue>, SlmpError> {
+        let ret = self.read_plan(names)?.execute(client)?;
+        decode_tags(&self.tags_of(names)?, ret)
+    }
+    /// タグへ値を書き込む
+    ///
+    /// ランダム書込みでまとめて書き込む
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 書込みに使うクライアント
+    /// * `values` - タグ名と書き込む値のリスト
+    pub fn write(
+        &self,
+        client: &mut SlmpClient,
+        values: &[(&str, SlmpTagValue)],
+    ) -> Result<(), SlmpError> {
+        let (word, dword, bit) = self.write_targets(values)?;
+        if !word.is_empty() || !dword.is_empty() {
+            client.write_random_words(&word, &dword)?;
+        }
+        if !bit.is_empty() {
+            client.write_random_bits(&bit)?;
+        }
+        Ok(())
+    }
+    /// タグの値を読み取る(tokio版)
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 読出しに使うクライアント
+    /// * `names` - タグ名のリスト
+    ///
+    /// # 返値
+    ///
+    /// タグ名の順の値
+    #[cfg(feature = "tokio")]
+    pub async fn read_async(
+        &self,
+        client: &mut crate::AsyncSlmpClient,
+        names: &[&str],
+    ) -> Result<Vec<SlmpTagValue>, SlmpError> {
+        let ret = self.read_plan(names)?.execute_async(client).await?;
+        decode_tags(&self.tags_of(names)?, ret)
+    }
+    /// タグへ値を書き込む(tokio版)
+    ///
+    /// # 引数
+    ///
+    /// * `client` - 書込みに使うクライアント
+    /// * `values` - タグ名と書き込む値のリスト
+    #[cfg(feature = "tokio")]
+    pub async fn write_async(
+        &self,
+        client: &mut crate::AsyncSlmpClient,
+        values: &[(&str, SlmpTagValue)],
+    ) -> Result<(), SlmpError> {
+        let (word, dword, bit) = self.write_targets(values)?;
+        if !word.is_empty() || !dword.is_empty() {
+            client.write_random_words(&word, &dword).await?;
+        }
+        if !bit.is_empty() {
+            client.write_random_bits(&bit).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SLMPConnectionInfo, SlmpServer};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    const CSV: &str = "# sample
+name,device,type,length,scale,offset,read_only
+Line1.Speed,D1200,INT,1,0.1
+Line1.Count,D1201,DINT
+Line1.Running,M100,BOOL
+Line1.Flags,M200,BOOL,3
+Line1.Temp,D1210,REAL,2
+Line1.Recipe,D1300,STRING,5
+Line1.Serial,W10,BCD32,1,,,true
+";
+
+    #[test]
+    fn test_load_csv() {
+        let db = SlmpTagDatabase::from_csv(CSV).unwrap();
+        assert_eq!(db.tags().len(), 7);
+        let tag = db.get("Line1.Temp").unwrap();
+        assert_eq!(tag.data_type, SlmpTagType::F32);
+        assert_eq!(tag.words(), 4);
+        assert_eq!(db.get("Line1.Recipe").unwrap().words(), 3);
+        assert!(db.get("Line1.Serial").unwrap().read_only);
+
+        let at_line = |line: usize, error: SlmpTagError| SlmpTagError::Line {
+            line,
+            error: Box::new(error),
+        };
+        assert_eq!(
+            SlmpTagDatabase::from_csv("A,D0,INT\nA,D1,INT").unwrap_err(),
+            at_line(2, SlmpTagError::DuplicateName(String::from("A")))
+        );
+        assert_eq!(
+            SlmpTagDatabase::from_csv("A,D0,BOOL").unwrap_err(),
+            at_line(
+                1,
+                SlmpTagError::InvalidTag {
+                    name: String::from("A"),
+                    reason: "bit tag on word device",
+                }
+            )
+        );
+        match SlmpTagDatabase::from_csv("A,Q0,INT").unwrap_err() {
+            SlmpTagError::Line { line: 1, error } => {
+                assert!(matches!(*error, SlmpTagError::Device { .. }))
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            SlmpTagDatabase::from_csv("A,D0,CHAR").unwrap_err(),
+            at_line(1, SlmpTagError::UnknownType(String::from("CHAR")))
+        );
+        assert_eq!(
+            SlmpTagDatabase::from_csv("# c\nA,D0,CHAR")
+                .unwrap_err()
+                .to_string(),
+            "line 2: unknown data type: CHAR"
+        );
+        assert!(matches!(
+            SlmpTagDatabase::from_csv("\nA,D0,INT,x"),
+            Err(SlmpTagError::Syntax { line: Some(2), .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_csv_quoted() {
+        let csv = "\"Line1,Speed\", D1200 ,INT,1,0.1\n\"Say \"\"Hi\"\"\",D1300,STRING,5,,,\"true\"";
+        let db = SlmpTagDatabase::from_csv(csv).unwrap();
+        assert_eq!(db.get("Line1,Speed").unwrap().scale, 0.1);
+        assert!(db.get("Say \"Hi\"").unwrap().read_only);
+
+        // 見出し行と同じ名前でも2行目以降はタグとして読み込む
+        let db = SlmpTagDatabase::from_csv("# c\nname,device,type\nname,D0,INT\n").unwrap();
+        assert_eq!(db.get("name").unwrap().device, "D0".parse().unwrap());
+        let db = SlmpTagDatabase::from_csv("name,D0,INT").unwrap();
+        assert_eq!(db.tags().len(), 1);
+
+        for csv in &["\"A,D0,INT", "\"A\"x,D0,INT", "A\",D0,INT"] {
+            assert!(matches!(
+                SlmpTagDatabase::from_csv(&format!("\n{}", csv)),
+                Err(SlmpTagError::Syntax { line: Some(2), .. })
+            ));
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_load_toml() {
+        let toml = r#"
+[[tag]]
+name = "Line1.Speed"
+device = "D1200"
+type = "INT"
+scale = 0.1
+
+[[tag]]
+name = "Line1.Running"
+device = "M100"
+type = "BOOL"
+read_only = true
+"#;
+        let db = SlmpTagDatabase::from_toml(toml).unwrap();
+        assert_eq!(db.get("Line1.Speed").unwrap().scale, 0.1);
+        assert!(db.get("Line1.Running").unwrap().read_only);
+        assert!(SlmpTagDatabase::from_toml("[[tag]]\nname = \"A\"").is_err());
+    }
+
+    #[test]
+    fn test_read_write_tags() {
+        let server =
+            SlmpServer::start_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        server
+            .state()
+            .devices
+            .set_dword("W10".parse().unwrap(), 0x12345678);
+        let connection_info = SLMPConnectionInfo::new_tcp(server.local_addr()).unwrap();
+        let mut client = SlmpClient::new(connection_info);
+        let db = SlmpTagDatabase::from_csv(CSV).unwrap();
+        db.write(
+            &mut client,
+            &[
+                ("Line1.Speed", SlmpTagValue::F64(12.3)),
+                ("Line1.Count", SlmpTagValue::I32(-100000)),
+                ("Line1.Running", SlmpTagValue::Bit(true)),
+                (
+                    "Line1.Flags",
+                    SlmpTagValue::Array(vec![
+                        SlmpTagValue::Bit(false),
+                        SlmpTagValue::Bit(true),
+                        SlmpTagValue::Bit(true),
+                    ]),
+                ),
+                (
+                    "Line1.Temp",
+                    SlmpTagValue::Array(vec![SlmpTagValue::F32(1.5), SlmpTagValue::F32(-0.25)]),
+                ),
+                ("Line1.Recipe", SlmpTagValue::String(String::from("ABCDE"))),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            server.state().devices.get_word("D1200".parse().unwrap()),
+            123
+        );
+        let names = [
+            "Line1.Speed",
+            "Line1.Count",
+            "Line1.Running",
+            "Line1.Flags",
+            "Line1.Temp",
+            "Line1.Recipe",
+            "Line1.Serial",
+        ];
+        let ret = db.read(&mut client, &names).unwrap();
+        match ret[0] {
+            SlmpTagValue::F64(v) => assert!((v - 12.3).abs() < 1e-9),
+            _ => panic!("scaled value expected"),
+        }
+        assert_eq!(
+            ret[1..],
+            [
+                SlmpTagValue::I32(-100000),
+                SlmpTagValue::Bit(true),
+                SlmpTagValue::Array(vec![
+                    SlmpTagValue::Bit(false),
+                    SlmpTagValue::Bit(true),
+                    SlmpTagValue::Bit(true),
+                ]),
+                SlmpTagValue::Array(vec![SlmpTagValue::F32(1.5), SlmpTagValue::F32(-0.25)]),
+                SlmpTagValue::String(String::from("ABCDE")),
+                SlmpTagValue::U32(12345678),
+            ]
+        );
+        assert!(db
+            .write(&mut client, &[("Line1.Serial", SlmpTagValue::U32(1))])
+            .is_err());
+        assert!(db
+            .write(&mut client, &[("Line1.Speed", SlmpTagValue::F64(1e6))])
+            .is_err());
+        assert!(db.read(&mut client, &["Line2.Speed"]).is_err());
+    }
+}
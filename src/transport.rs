@@ -0,0 +1,173 @@
+use crate::enums::TCPorUDP;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// 通信路の抽象化
+///
+/// [SLMPConnectionInfo](crate::SLMPConnectionInfo)はこのトレイトを実装した任意の通信路で動作する
+pub trait SlmpTransport: Send {
+    /// 指定されたバイト列を送信する
+    ///
+    /// # 引数
+    ///
+    /// * `buf` - 送信するバイト列
+    ///
+    /// # 返値
+    ///
+    /// 送信したバイト数
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize>;
+    /// 受信する
+    ///
+    /// タイムアウトした場合は[io::ErrorKind::WouldBlock]もしくは[io::ErrorKind::TimedOut]を返す
+    ///
+    /// # 引数
+    ///
+    /// * `buf` - 受信したデータを入れるバッファ
+    ///
+    /// # 返値
+    ///
+    /// 受信したバイト数、0の場合は切断された
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+    /// 読み取りタイムアウトの設定
+    ///
+    /// # 引数
+    ///
+    /// * `timeout` - ミリ秒単位での指定、0の場合は無期限に待つ
+    fn set_timeout(&mut self, timeout: u64) -> io::Result<()>;
+}
+
+impl SlmpTransport for TCPorUDP {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write(buf)
+    }
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read(buf)
+    }
+    fn set_timeout(&mut self, timeout: u64) -> io::Result<()> {
+        TCPorUDP::set_timeout(self, timeout)
+    }
+}
+
+/// 片方向の通信路
+#[derive(Default)]
+struct LoopbackQueue {
+    /// 受信待ちのデータ
+    data: VecDeque<u8>,
+    /// 送信側が破棄された
+    closed: bool,
+}
+
+type SharedQueue = Arc<(Mutex<LoopbackQueue>, Condvar)>;
+
+/// メモリ上で対になった通信路、試験用
+///
+/// 一方で送信したバイト列はもう一方で受信できる
+pub struct SlmpLoopback {
+    /// 送信先
+    tx: SharedQueue,
+    /// 受信元
+    rx: SharedQueue,
+    /// 読み取りタイムアウト
+    timeout: Option<Duration>,
+}
+
+impl SlmpLoopback {
+    /// 対になった通信路を作成
+    pub fn pair() -> (SlmpLoopback, SlmpLoopback) {
+        let a: SharedQueue = Arc::default();
+        let b: SharedQueue = Arc::default();
+        (
+            SlmpLoopback {
+                tx: a.clone(),
+                rx: b.clone(),
+                timeout: None,
+            },
+            SlmpLoopback {
+                tx: b,
+                rx: a,
+                timeout: None,
+            },
+        )
+    }
+}
+
+impl SlmpTransport for SlmpLoopback {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (lock, cvar) = &*self.tx;
+        let mut queue = lock.lock().unwrap();
+        if queue.closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "peer closed"));
+        }
+        queue.data.extend(buf.iter());
+        cvar.notify_all();
+        Ok(buf.len())
+    }
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (lock, cvar) = &*self.rx;
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut queue = lock.lock().unwrap();
+        while queue.data.is_empty() && !queue.closed {
+            queue = match deadline {
+                None => cvar.wait(queue).unwrap(),
+                Some(deadline) => {
+                    let remain = deadline.saturating_duration_since(Instant::now());
+                    if remain.is_zero() {
+                        return Err(io::Error::new(io::ErrorKind::WouldBlock, "timeout"));
+                    }
+                    cvar.wait_timeout(queue, remain).unwrap().0
+                }
+            };
+        }
+        let size = buf.len().min(queue.data.len());
+        for (dst, src) in buf.iter_mut().zip(queue.data.drain(..size)) {
+            *dst = src;
+        }
+        Ok(size)
+    }
+    fn set_timeout(&mut self, timeout: u64) -> io::Result<()> {
+        self.timeout = match timeout {
+            0 => None,
+            t => Some(Duration::from_millis(t)),
+        };
+        Ok(())
+    }
+}
+
+impl Drop for SlmpLoopback {
+    fn drop(&mut self) {
+        // 相手側の受信を終わらせ、送信を失敗させる
+        for shared in [&self.tx, &self.rx] {
+            let (lock, cvar) = &**shared;
+            if let Ok(mut queue) = lock.lock() {
+                queue.closed = true;
+            }
+            cvar.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback() {
+        let (mut a, mut b) = SlmpLoopback::pair();
+        assert_eq!(a.send(&[1, 2, 3]).unwrap(), 3);
+        let mut buf = [0u8; 2];
+        assert_eq!(b.recv(&mut buf).unwrap(), 2);
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(b.recv(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 3);
+        b.set_timeout(10).unwrap();
+        assert_eq!(
+            b.recv(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        drop(a);
+        assert_eq!(b.recv(&mut buf).unwrap(), 0);
+        assert!(b.send(&[0]).is_err());
+    }
+}